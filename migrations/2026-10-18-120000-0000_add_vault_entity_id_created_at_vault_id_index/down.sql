@@ -0,0 +1 @@
+DROP INDEX CONCURRENTLY IF EXISTS vault_entity_id_created_at_vault_id_idx;
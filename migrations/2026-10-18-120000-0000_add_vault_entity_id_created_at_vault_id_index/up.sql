@@ -0,0 +1 @@
+CREATE INDEX CONCURRENTLY IF NOT EXISTS vault_entity_id_created_at_vault_id_idx ON vault (entity_id, created_at, vault_id);
@@ -0,0 +1,4 @@
+-- Drop the optimistic concurrency `version` column from `vault` and `locker`.
+
+ALTER TABLE locker DROP COLUMN IF EXISTS version;
+ALTER TABLE vault  DROP COLUMN IF EXISTS version;
@@ -0,0 +1,8 @@
+-- Add a row version to `vault` and `locker` for optimistic concurrency control.
+-- Every update bumps it by one; writers may pass the version they read as a precondition.
+
+ALTER TABLE locker
+    ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+ALTER TABLE vault
+    ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,3 @@
+-- Drop the wrapped data encryption key from `entity`.
+
+ALTER TABLE entity DROP COLUMN IF EXISTS wrapped_dek;
@@ -0,0 +1,5 @@
+-- Store the per-entity data encryption key wrapped by the external key manager.
+-- Only populated when the locker runs in `local_envelope` data encryption mode.
+
+ALTER TABLE entity
+    ADD COLUMN IF NOT EXISTS wrapped_dek BYTEA;
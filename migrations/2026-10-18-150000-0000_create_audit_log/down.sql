@@ -0,0 +1,4 @@
+-- Drop the audit trail table and its append-only guard.
+
+DROP TABLE IF EXISTS audit_log;
+DROP FUNCTION IF EXISTS audit_log_reject_modification();
@@ -0,0 +1,35 @@
+-- Append-only, hash-chained audit trail of data access.
+-- `event` holds the exact JSON the hash was computed over; the other columns are copies for querying.
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id VARCHAR(64) NOT NULL,
+    sequence BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    tenant_id VARCHAR(255),
+    action VARCHAR(64) NOT NULL,
+    outcome VARCHAR(16) NOT NULL,
+    merchant_id VARCHAR(255),
+    resource_id VARCHAR(255),
+    request_id VARCHAR(64),
+    prev_hash VARCHAR(64) NOT NULL,
+    hash VARCHAR(64) NOT NULL,
+    event TEXT NOT NULL,
+    UNIQUE (chain_id, sequence)
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at_index ON audit_log (created_at);
+
+CREATE OR REPLACE FUNCTION audit_log_reject_modification() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_no_update_or_delete
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_reject_modification();
+
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_reject_modification();
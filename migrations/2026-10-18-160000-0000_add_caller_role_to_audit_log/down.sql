@@ -0,0 +1,6 @@
+-- Drop the audit query indexes and the caller role column.
+
+DROP INDEX IF EXISTS audit_log_resource_id_index;
+DROP INDEX IF EXISTS audit_log_tenant_id_merchant_id_created_at_index;
+
+ALTER TABLE audit_log DROP COLUMN IF EXISTS caller_role;
@@ -0,0 +1,9 @@
+-- Record the caller role of each audit event and index the columns the audit query filters on.
+
+ALTER TABLE audit_log
+    ADD COLUMN IF NOT EXISTS caller_role VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS audit_log_tenant_id_merchant_id_created_at_index
+    ON audit_log (tenant_id, merchant_id, created_at);
+
+CREATE INDEX IF NOT EXISTS audit_log_resource_id_index ON audit_log (resource_id);
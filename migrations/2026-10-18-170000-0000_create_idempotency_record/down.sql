@@ -0,0 +1,3 @@
+-- Drop the stored idempotent responses.
+
+DROP TABLE IF EXISTS idempotency_record;
@@ -0,0 +1,15 @@
+-- First responses to requests carrying an `Idempotency-Key`, replayed to retries of the same request.
+-- `response` is encrypted with the tenant master key and stays NULL while the first request runs.
+
+CREATE TABLE IF NOT EXISTS idempotency_record (
+    owner_id VARCHAR(255) NOT NULL,
+    route VARCHAR(64) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    request_hash VARCHAR(64) NOT NULL,
+    response BYTEA,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (owner_id, route, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_record_expires_at_index ON idempotency_record (expires_at);
@@ -0,0 +1,750 @@
+//! Detection of callers retrieving unusually many distinct cards.
+//!
+//! With `anomaly_detection.enabled`, every card and vault retrieval is counted against its
+//! caller, identified by its authenticated `x-caller-api-key` within the tenant. Each caller has a
+//! sliding window of `window_secs` over the distinct customers (`merchant_customer_id`, or `entity_id` on v2) and
+//! cards (`card_reference` / `vault_id`) it retrieved, failed attempts included. Going over
+//! `max_distinct_customers` or `max_distinct_cards` raises an alert (log, metric and audit event)
+//! and, depending on `action`:
+//! - `alert`: nothing else.
+//! - `throttle`: retrievals of customers or cards not already in the window are rejected with
+//!   `429` until the window slides back under the thresholds.
+//! - `block`: every retrieval of the caller is rejected with `403` until an admin clears the block
+//!   through `/admin/anomaly/unblock`.
+//!
+//! Requests without an API key share one [`UNKNOWN_CALLER`] window per tenant, which covers every
+//! caller not set up in `caller_auth`. A breach of it is only ever alerted on, since throttling or
+//! blocking it would reject the retrievals of all those callers at once; `throttle` and `block`
+//! therefore require `caller_auth.api_keys`.
+//!
+//! Windows are kept in memory per instance. With Redis configured, blocks are kept in a set of the
+//! tenant there, so they outlive restarts and hold on every instance; each instance keeps a copy,
+//! read again when another instance announces a change over pub/sub, and every minute in case an
+//! announcement was missed.
+
+#[cfg(feature = "redis")]
+mod redis;
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{BuildHasher, RandomState},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
+    config::CallerAuthConfig,
+    error::{self, ContainerError},
+    logger,
+    observability::metrics,
+};
+
+/// Caller of requests without an `x-caller-api-key`, shared by all of them and never throttled or
+/// blocked.
+pub const UNKNOWN_CALLER: &str = "unknown";
+
+/// Callers tracked before the least active windows are dropped.
+const MAX_TRACKED_CALLERS: u64 = 10_000;
+
+/// How long a copy of the blocked callers read from Redis is used before it is read again.
+#[cfg(feature = "redis")]
+const BLOCKS_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct AnomalyDetectionConfig {
+    pub enabled: bool,
+    pub window_secs: u64,
+    pub max_distinct_customers: usize,
+    pub max_distinct_cards: usize,
+    pub action: AnomalyAction,
+}
+
+impl Default for AnomalyDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 300,
+            max_distinct_customers: 100,
+            max_distinct_cards: 200,
+            action: AnomalyAction::Alert,
+        }
+    }
+}
+
+impl AnomalyDetectionConfig {
+    pub fn validate(
+        &self,
+        caller_auth: &CallerAuthConfig,
+    ) -> Result<(), error::ConfigurationError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.window_secs == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "anomaly_detection.window_secs must be greater than 0".into(),
+            ));
+        }
+        if self.max_distinct_customers == 0 || self.max_distinct_cards == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "anomaly_detection thresholds must be greater than 0".into(),
+            ));
+        }
+        // Without keys every caller is the unknown one, which is only alerted on.
+        if self.action != AnomalyAction::Alert && caller_auth.api_keys.is_empty() {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "anomaly_detection.action = \"throttle\" or \"block\" requires caller_auth.api_keys"
+                    .into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, serde::Deserialize, Debug, Default, PartialEq, Eq, strum::IntoStaticStr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AnomalyAction {
+    #[default]
+    Alert,
+    Throttle,
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+enum Threshold {
+    DistinctCustomers,
+    DistinctCards,
+}
+
+crate::impl_metric_value_from!(AnomalyAction, Threshold);
+
+/// A caller going over one of the thresholds.
+#[derive(Debug)]
+struct Breach {
+    threshold: Threshold,
+    count: usize,
+    limit: usize,
+}
+
+#[derive(Debug)]
+enum Observation {
+    Allowed,
+    /// Newly over a threshold; `rejected` unless the action is `alert`.
+    Breached {
+        breach: Breach,
+        rejected: bool,
+    },
+    Throttled,
+    Blocked,
+}
+
+#[derive(Default)]
+struct CallerWindow {
+    /// Hashed ids, with when they were last retrieved.
+    customers: HashMap<u64, Instant>,
+    cards: HashMap<u64, Instant>,
+    /// Set once an alert was raised, cleared once back under the thresholds.
+    breached: bool,
+}
+
+impl CallerWindow {
+    fn expire(&mut self, cutoff: Option<Instant>) {
+        if let Some(cutoff) = cutoff {
+            self.customers.retain(|_, seen| *seen >= cutoff);
+            self.cards.retain(|_, seen| *seen >= cutoff);
+        }
+    }
+}
+
+pub struct RetrievalDetector {
+    config: AnomalyDetectionConfig,
+    // only hashes of the ids are kept in memory
+    hasher: RandomState,
+    /// Windows of callers that retrieved anything within the last `window_secs`.
+    windows: moka::sync::Cache<String, Arc<Mutex<CallerWindow>>>,
+    blocked: Mutex<BlockedCallers>,
+}
+
+#[derive(Default)]
+struct BlockedCallers {
+    callers: HashSet<String>,
+    /// When `callers` was last read from Redis; `None` before the first read and after another
+    /// instance announced a change.
+    #[cfg(feature = "redis")]
+    loaded_at: Option<Instant>,
+}
+
+impl std::fmt::Debug for RetrievalDetector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetrievalDetector")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl RetrievalDetector {
+    /// `None` unless `anomaly_detection.enabled`.
+    pub fn new(config: &AnomalyDetectionConfig) -> Option<Self> {
+        config.enabled.then(|| Self {
+            config: config.clone(),
+            hasher: RandomState::new(),
+            // a window idle for `window_secs` has nothing left in it
+            windows: moka::sync::Cache::builder()
+                .max_capacity(MAX_TRACKED_CALLERS)
+                .time_to_idle(Duration::from_secs(config.window_secs))
+                .build(),
+            blocked: Mutex::new(BlockedCallers::default()),
+        })
+    }
+
+    /// The action taken on a breach of `caller`; callers without a key are only alerted on.
+    fn action_for(&self, caller: Option<&str>) -> AnomalyAction {
+        caller.map_or(AnomalyAction::Alert, |_| self.config.action)
+    }
+
+    fn observe(
+        &self,
+        caller: Option<&str>,
+        customer: (&str, &str),
+        card: &str,
+        now: Instant,
+    ) -> Observation {
+        if caller.is_some_and(|caller| self.is_blocked(caller)) {
+            return Observation::Blocked;
+        }
+        let action = self.action_for(caller);
+        let caller = caller.unwrap_or(UNKNOWN_CALLER);
+
+        let cutoff = now.checked_sub(Duration::from_secs(self.config.window_secs));
+        let customer = self.hasher.hash_one(customer);
+        let card = self.hasher.hash_one(card);
+
+        let entry = self.windows.get_with_by_ref(caller, Arc::default);
+        let mut window = entry.lock().unwrap_or_else(PoisonError::into_inner);
+        window.expire(cutoff);
+
+        let customers =
+            window.customers.len() + usize::from(!window.customers.contains_key(&customer));
+        let cards = window.cards.len() + usize::from(!window.cards.contains_key(&card));
+        let breach = if customers > self.config.max_distinct_customers {
+            Some(Breach {
+                threshold: Threshold::DistinctCustomers,
+                count: customers,
+                limit: self.config.max_distinct_customers,
+            })
+        } else if cards > self.config.max_distinct_cards {
+            Some(Breach {
+                threshold: Threshold::DistinctCards,
+                count: cards,
+                limit: self.config.max_distinct_cards,
+            })
+        } else {
+            None
+        };
+
+        let Some(breach) = breach else {
+            window.customers.insert(customer, now);
+            window.cards.insert(card, now);
+            if window.customers.len() < self.config.max_distinct_customers
+                && window.cards.len() < self.config.max_distinct_cards
+            {
+                window.breached = false;
+            }
+            return Observation::Allowed;
+        };
+
+        let newly_breached = !std::mem::replace(&mut window.breached, true);
+        match action {
+            AnomalyAction::Alert => {
+                window.customers.insert(customer, now);
+                window.cards.insert(card, now);
+                if newly_breached {
+                    Observation::Breached {
+                        breach,
+                        rejected: false,
+                    }
+                } else {
+                    Observation::Allowed
+                }
+            }
+            AnomalyAction::Throttle if newly_breached => Observation::Breached {
+                breach,
+                rejected: true,
+            },
+            AnomalyAction::Throttle => Observation::Throttled,
+            AnomalyAction::Block => {
+                drop(window);
+                self.windows.invalidate(caller);
+                self.block(caller);
+                Observation::Breached {
+                    breach,
+                    rejected: true,
+                }
+            }
+        }
+    }
+
+    fn is_blocked(&self, caller: &str) -> bool {
+        self.blocked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .callers
+            .contains(caller)
+    }
+
+    fn block(&self, caller: &str) {
+        self.blocked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .callers
+            .insert(caller.to_owned());
+    }
+
+    /// Returns whether `caller` was blocked.
+    fn unblock(&self, caller: &str) -> bool {
+        self.blocked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .callers
+            .remove(caller)
+    }
+
+    fn blocked_callers(&self) -> Vec<String> {
+        let mut callers = self
+            .blocked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .callers
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        callers.sort();
+        callers
+    }
+
+    /// Have the next retrieval read the blocked callers from Redis again.
+    #[cfg(feature = "redis")]
+    fn invalidate_blocks(&self) {
+        self.blocked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .loaded_at = None;
+    }
+
+    /// Read the blocked callers from Redis, if the copy held is older than
+    /// [`BLOCKS_RELOAD_INTERVAL`] or `force`. The copy held is kept when Redis is unavailable.
+    #[cfg(feature = "redis")]
+    async fn load_blocks(
+        &self,
+        store: &crate::storage::redis::RedisStore,
+        tenant_id: &str,
+        force: bool,
+    ) {
+        let loaded_at = self
+            .blocked
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .loaded_at;
+        if !force && loaded_at.is_some_and(|loaded_at| loaded_at.elapsed() < BLOCKS_RELOAD_INTERVAL)
+        {
+            return;
+        }
+
+        let started_at = Instant::now();
+        match redis::blocked_callers(store, tenant_id).await {
+            Ok(callers) => {
+                let mut blocked = self.blocked.lock().unwrap_or_else(PoisonError::into_inner);
+                blocked.callers = callers.into_iter().collect();
+                // an invalidation arriving during the read leaves the copy stale
+                if blocked.loaded_at == loaded_at {
+                    blocked.loaded_at = Some(started_at);
+                }
+            }
+            Err(error) => {
+                logger::warn!(
+                    ?error,
+                    "Failed to read blocked callers, keeping the last ones read"
+                );
+            }
+        }
+    }
+}
+
+/// Blocked callers of the tenant, sorted.
+pub async fn blocked_callers(tenant_app_state: &TenantAppState) -> Vec<String> {
+    let Some(detector) = &tenant_app_state.anomaly_detector else {
+        return Vec::new();
+    };
+
+    #[cfg(feature = "redis")]
+    if let Some(store) = &tenant_app_state.redis {
+        detector
+            .load_blocks(store, &tenant_app_state.config.tenant_id, true)
+            .await;
+    }
+
+    detector.blocked_callers()
+}
+
+/// Clear the block of `caller`, on every instance when Redis is configured. Returns whether the
+/// caller was blocked.
+pub async fn unblock_caller(
+    tenant_app_state: &TenantAppState,
+    caller: &str,
+) -> Result<bool, ContainerError<error::ApiError>> {
+    let Some(detector) = &tenant_app_state.anomaly_detector else {
+        return Err(error::ApiError::ValidationError("anomaly detection is not enabled").into());
+    };
+
+    #[cfg_attr(not(feature = "redis"), expect(unused_mut))]
+    let mut unblocked = detector.unblock(caller);
+
+    #[cfg(feature = "redis")]
+    if let Some(store) = &tenant_app_state.redis {
+        unblocked |= redis::unblock(store, &tenant_app_state.config.tenant_id, caller)
+            .await
+            .map_err(|err| err.change_context(error::ApiError::DatabaseError))?;
+        publish_blocks_changed(tenant_app_state).await;
+    }
+
+    Ok(unblocked)
+}
+
+/// Count a retrieval of `card` of `customer_id` (of `merchant_id`) against its caller, the key
+/// identity of `x-caller-api-key`, raising an alert on a breach. Fails when the caller is throttled
+/// or blocked.
+pub async fn screen_retrieval(
+    tenant_app_state: &TenantAppState,
+    audit: &AuditContext,
+    caller_key: Option<&str>,
+    merchant_id: &str,
+    customer_id: &str,
+    card: &str,
+) -> Result<(), ContainerError<error::ApiError>> {
+    let Some(detector) = &tenant_app_state.anomaly_detector else {
+        return Ok(());
+    };
+    let action = detector.action_for(caller_key);
+    let caller = caller_key.unwrap_or(UNKNOWN_CALLER);
+
+    #[cfg(feature = "redis")]
+    if let Some(store) = &tenant_app_state.redis {
+        detector
+            .load_blocks(store, &tenant_app_state.config.tenant_id, false)
+            .await;
+    }
+
+    let rejected = match detector.observe(
+        caller_key,
+        (merchant_id, customer_id),
+        card,
+        Instant::now(),
+    ) {
+        Observation::Allowed => false,
+        Observation::Breached { breach, rejected } => {
+            let tenant_id = &tenant_app_state.config.tenant_id;
+            let threshold = <&'static str>::from(breach.threshold);
+            let window_secs = detector.config.window_secs;
+
+            metrics::RETRIEVAL_ANOMALY_COUNT.add(
+                1,
+                crate::metric_attributes!(
+                    ("tenant_id", tenant_id.clone()),
+                    ("threshold", breach.threshold),
+                    ("action", action),
+                ),
+            );
+            logger::warn!(
+                tenant_id = %tenant_id,
+                caller,
+                threshold,
+                count = breach.count,
+                limit = breach.limit,
+                window_secs,
+                action = <&'static str>::from(action),
+                "Anomalous retrievals detected"
+            );
+            audit
+                .record(
+                    AuditEvent::new(AuditAction::RetrievalAnomaly)
+                        .merchant_id(merchant_id)
+                        .failed(format!(
+                            "{} {threshold} retrieved within {window_secs}s, over the limit of {}; action: {}",
+                            breach.count,
+                            breach.limit,
+                            <&'static str>::from(action),
+                        )),
+                )
+                .await;
+
+            #[cfg(feature = "redis")]
+            if let (AnomalyAction::Block, Some(store)) = (action, &tenant_app_state.redis) {
+                // the block holds on this instance even when Redis is unavailable
+                match redis::block(store, &tenant_app_state.config.tenant_id, caller).await {
+                    Ok(()) => publish_blocks_changed(tenant_app_state).await,
+                    Err(error) => logger::error!(
+                        ?error,
+                        caller,
+                        "Failed to store the block, it holds on this instance until blocks are next read"
+                    ),
+                }
+            }
+
+            rejected
+        }
+        Observation::Throttled | Observation::Blocked => true,
+    };
+
+    if !rejected {
+        return Ok(());
+    }
+
+    Err(match action {
+        AnomalyAction::Block => error::ApiError::Forbidden(
+            "caller is blocked after anomalous retrievals, an admin must clear the block",
+        ),
+        AnomalyAction::Alert | AnomalyAction::Throttle => error::ApiError::RateLimited,
+    }
+    .into())
+}
+
+/// Announces that the blocked callers of `tenant` changed in Redis.
+#[cfg(feature = "redis")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct BlocksChanged {
+    tenant: String,
+}
+
+/// Best effort: without it, other instances pick up the change on their next periodic reload.
+#[cfg(feature = "redis")]
+async fn publish_blocks_changed(tenant_app_state: &TenantAppState) {
+    let Some(redis) = &tenant_app_state.redis else {
+        return;
+    };
+
+    let change = BlocksChanged {
+        tenant: tenant_app_state.config.tenant_id.clone(),
+    };
+    match serde_json::to_string(&change) {
+        Ok(message) => {
+            if let Err(err) = redis
+                .publish(crate::storage::consts::ANOMALY_BLOCK_CHANNEL, message)
+                .await
+            {
+                logger::warn!(?err, "Failed to publish anomaly block change");
+            }
+        }
+        Err(err) => logger::warn!(?err, "Failed to serialize anomaly block change"),
+    }
+}
+
+/// Drop the copy of the blocked callers of a tenant whenever another instance changes them.
+/// Changes for tenants this instance has no state for yet (custodian still locked) are dropped;
+/// their blocks are read when the state is created.
+#[cfg(feature = "redis")]
+pub async fn spawn_block_listener(
+    global_app_state: &std::sync::Arc<crate::tenant::GlobalAppState>,
+) {
+    if !global_app_state.global_config.anomaly_detection.enabled {
+        return;
+    }
+    let Some(redis_store) = &global_app_state.redis_store else {
+        return;
+    };
+
+    let state = std::sync::Arc::clone(global_app_state);
+    let gap_state = std::sync::Arc::clone(global_app_state);
+    let subscribed = redis_store
+        .subscribe(
+            vec![crate::storage::consts::ANOMALY_BLOCK_CHANNEL.to_owned()],
+            move |payload| {
+                let state = state.clone();
+                async move {
+                    let change = match serde_json::from_str::<BlocksChanged>(&payload) {
+                        Ok(change) => change,
+                        Err(err) => {
+                            logger::warn!(?err, "Ignoring malformed anomaly block change");
+                            return;
+                        }
+                    };
+
+                    let Ok(tenant_state) = state.get_app_state_of_tenant(&change.tenant).await
+                    else {
+                        return;
+                    };
+                    if let Some(detector) = &tenant_state.anomaly_detector {
+                        detector.invalidate_blocks();
+                    }
+                }
+            },
+            move || {
+                let state = gap_state.clone();
+                async move {
+                    // Any tenant's change may have been missed.
+                    let tenants = state
+                        .tenants_app_state
+                        .read()
+                        .await
+                        .values()
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for tenant_state in tenants {
+                        if let Some(detector) = &tenant_state.anomaly_detector {
+                            detector.invalidate_blocks();
+                        }
+                    }
+                }
+            },
+        )
+        .await;
+
+    if let Err(err) = subscribed {
+        logger::error!(
+            ?err,
+            "Failed to subscribe to anomaly block changes; blocks made elsewhere apply after the periodic reload"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(clippy::expect_used)]
+    fn detector(action: AnomalyAction) -> RetrievalDetector {
+        RetrievalDetector::new(&AnomalyDetectionConfig {
+            enabled: true,
+            window_secs: 60,
+            max_distinct_customers: 2,
+            max_distinct_cards: 10,
+            action,
+        })
+        .expect("detector is enabled")
+    }
+
+    #[test]
+    fn test_throttle_rejects_only_new_customers_until_the_window_slides() {
+        let detector = detector(AnomalyAction::Throttle);
+        let now = Instant::now();
+
+        for customer in ["c1", "c2"] {
+            assert!(matches!(
+                detector.observe(Some("caller"), ("m", customer), customer, now),
+                Observation::Allowed
+            ));
+        }
+        assert!(matches!(
+            detector.observe(Some("caller"), ("m", "c3"), "c3", now),
+            Observation::Breached { rejected: true, .. }
+        ));
+        assert!(matches!(
+            detector.observe(Some("caller"), ("m", "c4"), "c4", now),
+            Observation::Throttled
+        ));
+        assert!(matches!(
+            detector.observe(Some("caller"), ("m", "c1"), "c1", now),
+            Observation::Allowed
+        ));
+        assert!(matches!(
+            detector.observe(Some("other"), ("m", "c3"), "c3", now),
+            Observation::Allowed
+        ));
+
+        let later = now + Duration::from_secs(61);
+        assert!(matches!(
+            detector.observe(Some("caller"), ("m", "c3"), "c3", later),
+            Observation::Allowed
+        ));
+    }
+
+    #[test]
+    fn test_block_holds_until_cleared() {
+        let detector = detector(AnomalyAction::Block);
+        let now = Instant::now();
+
+        for customer in ["c1", "c2"] {
+            detector.observe(Some("caller"), ("m", customer), customer, now);
+        }
+        assert!(matches!(
+            detector.observe(Some("caller"), ("m", "c3"), "c3", now),
+            Observation::Breached { rejected: true, .. }
+        ));
+
+        let later = now + Duration::from_secs(3600);
+        assert!(matches!(
+            detector.observe(Some("caller"), ("m", "c1"), "c1", later),
+            Observation::Blocked
+        ));
+        assert_eq!(detector.blocked_callers(), vec!["caller".to_owned()]);
+
+        assert!(detector.unblock("caller"));
+        assert!(matches!(
+            detector.observe(Some("caller"), ("m", "c1"), "c1", later),
+            Observation::Allowed
+        ));
+    }
+
+    #[test]
+    fn test_callers_without_a_key_are_only_alerted_on() {
+        let detector = detector(AnomalyAction::Block);
+        let now = Instant::now();
+
+        for customer in ["c1", "c2"] {
+            detector.observe(None, ("m", customer), customer, now);
+        }
+        assert!(matches!(
+            detector.observe(None, ("m", "c3"), "c3", now),
+            Observation::Breached {
+                rejected: false,
+                ..
+            }
+        ));
+        assert!(matches!(
+            detector.observe(None, ("m", "c4"), "c4", now),
+            Observation::Allowed
+        ));
+        assert!(detector.blocked_callers().is_empty());
+
+        // a caller with a key has a window of its own
+        assert!(matches!(
+            detector.observe(Some("payments:3f9a12c07be45d61"), ("m", "c1"), "c1", now),
+            Observation::Allowed
+        ));
+    }
+
+    #[test]
+    fn test_throttle_and_block_require_caller_keys() {
+        let config = |action| AnomalyDetectionConfig {
+            enabled: true,
+            action,
+            ..Default::default()
+        };
+        let caller_auth = CallerAuthConfig {
+            api_keys: [(
+                "payments".to_owned(),
+                hyperswitch_masking::Secret::new("pay-key".to_owned()),
+            )]
+            .into(),
+            admin_roles: Vec::new(),
+        };
+
+        assert!(
+            config(AnomalyAction::Alert)
+                .validate(&CallerAuthConfig::default())
+                .is_ok()
+        );
+        for action in [AnomalyAction::Throttle, AnomalyAction::Block] {
+            assert!(
+                config(action)
+                    .validate(&CallerAuthConfig::default())
+                    .is_err()
+            );
+            assert!(config(action).validate(&caller_auth).is_ok());
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Blocked callers kept in a Redis set of the tenant, so blocks outlive restarts and every locker
+//! instance enforces the same ones. The set is named after the tenant, so tenants stay apart even
+//! when they share a `redis_key_prefix`.
+
+use hyperswitch_redis_interface::errors::RedisError;
+
+use crate::storage::redis::{RedisStore, prefixed_key};
+
+fn blocked_callers_key(tenant_id: &str) -> String {
+    format!("anomaly:{tenant_id}:blocked_callers")
+}
+
+/// Add `ARGV[1]` to `KEYS[1]`. Returns whether it was not a member yet.
+const ADD_SCRIPT: &str = r"
+return redis.call('SADD', KEYS[1], ARGV[1])
+";
+
+/// Remove `ARGV[1]` from `KEYS[1]`. Returns whether it was a member.
+const REMOVE_SCRIPT: &str = r"
+return redis.call('SREM', KEYS[1], ARGV[1])
+";
+
+/// Every member of `KEYS[1]`.
+const MEMBERS_SCRIPT: &str = r"
+return redis.call('SMEMBERS', KEYS[1])
+";
+
+fn script_error(err: impl std::fmt::Debug) -> error_stack::Report<RedisError> {
+    error_stack::Report::new(RedisError::UnknownResult).attach_printable(format!("{err:?}"))
+}
+
+pub(super) async fn block(
+    store: &RedisStore,
+    tenant_id: &str,
+    caller: &str,
+) -> error_stack::Result<(), RedisError> {
+    let redis_conn = store.get_redis_conn();
+    redis_conn
+        .evaluate_redis_script::<_, i64>(
+            ADD_SCRIPT,
+            vec![prefixed_key(&redis_conn, &blocked_callers_key(tenant_id))],
+            vec![caller.to_owned()],
+        )
+        .await
+        .map_err(script_error)?;
+
+    Ok(())
+}
+
+/// Returns whether `caller` was blocked.
+pub(super) async fn unblock(
+    store: &RedisStore,
+    tenant_id: &str,
+    caller: &str,
+) -> error_stack::Result<bool, RedisError> {
+    let redis_conn = store.get_redis_conn();
+    let removed = redis_conn
+        .evaluate_redis_script::<_, i64>(
+            REMOVE_SCRIPT,
+            vec![prefixed_key(&redis_conn, &blocked_callers_key(tenant_id))],
+            vec![caller.to_owned()],
+        )
+        .await
+        .map_err(script_error)?;
+
+    Ok(removed == 1)
+}
+
+pub(super) async fn blocked_callers(
+    store: &RedisStore,
+    tenant_id: &str,
+) -> error_stack::Result<Vec<String>, RedisError> {
+    let redis_conn = store.get_redis_conn();
+    redis_conn
+        .evaluate_redis_script::<_, Vec<String>>(
+            MEMBERS_SCRIPT,
+            vec![prefixed_key(&redis_conn, &blocked_callers_key(tenant_id))],
+            Vec::<String>::new(),
+        )
+        .await
+        .map_err(script_error)
+}
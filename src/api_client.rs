@@ -1,3 +1,8 @@
+#[cfg(feature = "external_key_manager")]
+pub mod circuit_breaker;
+#[cfg(feature = "external_key_manager")]
+pub mod retry;
+
 use std::str::FromStr;
 
 use hyperswitch_masking::Maskable;
@@ -82,21 +87,64 @@ impl ApiClientConfig {
 
 #[derive(Clone)]
 pub struct ApiClient {
-    pub inner: reqwest::Client,
+    /// Swapped when the mTLS identity is rotated; requests in flight keep the client they started
+    /// with.
+    inner: std::sync::Arc<std::sync::RwLock<reqwest::Client>>,
+    #[cfg(feature = "external_key_manager")]
+    pub key_manager: std::sync::Arc<KeyManagerResilience>,
 }
 
-impl std::ops::Deref for ApiClient {
-    type Target = reqwest::Client;
-
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
+/// Retry policies and circuit breaker for calls to the external key manager. Built once and
+/// shared by every tenant, since they all talk to the same key manager.
+#[cfg(feature = "external_key_manager")]
+#[derive(Debug)]
+pub struct KeyManagerResilience {
+    pub policies: crate::config::KeyManagerClientConfig,
+    pub breaker: circuit_breaker::CircuitBreaker,
 }
 
 impl ApiClient {
     pub fn new(
         global_config: &GlobalConfig,
     ) -> Result<Self, error::ContainerError<error::ApiClientError>> {
+        Ok(Self {
+            inner: std::sync::Arc::new(std::sync::RwLock::new(Self::build_client(global_config)?)),
+            #[cfg(feature = "external_key_manager")]
+            key_manager: std::sync::Arc::new(KeyManagerResilience {
+                policies: global_config.key_manager_client.clone(),
+                breaker: circuit_breaker::CircuitBreaker::new(
+                    "external_key_manager",
+                    global_config.key_manager_client.circuit_breaker.clone(),
+                ),
+            }),
+        })
+    }
+
+    /// Rebuild the HTTP client from `global_config`, picking up a rotated mTLS identity or CA
+    /// certificate. Every clone of this client switches over.
+    pub fn reload(
+        &self,
+        global_config: &GlobalConfig,
+    ) -> Result<(), error::ContainerError<error::ApiClientError>> {
+        let client = Self::build_client(global_config)?;
+        *self
+            .inner
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = client;
+
+        Ok(())
+    }
+
+    fn client(&self) -> reqwest::Client {
+        self.inner
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    fn build_client(
+        global_config: &GlobalConfig,
+    ) -> Result<reqwest::Client, error::ContainerError<error::ApiClientError>> {
         #[cfg_attr(not(feature = "external_key_manager"), expect(unused_mut))]
         let mut client = reqwest::Client::builder()
             .redirect(reqwest::redirect::Policy::none())
@@ -137,13 +185,20 @@ impl ApiClient {
             }
         }
 
-        let client = client
+        client
             .build()
-            .change_error(error::ApiClientError::ClientConstructionFailed)?;
-
-        Ok(Self { inner: client })
+            .change_error(error::ApiClientError::ClientConstructionFailed)
     }
 
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            purpose = purpose,
+            method = <&'static str>::from(method),
+            host = tracing::field::Empty,
+            status_code = tracing::field::Empty,
+        )
+    )]
     pub async fn send_request<T>(
         &self,
         purpose: &'static str,
@@ -159,11 +214,14 @@ impl ApiClient {
             reqwest::Url::parse(&url).change_error(error::ApiClientError::UrlEncodingFailed)?;
 
         let host = url.host_str().unwrap_or("UNKNOWN").to_string();
-        let headers = headers.construct_header_map()?;
+        tracing::Span::current().record("host", host.as_str());
+        let mut headers = headers.construct_header_map()?;
+        crate::observability::traces::inject_current_context(&mut headers);
 
+        let client = self.client();
         let request_builder = match method {
-            Method::Get => self.get(url),
-            Method::Post => self.post(url).json(&request_body),
+            Method::Get => client.get(url),
+            Method::Post => client.post(url).json(&request_body),
         };
 
         crate::observability::metrics::EXTERNAL_HTTP_REQUEST_COUNT.add(
@@ -196,6 +254,7 @@ impl ApiClient {
         let status_code = status_code
             .map(|s| s.to_string())
             .unwrap_or_else(|| "UNKNOWN".to_string());
+        tracing::Span::current().record("status_code", status_code.as_str());
 
         crate::observability::metrics::EXTERNAL_HTTP_REQUEST_DURATION.record(
             start.elapsed().as_secs_f64(),
@@ -0,0 +1,307 @@
+//! Consecutive-failure circuit breaker for calls to an external service.
+//!
+//! `failure_threshold` consecutive failures open the breaker; while open, calls fail fast
+//! without touching the network. After `open_duration` a single probe is let through
+//! (half-open): its success closes the breaker, its failure re-opens it.
+
+use std::{
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{logger, observability::metrics};
+
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    // consecutive failed calls that open the breaker
+    pub failure_threshold: u32,
+    // time the breaker stays open before a probe call is allowed (in secs)
+    pub open_duration: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 5,
+            open_duration: 30,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn validate(&self, section: &str) -> Result<(), crate::error::ConfigurationError> {
+        if self.enabled && (self.failure_threshold == 0 || self.open_duration == 0) {
+            return Err(
+                crate::error::ConfigurationError::InvalidConfigurationValueError(format!(
+                    "{section}.failure_threshold and {section}.open_duration must be greater than 0"
+                )),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, strum::IntoStaticStr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum BreakerState {
+    #[default]
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+crate::impl_metric_value_from!(BreakerState);
+
+impl BreakerState {
+    /// Value exported on the `circuit_breaker.state` gauge.
+    fn gauge_value(self) -> u64 {
+        match self {
+            Self::Closed => 0,
+            Self::Open => 1,
+            Self::HalfOpen => 2,
+        }
+    }
+}
+
+/// Point-in-time view of a breaker, reported in diagnostics.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct BreakerSnapshot {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    /// Seconds until a probe is allowed; only set while open.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// Start of the in-flight half-open probe. A probe whose caller went away never reports
+    /// back, so a new one is allowed once `open_duration` has passed.
+    probe_started_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    service: &'static str,
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(service: &'static str, config: CircuitBreakerConfig) -> Self {
+        metrics::CIRCUIT_BREAKER_STATE.record(
+            BreakerState::Closed.gauge_value(),
+            crate::metric_attributes!(("service", service)),
+        );
+
+        Self {
+            service,
+            config,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_started_at: None,
+            }),
+        }
+    }
+
+    fn open_duration(&self) -> Duration {
+        Duration::from_secs(self.config.open_duration)
+    }
+
+    /// Whether a call may go out now. Rejections are counted on
+    /// `circuit_breaker.rejected.count`.
+    pub fn try_acquire(&self) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+
+        let allowed = match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open => {
+                let cooled_down = inner
+                    .opened_at
+                    .is_none_or(|opened_at| now.duration_since(opened_at) >= self.open_duration());
+                if cooled_down {
+                    self.transition(&mut inner, BreakerState::HalfOpen);
+                    inner.probe_started_at = Some(now);
+                }
+                cooled_down
+            }
+            BreakerState::HalfOpen => {
+                let probe_stale = inner.probe_started_at.is_none_or(|started_at| {
+                    now.duration_since(started_at) >= self.open_duration()
+                });
+                if probe_stale {
+                    inner.probe_started_at = Some(now);
+                }
+                probe_stale
+            }
+        };
+
+        if !allowed {
+            metrics::CIRCUIT_BREAKER_REJECTED_COUNT
+                .add(1, crate::metric_attributes!(("service", self.service)));
+        }
+
+        allowed
+    }
+
+    /// The service answered; closes a half-open breaker.
+    pub fn record_success(&self) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.consecutive_failures = 0;
+        inner.probe_started_at = None;
+        if inner.state != BreakerState::Closed {
+            self.transition(&mut inner, BreakerState::Closed);
+        }
+    }
+
+    /// The service failed or did not answer in time.
+    pub fn record_failure(&self) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        inner.probe_started_at = None;
+
+        let should_open = match inner.state {
+            BreakerState::HalfOpen => true,
+            BreakerState::Closed => inner.consecutive_failures >= self.config.failure_threshold,
+            BreakerState::Open => false,
+        };
+        if should_open {
+            inner.opened_at = Some(Instant::now());
+            self.transition(&mut inner, BreakerState::Open);
+        }
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let retry_after_secs = match (inner.state, inner.opened_at) {
+            (BreakerState::Open, Some(opened_at)) => Some(
+                self.open_duration()
+                    .saturating_sub(opened_at.elapsed())
+                    .as_secs(),
+            ),
+            _ => None,
+        };
+
+        BreakerSnapshot {
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_after_secs,
+        }
+    }
+
+    fn transition(&self, inner: &mut BreakerInner, to: BreakerState) {
+        let from = inner.state;
+        inner.state = to;
+
+        match to {
+            BreakerState::Open => logger::warn!(
+                service = self.service,
+                ?from,
+                consecutive_failures = inner.consecutive_failures,
+                "circuit breaker opened"
+            ),
+            BreakerState::HalfOpen | BreakerState::Closed => logger::info!(
+                service = self.service,
+                ?from,
+                ?to,
+                "circuit breaker state changed"
+            ),
+        }
+
+        metrics::CIRCUIT_BREAKER_TRANSITION_COUNT.add(
+            1,
+            crate::metric_attributes!(("service", self.service), ("state", to)),
+        );
+        metrics::CIRCUIT_BREAKER_STATE.record(
+            to.gauge_value(),
+            crate::metric_attributes!(("service", self.service)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(open_duration: u64) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "test",
+            CircuitBreakerConfig {
+                enabled: true,
+                failure_threshold: 2,
+                open_duration,
+            },
+        )
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = breaker(30);
+
+        breaker.record_failure();
+        assert!(breaker.try_acquire());
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.snapshot().state, BreakerState::Closed);
+
+        breaker.record_failure();
+        assert_eq!(breaker.snapshot().state, BreakerState::Open);
+        assert!(!breaker.try_acquire());
+    }
+
+    #[test]
+    fn half_open_probe_closes_or_reopens() {
+        let breaker = breaker(0);
+        breaker.record_failure();
+        breaker.record_failure();
+
+        // Zero cool-down: the next call is the probe.
+        assert!(breaker.try_acquire());
+        assert_eq!(breaker.snapshot().state, BreakerState::HalfOpen);
+        breaker.record_failure();
+        assert_eq!(breaker.snapshot().state, BreakerState::Open);
+
+        assert!(breaker.try_acquire());
+        breaker.record_success();
+        assert_eq!(breaker.snapshot().state, BreakerState::Closed);
+        assert_eq!(breaker.snapshot().consecutive_failures, 0);
+    }
+
+    #[test]
+    fn disabled_breaker_never_rejects() {
+        let breaker = CircuitBreaker::new(
+            "test",
+            CircuitBreakerConfig {
+                enabled: false,
+                ..Default::default()
+            },
+        );
+        for _ in 0..10 {
+            breaker.record_failure();
+        }
+        assert!(breaker.try_acquire());
+    }
+}
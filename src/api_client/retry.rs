@@ -0,0 +1,87 @@
+//! Retry policy for outgoing requests: bounded attempts, per-attempt timeout and capped
+//! exponential backoff with full jitter.
+
+use std::time::Duration;
+
+use ring::rand::SecureRandom;
+
+use crate::error;
+
+#[derive(Clone, Copy, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // total attempts, including the first one
+    pub max_attempts: u32,
+    // backoff ceiling before the first retry, doubled on each further retry (in millis)
+    pub base_delay_ms: u64,
+    // upper bound of the backoff ceiling (in millis)
+    pub max_delay_ms: u64,
+    // timeout of a single attempt (in millis)
+    pub timeout_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::single_attempt()
+    }
+}
+
+impl RetryPolicy {
+    /// One attempt, no retries.
+    pub const fn single_attempt() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay_ms: 50,
+            max_delay_ms: 1000,
+            timeout_ms: 5000,
+        }
+    }
+
+    pub const fn with_max_attempts(self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..self
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Delay before retry number `retry` (1-based): uniformly random between zero and
+    /// `min(max_delay_ms, base_delay_ms * 2^(retry - 1))`, so callers that failed together
+    /// do not come back together.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay_ms
+            .saturating_mul(
+                1_u64
+                    .checked_shl(retry.saturating_sub(1))
+                    .unwrap_or(u64::MAX),
+            )
+            .min(self.max_delay_ms);
+
+        let mut random = [0_u8; 8];
+        let jittered = match ring::rand::SystemRandom::new().fill(&mut random) {
+            Ok(()) => u64::from_be_bytes(random) % ceiling.saturating_add(1),
+            Err(_) => ceiling,
+        };
+
+        Duration::from_millis(jittered)
+    }
+
+    pub fn validate(&self, section: &str) -> Result<(), error::ConfigurationError> {
+        if self.max_attempts == 0 || self.timeout_ms == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                format!("{section}.max_attempts and {section}.timeout_ms must be greater than 0"),
+            ));
+        }
+        if self.base_delay_ms > self.max_delay_ms {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                format!("{section}.base_delay_ms must not exceed {section}.max_delay_ms"),
+            ));
+        }
+
+        Ok(())
+    }
+}
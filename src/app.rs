@@ -17,13 +17,14 @@ use crate::middleware as custom_middleware;
 #[cfg(feature = "caching")]
 use crate::storage::caching::Caching;
 use crate::{
+    anomaly,
     api_client::ApiClient,
     config::{self, GlobalConfig, TenantConfig},
-    error, logger, observability,
+    crypto, error, idempotency, logger, observability,
     routes::{self, routes_v2},
     storage,
     tenant::GlobalAppState,
-    utils,
+    usage, utils,
 };
 
 #[cfg(feature = "caching")]
@@ -45,6 +46,12 @@ pub struct TenantAppState {
     pub api_client: ApiClient,
     #[cfg(feature = "redis")]
     pub redis: Option<storage::redis::RedisStore>,
+    /// `None` unless `dek_cache.enabled`.
+    #[cfg(feature = "caching")]
+    pub dek_cache: Option<crypto::keymanager::dek_cache::DekCache>,
+    /// `None` unless `anomaly_detection.enabled`.
+    pub anomaly_detector: Option<Arc<anomaly::RetrievalDetector>>,
+    pub quota_usage: usage::QuotaUsage,
 }
 
 #[allow(clippy::expect_used)]
@@ -70,6 +77,8 @@ impl TenantAppState {
             &tenant_config.tenant_secrets.schema,
             runtime_config_manager,
             #[cfg(feature = "kv")]
+            &tenant_config.tenant_id,
+            #[cfg(feature = "kv")]
             tenant_redis.clone(),
             #[cfg(feature = "kv")]
             &global_config.kv,
@@ -77,7 +86,16 @@ impl TenantAppState {
         .await
         .map(
             #[cfg(feature = "caching")]
-            Caching::implement_cache(&global_config.cache),
+            Caching::implement_cache(
+                &global_config.cache,
+                #[cfg(feature = "redis")]
+                tenant_redis.clone().map(|redis| {
+                    storage::caching::invalidation::Publisher::new(
+                        tenant_config.tenant_id.clone(),
+                        redis,
+                    )
+                }),
+            ),
             #[cfg(not(feature = "caching"))]
             std::convert::identity,
         )
@@ -88,11 +106,98 @@ impl TenantAppState {
             api_client,
             #[cfg(feature = "redis")]
             redis: tenant_redis,
+            #[cfg(feature = "caching")]
+            dek_cache: crypto::keymanager::dek_cache::DekCache::new(&global_config.dek_cache),
+            anomaly_detector: anomaly::RetrievalDetector::new(&global_config.anomaly_detection)
+                .map(Arc::new),
+            quota_usage: usage::QuotaUsage::new(&global_config.usage),
             config: tenant_config,
         })
     }
 }
 
+#[cfg(test)]
+impl TenantAppState {
+    /// State of the `public` tenant of `config/development.toml`, after `configure` adjusted the
+    /// configuration. Talks to the development database; Redis is left out.
+    #[allow(clippy::unwrap_used)]
+    pub(crate) async fn for_tests(configure: impl FnOnce(&mut GlobalConfig)) -> Arc<Self> {
+        let mut global_config = GlobalConfig::new_with_config_path(Some(
+            config::workspace_path().join("config/development.toml"),
+        ))
+        .unwrap();
+        configure(&mut global_config);
+
+        let tenant_config = TenantConfig::from_global_config(&global_config, "public".to_owned());
+        let runtime_config_manager = Arc::new(
+            crate::runtime_config::RuntimeConfigManager::new(
+                &config::RuntimeConfig::Disabled,
+                global_config.api_client.client_idle_timeout,
+                global_config.api_client.pool_max_idle_per_host,
+            )
+            .unwrap(),
+        );
+
+        Arc::new(
+            Self::new(
+                &global_config,
+                tenant_config,
+                ApiClient::new(&global_config).unwrap(),
+                #[cfg(feature = "redis")]
+                None,
+                runtime_config_manager,
+            )
+            .await
+            .unwrap(),
+        )
+    }
+}
+
+#[cfg(feature = "caching")]
+impl TenantAppState {
+    /// Evict `key` from this pod's cache, or flush the whole cache when `key` is `None`, and
+    /// publish the same to every other pod.
+    pub async fn invalidate_cache(
+        &self,
+        cache_name: storage::caching::CacheName,
+        key: Option<String>,
+    ) {
+        let invalidation = storage::caching::invalidation::CacheInvalidation::new(
+            self.config.tenant_id.clone(),
+            cache_name,
+            key,
+        );
+        self.apply_invalidation(&invalidation).await;
+        self.db.publish(&invalidation).await;
+    }
+
+    /// Flush every cache of this pod, without telling the other pods. Used when invalidations
+    /// published by them may have been missed.
+    pub async fn flush_local_caches(&self) {
+        for cache_name in storage::caching::CacheName::ALL {
+            self.apply_invalidation(&storage::caching::invalidation::CacheInvalidation::new(
+                self.config.tenant_id.clone(),
+                cache_name,
+                None,
+            ))
+            .await;
+        }
+    }
+
+    /// Apply an invalidation to this pod's caches.
+    pub async fn apply_invalidation(
+        &self,
+        invalidation: &storage::caching::invalidation::CacheInvalidation,
+    ) {
+        match invalidation.cache_name {
+            storage::caching::CacheName::Dek => {
+                self.evict_dek(invalidation.key.as_deref()).await;
+            }
+            _ => self.db.apply(invalidation).await,
+        }
+    }
+}
+
 /// Temporary State to store keys
 #[cfg(feature = "key_custodian")]
 #[derive(Default, Debug)]
@@ -122,7 +227,7 @@ impl MakeRequestId for MakeUuidV7 {
 }
 
 #[allow(clippy::expect_used)]
-async fn shutdown_signal() {
+pub(crate) async fn shutdown_signal() {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
             .await
@@ -161,32 +266,27 @@ pub async fn server_builder(
         .runtime_config_manager
         .spawn_prefetch_task();
 
+    #[cfg(all(feature = "caching", feature = "redis"))]
+    global_app_state.spawn_cache_invalidation_listener().await;
+
+    #[cfg(feature = "redis")]
+    anomaly::spawn_block_listener(&global_app_state).await;
+
     let socket_addr = std::net::SocketAddr::new(
         global_app_state.global_config.server.host.parse()?,
         global_app_state.global_config.server.port,
     );
     let router = axum::Router::new()
-        .nest(
-            "/data",
-            routes::data::serve(
-                #[cfg(feature = "limit")]
-                global_app_state.clone(),
-            ),
-        )
-        .nest(
-            "/cards",
-            routes::data::serve(
-                #[cfg(feature = "limit")]
-                global_app_state.clone(),
-            ),
-        );
+        .nest("/data", routes::data::serve())
+        .nest("/cards", routes::data::serve());
 
     // v2 routes
     #[cfg_attr(
         all(
             not(feature = "middleware"),
             not(feature = "external_key_manager"),
-            not(feature = "key_custodian")
+            not(feature = "key_custodian"),
+            not(feature = "caching")
         ),
         allow(unused_mut)
     )]
@@ -197,6 +297,9 @@ pub async fn server_builder(
                 .route("/delete", post(routes_v2::data::delete_data))
                 .route("/add", post(routes_v2::data::add_data))
                 .route("/retrieve", post(routes_v2::data::retrieve_data))
+                .route("/ttl", post(routes_v2::data::update_data_ttl))
+                .route("/list", post(routes_v2::data::list_data))
+                .route("/update", post(routes_v2::data::update_data))
                 .route(
                     "/fingerprint",
                     post(routes::data::get_or_insert_fingerprint),
@@ -204,7 +307,37 @@ pub async fn server_builder(
         )
         // Explicit provisioning endpoint. Config decides the backing table: `merchant` under the
         // internal key manager, `entity` under the external key manager.
-        .route("/entity", post(routes::entity::create_entity));
+        .route("/entity", post(routes::entity::create_entity))
+        .route("/admin/audit/query", post(routes::audit::query_audit_log))
+        .route(
+            "/admin/anomaly/blocked",
+            post(routes::anomaly::list_blocked_callers),
+        )
+        .route(
+            "/admin/anomaly/unblock",
+            post(routes::anomaly::unblock_caller),
+        );
+
+    #[cfg(feature = "caching")]
+    {
+        router = router.nest("/admin", routes::admin::serve());
+    }
+
+    // Inside the JWE middleware, so the decrypted body is hashed and the plaintext response is
+    // stored, encrypted with the tenant master key.
+    router = router.layer(axum::middleware::from_fn_with_state(
+        global_app_state.clone(),
+        idempotency::middleware,
+    ));
+
+    // Inside the JWE middleware, so the limiter reads the decrypted body.
+    #[cfg(feature = "limit")]
+    {
+        router = router.layer(axum::middleware::from_fn_with_state(
+            global_app_state.clone(),
+            crate::rate_limit::middleware,
+        ));
+    }
 
     #[cfg(feature = "middleware")]
     {
@@ -232,8 +365,16 @@ pub async fn server_builder(
 
     router = router.nest("/health", routes::health::serve());
 
+    idempotency::spawn_purger(&global_app_state);
+
     if metrics_handle.provider().is_some() {
-        router = router.layer(observability::HttpRequestMetricsLayer);
+        router = router.layer(observability::HttpRequestMetricsLayer::new(
+            global_app_state
+                .global_config
+                .tenant_secrets
+                .keys()
+                .cloned(),
+        ));
     }
 
     if let observability::MetricsHandle::Prometheus {
@@ -254,6 +395,7 @@ pub async fn server_builder(
                 .metrics
                 .background_metrics_collection_interval_secs(),
         );
+        usage::spawn_collector(&global_app_state);
     }
 
     router = router.layer(
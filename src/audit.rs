@@ -0,0 +1,577 @@
+//! Tamper-evident audit trail of data access.
+//!
+//! Handlers describe what they did as an [`AuditEvent`] and hand it to the [`AuditContext`] of
+//! the request. Events are queued to a single writer task per process, which numbers them,
+//! links each one to the hash of the previous one (see [`record`]) and writes the result to
+//! every configured sink, retrying failed writes. A sink that still misses a record gets a gap
+//! marker naming the records it lacks before anything else. The queue is bounded: when the
+//! sinks fall behind, handlers wait for a free slot rather than dropping events.
+
+pub mod record;
+mod sink;
+pub mod verify;
+
+use std::{sync::Arc, time::Duration};
+
+use error_stack::ResultExt;
+use hyperswitch_masking::{PeekInterface, Secret};
+use ring::hmac;
+use tokio::sync::mpsc;
+
+use self::{
+    record::{ChainState, SealedAuditRecord},
+    sink::AuditSink,
+};
+use crate::{
+    config::GlobalConfig, error, logger, observability::metrics,
+    runtime_config::RuntimeConfigManager,
+};
+
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    // events buffered between the handlers and the writer task
+    pub channel_capacity: usize,
+    pub sinks: Vec<AuditSinkConfig>,
+    /// Hex-encoded key (at least 32 bytes) the records are sealed with, resolved through
+    /// `secrets_management`. Whoever can write to the sinks must not be able to read it.
+    pub hmac_key: Secret<String>,
+    /// Chain this process appends to, kept across restarts. Defaults to the host name, the pod
+    /// name on Kubernetes; every concurrently running process needs its own.
+    pub chain_id: Option<String>,
+    // attempts at writing a record to a sink before it is declared missing there
+    pub write_attempts: u8,
+    // delay before the second attempt, doubled for every further one (in millis)
+    pub retry_backoff_ms: u64,
+    // upper bound of the delay between attempts (in millis)
+    pub max_retry_backoff_ms: u64,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel_capacity: 1024,
+            sinks: Vec::new(),
+            hmac_key: Secret::default(),
+            chain_id: None,
+            write_attempts: 3,
+            retry_backoff_ms: 100,
+            max_retry_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl AuditConfig {
+    pub fn validate(&self) -> Result<(), error::ConfigurationError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.channel_capacity == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "audit.channel_capacity must be greater than 0".into(),
+            ));
+        }
+        if self.sinks.is_empty() {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "audit.sinks must not be empty when audit is enabled".into(),
+            ));
+        }
+        if self.hmac_key.peek().is_empty() {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "audit.hmac_key must be set when audit is enabled".into(),
+            ));
+        }
+        if self
+            .chain_id
+            .as_ref()
+            .is_some_and(|chain_id| chain_id.len() > 64)
+        {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "audit.chain_id must not be longer than 64 characters".into(),
+            ));
+        }
+        if self.write_attempts == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "audit.write_attempts must be greater than 0".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The key records are sealed and verified with. Only valid once secrets are resolved.
+    pub fn hmac_key(&self) -> Result<hmac::Key, error::ConfigurationError> {
+        match hex::decode(self.hmac_key.peek()) {
+            Ok(key) if key.len() >= 32 => Ok(hmac::Key::new(hmac::HMAC_SHA256, &key)),
+            _ => Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "audit.hmac_key must be at least 32 hex-encoded bytes".into(),
+            )),
+        }
+    }
+
+    /// `chain_id`, or the host name when unset. `None` when neither is known.
+    fn chain_id(&self) -> Option<String> {
+        self.chain_id
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .filter(|chain_id| !chain_id.trim().is_empty())
+    }
+}
+
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditSinkConfig {
+    /// One JSON event per line, appended to `path`.
+    File { path: std::path::PathBuf },
+    /// The append-only `audit_log` table in `schema` of the primary database.
+    Postgres {
+        #[serde(default = "default_audit_schema")]
+        schema: String,
+    },
+    /// `XADD` to the stream `stream` on the shared Redis.
+    #[cfg(feature = "redis")]
+    RedisStream { stream: String },
+}
+
+fn default_audit_schema() -> String {
+    "public".to_string()
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, strum::IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditAction {
+    CardAdd,
+    CardRetrieve,
+    CardDelete,
+    CardTtlUpdate,
+    Fingerprint,
+    DataAdd,
+    DataRetrieve,
+    DataDelete,
+    DataUpdate,
+    DataTtlUpdate,
+    DataList,
+    EntityCreate,
+    KeyTransfer,
+    CustodianKey1,
+    CustodianKey2,
+    CustodianUnlock,
+    AuditQuery,
+    RetrievalAnomaly,
+    AnomalyUnblock,
+    CacheInvalidate,
+    CacheFlush,
+    /// Written by the audit writer when a sink missed records, see [`record::AuditRecord`].
+    AuditGap,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, strum::IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+/// What a handler did, before it is placed on the chain.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub action: AuditAction,
+    pub merchant_id: Option<String>,
+    pub resource_id: Option<String>,
+    pub error: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction) -> Self {
+        Self {
+            action,
+            merchant_id: None,
+            resource_id: None,
+            error: None,
+        }
+    }
+
+    pub fn merchant_id(mut self, merchant_id: impl Into<String>) -> Self {
+        self.merchant_id = Some(merchant_id.into());
+        self
+    }
+
+    pub fn resource_id(mut self, resource_id: Option<impl Into<String>>) -> Self {
+        self.resource_id = resource_id.map(Into::into);
+        self
+    }
+
+    /// Mark the event failed when `result` is an error. Only the error message is kept: it
+    /// never carries request data.
+    pub fn result<T, E>(self, result: &Result<T, error::ContainerError<E>>) -> Self
+    where
+        E: std::fmt::Display + Send + Sync + 'static,
+    {
+        match result {
+            Ok(_) => self,
+            Err(err) => self.failed(err.get_inner().to_string()),
+        }
+    }
+
+    pub fn failed(mut self, reason: impl Into<String>) -> Self {
+        self.error = Some(reason.into());
+        self
+    }
+}
+
+/// An event together with the request it came from.
+#[derive(Debug)]
+struct QueuedEvent {
+    event: AuditEvent,
+    tenant_id: Option<String>,
+    request_id: Option<String>,
+    caller_role: Option<String>,
+    timestamp: time::PrimitiveDateTime,
+}
+
+/// Handle onto the audit writer task.
+#[derive(Clone)]
+pub struct Auditor {
+    sender: mpsc::Sender<QueuedEvent>,
+    /// Storage of the postgres sinks; audit queries read from the first one.
+    storages: Vec<crate::storage::Storage>,
+}
+
+impl std::fmt::Debug for Auditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Auditor")
+            .field("queryable", &!self.storages.is_empty())
+            .finish()
+    }
+}
+
+impl Auditor {
+    /// Open the configured sinks and start the writer task. `None` when auditing is disabled.
+    pub async fn new(
+        global_config: &GlobalConfig,
+        runtime_config_manager: Arc<RuntimeConfigManager>,
+        #[cfg(feature = "redis")] redis: Option<&crate::storage::redis::RedisStore>,
+    ) -> error_stack::Result<Option<Self>, error::ConfigurationError> {
+        let config = &global_config.audit;
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let mut sinks = Vec::with_capacity(config.sinks.len());
+        for sink_config in &config.sinks {
+            sinks.push(
+                AuditSink::open(
+                    sink_config,
+                    global_config,
+                    runtime_config_manager.clone(),
+                    #[cfg(feature = "redis")]
+                    redis,
+                )
+                .await?,
+            );
+        }
+
+        let storages = sinks
+            .iter()
+            .filter_map(AuditSink::storage)
+            .cloned()
+            .collect();
+        let key = config.hmac_key()?;
+        let (chain, sinks) = resume_chain(config, key, sinks).await?;
+        logger::info!(
+            chain_id = %chain.chain_id(),
+            next_sequence = chain.next_sequence(),
+            sinks = sinks.len(),
+            "audit trail started"
+        );
+
+        let (sender, receiver) = mpsc::channel(config.channel_capacity);
+        tokio::spawn(run_writer(receiver, chain, sinks, RetryPolicy::new(config)));
+
+        Ok(Some(Self { sender, storages }))
+    }
+
+    /// The `audit_log` table, when a postgres sink is configured.
+    pub(crate) fn storage(&self) -> Option<&crate::storage::Storage> {
+        self.storages.first()
+    }
+
+    /// Storage of every postgres sink, to reconnect them after a credential rotation.
+    pub(crate) fn storages(&self) -> &[crate::storage::Storage] {
+        &self.storages
+    }
+
+    async fn submit(&self, queued: QueuedEvent) {
+        if let Err(err) = self.sender.send(queued).await {
+            logger::error!(action = ?err.0.event.action, "audit writer stopped, event lost");
+        }
+
+        metrics::AUDIT_QUEUE_CAPACITY.record(
+            u64::try_from(self.sender.capacity()).unwrap_or(u64::MAX),
+            &[],
+        );
+    }
+}
+
+/// Pick up the chain of this process where the sinks left it before the last restart, and mark
+/// every sink that is behind the others as missing the records it lacks.
+async fn resume_chain(
+    config: &AuditConfig,
+    key: hmac::Key,
+    sinks: Vec<AuditSink>,
+) -> error_stack::Result<(ChainState, Vec<SinkState>), error::ConfigurationError> {
+    let mut sinks = sinks.into_iter().map(SinkState::new).collect::<Vec<_>>();
+
+    // Without a stable id or a sink to read the head back from, every start is a new chain.
+    let Some(chain_id) = config.chain_id() else {
+        return Ok((
+            ChainState::new(uuid::Uuid::now_v7().to_string(), key),
+            sinks,
+        ));
+    };
+
+    let mut heads = Vec::with_capacity(sinks.len());
+    for state in &sinks {
+        let head = state.sink.last_record(&chain_id).await.change_context(
+            error::ConfigurationError::InvalidConfigurationValueError(format!(
+                "failed to read the head of audit chain {chain_id} back"
+            )),
+        )?;
+        heads.push(head);
+    }
+
+    if heads.iter().all(Option::is_none) {
+        logger::warn!(%chain_id, "no audit sink can be read back; starting a new chain");
+        return Ok((
+            ChainState::new(uuid::Uuid::now_v7().to_string(), key),
+            sinks,
+        ));
+    }
+
+    let mut chain = ChainState::new(chain_id, key);
+    let head = heads
+        .iter()
+        .flatten()
+        .flatten()
+        .max_by_key(|head| head.record.sequence);
+    if let Some(head) = head {
+        if !head.is_intact(chain.key()) {
+            logger::warn!(
+                sequence = head.record.sequence,
+                "audit chain head does not match its hash; continuing after it"
+            );
+        }
+        chain.resume(head);
+    }
+
+    let next_sequence = chain.next_sequence();
+    for (state, head) in sinks.iter_mut().zip(&heads) {
+        let Some(head) = head else {
+            continue;
+        };
+        let written = head
+            .as_ref()
+            .map_or(0, |head| head.record.sequence.saturating_add(1));
+        if written < next_sequence {
+            state.missing_from = Some(written);
+        }
+    }
+
+    Ok((chain, sinks))
+}
+
+/// How often and how fast the writer retries a sink.
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    attempts: u8,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    fn new(config: &AuditConfig) -> Self {
+        Self {
+            attempts: config.write_attempts,
+            backoff: Duration::from_millis(config.retry_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_retry_backoff_ms),
+        }
+    }
+
+    fn next_backoff(&self, backoff: Duration) -> Duration {
+        backoff.saturating_mul(2).min(self.max_backoff)
+    }
+}
+
+/// A sink with the records it is missing.
+struct SinkState {
+    sink: AuditSink,
+    /// First record the sink lacks. Only a gap marker is offered to the sink until one lands.
+    missing_from: Option<u64>,
+    /// When to offer the next gap marker.
+    retry_at: tokio::time::Instant,
+    backoff: Duration,
+}
+
+impl SinkState {
+    fn new(sink: AuditSink) -> Self {
+        Self {
+            sink,
+            missing_from: None,
+            retry_at: tokio::time::Instant::now(),
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Write `sealed`, trying up to `attempts` times. Returns whether it was written.
+    async fn write(&self, sealed: &SealedAuditRecord, attempts: u8, retry: &RetryPolicy) -> bool {
+        let mut backoff = retry.backoff;
+        for attempt in 1..=attempts {
+            let result = self.sink.write(sealed).await;
+            metrics::AUDIT_EVENT_COUNT.add(
+                1,
+                crate::metric_attributes!(
+                    ("sink", self.sink.name()),
+                    (
+                        "outcome",
+                        if result.is_ok() { "success" } else { "failure" }
+                    )
+                ),
+            );
+
+            match result {
+                Ok(()) => return true,
+                Err(err) => {
+                    logger::error!(
+                        ?err,
+                        sink = self.sink.name(),
+                        sequence = sealed.record.sequence,
+                        attempt,
+                        "Failed to write audit event"
+                    );
+                    if attempt < attempts {
+                        tokio::time::sleep(backoff).await;
+                        backoff = retry.next_backoff(backoff);
+                    }
+                }
+            }
+        }
+
+        false
+    }
+}
+
+async fn run_writer(
+    mut receiver: mpsc::Receiver<QueuedEvent>,
+    mut chain: ChainState,
+    mut sinks: Vec<SinkState>,
+    retry: RetryPolicy,
+) {
+    while let Some(queued) = receiver.recv().await {
+        match chain.seal(queued) {
+            Ok(sealed) => write_to_healthy(&mut sinks, None, &sealed, &retry).await,
+            Err(err) => logger::error!(?err, "Failed to seal audit event"),
+        }
+
+        close_gaps(&mut chain, &mut sinks, &retry).await;
+    }
+}
+
+/// Write `sealed` to every sink, except `skip`, that is not missing records. A sink that still
+/// fails after every attempt is marked as missing it.
+async fn write_to_healthy(
+    sinks: &mut [SinkState],
+    skip: Option<usize>,
+    sealed: &SealedAuditRecord,
+    retry: &RetryPolicy,
+) {
+    for (index, state) in sinks.iter_mut().enumerate() {
+        if Some(index) == skip || state.missing_from.is_some() {
+            continue;
+        }
+        if !state.write(sealed, retry.attempts, retry).await {
+            state.missing_from = Some(sealed.record.sequence);
+            state.retry_at = tokio::time::Instant::now();
+            state.backoff = retry.backoff;
+        }
+    }
+}
+
+/// Append a gap marker for every sink missing records whose retry is due. The marker goes to
+/// that sink, once, and like any other record to every healthy sink; once it lands, the sink
+/// gets every record again and the verifier accepts the gap it declares.
+async fn close_gaps(chain: &mut ChainState, sinks: &mut [SinkState], retry: &RetryPolicy) {
+    let now = tokio::time::Instant::now();
+
+    for index in 0..sinks.len() {
+        let Some(state) = sinks.get(index) else {
+            continue;
+        };
+        let Some(missing_from) = state.missing_from else {
+            continue;
+        };
+        if state.retry_at > now {
+            continue;
+        }
+
+        let marker = match chain.seal_gap(state.sink.name(), missing_from) {
+            Ok(marker) => marker,
+            Err(err) => {
+                logger::error!(?err, "Failed to seal audit gap marker");
+                return;
+            }
+        };
+
+        if let Some(state) = sinks.get_mut(index) {
+            if state.write(&marker, 1, retry).await {
+                logger::warn!(
+                    sink = state.sink.name(),
+                    missing_from,
+                    missing_to = marker.record.sequence.saturating_sub(1),
+                    "audit sink recovered; gap marked"
+                );
+                state.missing_from = None;
+            } else {
+                state.retry_at = tokio::time::Instant::now() + state.backoff;
+                state.backoff = retry.next_backoff(state.backoff);
+            }
+        }
+
+        write_to_healthy(sinks, Some(index), &marker, retry).await;
+    }
+}
+
+/// Request metadata attached to every event a handler records.
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub(crate) auditor: Option<Auditor>,
+    pub(crate) tenant_id: Option<String>,
+    pub(crate) request_id: Option<String>,
+    pub(crate) caller_role: Option<String>,
+}
+
+impl AuditContext {
+    /// Queue `event`; waits while the queue is full. A no-op when auditing is disabled.
+    pub async fn record(&self, event: AuditEvent) {
+        let Some(auditor) = &self.auditor else {
+            return;
+        };
+
+        auditor
+            .submit(QueuedEvent {
+                event,
+                tenant_id: self.tenant_id.clone(),
+                request_id: self.request_id.clone(),
+                caller_role: self.caller_role.clone(),
+                timestamp: crate::utils::date_time::now(),
+            })
+            .await;
+    }
+}
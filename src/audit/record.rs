@@ -0,0 +1,260 @@
+//! Shape and hashing of audit records.
+//!
+//! Each writer appends to one chain, identified by a `chain_id` that survives restarts. A
+//! record's `hash` is the hex HMAC-SHA256, keyed with `audit.hmac_key`, of the JSON
+//! serialization of the record without the hash, and that JSON includes `prev_hash`, the hash
+//! of the record before it. Changing, removing or reordering any record therefore breaks every
+//! link after it, and without the key, which the sinks never hold, the links cannot be redone.
+
+use ring::hmac;
+
+use super::{AuditAction, AuditEvent, AuditOutcome, QueuedEvent};
+
+/// `prev_hash` of the first record of a chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AuditRecord {
+    pub chain_id: String,
+    /// Position in the chain, starting at 0.
+    pub sequence: u64,
+    #[serde(with = "crate::utils::primitive_datetime_serde::iso8601")]
+    pub timestamp: time::PrimitiveDateTime,
+    pub tenant_id: Option<String>,
+    pub request_id: Option<String>,
+    pub caller_role: Option<String>,
+    pub action: AuditAction,
+    pub merchant_id: Option<String>,
+    /// `card_reference` or `vault_id` the action applied to, when there is one.
+    pub resource_id: Option<String>,
+    pub outcome: AuditOutcome,
+    pub error: Option<String>,
+    pub prev_hash: String,
+    /// Gap markers only: the sink named in `resource_id` is missing the records from this
+    /// sequence up to the marker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_from: Option<u64>,
+}
+
+impl AuditRecord {
+    /// Hex HMAC-SHA256 of the record's JSON serialization under `key`.
+    pub fn digest(&self, key: &hmac::Key) -> Result<String, serde_json::Error> {
+        let serialized = serde_json::to_vec(self)?;
+        Ok(hex::encode(hmac::sign(key, &serialized)))
+    }
+
+    /// Whether this record stands in for the records before it that a sink is missing, from
+    /// `missing_from` up to its own sequence.
+    pub fn declares_missing(&self, missing_from: u64) -> bool {
+        self.action == AuditAction::AuditGap
+            && self.missing_from == Some(missing_from)
+            && self.sequence > missing_from
+    }
+}
+
+/// A record with its hash, as written to the sinks.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SealedAuditRecord {
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    pub hash: String,
+}
+
+impl SealedAuditRecord {
+    /// Whether `hash` matches the record's content under `key`.
+    pub fn is_intact(&self, key: &hmac::Key) -> bool {
+        self.record
+            .digest(key)
+            .is_ok_and(|digest| digest == self.hash)
+    }
+}
+
+/// The tail of the chain this process is writing.
+#[derive(Debug)]
+pub(super) struct ChainState {
+    chain_id: String,
+    next_sequence: u64,
+    prev_hash: String,
+    key: hmac::Key,
+}
+
+impl ChainState {
+    pub(super) fn new(chain_id: String, key: hmac::Key) -> Self {
+        Self {
+            chain_id,
+            next_sequence: 0,
+            prev_hash: GENESIS_HASH.to_string(),
+            key,
+        }
+    }
+
+    pub(super) fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    pub(super) fn key(&self) -> &hmac::Key {
+        &self.key
+    }
+
+    pub(super) fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// Continue the chain after `head`, its last record written before a restart.
+    pub(super) fn resume(&mut self, head: &SealedAuditRecord) {
+        self.next_sequence = head.record.sequence.saturating_add(1);
+        self.prev_hash.clone_from(&head.hash);
+    }
+
+    /// Append a marker recording that `sink` is missing the records from `missing_from` on.
+    pub(super) fn seal_gap(
+        &mut self,
+        sink: &'static str,
+        missing_from: u64,
+    ) -> Result<SealedAuditRecord, serde_json::Error> {
+        let missing_to = self.next_sequence.saturating_sub(1);
+        let event = AuditEvent::new(AuditAction::AuditGap)
+            .resource_id(Some(sink))
+            .failed(format!(
+                "records {missing_from} to {missing_to} were not written to the {sink} sink"
+            ));
+
+        self.append(
+            QueuedEvent {
+                event,
+                tenant_id: None,
+                request_id: None,
+                caller_role: None,
+                timestamp: crate::utils::date_time::now(),
+            },
+            Some(missing_from),
+        )
+    }
+
+    /// Append `queued` to the chain.
+    pub(super) fn seal(
+        &mut self,
+        queued: QueuedEvent,
+    ) -> Result<SealedAuditRecord, serde_json::Error> {
+        self.append(queued, None)
+    }
+
+    fn append(
+        &mut self,
+        queued: QueuedEvent,
+        missing_from: Option<u64>,
+    ) -> Result<SealedAuditRecord, serde_json::Error> {
+        let QueuedEvent {
+            event,
+            tenant_id,
+            request_id,
+            caller_role,
+            timestamp,
+        } = queued;
+
+        let record = AuditRecord {
+            chain_id: self.chain_id.clone(),
+            sequence: self.next_sequence,
+            timestamp,
+            tenant_id,
+            request_id,
+            caller_role,
+            action: event.action,
+            merchant_id: event.merchant_id,
+            resource_id: event.resource_id,
+            outcome: if event.error.is_some() {
+                AuditOutcome::Failure
+            } else {
+                AuditOutcome::Success
+            },
+            error: event.error,
+            prev_hash: self.prev_hash.clone(),
+            missing_from,
+        };
+        let hash = record.digest(&self.key)?;
+
+        self.next_sequence = self.next_sequence.saturating_add(1);
+        self.prev_hash.clone_from(&hash);
+
+        Ok(SealedAuditRecord { record, hash })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+
+    use super::*;
+    use crate::audit::AuditEvent;
+
+    fn key() -> hmac::Key {
+        hmac::Key::new(hmac::HMAC_SHA256, &[7; 32])
+    }
+
+    fn chain() -> ChainState {
+        ChainState::new("chain_1".to_string(), key())
+    }
+
+    fn queued(action: AuditAction) -> QueuedEvent {
+        QueuedEvent {
+            event: AuditEvent::new(action).merchant_id("merchant_1"),
+            tenant_id: Some("public".to_string()),
+            request_id: Some("request_1".to_string()),
+            caller_role: None,
+            timestamp: crate::utils::date_time::now(),
+        }
+    }
+
+    #[test]
+    fn records_are_linked_and_survive_a_round_trip() {
+        let mut chain = chain();
+        let first = chain.seal(queued(AuditAction::CardAdd)).unwrap();
+        let second = chain.seal(queued(AuditAction::CardRetrieve)).unwrap();
+
+        assert_eq!(first.record.prev_hash, GENESIS_HASH);
+        assert_eq!(second.record.prev_hash, first.hash);
+        assert_eq!(second.record.sequence, 1);
+
+        let line = serde_json::to_string(&second).unwrap();
+        let parsed: SealedAuditRecord = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed, second);
+        assert!(parsed.is_intact(&key()));
+        assert!(!parsed.is_intact(&hmac::Key::new(hmac::HMAC_SHA256, &[8; 32])));
+    }
+
+    #[test]
+    fn edited_record_is_detected() {
+        let mut chain = chain();
+        let mut sealed = chain.seal(queued(AuditAction::CardDelete)).unwrap();
+
+        sealed.record.merchant_id = Some("merchant_2".to_string());
+        assert!(!sealed.is_intact(&key()));
+    }
+
+    #[test]
+    fn resumed_chain_continues_after_its_head() {
+        let mut chain = chain();
+        chain.seal(queued(AuditAction::CardAdd)).unwrap();
+        let head = chain.seal(queued(AuditAction::CardAdd)).unwrap();
+
+        let mut restarted = ChainState::new("chain_1".to_string(), key());
+        restarted.resume(&head);
+        let next = restarted.seal(queued(AuditAction::CardRetrieve)).unwrap();
+
+        assert_eq!(next.record.sequence, 2);
+        assert_eq!(next.record.prev_hash, head.hash);
+    }
+
+    #[test]
+    fn gap_marker_declares_the_missing_records() {
+        let mut chain = chain();
+        chain.seal(queued(AuditAction::CardAdd)).unwrap();
+        chain.seal(queued(AuditAction::CardAdd)).unwrap();
+        let marker = chain.seal_gap("file", 1).unwrap();
+
+        assert!(marker.is_intact(&key()));
+        assert!(marker.record.declares_missing(1));
+        assert!(!marker.record.declares_missing(0));
+        assert_eq!(marker.record.resource_id.as_deref(), Some("file"));
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    io::{BufRead, Write},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use error_stack::ResultExt;
+
+use super::{AuditSinkConfig, record::SealedAuditRecord};
+use crate::{
+    config::GlobalConfig,
+    error,
+    runtime_config::RuntimeConfigManager,
+    storage::{self, AuditInterface, types::AuditLogNew},
+};
+
+/// Destination of sealed audit records.
+pub(super) enum AuditSink {
+    File {
+        file: Arc<Mutex<std::fs::File>>,
+        path: std::path::PathBuf,
+    },
+    Postgres(storage::Storage),
+    #[cfg(feature = "redis")]
+    RedisStream {
+        store: storage::redis::RedisStore,
+        stream: String,
+    },
+}
+
+/// Primary-only storage onto the `audit_log` table in `schema`.
+pub(super) async fn open_storage(
+    global_config: &GlobalConfig,
+    schema: &str,
+    runtime_config_manager: Arc<RuntimeConfigManager>,
+) -> error_stack::Result<storage::Storage, error::ConfigurationError> {
+    storage::Storage::new(
+        &global_config.database,
+        None,
+        schema,
+        runtime_config_manager,
+        #[cfg(feature = "kv")]
+        "audit",
+        #[cfg(feature = "kv")]
+        None,
+        #[cfg(feature = "kv")]
+        &global_config.kv,
+    )
+    .await
+    .change_context(error::ConfigurationError::DatabaseError)
+}
+
+impl AuditSink {
+    pub(super) async fn open(
+        config: &AuditSinkConfig,
+        global_config: &GlobalConfig,
+        runtime_config_manager: Arc<RuntimeConfigManager>,
+        #[cfg(feature = "redis")] redis: Option<&storage::redis::RedisStore>,
+    ) -> error_stack::Result<Self, error::ConfigurationError> {
+        match config {
+            AuditSinkConfig::File { path } => {
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(error::ConfigurationError::from)
+                    .attach_printable_lazy(|| {
+                        format!("Failed to open audit file {}", path.display())
+                    })?;
+                Ok(Self::File {
+                    file: Arc::new(Mutex::new(file)),
+                    path: path.clone(),
+                })
+            }
+            AuditSinkConfig::Postgres { schema } => Ok(Self::Postgres(
+                open_storage(global_config, schema, runtime_config_manager).await?,
+            )),
+            #[cfg(feature = "redis")]
+            AuditSinkConfig::RedisStream { stream } => {
+                let store = redis.cloned().ok_or_else(|| {
+                    error::ConfigurationError::InvalidConfigurationValueError(
+                        "audit redis_stream sink requires a reachable redis".into(),
+                    )
+                })?;
+                Ok(Self::RedisStream {
+                    store,
+                    stream: stream.clone(),
+                })
+            }
+        }
+    }
+
+    /// The table behind a postgres sink, for reading the events back.
+    pub(super) fn storage(&self) -> Option<&storage::Storage> {
+        match self {
+            Self::Postgres(storage) => Some(storage),
+            _ => None,
+        }
+    }
+
+    /// The last record of `chain_id` written here, for the writer to continue the chain after
+    /// a restart. `None` from sinks that cannot be read back.
+    pub(super) async fn last_record(
+        &self,
+        chain_id: &str,
+    ) -> error_stack::Result<Option<Option<SealedAuditRecord>>, error::AuditError> {
+        let read_failed = error::AuditError::SinkReadFailed(self.name());
+
+        match self {
+            // The whole file is read once at startup; the chain's records are in sequence order.
+            Self::File { path, .. } => {
+                let path = path.clone();
+                let chain_id = chain_id.to_owned();
+                let last = tokio::task::spawn_blocking(move || {
+                    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+                    let mut last = None;
+                    for line in reader.lines() {
+                        if let Ok(sealed) = serde_json::from_str::<SealedAuditRecord>(&line?) {
+                            if sealed.record.chain_id == chain_id {
+                                last = Some(sealed);
+                            }
+                        }
+                    }
+                    Ok::<_, std::io::Error>(last)
+                })
+                .await
+                .change_context(read_failed)?
+                .change_context(read_failed)?;
+                Ok(Some(last))
+            }
+            Self::Postgres(storage) => {
+                let event = storage
+                    .find_last_audit_event(chain_id)
+                    .await
+                    .map_err(|err| err.error.change_context(read_failed))?;
+                event
+                    .map(|event| serde_json::from_str::<SealedAuditRecord>(&event.event))
+                    .transpose()
+                    .change_context(read_failed)
+                    .map(Some)
+            }
+            #[cfg(feature = "redis")]
+            Self::RedisStream { .. } => Ok(None),
+        }
+    }
+
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            Self::File { .. } => "file",
+            Self::Postgres(_) => "postgres",
+            #[cfg(feature = "redis")]
+            Self::RedisStream { .. } => "redis_stream",
+        }
+    }
+
+    pub(super) async fn write(
+        &self,
+        sealed: &SealedAuditRecord,
+    ) -> error_stack::Result<(), error::AuditError> {
+        let event =
+            serde_json::to_string(sealed).change_context(error::AuditError::SerializationFailed)?;
+        let write_failed = error::AuditError::SinkWriteFailed(self.name());
+
+        match self {
+            Self::File { file, .. } => {
+                let file = file.clone();
+                tokio::task::spawn_blocking(move || {
+                    let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+                    writeln!(file, "{event}")
+                })
+                .await
+                .change_context(write_failed)?
+                .change_context(write_failed)
+            }
+            Self::Postgres(storage) => {
+                let record = &sealed.record;
+                storage
+                    .insert_audit_event(AuditLogNew {
+                        chain_id: record.chain_id.clone(),
+                        sequence: i64::try_from(record.sequence).unwrap_or(i64::MAX),
+                        created_at: record.timestamp,
+                        tenant_id: record.tenant_id.clone(),
+                        action: <&str>::from(record.action).to_string(),
+                        outcome: <&str>::from(record.outcome).to_string(),
+                        merchant_id: record.merchant_id.clone(),
+                        resource_id: record.resource_id.clone(),
+                        request_id: record.request_id.clone(),
+                        prev_hash: record.prev_hash.clone(),
+                        hash: sealed.hash.clone(),
+                        event,
+                        caller_role: record.caller_role.clone(),
+                    })
+                    .await
+                    .map_err(|err| err.error.change_context(write_failed))
+            }
+            #[cfg(feature = "redis")]
+            Self::RedisStream { store, stream } => store
+                .stream_append(
+                    stream,
+                    vec![
+                        ("sequence", sealed.record.sequence.to_string()),
+                        ("event", event),
+                    ],
+                )
+                .await
+                .change_context(write_failed),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Walk audit chains and report the first broken link of each.
+//!
+//! Records are fed in the order they were written. Every chain must start at sequence 0 with
+//! [`GENESIS_HASH`] and continue without gaps, each record carrying the hash of the one before
+//! it and a hash matching its own content under the audit key. The only gaps accepted are those
+//! a gap marker written by the writer itself declares, right where the records are missing.
+
+use std::{collections::BTreeMap, io::BufRead, path::Path, sync::Arc};
+
+use error_stack::ResultExt;
+use ring::hmac;
+
+use super::record::{GENESIS_HASH, SealedAuditRecord};
+use crate::{
+    config::GlobalConfig,
+    error,
+    runtime_config::RuntimeConfigManager,
+    storage::{AuditInterface, types::AuditLogFilter},
+};
+
+/// Rows fetched per query when reading the `audit_log` table.
+const PAGE_SIZE: i64 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum BrokenLink {
+    /// The record's content no longer matches its hash.
+    HashMismatch { sequence: u64 },
+    /// The record does not point at the hash of the record before it.
+    PrevHashMismatch {
+        sequence: u64,
+        expected: String,
+        found: String,
+    },
+    /// Records are missing, duplicated or out of order.
+    SequenceGap { expected: u64, found: u64 },
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChainReport {
+    pub chain_id: String,
+    /// Records checked up to and including the first broken link.
+    pub records: u64,
+    /// Records the writer declared it failed to write here, see [`AuditRecord::declares_missing`].
+    ///
+    /// [`AuditRecord::declares_missing`]: super::record::AuditRecord::declares_missing
+    pub declared_missing: u64,
+    /// Line (file) or row id (table) of the first broken link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken_link: Option<BrokenLink>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UnreadableRecord {
+    pub position: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VerifyReport {
+    pub chains: Vec<ChainReport>,
+    pub unreadable: Vec<UnreadableRecord>,
+}
+
+impl VerifyReport {
+    pub fn is_intact(&self) -> bool {
+        self.unreadable.is_empty() && self.chains.iter().all(|chain| chain.broken_link.is_none())
+    }
+}
+
+#[derive(Debug)]
+struct ChainProgress {
+    next_sequence: u64,
+    prev_hash: String,
+    report: ChainReport,
+}
+
+#[derive(Debug)]
+pub struct ChainVerifier {
+    key: hmac::Key,
+    chains: BTreeMap<String, ChainProgress>,
+    unreadable: Vec<UnreadableRecord>,
+}
+
+impl ChainVerifier {
+    /// Verify against `key`, the `audit.hmac_key` the records were written with.
+    pub fn new(key: hmac::Key) -> Self {
+        Self {
+            key,
+            chains: BTreeMap::new(),
+            unreadable: Vec::new(),
+        }
+    }
+
+    /// Check `sealed`, found at `position` in the source, against the tail of its chain.
+    pub fn push(&mut self, position: u64, sealed: &SealedAuditRecord) {
+        let record = &sealed.record;
+        let progress = self
+            .chains
+            .entry(record.chain_id.clone())
+            .or_insert_with(|| ChainProgress {
+                next_sequence: 0,
+                prev_hash: GENESIS_HASH.to_string(),
+                report: ChainReport {
+                    chain_id: record.chain_id.clone(),
+                    records: 0,
+                    declared_missing: 0,
+                    broken_at: None,
+                    broken_link: None,
+                },
+            });
+
+        // Only the first break of a chain is reported; everything after it is suspect anyway.
+        if progress.report.broken_link.is_some() {
+            return;
+        }
+        progress.report.records = progress.report.records.saturating_add(1);
+
+        let declared_gap = record.declares_missing(progress.next_sequence);
+        let broken_link = if record.sequence != progress.next_sequence && !declared_gap {
+            Some(BrokenLink::SequenceGap {
+                expected: progress.next_sequence,
+                found: record.sequence,
+            })
+        } else if record.prev_hash != progress.prev_hash && !declared_gap {
+            Some(BrokenLink::PrevHashMismatch {
+                sequence: record.sequence,
+                expected: progress.prev_hash.clone(),
+                found: record.prev_hash.clone(),
+            })
+        } else if !sealed.is_intact(&self.key) {
+            Some(BrokenLink::HashMismatch {
+                sequence: record.sequence,
+            })
+        } else {
+            None
+        };
+
+        match broken_link {
+            Some(broken_link) => {
+                progress.report.broken_at = Some(position);
+                progress.report.broken_link = Some(broken_link);
+            }
+            None => {
+                if declared_gap {
+                    progress.report.declared_missing = progress
+                        .report
+                        .declared_missing
+                        .saturating_add(record.sequence.saturating_sub(progress.next_sequence));
+                }
+                progress.next_sequence = record.sequence.saturating_add(1);
+                progress.prev_hash.clone_from(&sealed.hash);
+            }
+        }
+    }
+
+    /// Note a record at `position` that could not be parsed.
+    pub fn push_unreadable(&mut self, position: u64, reason: impl Into<String>) {
+        self.unreadable.push(UnreadableRecord {
+            position,
+            reason: reason.into(),
+        });
+    }
+
+    pub fn finish(self) -> VerifyReport {
+        VerifyReport {
+            chains: self
+                .chains
+                .into_values()
+                .map(|progress| progress.report)
+                .collect(),
+            unreadable: self.unreadable,
+        }
+    }
+
+    fn push_serialized(&mut self, position: u64, event: &str) {
+        match serde_json::from_str::<SealedAuditRecord>(event) {
+            Ok(sealed) => self.push(position, &sealed),
+            Err(err) => self.push_unreadable(position, err.to_string()),
+        }
+    }
+}
+
+/// Verify the JSON lines written by the file sink. Positions are 1-based line numbers.
+pub fn verify_file(path: &Path, key: hmac::Key) -> std::io::Result<VerifyReport> {
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut verifier = ChainVerifier::new(key);
+
+    for (line_number, line) in (1_u64..).zip(reader.lines()) {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        verifier.push_serialized(line_number, &line);
+    }
+
+    Ok(verifier.finish())
+}
+
+/// Verify the `audit_log` table in `schema` of the primary database. Positions are row ids.
+pub async fn verify_table(
+    global_config: &GlobalConfig,
+    schema: &str,
+) -> error_stack::Result<VerifyReport, error::ConfigurationError> {
+    let runtime_config_manager = Arc::new(
+        RuntimeConfigManager::new(
+            &global_config.runtime_config,
+            global_config.api_client.client_idle_timeout,
+            global_config.api_client.pool_max_idle_per_host,
+        )
+        .change_context(error::ConfigurationError::InvalidConfigurationValueError(
+            "failed to create runtime config manager".into(),
+        ))?,
+    );
+    let storage = super::sink::open_storage(global_config, schema, runtime_config_manager).await?;
+
+    let mut verifier = ChainVerifier::new(global_config.audit.hmac_key()?);
+    let mut filter = AuditLogFilter {
+        limit: PAGE_SIZE,
+        ..Default::default()
+    };
+
+    loop {
+        let page = storage.find_audit_events(&filter).await.map_err(|err| {
+            err.error
+                .change_context(error::ConfigurationError::DatabaseError)
+        })?;
+
+        for row in &page {
+            verifier.push_serialized(u64::try_from(row.id).unwrap_or_default(), &row.event);
+        }
+
+        match page.last() {
+            Some(last) if i64::try_from(page.len()).is_ok_and(|len| len >= PAGE_SIZE) => {
+                filter.after = Some(last.id);
+            }
+            _ => break,
+        }
+    }
+
+    Ok(verifier.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+
+    use super::*;
+    use crate::audit::{AuditAction, AuditEvent, QueuedEvent, record::ChainState};
+
+    fn key() -> hmac::Key {
+        hmac::Key::new(hmac::HMAC_SHA256, &[7; 32])
+    }
+
+    fn queued() -> QueuedEvent {
+        QueuedEvent {
+            event: AuditEvent::new(AuditAction::CardRetrieve).merchant_id("m1"),
+            tenant_id: Some("public".to_string()),
+            request_id: None,
+            caller_role: None,
+            timestamp: crate::utils::date_time::now(),
+        }
+    }
+
+    fn chain(length: usize) -> Vec<SealedAuditRecord> {
+        let mut chain = ChainState::new("chain_1".to_string(), key());
+        (0..length).map(|_| chain.seal(queued()).unwrap()).collect()
+    }
+
+    fn verify(records: &[SealedAuditRecord]) -> VerifyReport {
+        let mut verifier = ChainVerifier::new(key());
+        for (position, record) in (1_u64..).zip(records) {
+            verifier.push(position, record);
+        }
+        verifier.finish()
+    }
+
+    #[test]
+    fn untouched_chain_is_intact() {
+        let report = verify(&chain(3));
+
+        assert!(report.is_intact());
+        assert_eq!(report.chains.len(), 1);
+        assert_eq!(report.chains[0].records, 3);
+    }
+
+    #[test]
+    fn deleted_record_is_reported_as_gap() {
+        let mut records = chain(3);
+        records.remove(1);
+
+        let report = verify(&records);
+        assert_eq!(report.chains[0].broken_at, Some(2));
+        assert_eq!(
+            report.chains[0].broken_link,
+            Some(BrokenLink::SequenceGap {
+                expected: 1,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn edited_record_is_reported_at_its_position() {
+        let mut records = chain(3);
+        records[1].record.merchant_id = Some("m2".to_string());
+
+        let report = verify(&records);
+        assert!(!report.is_intact());
+        assert_eq!(report.chains[0].broken_at, Some(2));
+        assert_eq!(
+            report.chains[0].broken_link,
+            Some(BrokenLink::HashMismatch { sequence: 1 })
+        );
+    }
+
+    #[test]
+    fn rehashed_record_breaks_the_next_link() {
+        let mut records = chain(3);
+        records[1].record.merchant_id = Some("m2".to_string());
+        records[1].hash = records[1].record.digest(&key()).unwrap();
+
+        let report = verify(&records);
+        assert_eq!(report.chains[0].broken_at, Some(3));
+        assert!(matches!(
+            report.chains[0].broken_link,
+            Some(BrokenLink::PrevHashMismatch { sequence: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn record_rehashed_without_the_key_is_detected() {
+        let mut records = chain(2);
+        let forged_key = hmac::Key::new(hmac::HMAC_SHA256, &[8; 32]);
+        records[1].record.merchant_id = Some("m2".to_string());
+        records[1].hash = records[1].record.digest(&forged_key).unwrap();
+
+        let report = verify(&records);
+        assert_eq!(
+            report.chains[0].broken_link,
+            Some(BrokenLink::HashMismatch { sequence: 1 })
+        );
+    }
+
+    #[test]
+    fn gap_declared_by_a_marker_is_accepted() {
+        let mut chain = ChainState::new("chain_1".to_string(), key());
+        let first = chain.seal(queued()).unwrap();
+        // Records 1 and 2 never reached this sink.
+        chain.seal(queued()).unwrap();
+        chain.seal(queued()).unwrap();
+        let marker = chain.seal_gap("file", 1).unwrap();
+        let next = chain.seal(queued()).unwrap();
+
+        let report = verify(&[first, marker, next]);
+        assert!(report.is_intact());
+        assert_eq!(report.chains[0].records, 3);
+        assert_eq!(report.chains[0].declared_missing, 2);
+    }
+
+    #[test]
+    fn marker_for_other_records_does_not_cover_a_gap() {
+        let mut chain = ChainState::new("chain_1".to_string(), key());
+        let first = chain.seal(queued()).unwrap();
+        chain.seal(queued()).unwrap();
+        chain.seal(queued()).unwrap();
+        let marker = chain.seal_gap("file", 2).unwrap();
+
+        let report = verify(&[first, marker]);
+        assert_eq!(
+            report.chains[0].broken_link,
+            Some(BrokenLink::SequenceGap {
+                expected: 1,
+                found: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//!
+//! # Drainer
+//!
+//! Replays the KV drainer streams written by the locker into each tenant's Postgres schema
+//!
+
+use hyperswitch_card_vault::{logger, observability, storage::kv::drainer};
+
+#[allow(clippy::expect_used)]
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut global_config =
+        hyperswitch_card_vault::config::GlobalConfig::new().expect("Failed while parsing config");
+
+    let _guard = logger::setup(
+        &global_config.log,
+        hyperswitch_card_vault::service_name!(),
+        [hyperswitch_card_vault::service_name!(), "tower_http"],
+    )
+    .expect("Failed to initialize logging");
+
+    global_config
+        .validate()
+        .expect("Failed to validate application configuration");
+
+    let metrics_handle = observability::init_metrics(&global_config.metrics);
+
+    if let observability::MetricsHandle::Prometheus {
+        registry,
+        host,
+        port,
+        ..
+    } = &metrics_handle
+    {
+        observability::start_prometheus_metrics_server(host, *port, registry.clone())?;
+    }
+
+    global_config
+        .fetch_raw_secrets()
+        .await
+        .expect("Failed to fetch raw application secrets");
+
+    drainer::run(global_config)
+        .await
+        .expect("Drainer failed to start");
+
+    Ok(())
+}
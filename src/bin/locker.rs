@@ -1,4 +1,4 @@
-use hyperswitch_card_vault::{logger, observability, tenant::GlobalAppState};
+use hyperswitch_card_vault::{logger, observability, secrets_refresh, tenant::GlobalAppState};
 
 #[allow(clippy::expect_used)]
 #[tokio::main]
@@ -21,6 +21,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // when the secret manager is called.
     let metrics_handle = observability::init_metrics(&global_config.metrics);
 
+    // Kept unresolved, for the secrets refresher to resolve again.
+    let raw_config = global_config.clone();
+
     global_config
         .fetch_raw_secrets()
         .await
@@ -28,6 +31,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let global_app_state = GlobalAppState::new(global_config).await;
 
+    let _secrets_refresh_handle = secrets_refresh::spawn(&global_app_state, raw_config);
+
     hyperswitch_card_vault::app::server_builder(global_app_state, metrics_handle)
         .await
         .expect("Failed while building the server");
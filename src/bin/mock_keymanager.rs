@@ -0,0 +1,31 @@
+//!
+//! # Mock key manager
+//!
+//! Serves an in-memory implementation of the external key manager API for local development
+//! and end to end tests. Keys are lost on exit.
+//!
+
+use hyperswitch_card_vault::crypto::keymanager::external_keymanager::mock;
+
+#[derive(argh::FromArgs, Debug)]
+/// Run an in-memory external key manager
+struct Cli {
+    /// address to bind to
+    #[argh(option, default = "String::from(\"127.0.0.1\")")]
+    host: String,
+    /// port to listen on
+    #[argh(option, default = "5000")]
+    port: u16,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli: Cli = argh::from_env();
+
+    let listener = tokio::net::TcpListener::bind((cli.host.as_str(), cli.port)).await?;
+    println!("mock key manager listening on {}", listener.local_addr()?);
+
+    axum::serve(listener, mock::router()).await?;
+
+    Ok(())
+}
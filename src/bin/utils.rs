@@ -32,6 +32,11 @@ enum SubCommand {
     MasterKey(MasterKey),
     JweEncrypt(JweE),
     JweDecrypt(JweD),
+    #[cfg(feature = "kv")]
+    KvReconcile(KvReconcile),
+    #[cfg(feature = "external_key_manager")]
+    KeyTransfer(KeyTransfer),
+    AuditVerify(AuditVerify),
 }
 
 #[derive(argh::FromArgs, Debug)]
@@ -67,6 +72,59 @@ struct JweD {
     public_key: Option<String>,
 }
 
+#[cfg(feature = "kv")]
+#[derive(argh::FromArgs, Debug)]
+#[argh(subcommand, name = "kv-reconcile")]
+/// Compare a tenant's KV records with Postgres and report the drift, reading the locker config
+struct KvReconcile {
+    /// tenant whose keyspace is checked
+    #[argh(option)]
+    tenant_id: String,
+    /// insert rows missing from Postgres from their Redis copy
+    #[argh(switch)]
+    replay: bool,
+}
+
+#[cfg(feature = "external_key_manager")]
+#[derive(argh::FromArgs, Debug)]
+#[argh(subcommand, name = "key-transfer")]
+/// Migrate a tenant's merchant keys to the external key manager through a running locker,
+/// batch by batch until every merchant has been examined
+struct KeyTransfer {
+    /// base url of the locker, e.g. http://localhost:8080
+    #[argh(option)]
+    locker_url: String,
+    /// tenant whose keys are migrated
+    #[argh(option)]
+    tenant_id: String,
+    /// merchants examined per batch
+    #[argh(option, default = "100")]
+    batch_size: i64,
+    /// concurrent transfers within a batch
+    #[argh(option, default = "8")]
+    concurrency: usize,
+    /// cursor printed by an interrupted run, to resume from it
+    #[argh(option)]
+    cursor: Option<String>,
+    /// report what would be transferred without transferring
+    #[argh(switch)]
+    dry_run: bool,
+}
+
+#[derive(argh::FromArgs, Debug)]
+#[argh(subcommand, name = "audit-verify")]
+/// Walk the audit hash chains and report the first broken link of each, checking every record
+/// against the `audit.hmac_key` of the locker config. Reads the `audit_log` table of the postgres
+/// audit sink from the locker config unless `--file` is given
+struct AuditVerify {
+    /// audit file written by the file sink
+    #[argh(option)]
+    file: Option<String>,
+    /// schema holding the `audit_log` table, overriding the postgres sink config
+    #[argh(option)]
+    schema: Option<String>,
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Cli = argh::from_env();
 
@@ -112,6 +170,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 // (x)
             })?;
         }
+        #[cfg(feature = "kv")]
+        SubCommand::KvReconcile(kv_reconcile) => {
+            tokio::runtime::Runtime::new()?.block_on(reconcile_kv(kv_reconcile))?
+        }
+        #[cfg(feature = "external_key_manager")]
+        SubCommand::KeyTransfer(key_transfer) => {
+            tokio::runtime::Runtime::new()?.block_on(transfer_keys(key_transfer))?
+        }
+        SubCommand::AuditVerify(audit_verify) => {
+            tokio::runtime::Runtime::new()?.block_on(verify_audit(audit_verify))?
+        }
     }
 
     Ok(())
@@ -137,6 +206,149 @@ fn master_key_generator(master_key_conf: MasterKey) -> Result<(), Box<dyn std::e
     }
 }
 
+#[cfg(feature = "kv")]
+async fn reconcile_kv(
+    KvReconcile { tenant_id, replay }: KvReconcile,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut global_config = hyperswitch_card_vault::config::GlobalConfig::new()?;
+    global_config.validate()?;
+    global_config.fetch_raw_secrets().await?;
+
+    let report =
+        hyperswitch_card_vault::storage::kv::reconcile::run(&global_config, &tenant_id, replay)
+            .await?;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if report.has_drift() {
+        return Err("Postgres is missing records held in KV".into());
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "external_key_manager")]
+async fn transfer_keys(
+    KeyTransfer {
+        locker_url,
+        tenant_id,
+        batch_size,
+        concurrency,
+        mut cursor,
+        dry_run,
+    }: KeyTransfer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use hyperswitch_card_vault::{
+        routes::key_migration::{MerchantKeyTransferRequest, TransferKeyResponse, TransferOutcome},
+        storage::consts::X_TENANT_ID,
+    };
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/key/transfer", locker_url.trim_end_matches('/'));
+    let (mut transferred, mut already_present, mut failed) = (0, 0, 0);
+
+    loop {
+        let response = client
+            .post(&url)
+            .header(X_TENANT_ID, &tenant_id)
+            .json(&MerchantKeyTransferRequest {
+                limit: batch_size,
+                cursor: cursor.clone(),
+                dry_run,
+                concurrency,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            eprintln!(
+                "resume with: --cursor {}",
+                cursor.as_deref().unwrap_or("<none>")
+            );
+            return Err(format!("batch failed with {status}: {body}").into());
+        }
+
+        let batch: TransferKeyResponse = response.json().await?;
+        for result in &batch.results {
+            if let TransferOutcome::Failed { reason } = &result.outcome {
+                eprintln!("failed: {}: {}", result.merchant_id, reason);
+            } else if dry_run && result.outcome == TransferOutcome::Pending {
+                println!("would transfer: {}", result.merchant_id);
+            }
+        }
+
+        transferred += batch.total_transferred;
+        already_present += batch.already_present;
+        failed += batch.failed;
+        println!(
+            "batch done: transferred {}, already present {}, failed {}, next cursor {}",
+            batch.total_transferred,
+            batch.already_present,
+            batch.failed,
+            batch.next_cursor.as_deref().unwrap_or("<end>")
+        );
+
+        match batch.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    println!(
+        "migration finished: transferred {transferred}, already present {already_present}, failed {failed}"
+    );
+
+    if failed > 0 {
+        return Err(
+            format!("{failed} merchant keys failed to migrate; rerun to retry them").into(),
+        );
+    }
+
+    Ok(())
+}
+
+async fn verify_audit(
+    AuditVerify { file, schema }: AuditVerify,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use hyperswitch_card_vault::audit::{AuditSinkConfig, verify};
+
+    let mut global_config = hyperswitch_card_vault::config::GlobalConfig::new()?;
+    global_config.validate()?;
+    global_config.fetch_raw_secrets().await?;
+
+    let report = match file {
+        Some(file) => {
+            verify::verify_file(std::path::Path::new(&file), global_config.audit.hmac_key()?)?
+        }
+        None => {
+            let schema = schema
+                .or_else(|| {
+                    global_config
+                        .audit
+                        .sinks
+                        .iter()
+                        .find_map(|sink| match sink {
+                            AuditSinkConfig::Postgres { schema } => Some(schema.clone()),
+                            _ => None,
+                        })
+                })
+                .ok_or("no postgres audit sink configured; pass --schema or --file")?;
+
+            verify::verify_table(&global_config, &schema).await?
+        }
+    };
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if !report.is_intact() {
+        return Err("audit log failed verification".into());
+    }
+
+    Ok(())
+}
+
 fn read_file_to_string(name: &str) -> Result<String, Box<dyn std::error::Error>> {
     let mut file = std::fs::File::open(name)?;
     let mut output = String::new();
@@ -9,16 +9,94 @@ use hyperswitch_masking::{ExposeInterface, PeekInterface, Secret};
 #[cfg(feature = "redis")]
 use hyperswitch_redis_interface::RedisSettings;
 
+#[cfg(feature = "external_key_manager")]
+use crate::api_client::{circuit_breaker::CircuitBreakerConfig, retry::RetryPolicy};
+#[cfg(feature = "limit")]
+use crate::rate_limit::RateLimitConfig;
 use crate::{
+    anomaly::AnomalyDetectionConfig,
     api_client::ApiClientConfig,
+    audit::AuditConfig,
     crypto::secrets_manager::{
-        secrets_interface::SecretManager, secrets_management::SecretsManagementConfig,
+        managers::file::core::SecretReference, secrets_interface::SecretManager,
+        secrets_management::SecretsManagementConfig,
     },
     error,
+    idempotency::IdempotencyConfig,
     logger::config::Log,
     observability::MetricsConfig,
+    secrets_refresh::SecretsRefreshConfig,
+    usage::{QuotaConfig, UsageConfig},
 };
 
+/// Secrets the locker can pick up again without a restart, resolved by
+/// [`GlobalConfig::fetch_rotatable_secrets`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RotatableSecrets {
+    pub database_password: Secret<String>,
+    pub read_replica_password: Option<Secret<String>>,
+    pub runtime_config_api_key: Option<Secret<String>>,
+    #[cfg(feature = "external_key_manager")]
+    pub mtls: Option<MtlsSecrets>,
+}
+
+/// Client identity and CA certificate used for mTLS with the external key manager.
+#[cfg(feature = "external_key_manager")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MtlsSecrets {
+    pub identity: Secret<String>,
+    pub ca_cert: Secret<String>,
+}
+
+impl RotatableSecrets {
+    /// The values `config` currently holds.
+    pub fn of(config: &GlobalConfig) -> Self {
+        Self {
+            database_password: config.database.password.clone(),
+            read_replica_password: config
+                .read_replica
+                .as_ref()
+                .map(|read_replica| read_replica.password.clone()),
+            runtime_config_api_key: match &config.runtime_config {
+                RuntimeConfig::Enabled { endpoint, .. } => Some(endpoint.api_key.clone()),
+                RuntimeConfig::Disabled => None,
+            },
+            #[cfg(feature = "external_key_manager")]
+            mtls: match &config.external_key_manager {
+                ExternalKeyManagerConfig::EnabledWithMtls { ca_cert, .. } => Some(MtlsSecrets {
+                    identity: config.api_client.identity.clone(),
+                    ca_cert: ca_cert.clone(),
+                }),
+                ExternalKeyManagerConfig::Enabled { .. } | ExternalKeyManagerConfig::Disabled => {
+                    None
+                }
+            },
+        }
+    }
+
+    /// Put the resolved values in place of the configured ones.
+    pub fn apply(self, config: &mut GlobalConfig) {
+        config.database.password = self.database_password;
+        if let (Some(read_replica), Some(password)) =
+            (config.read_replica.as_mut(), self.read_replica_password)
+        {
+            read_replica.password = password;
+        }
+        if let (RuntimeConfig::Enabled { endpoint, .. }, Some(api_key)) =
+            (&mut config.runtime_config, self.runtime_config_api_key)
+        {
+            endpoint.api_key = api_key;
+        }
+        #[cfg(feature = "external_key_manager")]
+        if let (ExternalKeyManagerConfig::EnabledWithMtls { ca_cert, .. }, Some(mtls)) =
+            (&mut config.external_key_manager, self.mtls)
+        {
+            config.api_client.identity = mtls.identity;
+            *ca_cert = mtls.ca_cert;
+        }
+    }
+}
+
 #[derive(Clone, serde::Deserialize, Debug)]
 pub struct GlobalConfig {
     pub server: Server,
@@ -31,15 +109,22 @@ pub struct GlobalConfig {
     #[serde(default)]
     pub metrics: MetricsConfig,
     #[cfg(feature = "limit")]
-    pub limit: Limit,
+    #[serde(default)]
+    pub limit: RateLimitConfig,
     #[cfg(feature = "caching")]
     pub cache: Cache,
+    #[cfg(feature = "caching")]
+    #[serde(default)]
+    pub dek_cache: DekCache,
     pub tenant_secrets: TenantsSecrets,
     pub tls: Option<ServerTls>,
     #[serde(default)]
     pub api_client: ApiClientConfig,
     #[serde(default)]
     pub external_key_manager: ExternalKeyManagerConfig,
+    #[cfg(feature = "external_key_manager")]
+    #[serde(default)]
+    pub key_manager_client: KeyManagerClientConfig,
     #[cfg(feature = "redis")]
     pub redis: Option<RedisSettings>,
     #[serde(default)]
@@ -47,6 +132,20 @@ pub struct GlobalConfig {
     #[cfg(feature = "kv")]
     #[serde(default)]
     pub kv: KvConfig,
+    #[serde(default)]
+    pub retrieve: RetrieveConfig,
+    #[serde(default)]
+    pub caller_auth: CallerAuthConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub secrets_refresh: SecretsRefreshConfig,
+    #[serde(default)]
+    pub usage: UsageConfig,
+    #[serde(default)]
+    pub anomaly_detection: AnomalyDetectionConfig,
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
 }
 
 #[derive(Clone, Debug)]
@@ -55,6 +154,8 @@ pub struct TenantConfig {
     pub locker_secrets: Secrets,
     pub tenant_secrets: TenantSecrets,
     pub external_key_manager: ExternalKeyManagerConfig,
+    pub retrieve: RetrieveConfig,
+    pub quota: QuotaConfig,
     /// Redis key namespace for this tenant.
     #[cfg(feature = "redis")]
     pub redis_key_prefix: String,
@@ -82,20 +183,14 @@ impl TenantConfig {
             locker_secrets: global_config.secrets.clone(),
             tenant_secrets,
             external_key_manager: global_config.external_key_manager.clone(),
+            retrieve: global_config.retrieve.clone(),
+            quota: global_config.usage.quota.clone(),
             #[cfg(feature = "redis")]
             redis_key_prefix,
         }
     }
 }
 
-#[cfg(feature = "limit")]
-#[derive(Clone, serde::Deserialize, Debug)]
-pub struct Limit {
-    pub request_count: u64,
-    pub duration: u64, // in sec
-    pub buffer_size: Option<usize>,
-}
-
 #[derive(Clone, serde::Deserialize, Debug)]
 pub struct Server {
     pub host: String,
@@ -122,6 +217,44 @@ pub struct Cache {
     pub max_capacity: u64,
 }
 
+/// Per-tenant cache of ready key managers (decrypted DEKs under the internal key manager).
+/// Kept apart from [`Cache`] because it holds key material: entries expire a fixed `ttl` after
+/// insertion, however often they are used.
+#[cfg(feature = "caching")]
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct DekCache {
+    pub enabled: bool,
+    // time to live (in secs)
+    pub ttl: u64,
+    // maximum number of entities whose key manager is held, per tenant
+    pub max_capacity: u64,
+}
+
+#[cfg(feature = "caching")]
+impl Default for DekCache {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: 60,
+            max_capacity: 1000,
+        }
+    }
+}
+
+#[cfg(feature = "caching")]
+impl DekCache {
+    pub fn validate(&self) -> Result<(), error::ConfigurationError> {
+        if self.enabled && self.ttl == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "dek_cache.ttl must be greater than 0 when the DEK cache is enabled".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone, serde::Deserialize, Debug)]
 pub struct Secrets {
     // KMS encrypted
@@ -173,6 +306,89 @@ impl DerefMut for TenantsSecrets {
     }
 }
 
+/// API keys callers authenticate with, one per role.
+///
+/// The role whose key is sent in `x-caller-api-key` is the caller identity used by the retrieve
+/// access rules and the audit log; anomaly detection tells callers apart by key. Requests without
+/// a key act as no role.
+#[derive(Debug, Default, serde::Deserialize, Clone)]
+pub struct CallerAuthConfig {
+    /// Role → API key, resolved through `secrets_management` like every other secret.
+    #[serde(default)]
+    pub api_keys: HashMap<String, Secret<String>>,
+    /// Roles allowed to call the `/admin` endpoints. Empty refuses them to every caller.
+    #[serde(default)]
+    pub admin_roles: Vec<String>,
+}
+
+impl CallerAuthConfig {
+    /// Role of the caller presenting `api_key`. Keys are compared by SHA-256 digest, so the
+    /// comparison time does not depend on how much of a configured key was guessed.
+    pub fn role_of(&self, api_key: &str) -> Option<&str> {
+        self.authenticate(api_key).map(|(role, _)| role)
+    }
+
+    /// Identity of the key `api_key`: its role and the start of the key's SHA-256 digest in hex,
+    /// e.g. `payments:3f9a12c07be45d61`. Unlike the role it changes with the key, without
+    /// revealing it.
+    pub fn key_id_of(&self, api_key: &str) -> Option<String> {
+        self.authenticate(api_key)
+            .map(|(role, digest)| format!("{role}:{}", hex::encode(&digest.as_ref()[..8])))
+    }
+
+    fn authenticate(&self, api_key: &str) -> Option<(&str, ring::digest::Digest)> {
+        let presented = ring::digest::digest(&ring::digest::SHA256, api_key.as_bytes());
+
+        self.api_keys
+            .iter()
+            .find(|(_, key)| {
+                ring::digest::digest(&ring::digest::SHA256, key.peek().as_bytes()).as_ref()
+                    == presented.as_ref()
+            })
+            .map(|(role, _)| (role.as_str(), presented))
+    }
+
+    pub fn is_admin(&self, role: &str) -> bool {
+        self.admin_roles.iter().any(|admin_role| admin_role == role)
+    }
+}
+
+/// Access rules for `/api/v2/vault/retrieve`.
+#[derive(Debug, Default, serde::Deserialize, Clone)]
+pub struct RetrieveConfig {
+    /// Caller roles allowed to fetch whole documents and `restricted_fields`. Unset leaves
+    /// retrieval open to everyone.
+    pub full_document_roles: Option<Vec<String>>,
+    /// JSON pointers (RFC 6901) only `full_document_roles` may read. A projection is refused
+    /// when one of its `fields` is a restricted pointer, a parent of one or a child of one.
+    #[serde(default)]
+    pub restricted_fields: Vec<String>,
+}
+
+impl RetrieveConfig {
+    /// Whether a caller acting as `role` may retrieve `fields`, or the whole document for `None`.
+    pub fn allows(&self, role: Option<&str>, fields: Option<&[String]>) -> bool {
+        let privileged = self.full_document_roles.as_ref().is_none_or(|roles| {
+            role.is_some_and(|role| roles.iter().any(|allowed| allowed == role))
+        });
+
+        privileged
+            || fields.is_some_and(|fields| !fields.iter().any(|field| self.is_restricted(field)))
+    }
+
+    fn is_restricted(&self, field: &str) -> bool {
+        let within = |pointer: &str, parent: &str| {
+            pointer
+                .strip_prefix(parent)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+
+        self.restricted_fields
+            .iter()
+            .any(|restricted| within(field, restricted) || within(restricted, field))
+    }
+}
+
 #[derive(Debug, serde::Deserialize, Clone)]
 pub struct ServerTls {
     /// certificate file associated with TLS (path to the certificate file (`pem` format))
@@ -271,21 +487,9 @@ impl GlobalConfig {
             .await
             .expect("Failed to create secret management client");
 
-        self.database.password = secret_management_client
-            .get_secret(self.database.password.clone())
-            .await
-            .change_context(error::ConfigurationError::KmsDecryptError(
-                "database_password",
-            ))?;
-
-        if let Some(ref mut read_replica) = self.read_replica {
-            read_replica.password = secret_management_client
-                .get_secret(read_replica.password.clone())
-                .await
-                .change_context(error::ConfigurationError::KmsDecryptError(
-                    "read_replica_password",
-                ))?;
-        }
+        self.resolve_rotatable_secrets(&secret_management_client)
+            .await?
+            .apply(self);
 
         for tenant_secrets in self.tenant_secrets.values_mut() {
             tenant_secrets.master_key = hex::decode(
@@ -303,6 +507,22 @@ impl GlobalConfig {
             .expect("Failed to hex decode master key")
         }
 
+        if self.audit.enabled {
+            self.audit.hmac_key = secret_management_client
+                .get_secret(self.audit.hmac_key.clone())
+                .await
+                .change_context(error::ConfigurationError::KmsDecryptError("audit hmac_key"))?;
+        }
+
+        for api_key in self.caller_auth.api_keys.values_mut() {
+            *api_key = secret_management_client
+                .get_secret(api_key.clone())
+                .await
+                .change_context(error::ConfigurationError::KmsDecryptError(
+                    "caller_auth api_key",
+                ))?;
+        }
+
         #[cfg(feature = "middleware")]
         {
             for tenant_secrets in self.tenant_secrets.values_mut() {
@@ -320,55 +540,91 @@ impl GlobalConfig {
                 ))?;
         }
 
-        if let RuntimeConfig::Enabled {
-            ref mut endpoint, ..
-        } = self.runtime_config
-        {
-            endpoint.api_key = secret_management_client
-                .get_secret(endpoint.api_key.clone())
-                .await
-                .change_context(error::ConfigurationError::KmsDecryptError(
-                    "runtime_config api_key",
-                ))?;
-        }
+        Ok(())
+    }
+
+    /// Resolve the secrets that may be rotated while the locker runs, from this configuration as
+    /// it was read (i.e. before [`Self::fetch_raw_secrets`]).
+    pub async fn fetch_rotatable_secrets(
+        &self,
+    ) -> error_stack::Result<RotatableSecrets, error::ConfigurationError> {
+        let secret_management_client = self
+            .secrets_management
+            .get_secret_management_client()
+            .await
+            .change_context(error::ConfigurationError::KmsDecryptError(
+                "secret management client",
+            ))?;
+
+        self.resolve_rotatable_secrets(&secret_management_client)
+            .await
+    }
+
+    async fn resolve_rotatable_secrets(
+        &self,
+        secret_management_client: &impl SecretManager,
+    ) -> error_stack::Result<RotatableSecrets, error::ConfigurationError> {
+        let database_password = secret_management_client
+            .get_secret(self.database.password.clone())
+            .await
+            .change_context(error::ConfigurationError::KmsDecryptError(
+                "database_password",
+            ))?;
+
+        let read_replica_password = match self.read_replica {
+            Some(ref read_replica) => Some(
+                secret_management_client
+                    .get_secret(read_replica.password.clone())
+                    .await
+                    .change_context(error::ConfigurationError::KmsDecryptError(
+                        "read_replica_password",
+                    ))?,
+            ),
+            None => None,
+        };
+
+        let runtime_config_api_key = match self.runtime_config {
+            RuntimeConfig::Enabled { ref endpoint, .. } => Some(
+                secret_management_client
+                    .get_secret(endpoint.api_key.clone())
+                    .await
+                    .change_context(error::ConfigurationError::KmsDecryptError(
+                        "runtime_config api_key",
+                    ))?,
+            ),
+            RuntimeConfig::Disabled => None,
+        };
 
+        // Decrypt api_client.identity and ca_cert only when mTLS is enabled, as they're required for client certificate authentication
         #[cfg(feature = "external_key_manager")]
-        {
-            // Decrypt api_client.identity only when mTLS is enabled, as it's required for client certificate authentication
-            if self.external_key_manager.is_mtls_enabled() {
-                let decrypted_identity = secret_management_client
+        let mtls = match &self.external_key_manager {
+            ExternalKeyManagerConfig::EnabledWithMtls { ca_cert, .. } => Some(MtlsSecrets {
+                identity: secret_management_client
                     .get_secret(self.api_client.identity.clone())
                     .await
                     .change_context(error::ConfigurationError::KmsDecryptError(
                         "api_client-identity",
-                    ))?;
-
-                self.api_client.identity = decrypted_identity;
-            }
-
-            self.external_key_manager = match &self.external_key_manager {
-                ExternalKeyManagerConfig::EnabledWithMtls { url, ca_cert } => {
-                    let decrypted_ca_cert = secret_management_client
-                        .get_secret(ca_cert.clone())
-                        .await
-                        .change_context(error::ConfigurationError::KmsDecryptError("ca_cert"))?;
-
-                    ExternalKeyManagerConfig::EnabledWithMtls {
-                        url: url.clone(),
-                        ca_cert: decrypted_ca_cert,
-                    }
-                }
-                ExternalKeyManagerConfig::Enabled { .. } | ExternalKeyManagerConfig::Disabled => {
-                    self.external_key_manager.clone()
-                }
-            };
-        }
-
-        Ok(())
+                    ))?,
+                ca_cert: secret_management_client
+                    .get_secret(ca_cert.clone())
+                    .await
+                    .change_context(error::ConfigurationError::KmsDecryptError("ca_cert"))?,
+            }),
+            ExternalKeyManagerConfig::Enabled { .. } | ExternalKeyManagerConfig::Disabled => None,
+        };
+
+        Ok(RotatableSecrets {
+            database_password,
+            read_replica_password,
+            runtime_config_api_key,
+            #[cfg(feature = "external_key_manager")]
+            mtls,
+        })
     }
 
     pub fn validate(&self) -> error_stack::Result<(), error::ConfigurationError> {
         self.secrets_management.validate()?;
+        self.validate_secret_references()?;
         self.runtime_config.validate()?;
         #[cfg(feature = "kv")]
         {
@@ -380,8 +636,87 @@ impl GlobalConfig {
             self.external_key_manager.validate()?;
             self.api_client
                 .validate_for_mtls(&self.external_key_manager)?;
+            self.key_manager_client.validate()?;
         }
         self.metrics.validate()?;
+        self.log.traces.validate()?;
+        #[cfg(feature = "caching")]
+        self.dek_cache.validate()?;
+        self.audit.validate()?;
+        self.secrets_refresh.validate()?;
+        self.usage.validate()?;
+        self.anomaly_detection.validate(&self.caller_auth)?;
+        self.idempotency.validate()?;
+        #[cfg(feature = "redis")]
+        if self.idempotency.enabled
+            && self.idempotency.store == crate::idempotency::IdempotencyStore::Redis
+            && self.redis.is_none()
+        {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "idempotency.store = \"redis\" requires redis to be configured".into(),
+            )
+            .into());
+        }
+        #[cfg(feature = "limit")]
+        self.limit.validate()?;
+
+        Ok(())
+    }
+
+    /// With the `file` secrets manager, fail fast on `file://` and `env:` references that
+    /// `fetch_raw_secrets` would not be able to resolve.
+    fn validate_secret_references(&self) -> error_stack::Result<(), error::ConfigurationError> {
+        if self.secrets_management != SecretsManagementConfig::File {
+            return Ok(());
+        }
+
+        let mut references = vec![("database_password", self.database.password.peek().clone())];
+        if let Some(ref read_replica) = self.read_replica {
+            references.push((
+                "read_replica_password",
+                read_replica.password.peek().clone(),
+            ));
+        }
+        for tenant_secrets in self.tenant_secrets.values() {
+            references.push((
+                "master_key",
+                String::from_utf8_lossy(tenant_secrets.master_key.peek()).into_owned(),
+            ));
+            #[cfg(feature = "middleware")]
+            references.push(("public_key", tenant_secrets.public_key.peek().clone()));
+        }
+        #[cfg(feature = "middleware")]
+        references.push((
+            "locker_private_key",
+            self.secrets.locker_private_key.peek().clone(),
+        ));
+        if let RuntimeConfig::Enabled { ref endpoint, .. } = self.runtime_config {
+            references.push(("runtime_config api_key", endpoint.api_key.peek().clone()));
+        }
+        for api_key in self.caller_auth.api_keys.values() {
+            references.push(("caller_auth api_key", api_key.peek().clone()));
+        }
+        if self.audit.enabled {
+            references.push(("audit hmac_key", self.audit.hmac_key.peek().clone()));
+        }
+        #[cfg(feature = "external_key_manager")]
+        if let ExternalKeyManagerConfig::EnabledWithMtls { ca_cert, .. } =
+            &self.external_key_manager
+        {
+            references.push((
+                "api_client-identity",
+                self.api_client.identity.peek().clone(),
+            ));
+            references.push(("ca_cert", ca_cert.peek().clone()));
+        }
+
+        for (name, value) in references {
+            SecretReference::parse(&value)
+                .and_then(|reference| reference.check())
+                .change_context(error::ConfigurationError::InvalidConfigurationValueError(
+                    format!("{name} refers to a secret that cannot be read"),
+                ))?;
+        }
 
         Ok(())
     }
@@ -426,6 +761,53 @@ pub struct KvConfig {
     /// TTL (seconds) for KV keys in Redis. Must exceed max drainer replay lag.
     #[serde(default = "default_ttl_for_kv")]
     pub ttl_for_kv: u32,
+    /// Settings for the `drainer` binary; unused by the locker itself.
+    #[serde(default)]
+    pub drainer: DrainerConfig,
+}
+
+#[cfg(feature = "kv")]
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(default)]
+pub struct DrainerConfig {
+    /// Consumer group shared by every drainer replica.
+    pub consumer_group: String,
+    /// Consumer name within the group, also naming the replica in shard leases; defaults to the
+    /// hostname. Must be unique per replica.
+    pub consumer_name: Option<String>,
+    /// How long a replica's lease on a shard lasts without renewal (milliseconds). Only the
+    /// holder drains the shard; it renews the lease every third of this.
+    pub lease_ms: u64,
+    /// Entries fetched per `XREADGROUP`.
+    pub read_count: u64,
+    /// How long an idle `XREADGROUP` blocks (milliseconds).
+    pub block_ms: u64,
+    /// Replay attempts before an entry is dead-lettered. Must be `> 0`.
+    pub max_attempts: u8,
+    /// Delay before the first retry (milliseconds); doubled after every failed attempt.
+    pub retry_backoff_ms: u64,
+    /// Upper bound on the retry delay (milliseconds). Entries that fail because Postgres is
+    /// unreachable are retried at this pace until it is back instead of being dead-lettered.
+    pub max_retry_backoff_ms: u64,
+    /// Dead-letter stream suffix: `{drainer stream}_{suffix}`.
+    pub dead_letter_suffix: String,
+}
+
+#[cfg(feature = "kv")]
+impl Default for DrainerConfig {
+    fn default() -> Self {
+        Self {
+            consumer_group: "vault_drainer".to_string(),
+            consumer_name: None,
+            lease_ms: 30_000,
+            read_count: 100,
+            block_ms: 1000,
+            max_attempts: 5,
+            retry_backoff_ms: 100,
+            max_retry_backoff_ms: 30_000,
+            dead_letter_suffix: "DEAD_LETTER".to_string(),
+        }
+    }
 }
 
 #[cfg(feature = "kv")]
@@ -450,6 +832,7 @@ impl Default for KvConfig {
             drainer_stream_suffix: default_drainer_stream_suffix(),
             drainer_num_partitions: default_drainer_num_partitions(),
             ttl_for_kv: default_ttl_for_kv(),
+            drainer: DrainerConfig::default(),
         }
     }
 }
@@ -461,13 +844,29 @@ impl KvConfig {
         format!("{{{}}}_{}", shard_key, self.drainer_stream_suffix)
     }
 
-    /// Reject `drainer_num_partitions == 0` (crc32 % 0 panics).
+    /// Dead-letter stream paired with `stream_name`.
+    pub fn drainer_dead_letter_stream_name(&self, stream_name: &str) -> String {
+        format!("{}_{}", stream_name, self.drainer.dead_letter_suffix)
+    }
+
+    /// Reject `drainer_num_partitions == 0` (crc32 % 0 panics), `drainer.max_attempts == 0` and
+    /// shard leases too short to be renewed in time.
     pub fn validate(&self) -> Result<(), error::ConfigurationError> {
         if self.drainer_num_partitions == 0 {
             return Err(error::ConfigurationError::InvalidConfigurationValueError(
                 "kv.drainer_num_partitions must be greater than 0".into(),
             ));
         }
+        if self.drainer.max_attempts == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "kv.drainer.max_attempts must be greater than 0".into(),
+            ));
+        }
+        if self.drainer.lease_ms < 1000 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "kv.drainer.lease_ms must be at least 1000".into(),
+            ));
+        }
         Ok(())
     }
 }
@@ -568,14 +967,78 @@ pub enum ExternalKeyManagerConfig {
     #[default]
     Disabled,
     #[cfg(feature = "external_key_manager")]
-    Enabled { url: String },
+    Enabled {
+        url: String,
+        #[serde(default)]
+        data_encryption: DataEncryptionMode,
+    },
     #[cfg(feature = "external_key_manager")]
     EnabledWithMtls {
         url: String,
         ca_cert: Secret<String>,
+        #[serde(default)]
+        data_encryption: DataEncryptionMode,
     },
 }
 
+/// Where payloads are encrypted when the external key manager is enabled.
+#[cfg(feature = "external_key_manager")]
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataEncryptionMode {
+    /// Every payload is sent to the key manager's `/data/encrypt` and `/data/decrypt`.
+    #[default]
+    Remote,
+    /// Each entity gets a DEK wrapped by the key manager; payloads are encrypted locally with
+    /// the unwrapped DEK. Data written in either mode stays readable in both.
+    LocalEnvelope,
+}
+
+/// Retry policy per key manager operation and the circuit breaker shared by all of them.
+///
+/// Only the data operations retry by default; `key_create` and `key_transfer` are not
+/// guaranteed to be idempotent on the key manager side.
+#[cfg(feature = "external_key_manager")]
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct KeyManagerClientConfig {
+    pub circuit_breaker: CircuitBreakerConfig,
+    pub key_create: RetryPolicy,
+    pub key_transfer: RetryPolicy,
+    pub data_encrypt: RetryPolicy,
+    pub data_decrypt: RetryPolicy,
+}
+
+#[cfg(feature = "external_key_manager")]
+impl Default for KeyManagerClientConfig {
+    fn default() -> Self {
+        Self {
+            circuit_breaker: CircuitBreakerConfig::default(),
+            key_create: RetryPolicy::single_attempt(),
+            key_transfer: RetryPolicy::single_attempt(),
+            data_encrypt: RetryPolicy::single_attempt().with_max_attempts(2),
+            data_decrypt: RetryPolicy::single_attempt().with_max_attempts(3),
+        }
+    }
+}
+
+#[cfg(feature = "external_key_manager")]
+impl KeyManagerClientConfig {
+    pub fn validate(&self) -> Result<(), error::ConfigurationError> {
+        self.circuit_breaker
+            .validate("key_manager_client.circuit_breaker")?;
+        self.key_create.validate("key_manager_client.key_create")?;
+        self.key_transfer
+            .validate("key_manager_client.key_transfer")?;
+        self.data_encrypt
+            .validate("key_manager_client.data_encrypt")?;
+        self.data_decrypt
+            .validate("key_manager_client.data_decrypt")?;
+
+        Ok(())
+    }
+}
+
 impl ExternalKeyManagerConfig {
     pub fn is_external(&self) -> bool {
         !matches!(self, Self::Disabled)
@@ -590,7 +1053,20 @@ impl ExternalKeyManagerConfig {
         match self {
             Self::Disabled => None,
             #[cfg(feature = "external_key_manager")]
-            Self::Enabled { url } | Self::EnabledWithMtls { url, .. } => Some(url),
+            Self::Enabled { url, .. } | Self::EnabledWithMtls { url, .. } => Some(url),
+        }
+    }
+
+    #[cfg(feature = "external_key_manager")]
+    pub fn data_encryption(&self) -> DataEncryptionMode {
+        match self {
+            Self::Disabled => DataEncryptionMode::Remote,
+            Self::Enabled {
+                data_encryption, ..
+            }
+            | Self::EnabledWithMtls {
+                data_encryption, ..
+            } => *data_encryption,
         }
     }
 
@@ -615,7 +1091,7 @@ impl ExternalKeyManagerConfig {
         match self {
             Self::Disabled => Ok(()),
             #[cfg(feature = "external_key_manager")]
-            Self::Enabled { url } => {
+            Self::Enabled { url, .. } => {
                 if url.trim().is_empty() {
                     return Err(crate::error::ConfigurationError::InvalidConfigurationValueError(
                         "external_key_manager.url is required when external key manager is enabled".into(),
@@ -624,7 +1100,7 @@ impl ExternalKeyManagerConfig {
                 Ok(())
             }
             #[cfg(feature = "external_key_manager")]
-            Self::EnabledWithMtls { url, ca_cert } => {
+            Self::EnabledWithMtls { url, ca_cert, .. } => {
                 if url.trim().is_empty() {
                     return Err(crate::error::ConfigurationError::InvalidConfigurationValueError(
                         "external_key_manager.url is required when external key manager is enabled".into(),
@@ -729,4 +1205,96 @@ mod tests {
             _ => assert!(false),
         }
     }
+
+    #[cfg(feature = "kms-hashicorp-vault")]
+    #[test]
+    fn test_hashicorp_transit_approle_case() {
+        use crate::crypto::secrets_manager::managers::hcvault::core::{
+            HashiCorpVaultAuth, HashiCorpVaultEngine,
+        };
+
+        let data = r#"
+        [secrets_management]
+        secrets_manager = "hashi_corp_vault"
+
+        [secrets_management.hashi_corp_vault]
+        url = "http://127.0.0.1:8200"
+
+        [secrets_management.hashi_corp_vault.auth]
+        method = "app_role"
+        role_id = "role"
+        secret_id = "secret"
+
+        [secrets_management.hashi_corp_vault.engine]
+        type = "transit"
+        key = "locker"
+        "#;
+        let parsed: TestDeser = serde_path_to_error::deserialize(
+            config::Config::builder()
+                .add_source(config::File::from_str(data, config::FileFormat::Toml))
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        match parsed.secrets_management {
+            SecretsManagementConfig::HashiCorpVault { hashi_corp_vault } => {
+                assert!(hashi_corp_vault.validate().is_ok());
+                assert!(matches!(
+                    hashi_corp_vault.auth,
+                    HashiCorpVaultAuth::AppRole { ref mount, .. } if mount == "approle"
+                ));
+                assert_eq!(
+                    hashi_corp_vault.engine,
+                    HashiCorpVaultEngine::Transit {
+                        mount: "transit".to_string(),
+                        key: "locker".to_string()
+                    }
+                );
+            }
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn caller_auth_resolves_the_role_of_a_known_key() {
+        let caller_auth = CallerAuthConfig {
+            api_keys: HashMap::from([
+                ("payments".to_string(), Secret::new("pay-key".to_string())),
+                (
+                    "support".to_string(),
+                    Secret::new("support-key".to_string()),
+                ),
+            ]),
+            admin_roles: vec!["support".to_string()],
+        };
+
+        assert_eq!(caller_auth.role_of("support-key"), Some("support"));
+        assert_eq!(caller_auth.role_of("support"), None);
+        assert_eq!(caller_auth.role_of(""), None);
+        let key_id = caller_auth.key_id_of("support-key").unwrap();
+        assert!(key_id.starts_with("support:"));
+        assert_eq!(key_id.len(), "support:".len() + 16);
+        assert_ne!(caller_auth.key_id_of("pay-key").unwrap(), key_id);
+        assert_eq!(caller_auth.key_id_of("support"), None);
+        assert!(caller_auth.is_admin("support"));
+        assert!(!caller_auth.is_admin("payments"));
+    }
+
+    #[test]
+    fn retrieve_config_guards_restricted_fields_in_projections() {
+        let retrieve = RetrieveConfig {
+            full_document_roles: Some(vec!["payments".to_string()]),
+            restricted_fields: vec!["/card/number".to_string()],
+        };
+        let fields = |fields: &[&str]| fields.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert!(retrieve.allows(Some("payments"), None));
+        assert!(retrieve.allows(Some("payments"), Some(&fields(&["/card/number"]))));
+        assert!(!retrieve.allows(Some("support"), None));
+        assert!(!retrieve.allows(None, Some(&fields(&["/card/number"]))));
+        assert!(!retrieve.allows(None, Some(&fields(&["/card"]))));
+        assert!(!retrieve.allows(None, Some(&fields(&["/name", "/card/number/0"]))));
+        assert!(retrieve.allows(None, Some(&fields(&["/card/numbers", "/card/expiry"]))));
+    }
 }
@@ -4,7 +4,11 @@ pub mod keymanager;
 pub mod secrets_manager;
 
 pub mod consts {
-    #[cfg(any(feature = "external_key_manager", feature = "kms-aws"))]
+    #[cfg(any(
+        feature = "external_key_manager",
+        feature = "kms-aws",
+        feature = "kms-hashicorp-vault"
+    ))]
     /// General purpose base64 engine
     pub(crate) const BASE64_ENGINE: base64::engine::GeneralPurpose =
         base64::engine::general_purpose::STANDARD;
@@ -1,4 +1,5 @@
 use error_stack::ResultExt;
+use hyperswitch_masking::{PeekInterface, StrongSecret};
 use ring::aead::{self, BoundKey};
 
 use crate::{
@@ -9,15 +10,17 @@ use crate::{
 /// GcmAes256
 ///
 /// The algorithm use to perform GcmAes256 encryption/decryption. This is implemented for data
-/// `Vec<u8>`
+/// `Vec<u8>`. The key is zeroed when the instance is dropped.
 ///
 pub struct GcmAes256 {
-    secret: Vec<u8>,
+    secret: StrongSecret<Vec<u8>>,
 }
 
 impl GcmAes256 {
     pub fn new(key: Vec<u8>) -> Self {
-        Self { secret: key }
+        Self {
+            secret: StrongSecret::new(key),
+        }
     }
 }
 
@@ -78,7 +81,7 @@ impl Encryption<Vec<u8>, Vec<u8>> for GcmAes256 {
         let nonce_sequence =
             NonceSequence::new().change_context(error::CryptoError::EncryptionError)?;
         let current_nonce = nonce_sequence.current();
-        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &self.secret)
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, self.secret.peek())
             .change_context(error::CryptoError::EncryptionError)?;
         let mut key = aead::SealingKey::new(key, nonce_sequence);
 
@@ -90,7 +93,7 @@ impl Encryption<Vec<u8>, Vec<u8>> for GcmAes256 {
     }
 
     fn decrypt(&self, input: Vec<u8>) -> Self::ReturnType<'_, Vec<u8>> {
-        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &self.secret)
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, self.secret.peek())
             .change_context(error::CryptoError::DecryptionError)?;
 
         let nonce_sequence = NonceSequence::from_bytes(
@@ -140,7 +143,7 @@ mod tests {
         let secret =
             hex::decode("000102030405060708090a0b0c0d0e0f000102030405060708090a0b0c0d0e0f")
                 .expect("Secret decoding");
-        let algorithm = GcmAes256 { secret };
+        let algorithm = GcmAes256::new(secret);
 
         let encoded_message = algorithm
             .encrypt(message.to_vec())
@@ -171,12 +174,8 @@ mod tests {
                  b094dac5d93471bdec1a502270e3cc6c"
             ).expect("Message decoding");
 
-        let algorithm1 = GcmAes256 {
-            secret: right_secret,
-        };
-        let algorithm2 = GcmAes256 {
-            secret: wrong_secret,
-        };
+        let algorithm1 = GcmAes256::new(right_secret);
+        let algorithm2 = GcmAes256::new(wrong_secret);
 
         let decoded = algorithm1
             .decrypt(message.clone())
@@ -1,3 +1,5 @@
+#[cfg(feature = "caching")]
+pub mod dek_cache;
 pub mod internal_keymanager;
 
 #[cfg(feature = "external_key_manager")]
@@ -57,6 +59,17 @@ pub trait CryptoOperationsManager: Send + Sync {
 }
 
 pub fn get_dek_manager(config: &ExternalKeyManagerConfig) -> Box<dyn KeyProvider> {
+    #[cfg(feature = "caching")]
+    {
+        Box::new(dek_cache::CachedKeyProvider(key_provider(config)))
+    }
+    #[cfg(not(feature = "caching"))]
+    {
+        key_provider(config)
+    }
+}
+
+fn key_provider(config: &ExternalKeyManagerConfig) -> Box<dyn KeyProvider> {
     match config {
         ExternalKeyManagerConfig::Disabled => Box::new(internal_keymanager::InternalKeyManager),
         #[cfg(feature = "external_key_manager")]
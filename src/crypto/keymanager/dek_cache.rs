@@ -0,0 +1,291 @@
+//! Read-through cache of ready [`CryptoOperationsManager`]s, keyed by entity id.
+//!
+//! Saves the merchant lookup and DEK decryption (internal key manager) or the entity lookup
+//! (external key manager) on every request. Entries expire a fixed TTL after insertion; the key
+//! material of an evicted internal manager is zeroed once the last request using it finishes.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use hyperswitch_masking::{Secret, StrongSecret};
+
+use super::{CreatedEntity, CryptoOperationsManager, KeyProvider};
+use crate::{
+    app::TenantAppState,
+    config,
+    error::{self, ContainerError},
+    observability::metrics,
+    storage::caching::{self, CacheReport},
+};
+
+const CACHE_NAME: &str = "dek";
+
+#[derive(Clone)]
+pub struct DekCache {
+    managers: moka::future::Cache<String, Arc<dyn CryptoOperationsManager>>,
+    stats: Arc<caching::CacheStats>,
+}
+
+impl DekCache {
+    /// `None` when the cache is disabled.
+    pub fn new(config: &config::DekCache) -> Option<Self> {
+        config.enabled.then(|| Self {
+            managers: moka::future::CacheBuilder::new(config.max_capacity)
+                .name(CACHE_NAME)
+                .time_to_live(std::time::Duration::from_secs(config.ttl))
+                .eviction_listener(|_key, _value, removal_cause| {
+                    caching::cache_eviction_listener(CACHE_NAME, removal_cause);
+                })
+                .build(),
+            stats: Arc::default(),
+        })
+    }
+
+    /// Return the cached manager of `entity_id`, or build it with `init`. Concurrent misses on
+    /// the same entity share one `init`; an error is returned to all of them and not cached.
+    async fn get_or_try_insert<F>(
+        &self,
+        entity_id: String,
+        init: F,
+    ) -> Result<Arc<dyn CryptoOperationsManager>, ContainerError<error::ApiError>>
+    where
+        F: Future<
+            Output = Result<Box<dyn CryptoOperationsManager>, ContainerError<error::ApiError>>,
+        >,
+    {
+        let initialized = AtomicBool::new(false);
+        let result = self
+            .managers
+            .try_get_with(entity_id, async {
+                initialized.store(true, Ordering::Relaxed);
+                init.await.map(Arc::from)
+            })
+            .await;
+
+        let hit = !initialized.load(Ordering::Relaxed);
+        self.stats.record_lookup(hit);
+        metrics::CACHE_LOOKUP_COUNT.add(
+            1,
+            crate::metric_attributes!(
+                ("cache", CACHE_NAME),
+                ("outcome", if hit { "hit" } else { "miss" })
+            ),
+        );
+
+        match result {
+            Ok(manager) => {
+                if !hit {
+                    metrics::CACHE_INSERT_COUNT
+                        .add(1, crate::metric_attributes!(("cache", CACHE_NAME)));
+                }
+                Ok(manager)
+            }
+            // Waiters that joined another request's `init` share its error.
+            Err(err) => Err(Arc::try_unwrap(err)
+                .unwrap_or_else(|shared| ContainerError::from(*shared.get_inner()))),
+        }
+    }
+
+    pub async fn invalidate(&self, entity_id: &str) {
+        self.managers.invalidate(entity_id).await;
+    }
+
+    pub fn flush(&self) {
+        self.managers.invalidate_all();
+    }
+
+    pub async fn report(&self) -> CacheReport {
+        self.managers.run_pending_tasks().await;
+        self.stats.report(
+            CACHE_NAME,
+            self.managers.entry_count(),
+            self.managers.weighted_size(),
+        )
+    }
+
+    pub async fn collect_entry_count(&self, tenant_id: &str) {
+        self.managers.run_pending_tasks().await;
+        metrics::CACHE_ENTRY_COUNT.record(
+            self.managers.entry_count(),
+            crate::metric_attributes!(("cache", CACHE_NAME), ("tenant_id", tenant_id.to_owned())),
+        );
+    }
+}
+
+impl TenantAppState {
+    /// Evict the cached key manager of `entity_id`, or all of them, on this pod.
+    pub(crate) async fn evict_dek(&self, entity_id: Option<&str>) {
+        let Some(dek_cache) = &self.dek_cache else {
+            return;
+        };
+
+        match entity_id {
+            Some(entity_id) => dek_cache.invalidate(entity_id).await,
+            None => dek_cache.flush(),
+        }
+    }
+}
+
+/// Fronts a [`KeyProvider`] with the tenant's [`DekCache`], when one is configured.
+pub struct CachedKeyProvider(pub Box<dyn KeyProvider>);
+
+#[async_trait::async_trait]
+impl KeyProvider for CachedKeyProvider {
+    async fn find_by_entity_id(
+        &self,
+        tenant_app_state: &TenantAppState,
+        entity_id: String,
+    ) -> Result<Box<dyn CryptoOperationsManager>, ContainerError<error::ApiError>> {
+        match &tenant_app_state.dek_cache {
+            Some(dek_cache) => dek_cache
+                .get_or_try_insert(
+                    entity_id.clone(),
+                    self.0.find_by_entity_id(tenant_app_state, entity_id),
+                )
+                .await
+                .map(|manager| Box::new(manager) as Box<dyn CryptoOperationsManager>),
+            None => self.0.find_by_entity_id(tenant_app_state, entity_id).await,
+        }
+    }
+
+    async fn find_or_create_entity(
+        &self,
+        tenant_app_state: &TenantAppState,
+        entity_id: String,
+    ) -> Result<Box<dyn CryptoOperationsManager>, ContainerError<error::ApiError>> {
+        match &tenant_app_state.dek_cache {
+            Some(dek_cache) => dek_cache
+                .get_or_try_insert(
+                    entity_id.clone(),
+                    self.0.find_or_create_entity(tenant_app_state, entity_id),
+                )
+                .await
+                .map(|manager| Box::new(manager) as Box<dyn CryptoOperationsManager>),
+            None => {
+                self.0
+                    .find_or_create_entity(tenant_app_state, entity_id)
+                    .await
+            }
+        }
+    }
+
+    async fn create_entity(
+        &self,
+        tenant_app_state: &TenantAppState,
+        entity_id: String,
+    ) -> Result<CreatedEntity, ContainerError<error::ApiError>> {
+        self.0.create_entity(tenant_app_state, entity_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl CryptoOperationsManager for Arc<dyn CryptoOperationsManager> {
+    async fn encrypt_data(
+        &self,
+        tenant_app_state: &TenantAppState,
+        decryted_data: StrongSecret<Vec<u8>>,
+    ) -> Result<Secret<Vec<u8>>, ContainerError<error::ApiError>> {
+        self.as_ref()
+            .encrypt_data(tenant_app_state, decryted_data)
+            .await
+    }
+
+    async fn decrypt_data(
+        &self,
+        tenant_app_state: &TenantAppState,
+        encrypted_data: Secret<Vec<u8>>,
+    ) -> Result<StrongSecret<Vec<u8>>, ContainerError<error::ApiError>> {
+        self.as_ref()
+            .decrypt_data(tenant_app_state, encrypted_data)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::sync::atomic::AtomicUsize;
+
+    use hyperswitch_masking::PeekInterface;
+
+    use super::*;
+
+    struct Plaintext;
+
+    #[async_trait::async_trait]
+    impl CryptoOperationsManager for Plaintext {
+        async fn encrypt_data(
+            &self,
+            _tenant_app_state: &TenantAppState,
+            decryted_data: StrongSecret<Vec<u8>>,
+        ) -> Result<Secret<Vec<u8>>, ContainerError<error::ApiError>> {
+            Ok(Secret::new(decryted_data.peek().clone()))
+        }
+
+        async fn decrypt_data(
+            &self,
+            _tenant_app_state: &TenantAppState,
+            encrypted_data: Secret<Vec<u8>>,
+        ) -> Result<StrongSecret<Vec<u8>>, ContainerError<error::ApiError>> {
+            Ok(StrongSecret::new(encrypted_data.peek().clone()))
+        }
+    }
+
+    fn dek_cache() -> DekCache {
+        DekCache::new(&config::DekCache {
+            enabled: true,
+            ttl: 60,
+            max_capacity: 10,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_share_one_init() {
+        let cache = dek_cache();
+        let inits = AtomicUsize::new(0);
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+
+        let first = cache.get_or_try_insert("entity".to_string(), async {
+            inits.fetch_add(1, Ordering::Relaxed);
+            released.await.unwrap();
+            Ok(Box::new(Plaintext) as Box<dyn CryptoOperationsManager>)
+        });
+        let second = cache.get_or_try_insert("entity".to_string(), async {
+            inits.fetch_add(1, Ordering::Relaxed);
+            Ok(Box::new(Plaintext) as Box<dyn CryptoOperationsManager>)
+        });
+        // Let both lookups reach the cache before the first init completes.
+        let unblock = async {
+            tokio::task::yield_now().await;
+            release.send(()).unwrap();
+        };
+
+        let (first, second, ()) = tokio::join!(first, second, unblock);
+
+        assert_eq!(inits.load(Ordering::Relaxed), 1);
+        assert!(Arc::ptr_eq(&first.unwrap(), &second.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn failed_init_is_not_cached() {
+        let cache = dek_cache();
+
+        let failed = cache
+            .get_or_try_insert("entity".to_string(), async {
+                Err(error::ApiError::RetrieveDataFailed("entity").into())
+            })
+            .await;
+        assert!(failed.is_err());
+
+        let retried = cache
+            .get_or_try_insert("entity".to_string(), async {
+                Ok(Box::new(Plaintext) as Box<dyn CryptoOperationsManager>)
+            })
+            .await;
+        assert!(retried.is_ok());
+    }
+}
@@ -1,20 +1,26 @@
+pub mod envelope;
+pub mod mock;
 pub mod types;
 pub mod utils;
 
-use hyperswitch_masking::{Secret, StrongSecret};
+use hyperswitch_masking::{PeekInterface, Secret, StrongSecret};
 
 pub use crate::config::ExternalKeyManagerConfig;
 use crate::{
-    api_client::{ApiResponse, Method},
+    api_client::{ApiResponse, Method, retry::RetryPolicy},
     app::TenantAppState,
-    crypto::keymanager::{
-        CryptoOperationsManager,
-        external_keymanager::{
-            self,
-            types::{
-                DataDecryptionRequest, DataDecryptionResponse, DataEncryptionRequest,
-                DataKeyCreateRequest, DataKeyCreateResponse, DataKeyTransferRequest,
-                DateEncryptionResponse, DecryptedData, EncryptedData,
+    config::{DataEncryptionMode, KeyManagerClientConfig},
+    crypto::{
+        encryption_manager::managers::aes::GcmAes256,
+        keymanager::{
+            CryptoOperationsManager,
+            external_keymanager::{
+                self,
+                types::{
+                    DataDecryptionRequest, DataDecryptionResponse, DataEncryptionRequest,
+                    DataKeyCreateRequest, DataKeyCreateResponse, DataKeyTransferRequest,
+                    DateEncryptionResponse, DecryptedData, EncryptedData,
+                },
             },
         },
     },
@@ -39,7 +45,7 @@ pub async fn create_key_in_key_manager(
 
     let response = call_encryption_service::<_, error::DataKeyCreationError>(
         tenant_app_state,
-        "key_create",
+        KeyManagerOperation::KeyCreate,
         url,
         Method::Post,
         request_body,
@@ -67,7 +73,7 @@ pub async fn transfer_key_to_key_manager(
 
     let response = call_encryption_service::<_, error::DataKeyTransferError>(
         tenant_app_state,
-        "key_transfer",
+        KeyManagerOperation::KeyTransfer,
         url,
         Method::Post,
         request_body,
@@ -93,7 +99,7 @@ pub async fn encrypt_data_using_key_manager(
 
     let response = call_encryption_service::<_, error::DataEncryptionError>(
         tenant_app_state,
-        "data_encrypt",
+        KeyManagerOperation::DataEncrypt,
         url,
         Method::Post,
         request_body,
@@ -119,7 +125,7 @@ pub async fn decrypt_data_using_key_manager(
 
     let response = call_encryption_service::<_, error::DataDecryptionError>(
         tenant_app_state,
-        "data_decrypt",
+        KeyManagerOperation::DataDecrypt,
         url,
         Method::Post,
         request_body,
@@ -150,7 +156,7 @@ pub async fn health_check_keymanager(
 
     call_encryption_service::<_, error::KeyManagerHealthCheckError>(
         tenant_app_state,
-        "health",
+        KeyManagerOperation::Health,
         url,
         Method::Get,
         (),
@@ -160,25 +166,105 @@ pub async fn health_check_keymanager(
     Ok(health::HealthState::Working)
 }
 
+/// Key manager API calls. Each data/key operation has its own [`RetryPolicy`] and goes through
+/// the shared circuit breaker; health checks always make a single unguarded attempt so they
+/// report the key manager's real state.
+#[derive(Debug, Clone, Copy, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum KeyManagerOperation {
+    KeyCreate,
+    KeyTransfer,
+    DataEncrypt,
+    DataDecrypt,
+    Health,
+}
+
+crate::impl_metric_value_from!(KeyManagerOperation);
+
+impl KeyManagerOperation {
+    fn retry_policy(self, config: &KeyManagerClientConfig) -> RetryPolicy {
+        match self {
+            Self::KeyCreate => config.key_create,
+            Self::KeyTransfer => config.key_transfer,
+            Self::DataEncrypt => config.data_encrypt,
+            Self::DataDecrypt => config.data_decrypt,
+            Self::Health => RetryPolicy::single_attempt(),
+        }
+    }
+
+    fn uses_circuit_breaker(self) -> bool {
+        !matches!(self, Self::Health)
+    }
+}
+
 pub async fn call_encryption_service<T, E>(
     tenant_app_state: &TenantAppState,
-    operation: &'static str,
+    operation: KeyManagerOperation,
     url: String,
     method: Method,
     request_body: T,
 ) -> Result<ApiResponse, ContainerError<E>>
 where
-    T: serde::Serialize + Send + Sync + 'static,
+    T: serde::Serialize + Clone + Send + Sync + 'static,
     ContainerError<E>: From<ContainerError<error::ApiClientError>> + Send + Sync,
 {
-    let headers = utils::get_key_manager_header(tenant_app_state);
-
-    let response = tenant_app_state
-        .api_client
-        .send_request(operation, url, headers, method, request_body)
-        .await?;
-
-    Ok(response)
+    let resilience = &tenant_app_state.api_client.key_manager;
+    let policy = operation.retry_policy(&resilience.policies);
+    let guarded = operation.uses_circuit_breaker();
+    let mut attempt = 1;
+
+    loop {
+        if guarded && !resilience.breaker.try_acquire() {
+            return Err(ContainerError::from(error::ApiClientError::CircuitOpen(
+                "external_key_manager",
+            ))
+            .into());
+        }
+
+        let headers = utils::get_key_manager_header(tenant_app_state);
+        let response = tokio::time::timeout(
+            policy.timeout(),
+            tenant_app_state.api_client.send_request(
+                operation.into(),
+                url.clone(),
+                headers,
+                method,
+                request_body.clone(),
+            ),
+        )
+        .await
+        .unwrap_or_else(|_| Err(error::ApiClientError::RequestTimedOut.into()));
+
+        let service_failure = response
+            .as_ref()
+            .err()
+            .is_some_and(|err| err.get_inner().is_service_failure());
+        if guarded {
+            if service_failure {
+                resilience.breaker.record_failure();
+            } else {
+                resilience.breaker.record_success();
+            }
+        }
+
+        match response {
+            Err(err) if service_failure && attempt < policy.max_attempts => {
+                let delay = policy.backoff(attempt);
+                logger::warn!(
+                    operation = ?operation,
+                    attempt,
+                    ?delay,
+                    error = ?err,
+                    "key manager call failed, retrying"
+                );
+                metrics::EXTERNAL_HTTP_RETRY_COUNT
+                    .add(1, crate::metric_attributes!(("purpose", operation)));
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            response => return Ok(response?),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -191,12 +277,11 @@ impl super::KeyProvider for ExternalKeyManager {
         tenant_app_state: &TenantAppState,
         entity_id: String,
     ) -> Result<Box<dyn CryptoOperationsManager>, ContainerError<error::ApiError>> {
-        Ok(tenant_app_state
-            .db
-            .find_by_entity_id(&entity_id)
-            .await
-            .map(ExternalCryptoManager::from_entity)
-            .map(Box::new)?)
+        let entity = tenant_app_state.db.find_by_entity_id(&entity_id).await?;
+
+        Ok(Box::new(
+            ExternalCryptoManager::load(tenant_app_state, entity).await?,
+        ))
     }
 
     async fn find_or_create_entity(
@@ -273,9 +358,9 @@ impl super::KeyProvider for ExternalKeyManager {
             },
         };
 
-        Ok(entity
-            .map(ExternalCryptoManager::from_entity)
-            .map(Box::new)?)
+        Ok(Box::new(
+            ExternalCryptoManager::load(tenant_app_state, entity?).await?,
+        ))
     }
 
     async fn create_entity(
@@ -312,15 +397,35 @@ impl super::KeyProvider for ExternalKeyManager {
     }
 }
 
-pub struct ExternalCryptoManager(Entity);
+/// Crypto operations for one entity. In [`DataEncryptionMode::LocalEnvelope`] the entity's DEK
+/// is unwrapped once when the manager is built; cache the manager (`dek_cache`) to avoid a key
+/// manager round trip per request.
+pub struct ExternalCryptoManager {
+    entity: Entity,
+    data_key: Option<GcmAes256>,
+}
 
 impl ExternalCryptoManager {
-    fn from_entity(entity: Entity) -> Self {
-        Self(entity)
+    async fn load(
+        tenant_app_state: &TenantAppState,
+        entity: Entity,
+    ) -> Result<Self, ContainerError<error::ApiError>> {
+        let data_key = match tenant_app_state
+            .config
+            .external_key_manager
+            .data_encryption()
+        {
+            DataEncryptionMode::Remote => None,
+            DataEncryptionMode::LocalEnvelope => {
+                Some(envelope::load_data_key(tenant_app_state, &entity).await?)
+            }
+        };
+
+        Ok(Self { entity, data_key })
     }
 
     fn get_inner(&self) -> &Entity {
-        &self.0
+        &self.entity
     }
 }
 
@@ -331,6 +436,10 @@ impl super::CryptoOperationsManager for ExternalCryptoManager {
         tenant_app_state: &TenantAppState,
         decryted_data: StrongSecret<Vec<u8>>,
     ) -> Result<Secret<Vec<u8>>, ContainerError<error::ApiError>> {
+        if let Some(data_key) = &self.data_key {
+            return envelope::seal(data_key, decryted_data);
+        }
+
         let encryption_req = DataEncryptionRequest::create_request(
             self.get_inner().enc_key_id.clone(),
             decryted_data,
@@ -346,6 +455,22 @@ impl super::CryptoOperationsManager for ExternalCryptoManager {
         tenant_app_state: &TenantAppState,
         encrypted_data: Secret<Vec<u8>>,
     ) -> Result<StrongSecret<Vec<u8>>, ContainerError<error::ApiError>> {
+        if envelope::is_envelope(encrypted_data.peek()) {
+            return match &self.data_key {
+                Some(data_key) => envelope::open(data_key, encrypted_data),
+                // Written in envelope mode, read after switching back to remote mode.
+                None => {
+                    let wrapped_dek = self.get_inner().wrapped_dek.clone().ok_or(
+                        error::ApiError::KeyManagerError("entity has no wrapped data key"),
+                    )?;
+                    let data_key =
+                        envelope::unwrap_data_key(tenant_app_state, self.get_inner(), wrapped_dek)
+                            .await?;
+                    envelope::open(&data_key, encrypted_data)
+                }
+            };
+        }
+
         let decryption_req = DataDecryptionRequest::create_request(
             self.get_inner().enc_key_id.clone(),
             encrypted_data,
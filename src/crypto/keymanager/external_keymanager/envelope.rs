@@ -0,0 +1,166 @@
+//! Local envelope encryption for the external key manager.
+//!
+//! Each entity owns a DEK generated by the locker and wrapped (encrypted) by the key manager
+//! under the entity's key. The wrapped form is persisted on the entity row; the unwrapped DEK
+//! only lives in memory. Payloads are sealed locally with [`GcmAes256`] and tagged with
+//! [`ENVELOPE_PREFIX`] so reads can tell them apart from ciphertext produced by the key
+//! manager's `/data/encrypt`, whatever mode the locker currently runs in.
+
+use base64::Engine;
+use hyperswitch_masking::{ExposeInterface, PeekInterface, Secret, StrongSecret};
+
+use super::types::{DataDecryptionRequest, DataEncryptionRequest};
+use crate::{
+    app::TenantAppState,
+    crypto::{
+        consts::BASE64_ENGINE,
+        encryption_manager::{
+            encryption_interface::Encryption,
+            managers::aes::{self, GcmAes256},
+        },
+    },
+    error::{self, ContainerError, ResultContainerExt},
+    logger,
+    storage::{EntityInterface, types::Entity},
+};
+
+/// Marks ciphertext sealed locally with an unwrapped DEK. Remote ciphertext is stored as
+/// `{version}:{base64}` as returned by the key manager, so the two can never collide.
+pub const ENVELOPE_PREFIX: &[u8] = b"envelope:v1:";
+
+/// AES-256 key length in bytes.
+const DATA_KEY_LEN: usize = 256 / 8;
+
+pub fn is_envelope(ciphertext: &[u8]) -> bool {
+    ciphertext.starts_with(ENVELOPE_PREFIX)
+}
+
+/// Encrypt `data` with the local DEK and tag the result with [`ENVELOPE_PREFIX`].
+pub fn seal(
+    key: &GcmAes256,
+    data: StrongSecret<Vec<u8>>,
+) -> Result<Secret<Vec<u8>>, ContainerError<error::ApiError>> {
+    let encrypted = key.encrypt(data.peek().clone())?;
+
+    let mut output = ENVELOPE_PREFIX.to_vec();
+    output.extend_from_slice(BASE64_ENGINE.encode(encrypted).as_bytes());
+
+    Ok(output.into())
+}
+
+/// Strip [`ENVELOPE_PREFIX`] and decrypt with the local DEK.
+pub fn open(
+    key: &GcmAes256,
+    ciphertext: Secret<Vec<u8>>,
+) -> Result<StrongSecret<Vec<u8>>, ContainerError<error::ApiError>> {
+    let ciphertext = ciphertext.expose();
+    let encoded = ciphertext
+        .strip_prefix(ENVELOPE_PREFIX)
+        .ok_or(error::ApiError::DecodingError)?;
+    let encrypted = BASE64_ENGINE
+        .decode(encoded)
+        .change_error(error::ApiError::DecodingError)?;
+
+    Ok(key.decrypt(encrypted)?.into())
+}
+
+/// Return the unwrapped DEK of `entity`, generating and wrapping a new one on first use.
+///
+/// Concurrent first uses race on [`EntityInterface::set_wrapped_dek`]; the losers discard their
+/// key and unwrap the winner's, so every pod ends up with the same DEK.
+pub async fn load_data_key(
+    tenant_app_state: &TenantAppState,
+    entity: &Entity,
+) -> Result<GcmAes256, ContainerError<error::ApiError>> {
+    if let Some(wrapped_dek) = &entity.wrapped_dek {
+        return unwrap_data_key(tenant_app_state, entity, wrapped_dek.clone()).await;
+    }
+
+    // The entity may come from a lagging replica; only generate a key if the primary has none.
+    let entity = &tenant_app_state
+        .db
+        .find_by_entity_id_on_primary(&entity.entity_id)
+        .await?;
+    if let Some(wrapped_dek) = &entity.wrapped_dek {
+        return unwrap_data_key(tenant_app_state, entity, wrapped_dek.clone()).await;
+    }
+
+    let data_key = StrongSecret::new(aes::generate_aes256_key().to_vec());
+    let wrapped_dek = super::encrypt_data_using_key_manager(
+        tenant_app_state,
+        DataEncryptionRequest::create_request(entity.enc_key_id.clone(), data_key.clone())?,
+    )
+    .await?
+    .inner();
+
+    let stored = tenant_app_state
+        .db
+        .set_wrapped_dek(&entity.entity_id, wrapped_dek.clone())
+        .await?;
+
+    match stored.wrapped_dek {
+        Some(stored_dek) if stored_dek.peek() == wrapped_dek.peek() => {
+            logger::info!(entity_id = %entity.entity_id, "generated wrapped data key for entity");
+            Ok(GcmAes256::new(data_key.peek().clone()))
+        }
+        Some(stored_dek) => unwrap_data_key(tenant_app_state, entity, stored_dek).await,
+        None => Err(error::ApiError::KeyManagerError("entity has no wrapped data key").into()),
+    }
+}
+
+/// Unwrap a stored DEK through the key manager's `/data/decrypt`.
+pub async fn unwrap_data_key(
+    tenant_app_state: &TenantAppState,
+    entity: &Entity,
+    wrapped_dek: Secret<Vec<u8>>,
+) -> Result<GcmAes256, ContainerError<error::ApiError>> {
+    let data_key = super::decrypt_data_using_key_manager(
+        tenant_app_state,
+        DataDecryptionRequest::create_request(entity.enc_key_id.clone(), wrapped_dek),
+    )
+    .await?
+    .inner();
+
+    if data_key.peek().len() != DATA_KEY_LEN {
+        return Err(
+            error::ApiError::KeyManagerError("unwrapped data key has an invalid length").into(),
+        );
+    }
+
+    Ok(GcmAes256::new(data_key.peek().clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+
+    use super::*;
+
+    #[test]
+    fn envelope_round_trip() {
+        let key = GcmAes256::new(aes::generate_aes256_key().to_vec());
+        let data = StrongSecret::new(b"4242424242424242".to_vec());
+
+        let sealed = seal(&key, data.clone()).unwrap();
+        assert!(is_envelope(sealed.peek()));
+
+        let opened = open(&key, sealed).unwrap();
+        assert_eq!(opened.peek(), data.peek());
+    }
+
+    #[test]
+    fn remote_ciphertext_is_not_envelope() {
+        assert!(!is_envelope(b"v1:c29tZSBkYXRh"));
+        assert!(!is_envelope(b""));
+    }
+
+    #[test]
+    fn open_rejects_wrong_key_and_missing_prefix() {
+        let key = GcmAes256::new(aes::generate_aes256_key().to_vec());
+        let other = GcmAes256::new(aes::generate_aes256_key().to_vec());
+        let sealed = seal(&key, StrongSecret::new(b"secret".to_vec())).unwrap();
+
+        assert!(open(&other, sealed).is_err());
+        assert!(open(&key, Secret::new(b"v1:c2VjcmV0".to_vec())).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! In-memory stand-in for the external key manager API.
+//!
+//! Speaks the same request/response shapes as the real service (`/key/create`,
+//! `/key/transfer`, `/data/encrypt`, `/data/decrypt`, `/health`) so the locker can be run and
+//! tested end to end without one. Keys live only in process memory and are shared across
+//! tenants. Not for production use.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing};
+use base64::Engine;
+use hyperswitch_masking::{ExposeInterface, PeekInterface, StrongSecret};
+
+use super::types::{
+    DataDecryptionRequest, DataDecryptionResponse, DataEncryptionRequest, DataKeyCreateRequest,
+    DataKeyCreateResponse, DataKeyTransferRequest, DateEncryptionResponse, DecryptedData,
+    EncryptedData,
+};
+use crate::crypto::{
+    consts::BASE64_ENGINE,
+    encryption_manager::{encryption_interface::Encryption, managers::aes},
+};
+
+const KEY_VERSION: &str = "v1";
+
+type MockResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+#[derive(Clone, Default)]
+pub struct MockKeyManager {
+    keys: Arc<RwLock<HashMap<String, StrongSecret<Vec<u8>>>>>,
+}
+
+impl MockKeyManager {
+    fn insert(&self, identifier: String, key: Vec<u8>) -> Result<(), (StatusCode, String)> {
+        self.keys
+            .write()
+            .map_err(|_| internal("key store poisoned"))?
+            .insert(identifier, StrongSecret::new(key));
+        Ok(())
+    }
+
+    fn cipher(&self, identifier: &str) -> Result<aes::GcmAes256, (StatusCode, String)> {
+        let keys = self
+            .keys
+            .read()
+            .map_err(|_| internal("key store poisoned"))?;
+        let key = keys
+            .get(identifier)
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("unknown key {identifier}")))?;
+        Ok(aes::GcmAes256::new(key.peek().clone()))
+    }
+}
+
+/// Router serving the key manager API from an empty key store.
+pub fn router() -> Router {
+    Router::new()
+        .route("/health", routing::get(|| async { StatusCode::OK }))
+        .route("/key/create", routing::post(create_key))
+        .route("/key/transfer", routing::post(transfer_key))
+        .route("/data/encrypt", routing::post(encrypt_data))
+        .route("/data/decrypt", routing::post(decrypt_data))
+        .with_state(MockKeyManager::default())
+}
+
+fn internal(message: &str) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+}
+
+fn bad_request(message: &str) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message.to_string())
+}
+
+async fn create_key(
+    State(state): State<MockKeyManager>,
+    Json(request): Json<DataKeyCreateRequest>,
+) -> MockResult<DataKeyCreateResponse> {
+    state.insert(
+        request.identifier.get_identifier(),
+        aes::generate_aes256_key().to_vec(),
+    )?;
+
+    Ok(Json(DataKeyCreateResponse {
+        identifier: request.identifier,
+        key_version: KEY_VERSION.to_string(),
+    }))
+}
+
+async fn transfer_key(
+    State(state): State<MockKeyManager>,
+    Json(request): Json<DataKeyTransferRequest>,
+) -> MockResult<DataKeyCreateResponse> {
+    let key = BASE64_ENGINE
+        .decode(&request.key)
+        .map_err(|_| bad_request("key is not valid base64"))?;
+    state.insert(request.identifier.get_identifier(), key)?;
+
+    Ok(Json(DataKeyCreateResponse {
+        identifier: request.identifier,
+        key_version: KEY_VERSION.to_string(),
+    }))
+}
+
+async fn encrypt_data(
+    State(state): State<MockKeyManager>,
+    Json(request): Json<DataEncryptionRequest>,
+) -> MockResult<DateEncryptionResponse> {
+    let cipher = state.cipher(&request.identifier.get_identifier())?;
+    let encrypted = cipher
+        .encrypt(request.data.inner().peek().clone())
+        .map_err(|_| internal("encryption failed"))?;
+    let encoded = format!("{KEY_VERSION}:{}", BASE64_ENGINE.encode(encrypted));
+
+    Ok(Json(DateEncryptionResponse {
+        data: EncryptedData::from_secret(encoded.into_bytes().into()),
+    }))
+}
+
+async fn decrypt_data(
+    State(state): State<MockKeyManager>,
+    Json(request): Json<DataDecryptionRequest>,
+) -> MockResult<DataDecryptionResponse> {
+    let cipher = state.cipher(&request.identifier.get_identifier())?;
+    let data = request.data.inner().expose();
+    let encoded = data
+        .strip_prefix(format!("{KEY_VERSION}:").as_bytes())
+        .ok_or_else(|| bad_request("unsupported ciphertext version"))?;
+    let encrypted = BASE64_ENGINE
+        .decode(encoded)
+        .map_err(|_| bad_request("ciphertext is not valid base64"))?;
+    let decrypted = cipher
+        .decrypt(encrypted)
+        .map_err(|_| bad_request("decryption failed"))?;
+
+    Ok(Json(DataDecryptionResponse {
+        data: DecryptedData::from_secret(decrypted.into()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+
+    use axum_test::TestServer;
+    use hyperswitch_masking::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn wrap_and_unwrap_round_trip() {
+        let server = TestServer::new(router()).unwrap();
+
+        let created: DataKeyCreateResponse = server
+            .post("/key/create")
+            .json(&DataKeyCreateRequest::create_request())
+            .await
+            .json();
+        let identifier = created.identifier.get_identifier();
+
+        let data_key = StrongSecret::new(aes::generate_aes256_key().to_vec());
+        let request =
+            DataEncryptionRequest::create_request(identifier.clone(), data_key.clone()).unwrap();
+        let wrapped: DateEncryptionResponse =
+            server.post("/data/encrypt").json(&request).await.json();
+        let wrapped = wrapped.data.inner();
+        assert!(wrapped.peek().starts_with(b"v1:"));
+
+        let request = DataDecryptionRequest::create_request(identifier, wrapped);
+        let unwrapped: DataDecryptionResponse =
+            server.post("/data/decrypt").json(&request).await.json();
+        assert_eq!(unwrapped.data.inner().peek(), data_key.peek());
+    }
+
+    #[tokio::test]
+    async fn unknown_key_is_rejected() {
+        let server = TestServer::new(router()).unwrap();
+
+        let request = DataDecryptionRequest::create_request(
+            "missing".to_string(),
+            Secret::new(b"v1:AAAA".to_vec()),
+        );
+        server
+            .post("/data/decrypt")
+            .json(&request)
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -50,7 +50,7 @@ impl DataKeyTransferRequest {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DataEncryptionRequest {
     #[serde(flatten)]
     pub identifier: Identifier,
@@ -75,7 +75,7 @@ pub struct DateEncryptionResponse {
     pub data: EncryptedData,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DataDecryptionRequest {
     #[serde(flatten)]
     pub identifier: Identifier,
@@ -1,5 +1,6 @@
 #[cfg(feature = "kms-aws")]
 pub mod aws_kms;
+pub mod file;
 #[cfg(feature = "kms-hashicorp-vault")]
 pub mod hcvault;
 pub mod hollow;
@@ -0,0 +1,166 @@
+//! Resolution of secrets mounted as files or passed through environment variables
+
+use std::path::Path;
+
+use error_stack::{ResultExt, report};
+
+/// Prefix of values read from a file, e.g. `file:///run/secrets/master_key`.
+const FILE_PREFIX: &str = "file://";
+/// Prefix of values read from an environment variable, e.g. `env:LOCKER_MASTER_KEY`.
+const ENV_PREFIX: &str = "env:";
+
+/// Secrets manager resolving `file://` and `env:` references. Any other value is used verbatim.
+#[derive(Debug, Clone)]
+pub struct FileSecretsManager;
+
+/// Where the value of a secret comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretReference<'a> {
+    /// The contents of the file at this absolute path, without the trailing newline.
+    File(&'a Path),
+    /// The value of this environment variable.
+    Env(&'a str),
+    /// The configured value itself.
+    Literal(&'a str),
+}
+
+impl<'a> SecretReference<'a> {
+    /// Classify a configured value.
+    pub fn parse(value: &'a str) -> error_stack::Result<Self, FileSecretError> {
+        if let Some(path) = value.strip_prefix(FILE_PREFIX) {
+            let path = Path::new(path);
+            if !path.is_absolute() {
+                return Err(report!(FileSecretError::InvalidReference))
+                    .attach_printable("file references must be absolute, as in file:///path");
+            }
+            return Ok(Self::File(path));
+        }
+
+        if let Some(name) = value.strip_prefix(ENV_PREFIX) {
+            if name.is_empty() {
+                return Err(report!(FileSecretError::InvalidReference))
+                    .attach_printable("env references must name a variable");
+            }
+            return Ok(Self::Env(name));
+        }
+
+        Ok(Self::Literal(value))
+    }
+
+    /// Check that the referenced secret exists and, for files, is not readable by everyone.
+    pub fn check(&self) -> error_stack::Result<(), FileSecretError> {
+        match self {
+            Self::File(path) => check_file(path),
+            Self::Env(name) => std::env::var(name)
+                .map(|_| ())
+                .change_context(FileSecretError::MissingEnvVar)
+                .attach_printable_lazy(|| format!("{name} is not set")),
+            Self::Literal(_) => Ok(()),
+        }
+    }
+
+    /// The value of the secret.
+    pub fn resolve(&self) -> error_stack::Result<String, FileSecretError> {
+        match self {
+            Self::File(path) => {
+                check_file(path)?;
+                let contents = std::fs::read_to_string(path)
+                    .change_context(FileSecretError::ReadFailed)
+                    .attach_printable_lazy(|| format!("Failed to read {}", path.display()))?;
+                // files written by `echo` or editors end with a newline that is not part of the secret
+                Ok(contents.trim_end_matches(['\n', '\r']).to_string())
+            }
+            Self::Env(name) => std::env::var(name)
+                .change_context(FileSecretError::MissingEnvVar)
+                .attach_printable_lazy(|| format!("{name} is not set")),
+            Self::Literal(value) => Ok((*value).to_string()),
+        }
+    }
+}
+
+fn check_file(path: &Path) -> error_stack::Result<(), FileSecretError> {
+    let metadata = std::fs::metadata(path)
+        .change_context(FileSecretError::ReadFailed)
+        .attach_printable_lazy(|| format!("Failed to access {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if metadata.permissions().mode() & 0o004 != 0 {
+            return Err(report!(FileSecretError::WorldReadable)).attach_printable_lazy(|| {
+                format!("{} must not be readable by other users", path.display())
+            });
+        }
+    }
+
+    if !metadata.is_file() {
+        return Err(report!(FileSecretError::ReadFailed))
+            .attach_printable_lazy(|| format!("{} is not a file", path.display()));
+    }
+
+    Ok(())
+}
+
+/// Errors that can occur while resolving file and environment secrets.
+#[derive(Debug, thiserror::Error)]
+pub enum FileSecretError {
+    /// The reference is not a valid `file://` or `env:` reference
+    #[error("Invalid secret reference")]
+    InvalidReference,
+
+    /// The referenced file is missing or could not be read
+    #[error("Failed while reading the secret file")]
+    ReadFailed,
+
+    /// The referenced file can be read by any user
+    #[error("Secret file is readable by all users")]
+    WorldReadable,
+
+    /// The referenced environment variable is not set or not valid UTF-8
+    #[error("Secret environment variable is not set")]
+    MissingEnvVar,
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+
+    use super::*;
+
+    #[test]
+    fn references_are_classified() {
+        assert_eq!(
+            SecretReference::parse("file:///run/secrets/master_key").unwrap(),
+            SecretReference::File(Path::new("/run/secrets/master_key"))
+        );
+        assert_eq!(
+            SecretReference::parse("env:LOCKER_MASTER_KEY").unwrap(),
+            SecretReference::Env("LOCKER_MASTER_KEY")
+        );
+        assert_eq!(
+            SecretReference::parse("postgres").unwrap(),
+            SecretReference::Literal("postgres")
+        );
+        assert!(SecretReference::parse("file://relative/path").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn world_readable_files_are_rejected() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("locker-secret-{}", uuid::Uuid::now_v7()));
+        std::fs::write(&path, "secret\n").unwrap();
+        let reference = SecretReference::File(&path);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(reference.check().is_err());
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(reference.resolve().unwrap(), "secret");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(reference.check().is_err());
+    }
+}
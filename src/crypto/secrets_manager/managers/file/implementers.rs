@@ -0,0 +1,20 @@
+use error_stack::ResultExt;
+use hyperswitch_masking::{PeekInterface, Secret};
+
+use crate::crypto::secrets_manager::{
+    managers::file::core::{FileSecretsManager, SecretReference},
+    secrets_interface::{SecretManager, SecretsManagementError},
+};
+
+#[async_trait::async_trait]
+impl SecretManager for FileSecretsManager {
+    async fn get_secret(
+        &self,
+        input: Secret<String>,
+    ) -> error_stack::Result<Secret<String>, SecretsManagementError> {
+        SecretReference::parse(input.peek())
+            .and_then(|reference| reference.resolve())
+            .map(Into::into)
+            .change_context(SecretsManagementError::FetchSecretFailed)
+    }
+}
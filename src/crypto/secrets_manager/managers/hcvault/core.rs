@@ -1,18 +1,35 @@
 //! Interactions with the HashiCorp Vault
 
-use std::{collections::HashMap, future::Future, pin::Pin};
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    time::{Duration, Instant},
+};
 
+use base64::Engine as _;
 use error_stack::{Report, ResultExt};
 use hyperswitch_masking::{PeekInterface, Secret};
-use vaultrs::client::{VaultClient, VaultClientSettingsBuilder};
+use tokio::sync::{RwLock, RwLockReadGuard};
+use vaultrs::client::{Client, VaultClient, VaultClientSettingsBuilder};
 
-use crate::error::ConfigurationError;
+use crate::{crypto::consts::BASE64_ENGINE, error::ConfigurationError, logger};
 
 #[allow(missing_debug_implementations)]
 /// A struct representing a connection to HashiCorp Vault.
 pub struct HashiCorpVault {
     /// The underlying client used for interacting with HashiCorp Vault.
+    session: RwLock<Session>,
+    auth: HashiCorpVaultAuth,
+    engine: HashiCorpVaultEngine,
+}
+
+/// The client together with the lifetime of the token it carries.
+struct Session {
     client: VaultClient,
+    /// When to log in again; `None` for tokens that are not renewed by the locker.
+    renew_at: Option<Instant>,
 }
 
 /// Configuration for connecting to HashiCorp Vault.
@@ -21,8 +38,68 @@ pub struct HashiCorpVault {
 pub struct HashiCorpVaultConfig {
     /// The URL of the HashiCorp Vault server.
     pub url: String,
-    /// The authentication token used to access HashiCorp Vault.
+    /// The authentication token used to access HashiCorp Vault, when `auth.method` is `token`.
     pub token: Secret<String>,
+    /// How the locker authenticates against HashiCorp Vault.
+    pub auth: HashiCorpVaultAuth,
+    /// How the protected configuration values are resolved.
+    pub engine: HashiCorpVaultEngine,
+}
+
+/// Authentication methods supported for HashiCorp Vault.
+#[derive(Clone, Debug, Default, serde::Deserialize, Eq, PartialEq)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum HashiCorpVaultAuth {
+    /// A static token, taken from `token`.
+    #[default]
+    Token,
+    /// AppRole login with a role id and a secret id.
+    AppRole {
+        #[serde(default = "default_approle_mount")]
+        mount: String,
+        role_id: String,
+        secret_id: Secret<String>,
+    },
+    /// Kubernetes login with the service account token of the pod.
+    Kubernetes {
+        #[serde(default = "default_kubernetes_mount")]
+        mount: String,
+        role: String,
+        #[serde(default = "default_service_account_token_path")]
+        jwt_path: PathBuf,
+    },
+}
+
+fn default_approle_mount() -> String {
+    "approle".to_string()
+}
+
+fn default_kubernetes_mount() -> String {
+    "kubernetes".to_string()
+}
+
+fn default_service_account_token_path() -> PathBuf {
+    PathBuf::from("/var/run/secrets/kubernetes.io/serviceaccount/token")
+}
+
+/// Secrets engines supported for HashiCorp Vault.
+#[derive(Clone, Debug, Default, serde::Deserialize, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HashiCorpVaultEngine {
+    /// Values are `mount:path[:key[:version]]` locations in a KV version 2 engine. `key` defaults
+    /// to `value`; without a `version` the latest version is read.
+    #[default]
+    Kv2,
+    /// Values are ciphertexts (`vault:v1:...`) decrypted with the Transit key `key`.
+    Transit {
+        #[serde(default = "default_transit_mount")]
+        mount: String,
+        key: String,
+    },
+}
+
+fn default_transit_mount() -> String {
+    "transit".to_string()
 }
 
 impl HashiCorpVaultConfig {
@@ -34,10 +111,38 @@ impl HashiCorpVaultConfig {
             ));
         }
 
-        if self.token.peek().trim().is_empty() {
-            return Err(ConfigurationError::InvalidConfigurationValueError(
-                "HashiCorp vault token must not be empty".into(),
-            ));
+        match &self.auth {
+            HashiCorpVaultAuth::Token => {
+                if self.token.peek().trim().is_empty() {
+                    return Err(ConfigurationError::InvalidConfigurationValueError(
+                        "HashiCorp vault token must not be empty".into(),
+                    ));
+                }
+            }
+            HashiCorpVaultAuth::AppRole {
+                role_id, secret_id, ..
+            } => {
+                if role_id.trim().is_empty() || secret_id.peek().trim().is_empty() {
+                    return Err(ConfigurationError::InvalidConfigurationValueError(
+                        "HashiCorp vault approle role_id and secret_id must not be empty".into(),
+                    ));
+                }
+            }
+            HashiCorpVaultAuth::Kubernetes { role, .. } => {
+                if role.trim().is_empty() {
+                    return Err(ConfigurationError::InvalidConfigurationValueError(
+                        "HashiCorp vault kubernetes role must not be empty".into(),
+                    ));
+                }
+            }
+        }
+
+        if let HashiCorpVaultEngine::Transit { key, .. } = &self.engine {
+            if key.trim().is_empty() {
+                return Err(ConfigurationError::InvalidConfigurationValueError(
+                    "HashiCorp vault transit key must not be empty".into(),
+                ));
+            }
         }
 
         Ok(())
@@ -68,27 +173,113 @@ pub trait Engine: Sized {
 #[derive(Debug)]
 pub enum Kv2 {}
 
+/// A `mount:path[:key[:version]]` location in a KV version 2 engine.
+#[derive(Debug, PartialEq, Eq)]
+struct Kv2Location<'a> {
+    mount: &'a str,
+    path: &'a str,
+    key: &'a str,
+    /// `None` reads the latest version.
+    version: Option<u64>,
+}
+
+impl<'a> Kv2Location<'a> {
+    fn parse(location: &'a str) -> error_stack::Result<Self, HashiCorpError> {
+        let mut split = location.split(':');
+        let mount = split.next().ok_or(HashiCorpError::IncompleteData)?;
+        let path = split.next().ok_or(HashiCorpError::IncompleteData)?;
+        let key = split.next().unwrap_or("value");
+        let version = split
+            .next()
+            .map(|version| {
+                version
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|version| *version > 0)
+                    .ok_or(HashiCorpError::IncompleteData)
+            })
+            .transpose()
+            .attach_printable("KV version must be a positive integer")?;
+
+        Ok(Self {
+            mount,
+            path,
+            key,
+            version,
+        })
+    }
+}
+
 impl Engine for Kv2 {
     type ReturnType<'b, T: 'b> =
         Pin<Box<dyn Future<Output = error_stack::Result<T, HashiCorpError>> + Send + 'b>>;
     fn read(client: &HashiCorpVault, location: String) -> Self::ReturnType<'_, String> {
         Box::pin(async move {
-            let mut split = location.split(':');
-            let mount = split.next().ok_or(HashiCorpError::IncompleteData)?;
-            let path = split.next().ok_or(HashiCorpError::IncompleteData)?;
-            let key = split.next().unwrap_or("value");
+            let Kv2Location {
+                mount,
+                path,
+                key,
+                version,
+            } = Kv2Location::parse(&location)?;
 
-            let mut output =
-                vaultrs::kv2::read::<HashMap<String, String>>(&client.client, mount, path)
+            let session = client.session().await?;
+            let mut output = match version {
+                Some(version) => {
+                    vaultrs::kv2::read_version::<HashMap<String, String>>(
+                        &session.client,
+                        mount,
+                        path,
+                        version,
+                    )
                     .await
-                    .map_err(Into::<Report<_>>::into)
-                    .change_context(HashiCorpError::FetchFailed)?;
+                }
+                None => {
+                    vaultrs::kv2::read::<HashMap<String, String>>(&session.client, mount, path)
+                        .await
+                }
+            }
+            .map_err(Into::<Report<_>>::into)
+            .change_context(HashiCorpError::FetchFailed)?;
 
             Ok(output.remove(key).ok_or(HashiCorpError::ParseError)?)
         })
     }
 }
 
+/// An implementation of the `Engine` trait for the Transit engine. The location is the
+/// ciphertext to decrypt with the configured Transit key.
+#[derive(Debug)]
+pub enum Transit {}
+
+impl Engine for Transit {
+    type ReturnType<'b, T: 'b> =
+        Pin<Box<dyn Future<Output = error_stack::Result<T, HashiCorpError>> + Send + 'b>>;
+    fn read(client: &HashiCorpVault, location: String) -> Self::ReturnType<'_, String> {
+        Box::pin(async move {
+            let HashiCorpVaultEngine::Transit { mount, key } = &client.engine else {
+                return Err(error_stack::report!(
+                    HashiCorpError::ConfigurationBuildFailed
+                ))
+                .attach_printable("HashiCorp vault is not configured with a transit engine");
+            };
+
+            let session = client.session().await?;
+            let output =
+                vaultrs::transit::data::decrypt(&session.client, mount, key, &location, None)
+                    .await
+                    .map_err(Into::<Report<_>>::into)
+                    .change_context(HashiCorpError::DecryptionFailed)?;
+
+            // Transit returns the plaintext base64 encoded
+            let plaintext = BASE64_ENGINE
+                .decode(output.plaintext)
+                .change_context(HashiCorpError::Base64DecodingFailed)?;
+
+            String::from_utf8(plaintext).change_context(HashiCorpError::Utf8DecodingFailed)
+        })
+    }
+}
+
 impl HashiCorpVault {
     /// Creates a new instance of HashiCorpVault based on the provided configuration.
     ///
@@ -96,8 +287,10 @@ impl HashiCorpVault {
     ///
     /// - `config`: A reference to a `HashiCorpVaultConfig` containing the configuration details.
     ///
-    pub fn new(config: &HashiCorpVaultConfig) -> error_stack::Result<Self, HashiCorpError> {
-        VaultClient::new(
+    /// For AppRole and Kubernetes authentication this logs in right away, so that a wrong role
+    /// fails at startup rather than on the first read.
+    pub async fn new(config: &HashiCorpVaultConfig) -> error_stack::Result<Self, HashiCorpError> {
+        let client = VaultClient::new(
             VaultClientSettingsBuilder::default()
                 .address(&config.url)
                 .token(config.token.peek())
@@ -107,8 +300,48 @@ impl HashiCorpVault {
                 .attach_printable("Failed while building vault settings")?,
         )
         .map_err(Into::<Report<_>>::into)
-        .change_context(HashiCorpError::ClientCreationFailed)
-        .map(|client| Self { client })
+        .change_context(HashiCorpError::ClientCreationFailed)?;
+
+        let mut session = Session {
+            client,
+            renew_at: None,
+        };
+        login(&config.auth, &mut session).await?;
+
+        Ok(Self {
+            session: RwLock::new(session),
+            auth: config.auth.clone(),
+            engine: config.engine.clone(),
+        })
+    }
+
+    /// The secrets engine the configured values are resolved with.
+    pub fn engine(&self) -> &HashiCorpVaultEngine {
+        &self.engine
+    }
+
+    /// The client, after logging in again if its token is about to expire.
+    async fn session(&self) -> error_stack::Result<RwLockReadGuard<'_, Session>, HashiCorpError> {
+        {
+            let session = self.session.read().await;
+            if session
+                .renew_at
+                .is_none_or(|renew_at| renew_at > Instant::now())
+            {
+                return Ok(session);
+            }
+        }
+
+        let mut session = self.session.write().await;
+        // another task may have logged in while we waited for the lock
+        if session
+            .renew_at
+            .is_some_and(|renew_at| renew_at <= Instant::now())
+        {
+            login(&self.auth, &mut session).await?;
+        }
+
+        Ok(session.downgrade())
     }
 
     /// Asynchronously fetches data from HashiCorp Vault using the specified engine.
@@ -140,6 +373,58 @@ impl HashiCorpVault {
     }
 }
 
+/// Log in with `auth` and put the resulting token on the session client. A no-op for static
+/// tokens, which are set when the client is built.
+async fn login(
+    auth: &HashiCorpVaultAuth,
+    session: &mut Session,
+) -> error_stack::Result<(), HashiCorpError> {
+    let auth_info = match auth {
+        HashiCorpVaultAuth::Token => return Ok(()),
+        HashiCorpVaultAuth::AppRole {
+            mount,
+            role_id,
+            secret_id,
+        } => vaultrs::auth::approle::login(&session.client, mount, role_id, secret_id.peek())
+            .await
+            .map_err(Into::<Report<_>>::into)
+            .change_context(HashiCorpError::LoginFailed)
+            .attach_printable("AppRole login failed")?,
+        HashiCorpVaultAuth::Kubernetes {
+            mount,
+            role,
+            jwt_path,
+        } => {
+            let jwt = tokio::fs::read_to_string(jwt_path)
+                .await
+                .change_context(HashiCorpError::LoginFailed)
+                .attach_printable_lazy(|| {
+                    format!(
+                        "Failed to read the service account token at {}",
+                        jwt_path.display()
+                    )
+                })?;
+            vaultrs::auth::kubernetes::login(&session.client, mount, role, jwt.trim())
+                .await
+                .map_err(Into::<Report<_>>::into)
+                .change_context(HashiCorpError::LoginFailed)
+                .attach_printable("Kubernetes login failed")?
+        }
+    };
+
+    session.client.set_token(&auth_info.client_token);
+    // log in again once three quarters of the lease have passed
+    session.renew_at = (auth_info.lease_duration > 0).then(|| {
+        Instant::now() + Duration::from_secs(auth_info.lease_duration.saturating_mul(3) / 4)
+    });
+    logger::info!(
+        lease_duration = auth_info.lease_duration,
+        "Logged in to HashiCorp vault"
+    );
+
+    Ok(())
+}
+
 /// A trait for types that can be constructed from encoded data in the form of a String.
 pub trait FromEncoded: Sized {
     fn from_encoded(input: String) -> Option<Self>;
@@ -199,4 +484,180 @@ pub enum HashiCorpError {
     /// Failed while parsing received data
     #[error("Failed while parsing the response")]
     ParseError,
+
+    /// Failed while logging in to obtain a token
+    #[error("Failed while logging in to the vault")]
+    LoginFailed,
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn kv2_location_defaults_key_and_version() {
+        assert_eq!(
+            Kv2Location::parse("secret:locker/master_key").unwrap(),
+            Kv2Location {
+                mount: "secret",
+                path: "locker/master_key",
+                key: "value",
+                version: None,
+            }
+        );
+    }
+
+    #[test]
+    fn kv2_location_reads_key_and_version() {
+        assert_eq!(
+            Kv2Location::parse("secret:locker/master_key:key:3").unwrap(),
+            Kv2Location {
+                mount: "secret",
+                path: "locker/master_key",
+                key: "key",
+                version: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn kv2_location_needs_a_path() {
+        assert!(matches!(
+            Kv2Location::parse("secret").unwrap_err().current_context(),
+            HashiCorpError::IncompleteData
+        ));
+    }
+
+    #[test]
+    fn kv2_location_rejects_invalid_versions() {
+        for location in [
+            "secret:locker:value:0",
+            "secret:locker:value:-1",
+            "secret:locker:value:latest",
+            "secret:locker:value:",
+        ] {
+            assert!(
+                matches!(
+                    Kv2Location::parse(location).unwrap_err().current_context(),
+                    HashiCorpError::IncompleteData
+                ),
+                "{location}"
+            );
+        }
+    }
+
+    /// A root client for the development server from the setup guide.
+    fn dev_server() -> (String, VaultClient) {
+        let url = std::env::var("VAULT_ADDR").unwrap_or_else(|_| "http://127.0.0.1:8200".into());
+        let token = std::env::var("VAULT_TOKEN").unwrap_or_else(|_| "root".into());
+        let client = VaultClient::new(
+            VaultClientSettingsBuilder::default()
+                .address(&url)
+                .token(token)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        (url, client)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a HashiCorp Vault development server"]
+    async fn transit_decrypts_ciphertexts() {
+        let (url, root) = dev_server();
+        // fails when the engine is already enabled
+        let _ = vaultrs::sys::mount::enable(&root, "transit", "transit", None).await;
+        vaultrs::transit::key::create(&root, "transit", "locker-test", None)
+            .await
+            .unwrap();
+        let ciphertext = vaultrs::transit::data::encrypt(
+            &root,
+            "transit",
+            "locker-test",
+            &BASE64_ENGINE.encode("master key"),
+            None,
+        )
+        .await
+        .unwrap()
+        .ciphertext;
+
+        let vault = HashiCorpVault::new(&HashiCorpVaultConfig {
+            url,
+            token: root.settings().token.clone().into(),
+            auth: HashiCorpVaultAuth::Token,
+            engine: HashiCorpVaultEngine::Transit {
+                mount: "transit".into(),
+                key: "locker-test".into(),
+            },
+        })
+        .await
+        .unwrap();
+
+        let plaintext = vault
+            .fetch::<Transit, Secret<String>>(ciphertext)
+            .await
+            .unwrap();
+        assert_eq!(plaintext.peek(), "master key");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a HashiCorp Vault development server"]
+    async fn session_logs_in_again_before_the_token_expires() {
+        let (url, root) = dev_server();
+        // fails when the method is already enabled
+        let _ = vaultrs::sys::auth::enable(&root, "approle", "approle", None).await;
+        vaultrs::auth::approle::role::set(
+            &root,
+            "approle",
+            "locker-test",
+            Some(
+                vaultrs::api::auth::approle::requests::SetAppRoleRequest::builder().token_ttl("4s"),
+            ),
+        )
+        .await
+        .unwrap();
+        let role_id = vaultrs::auth::approle::role::read_id(&root, "approle", "locker-test")
+            .await
+            .unwrap()
+            .role_id;
+        let secret_id =
+            vaultrs::auth::approle::role::secret::generate(&root, "approle", "locker-test", None)
+                .await
+                .unwrap()
+                .secret_id;
+
+        let vault = HashiCorpVault::new(&HashiCorpVaultConfig {
+            url,
+            token: Secret::default(),
+            auth: HashiCorpVaultAuth::AppRole {
+                mount: "approle".into(),
+                role_id,
+                secret_id: secret_id.into(),
+            },
+            engine: HashiCorpVaultEngine::Kv2,
+        })
+        .await
+        .unwrap();
+
+        let first = vault
+            .session()
+            .await
+            .unwrap()
+            .client
+            .settings()
+            .token
+            .clone();
+        // before three quarters of the 4s lease the token is reused
+        assert_eq!(
+            vault.session().await.unwrap().client.settings().token,
+            first
+        );
+
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        let session = vault.session().await.unwrap();
+        assert_ne!(session.client.settings().token, first);
+        assert!(session.renew_at.unwrap() > Instant::now());
+    }
 }
@@ -2,7 +2,7 @@ use error_stack::ResultExt;
 use hyperswitch_masking::{ExposeInterface, Secret};
 
 use crate::crypto::secrets_manager::{
-    managers::hcvault::core::{HashiCorpVault, Kv2},
+    managers::hcvault::core::{HashiCorpVault, HashiCorpVaultEngine, Kv2, Transit},
     secrets_interface::{SecretManager, SecretsManagementError},
 };
 
@@ -12,8 +12,12 @@ impl SecretManager for HashiCorpVault {
         &self,
         input: Secret<String>,
     ) -> error_stack::Result<Secret<String>, SecretsManagementError> {
-        self.fetch::<Kv2, Secret<String>>(input.expose())
-            .await
-            .change_context(SecretsManagementError::FetchSecretFailed)
+        match self.engine() {
+            HashiCorpVaultEngine::Kv2 => self.fetch::<Kv2, Secret<String>>(input.expose()).await,
+            HashiCorpVaultEngine::Transit { .. } => {
+                self.fetch::<Transit, Secret<String>>(input.expose()).await
+            }
+        }
+        .change_context(SecretsManagementError::FetchSecretFailed)
     }
 }
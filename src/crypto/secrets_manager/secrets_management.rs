@@ -6,11 +6,11 @@ use hyperswitch_masking::Secret;
 use crate::crypto::secrets_manager::managers::aws_kms::core::{AwsKmsClient, AwsKmsConfig};
 #[cfg(feature = "kms-hashicorp-vault")]
 use crate::crypto::secrets_manager::managers::hcvault::core::{
-    HashiCorpVault, HashiCorpVaultConfig,
+    HashiCorpVault, HashiCorpVaultConfig, HashiCorpVaultEngine,
 };
 use crate::{
     crypto::secrets_manager::{
-        managers::hollow::core::NoEncryption,
+        managers::{file::core::FileSecretsManager, hollow::core::NoEncryption},
         secrets_interface::{SecretManager, SecretsManagementError},
     },
     error::ConfigurationError,
@@ -61,6 +61,10 @@ pub enum SecretsManagementConfig {
         hashi_corp_vault: HashiCorpVaultConfig,
     },
 
+    /// Values are read from mounted files (`file:///path`) or environment variables
+    /// (`env:NAME`); any other value is used as is
+    File,
+
     /// Variant representing no encryption
     #[default]
     NoEncryption,
@@ -71,6 +75,7 @@ enum SecretsManagerClient {
     AwsKms(AwsKmsClient),
     #[cfg(feature = "kms-hashicorp-vault")]
     HashiCorp(Box<HashiCorpVault>),
+    File(FileSecretsManager),
     NoEncryption(NoEncryption),
 }
 
@@ -87,9 +92,18 @@ impl SecretManager for SecretsManagerClient {
             }
             #[cfg(feature = "kms-hashicorp-vault")]
             Self::HashiCorp(config) => {
-                record_secret_manager_duration(config.get_secret(input), "hashicorp_vault", "read")
-                    .await
+                let operation = match config.engine() {
+                    HashiCorpVaultEngine::Kv2 => "read",
+                    HashiCorpVaultEngine::Transit { .. } => "decrypt",
+                };
+                record_secret_manager_duration(
+                    config.get_secret(input),
+                    "hashicorp_vault",
+                    operation,
+                )
+                .await
             }
+            Self::File(config) => config.get_secret(input).await,
             Self::NoEncryption(config) => config.get_secret(input).await,
         }
     }
@@ -103,7 +117,7 @@ impl SecretsManagementConfig {
             Self::AwsKms { aws_kms } => aws_kms.validate(),
             #[cfg(feature = "kms-hashicorp-vault")]
             Self::HashiCorpVault { hashi_corp_vault } => hashi_corp_vault.validate(),
-            Self::NoEncryption => Ok(()),
+            Self::File | Self::NoEncryption => Ok(()),
         }
     }
 
@@ -118,8 +132,10 @@ impl SecretsManagementConfig {
             )),
             #[cfg(feature = "kms-hashicorp-vault")]
             Self::HashiCorpVault { hashi_corp_vault } => HashiCorpVault::new(hashi_corp_vault)
+                .await
                 .change_context(SecretsManagementError::ClientCreationFailed)
                 .map(|vault| SecretsManagerClient::HashiCorp(Box::new(vault))),
+            Self::File => Ok(SecretsManagerClient::File(FileSecretsManager)),
             Self::NoEncryption => Ok(SecretsManagerClient::NoEncryption(NoEncryption)),
         }
     }
@@ -5,6 +5,8 @@ use hyperswitch_masking::Secret;
 
 use crate::{
     app::TenantAppState,
+    audit::AuditContext,
+    config::CallerAuthConfig,
     error::{ApiError, ContainerError},
     storage::consts,
     tenant::GlobalAppState,
@@ -91,3 +93,117 @@ impl FromRequestParts<Arc<GlobalAppState>> for OptionalFingerprintId {
         Ok(Self(fingerprint_id))
     }
 }
+
+/// Role of the caller, authenticated by the API key in `x-caller-api-key`.
+/// `None` when no key was sent; an unknown key is rejected.
+#[derive(Debug)]
+pub struct OptionalCallerRole(pub Option<String>);
+
+/// Identity of the API key in `x-caller-api-key`, e.g. `payments:3f9a12c07be45d61`, telling apart
+/// callers that share a role. `None` when no key was sent; an unknown key is rejected.
+#[derive(Debug)]
+pub struct OptionalCallerKey(pub Option<String>);
+
+/// Resolve the role `x-caller-api-key` authenticates against `caller_auth.api_keys`.
+fn authenticated_role(parts: &Parts, state: &GlobalAppState) -> Result<Option<String>, ApiError> {
+    authenticate(parts, state, |caller_auth, api_key| {
+        caller_auth.role_of(api_key).map(ToString::to_string)
+    })
+}
+
+/// Resolve `x-caller-api-key` with `resolve`, which returns `None` for an unknown key.
+fn authenticate<T>(
+    parts: &Parts,
+    state: &GlobalAppState,
+    resolve: impl FnOnce(&CallerAuthConfig, &str) -> Option<T>,
+) -> Result<Option<T>, ApiError> {
+    parts
+        .headers
+        .get(consts::X_CALLER_API_KEY)
+        .map(|api_key| {
+            api_key
+                .to_str()
+                .ok()
+                .and_then(|api_key| resolve(&state.global_config.caller_auth, api_key))
+                .ok_or(ApiError::Unauthenticated("unknown x-caller-api-key"))
+        })
+        .transpose()
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<GlobalAppState>> for OptionalCallerRole {
+    type Rejection = ContainerError<ApiError>;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<GlobalAppState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self(authenticated_role(parts, state)?))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<GlobalAppState>> for OptionalCallerKey {
+    type Rejection = ContainerError<ApiError>;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<GlobalAppState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self(authenticate(
+            parts,
+            state,
+            CallerAuthConfig::key_id_of,
+        )?))
+    }
+}
+
+/// Role of a caller allowed to use the `/admin` endpoints, i.e. one of `caller_auth.admin_roles`.
+#[derive(Debug)]
+pub struct AdminCaller(pub String);
+
+#[async_trait]
+impl FromRequestParts<Arc<GlobalAppState>> for AdminCaller {
+    type Rejection = ContainerError<ApiError>;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<GlobalAppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let role = authenticated_role(parts, state)?.ok_or(ApiError::Unauthenticated(
+            "x-caller-api-key is required for admin endpoints",
+        ))?;
+
+        if !state.global_config.caller_auth.is_admin(&role) {
+            return Err(ApiError::Forbidden("caller role may not use admin endpoints").into());
+        }
+
+        Ok(Self(role))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<GlobalAppState>> for AuditContext {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<GlobalAppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(ToString::to_string)
+        };
+
+        Ok(Self {
+            auditor: state.auditor.clone(),
+            tenant_id: header(consts::X_TENANT_ID),
+            request_id: header(consts::X_REQUEST_ID),
+            // Handlers reject unknown keys through `OptionalCallerRole`; record them as no role.
+            caller_role: authenticated_role(parts, state).ok().flatten(),
+        })
+    }
+}
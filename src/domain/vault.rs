@@ -4,7 +4,7 @@ use crate::{
     observability::metrics,
     storage::storage_v2::{
         VaultInterface,
-        types::{Vault, VaultNew, VaultUpdate},
+        types::{Vault, VaultNew, VaultPrecondition, VaultUpdate},
     },
 };
 
@@ -57,14 +57,34 @@ pub async fn get_or_insert(
 }
 
 /// Insert the vault row, or overwrite the existing one on a duplicate-key conflict.
+///
+/// With a `precondition` the row must already exist at the expected version: nothing is inserted
+/// and a mismatch fails with `VaultDBError::Conflict`.
 pub async fn upsert(
     state: &TenantAppState,
     new: VaultNew,
+    precondition: Option<VaultPrecondition>,
 ) -> Result<Vault, ContainerError<error::VaultDBError>> {
     let vault_id = new.vault_id.clone();
     let entity_id = new.entity_id.clone();
     let update = VaultUpdate::from(new.clone());
 
+    if let Some(precondition) = precondition {
+        let result = state
+            .db
+            .update_vault_data_if(vault_id, entity_id, update, precondition)
+            .await;
+        super::record_get_or_insert_outcome(
+            metrics::Resource::Vault,
+            if result.is_ok() {
+                metrics::DomainGetOrInsertOutcome::Updated
+            } else {
+                metrics::DomainGetOrInsertOutcome::Error
+            },
+        );
+        return result;
+    }
+
     match state.db.insert_vault(new).await {
         Ok(vault) => {
             super::record_get_or_insert_outcome(
@@ -30,6 +30,16 @@ pub enum ConfigurationError {
     InvalidConfigurationValueError(String),
 }
 
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum AuditError {
+    #[error("Failed while serializing the audit record")]
+    SerializationFailed,
+    #[error("Failed while writing the audit record to the {0} sink")]
+    SinkWriteFailed(&'static str),
+    #[error("Failed while reading the last audit record back from the {0} sink")]
+    SinkReadFailed(&'static str),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum CryptoError {
     #[error("failed while serializing with serde_json")]
@@ -116,6 +126,15 @@ pub enum ApiError {
     #[error("Requested resource not found")]
     NotFoundError,
 
+    #[error("Concurrent modification of {0}, retry the request")]
+    Conflict(&'static str),
+
+    #[error("Access denied: {0}")]
+    Forbidden(&'static str),
+
+    #[error("Authentication failed: {0}")]
+    Unauthenticated(&'static str),
+
     #[error("TTL is invalid")]
     InvalidTtl,
 
@@ -130,6 +149,15 @@ pub enum ApiError {
 
     #[error("Key manager error: {0}")]
     KeyManagerError(&'static str),
+
+    #[error("Stored row quota exceeded for {0}, delete stored data before adding more")]
+    QuotaExceeded(&'static str),
+
+    #[error("Rate limit exceeded, retry later")]
+    RateLimited,
+
+    #[error("Idempotency-Key was already used for a different request")]
+    IdempotencyKeyReused,
 }
 
 /// Errors that could occur during KMS operations.
@@ -201,6 +229,33 @@ pub enum ApiClientError {
     InternalServerError(bytes::Bytes),
     #[error("Missing configuration: {0}")]
     MissingConfigurationError(&'static str),
+    #[error("Request timed out")]
+    RequestTimedOut,
+    #[error("Circuit breaker for {0} is open")]
+    CircuitOpen(&'static str),
+}
+
+impl ApiClientError {
+    /// Failures that say the remote service is unhealthy rather than that the request was
+    /// wrong: worth retrying, and counted by the circuit breaker.
+    pub fn is_service_failure(&self) -> bool {
+        match self {
+            Self::RequestNotSent | Self::RequestTimedOut | Self::InternalServerError(_) => true,
+            Self::Unexpected { status_code, .. } => {
+                status_code.is_server_error() || *status_code == StatusCode::TOO_MANY_REQUESTS
+            }
+            Self::ClientConstructionFailed
+            | Self::HeaderMapConstructionFailed
+            | Self::IdentityParseFailed
+            | Self::CertificateParseFailed { .. }
+            | Self::UrlEncodingFailed
+            | Self::ResponseDecodingFailed
+            | Self::BadRequest(_)
+            | Self::Unauthorized(_)
+            | Self::MissingConfigurationError(_)
+            | Self::CircuitOpen(_) => false,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -324,6 +379,24 @@ mod error_codes {
 
     /// Validation error: Represents an error occurring during data validation or integrity checks.
     pub const TE_03: &str = "TE_03";
+
+    /// Conflict: the resource changed between being read and written, or a precondition failed.
+    pub const TE_04: &str = "TE_04";
+
+    /// Forbidden: the caller is not allowed to perform the requested operation.
+    pub const TE_05: &str = "TE_05";
+
+    /// Quota exceeded: the merchant already stores as much data as its quota allows.
+    pub const TE_06: &str = "TE_06";
+
+    /// Rate limited: the tenant or merchant sent more requests than its rate limit allows.
+    pub const TE_07: &str = "TE_07";
+
+    /// Idempotency key reused: the `Idempotency-Key` was first sent with a different request.
+    pub const TE_08: &str = "TE_08";
+
+    /// Unauthenticated: the caller API key is missing where one is required, or unknown.
+    pub const TE_09: &str = "TE_09";
 }
 
 impl axum::response::IntoResponse for ApiError {
@@ -404,6 +477,60 @@ impl axum::response::IntoResponse for ApiError {
                 )),
             )
                 .into_response(),
+            data @ Self::Conflict(_) => (
+                hyper::StatusCode::CONFLICT,
+                axum::Json(ApiErrorResponse::new(
+                    error_codes::TE_04,
+                    format!("{}", data),
+                    None,
+                )),
+            )
+                .into_response(),
+            data @ Self::Forbidden(_) => (
+                hyper::StatusCode::FORBIDDEN,
+                axum::Json(ApiErrorResponse::new(
+                    error_codes::TE_05,
+                    format!("{}", data),
+                    None,
+                )),
+            )
+                .into_response(),
+            data @ Self::Unauthenticated(_) => (
+                hyper::StatusCode::UNAUTHORIZED,
+                axum::Json(ApiErrorResponse::new(
+                    error_codes::TE_09,
+                    format!("{}", data),
+                    None,
+                )),
+            )
+                .into_response(),
+            data @ Self::QuotaExceeded(_) => (
+                hyper::StatusCode::FORBIDDEN,
+                axum::Json(ApiErrorResponse::new(
+                    error_codes::TE_06,
+                    format!("{}", data),
+                    None,
+                )),
+            )
+                .into_response(),
+            data @ Self::RateLimited => (
+                hyper::StatusCode::TOO_MANY_REQUESTS,
+                axum::Json(ApiErrorResponse::new(
+                    error_codes::TE_07,
+                    format!("{}", data),
+                    None,
+                )),
+            )
+                .into_response(),
+            data @ Self::IdempotencyKeyReused => (
+                hyper::StatusCode::UNPROCESSABLE_ENTITY,
+                axum::Json(ApiErrorResponse::new(
+                    error_codes::TE_08,
+                    format!("{}", data),
+                    None,
+                )),
+            )
+                .into_response(),
             data @ Self::MerchantError => (
                 hyper::StatusCode::INTERNAL_SERVER_ERROR,
                 axum::Json(ApiErrorResponse::new(
@@ -36,6 +36,8 @@ pub enum VaultDBError {
     NotFoundError,
     #[error("Vault record already exists in database")]
     Duplicate,
+    #[error("Vault record was modified concurrently")]
+    Conflict,
     #[error("Unpredictable error occurred")]
     UnknownError,
 }
@@ -118,6 +120,42 @@ pub enum ReverseLookupDBError {
     UnknownError,
 }
 
+#[derive(Debug, Copy, Clone, thiserror::Error)]
+pub enum AuditDBError {
+    #[error("Error while connecting to database")]
+    DBError,
+    #[error("Error while finding audit record in the database")]
+    DBFilterError,
+    #[error("Error while inserting audit record in the database")]
+    DBInsertError,
+    #[error("Audit record not found in database")]
+    NotFoundError,
+    #[error("Audit record already exists in database")]
+    Duplicate,
+    #[error("Unpredictable error occurred")]
+    UnknownError,
+}
+
+#[derive(Debug, Copy, Clone, thiserror::Error)]
+pub enum IdempotencyDBError {
+    #[error("Error while connecting to database")]
+    DBError,
+    #[error("Error while finding idempotency record in the database")]
+    DBFilterError,
+    #[error("Error while inserting idempotency record in the database")]
+    DBInsertError,
+    #[error("Error while updating idempotency record in the database")]
+    DBUpdateError,
+    #[error("Error while deleting idempotency record in the database")]
+    DBDeleteError,
+    #[error("Idempotency record not found in database")]
+    NotFoundError,
+    #[error("Idempotency record already exists in database")]
+    Duplicate,
+    #[error("Unpredictable error occurred")]
+    UnknownError,
+}
+
 pub trait NotFoundError {
     fn is_not_found(&self) -> bool;
 }
@@ -142,6 +180,14 @@ impl NotFoundError for super::ContainerError<ReverseLookupDBError> {
         )
     }
 }
+impl NotFoundError for super::ContainerError<IdempotencyDBError> {
+    fn is_not_found(&self) -> bool {
+        matches!(
+            self.error.current_context(),
+            IdempotencyDBError::NotFoundError
+        )
+    }
+}
 impl NotFoundError for super::ContainerError<VaultDBError> {
     fn is_not_found(&self) -> bool {
         matches!(self.error.current_context(), VaultDBError::NotFoundError)
@@ -232,3 +278,15 @@ impl_storage_error!(
     not_found = NotFoundError,
     other = DBError
 );
+impl_storage_error!(
+    AuditDBError,
+    duplicate = Duplicate,
+    not_found = NotFoundError,
+    other = DBError
+);
+impl_storage_error!(
+    IdempotencyDBError,
+    duplicate = Duplicate,
+    not_found = NotFoundError,
+    other = DBError
+);
@@ -9,6 +9,8 @@ use hyperswitch_redis_interface::errors::RedisError;
 pub enum KvError {
     #[error("Duplicate value already exists for key `{key}`")]
     DuplicateValue { key: String },
+    #[error("Value for key `{key}` changed since it was read")]
+    Conflict { key: String },
     #[error("KV backend error")]
     Backend,
     #[error("Value not found: {0}")]
@@ -203,6 +203,7 @@ impl<'a> From<&'a super::VaultDBError> for super::ApiError {
             super::VaultDBError::UnknownError => Self::UnknownError,
             super::VaultDBError::NotFoundError => Self::NotFoundError,
             super::VaultDBError::Duplicate => Self::DatabaseInsertFailed("locker"),
+            super::VaultDBError::Conflict => Self::Conflict("vault"),
         }
     }
 }
@@ -319,6 +320,80 @@ impl<'a> From<&'a super::StorageError> for super::ReverseLookupDBError {
     }
 }
 
+error_transform!(super::StorageError => super::AuditDBError);
+impl<'a> From<&'a super::StorageError> for super::AuditDBError {
+    fn from(value: &'a super::StorageError) -> Self {
+        match value {
+            super::StorageError::DBPoolError
+            | super::StorageError::PoolClientFailure
+            | super::StorageError::ReplicaPoolNotConfigured => Self::DBError,
+            super::StorageError::FindError => Self::DBFilterError,
+            super::StorageError::NotFoundError => Self::NotFoundError,
+            super::StorageError::DecryptionError
+            | super::StorageError::EncryptionError
+            | super::StorageError::DeleteError
+            | super::StorageError::UpdateError => Self::UnknownError,
+            super::StorageError::InsertError => Self::DBInsertError,
+        }
+    }
+}
+
+error_transform!(super::AuditDBError => super::ApiError);
+impl<'a> From<&'a super::AuditDBError> for super::ApiError {
+    fn from(value: &'a super::AuditDBError) -> Self {
+        match value {
+            super::AuditDBError::DBError => Self::DatabaseError,
+            super::AuditDBError::DBFilterError => Self::RetrieveDataFailed("audit log"),
+            super::AuditDBError::DBInsertError | super::AuditDBError::Duplicate => {
+                Self::DatabaseInsertFailed("audit log")
+            }
+            super::AuditDBError::NotFoundError => Self::NotFoundError,
+            super::AuditDBError::UnknownError => Self::UnknownError,
+        }
+    }
+}
+
+error_transform!(super::StorageError => super::IdempotencyDBError);
+impl<'a> From<&'a super::StorageError> for super::IdempotencyDBError {
+    fn from(value: &'a super::StorageError) -> Self {
+        match value {
+            super::StorageError::DBPoolError
+            | super::StorageError::PoolClientFailure
+            | super::StorageError::ReplicaPoolNotConfigured => Self::DBError,
+            super::StorageError::FindError => Self::DBFilterError,
+            super::StorageError::NotFoundError => Self::NotFoundError,
+            super::StorageError::DecryptionError | super::StorageError::EncryptionError => {
+                Self::UnknownError
+            }
+            super::StorageError::InsertError => Self::DBInsertError,
+            super::StorageError::UpdateError => Self::DBUpdateError,
+            super::StorageError::DeleteError => Self::DBDeleteError,
+        }
+    }
+}
+
+error_transform!(super::IdempotencyDBError => super::ApiError);
+impl<'a> From<&'a super::IdempotencyDBError> for super::ApiError {
+    fn from(value: &'a super::IdempotencyDBError) -> Self {
+        match value {
+            super::IdempotencyDBError::DBError => Self::DatabaseError,
+            super::IdempotencyDBError::DBFilterError => {
+                Self::RetrieveDataFailed("idempotency record")
+            }
+            super::IdempotencyDBError::DBInsertError
+            | super::IdempotencyDBError::DBUpdateError
+            | super::IdempotencyDBError::Duplicate => {
+                Self::DatabaseInsertFailed("idempotency record")
+            }
+            super::IdempotencyDBError::DBDeleteError => {
+                Self::DatabaseDeleteFailed("idempotency record")
+            }
+            super::IdempotencyDBError::NotFoundError => Self::NotFoundError,
+            super::IdempotencyDBError::UnknownError => Self::UnknownError,
+        }
+    }
+}
+
 error_transform!(super::ReverseLookupDBError => super::ApiError);
 impl<'a> From<&'a super::ReverseLookupDBError> for super::ApiError {
     fn from(value: &'a super::ReverseLookupDBError) -> Self {
@@ -389,7 +464,9 @@ impl<'a> From<&'a super::ApiClientError> for super::DataKeyCreationError {
             | super::ApiClientError::UrlEncodingFailed => Self::RequestConstructionFailed,
             super::ApiClientError::IdentityParseFailed
             | super::ApiClientError::CertificateParseFailed { .. } => Self::CertificateParseFailed,
-            super::ApiClientError::RequestNotSent => Self::RequestSendFailed,
+            super::ApiClientError::RequestNotSent
+            | super::ApiClientError::RequestTimedOut
+            | super::ApiClientError::CircuitOpen(_) => Self::RequestSendFailed,
             super::ApiClientError::ResponseDecodingFailed => Self::ResponseDecodingFailed,
             super::ApiClientError::BadRequest(_) => Self::BadRequest,
             super::ApiClientError::InternalServerError(_) => Self::InternalServerError,
@@ -425,7 +502,9 @@ impl<'a> From<&'a super::ApiClientError> for super::DataKeyTransferError {
             | super::ApiClientError::UrlEncodingFailed => Self::RequestConstructionFailed,
             super::ApiClientError::IdentityParseFailed
             | super::ApiClientError::CertificateParseFailed { .. } => Self::CertificateParseFailed,
-            super::ApiClientError::RequestNotSent => Self::RequestSendFailed,
+            super::ApiClientError::RequestNotSent
+            | super::ApiClientError::RequestTimedOut
+            | super::ApiClientError::CircuitOpen(_) => Self::RequestSendFailed,
             super::ApiClientError::ResponseDecodingFailed => Self::ResponseDecodingFailed,
             super::ApiClientError::BadRequest(_) => Self::BadRequest,
             super::ApiClientError::InternalServerError(_) => Self::InternalServerError,
@@ -477,7 +556,9 @@ impl<'a> From<&'a super::ApiClientError> for super::DataEncryptionError {
             | super::ApiClientError::UrlEncodingFailed => Self::RequestConstructionFailed,
             super::ApiClientError::IdentityParseFailed
             | super::ApiClientError::CertificateParseFailed { .. } => Self::CertificateParseFailed,
-            super::ApiClientError::RequestNotSent => Self::RequestSendFailed,
+            super::ApiClientError::RequestNotSent
+            | super::ApiClientError::RequestTimedOut
+            | super::ApiClientError::CircuitOpen(_) => Self::RequestSendFailed,
             super::ApiClientError::ResponseDecodingFailed => Self::ResponseDecodingFailed,
             super::ApiClientError::BadRequest(_) => Self::BadRequest,
             super::ApiClientError::InternalServerError(_) => Self::InternalServerError,
@@ -513,7 +594,9 @@ impl<'a> From<&'a super::ApiClientError> for super::DataDecryptionError {
             | super::ApiClientError::UrlEncodingFailed => Self::RequestConstructionFailed,
             super::ApiClientError::IdentityParseFailed
             | super::ApiClientError::CertificateParseFailed { .. } => Self::CertificateParseFailed,
-            super::ApiClientError::RequestNotSent => Self::RequestSendFailed,
+            super::ApiClientError::RequestNotSent
+            | super::ApiClientError::RequestTimedOut
+            | super::ApiClientError::CircuitOpen(_) => Self::RequestSendFailed,
             super::ApiClientError::ResponseDecodingFailed => Self::ResponseDecodingFailed,
             super::ApiClientError::BadRequest(_) => Self::BadRequest,
             super::ApiClientError::InternalServerError(_) => Self::InternalServerError,
@@ -538,7 +621,9 @@ impl<'a> From<&'a super::ApiClientError> for super::KeyManagerHealthCheckError {
             | super::ApiClientError::Unexpected { .. }
             | super::ApiClientError::InternalServerError(_)
             | super::ApiClientError::Unauthorized(_)
-            | super::ApiClientError::MissingConfigurationError(_) => Self::FailedToConnect,
+            | super::ApiClientError::MissingConfigurationError(_)
+            | super::ApiClientError::RequestTimedOut
+            | super::ApiClientError::CircuitOpen(_) => Self::FailedToConnect,
         }
     }
 }
@@ -0,0 +1,614 @@
+//! Replay of the first response to retried writes carrying an `Idempotency-Key`.
+//!
+//! With `idempotency.enabled`, a request to one of the idempotent routes that sends an
+//! `Idempotency-Key` header reserves the key for its merchant (`merchant_id`, or `entity_id` on v2)
+//! and route before running. Its response, status and body, is then stored encrypted with a key
+//! derived from the tenant master key for `ttl_secs`, and returned as is, with
+//! `idempotent-replayed: true`, to every retry sending the same key and an identical body. Under
+//! the same key:
+//! - a different body is rejected with `422` (`TE_08`);
+//! - a retry arriving while the first request still runs is rejected with `409`, until the
+//!   reservation lapses after `in_flight_secs` in case the first request never finished.
+//!
+//! Server errors are not stored, so a retry after one runs the request again. Responses that cannot
+//! be read whole are passed on unstored, and their reservation lapses. Records live in the
+//! `idempotency_record` table of the tenant, or in Redis with `store = "redis"`.
+
+#[cfg(feature = "redis")]
+mod redis;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, StatusCode, header, request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use hyperswitch_masking::PeekInterface;
+use ring::hkdf;
+use tracing::Instrument;
+
+use crate::{
+    app::TenantAppState,
+    crypto::encryption_manager::{encryption_interface::Encryption, managers::aes::GcmAes256},
+    error::{self, ContainerError, NotFoundError as _},
+    logger,
+    observability::metrics,
+    storage::{
+        IdempotencyInterface,
+        consts::{IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED},
+        types::{IdempotencyKeyId, IdempotencyRecordNew},
+    },
+    tenant::GlobalAppState,
+    utils,
+};
+
+/// Routes honouring `Idempotency-Key`.
+const IDEMPOTENT_ROUTES: [&str; 3] = ["/api/v2/vault/add", "/data/delete", "/cards/delete"];
+
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Responses larger than this are not stored; the locker's responses are far smaller.
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// HKDF info deriving the response encryption key from the tenant master key.
+const CIPHER_KEY_INFO: &[u8] = b"idempotency";
+
+/// How often expired records are deleted from the `idempotency_record` table.
+const PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    pub enabled: bool,
+    /// How long a response is replayed for (in sec).
+    pub ttl_secs: u32,
+    /// How long a key stays reserved by a request that has not responded yet (in sec).
+    pub in_flight_secs: u32,
+    pub store: IdempotencyStore,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 86400,
+            in_flight_secs: 60,
+            store: IdempotencyStore::Postgres,
+        }
+    }
+}
+
+impl IdempotencyConfig {
+    pub fn validate(&self) -> Result<(), error::ConfigurationError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.ttl_secs == 0 || self.in_flight_secs == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "idempotency.ttl_secs and idempotency.in_flight_secs must be greater than 0".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, serde::Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdempotencyStore {
+    #[default]
+    Postgres,
+    #[cfg(feature = "redis")]
+    Redis,
+}
+
+/// What the store holds for a key when a request tries to reserve it.
+#[derive(Debug)]
+enum Claim {
+    Acquired,
+    /// The encrypted response to replay.
+    Replay(Vec<u8>),
+    InProgress,
+    Mismatch,
+}
+
+impl Claim {
+    fn of_existing(stored_hash: &str, response: Option<Vec<u8>>, request_hash: &str) -> Self {
+        match response {
+            _ if stored_hash != request_hash => Self::Mismatch,
+            Some(response) => Self::Replay(response),
+            None => Self::InProgress,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+enum Outcome {
+    Executed,
+    Replayed,
+    InProgress,
+    Mismatch,
+    Error,
+}
+
+crate::impl_metric_value_from!(Outcome);
+
+fn record_outcome(route: &str, outcome: Outcome) {
+    metrics::IDEMPOTENCY_REQUEST_COUNT.add(
+        1,
+        crate::metric_attributes!(("http.route", route.to_owned()), ("outcome", outcome)),
+    );
+}
+
+/// Middleware replaying stored responses on the idempotent routes.
+pub async fn middleware(
+    State(state): State<Arc<GlobalAppState>>,
+    matched_path: Option<MatchedPath>,
+    parts: request::Parts,
+    body: Bytes,
+    next: Next,
+) -> Response {
+    let config = &state.global_config.idempotency;
+    let request_scope = utils::RequestScope::new(matched_path.as_ref(), &parts, &body);
+    let route = request_scope.route;
+
+    let key = match parts.headers.get(IDEMPOTENCY_KEY) {
+        Some(key) if config.enabled && IDEMPOTENT_ROUTES.contains(&route) => key,
+        _ => return next.run(Request::from_parts(parts, Body::from(body))).await,
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_owned(),
+        _ => {
+            return error::ApiError::ValidationError(
+                "Idempotency-Key must be between 1 and 255 visible ASCII characters",
+            )
+            .into_response();
+        }
+    };
+
+    // Requests of unknown tenants or without an owner are rejected by the handler.
+    let tenant_state = match request_scope.tenant_id {
+        Some(tenant_id) => state.get_app_state_of_tenant(tenant_id).await.ok(),
+        None => None,
+    };
+    let owner_id = request_scope.owner_id();
+    let (Some(tenant_state), Some(owner_id)) = (tenant_state, owner_id) else {
+        return next.run(Request::from_parts(parts, Body::from(body))).await;
+    };
+
+    let route = route.to_owned();
+    let id = IdempotencyKeyId {
+        owner_id: &owner_id,
+        route: &route,
+        idempotency_key: &key,
+    };
+    let request_hash = hex::encode(ring::digest::digest(&ring::digest::SHA256, &body));
+
+    let replay = match reserve(&tenant_state, config, id, &request_hash).await {
+        Ok(Claim::Acquired) => None,
+        Ok(Claim::Replay(response)) => Some(response),
+        Ok(Claim::InProgress) => {
+            record_outcome(&route, Outcome::InProgress);
+            return error::ApiError::Conflict("idempotency key").into_response();
+        }
+        Ok(Claim::Mismatch) => {
+            record_outcome(&route, Outcome::Mismatch);
+            logger::warn!(%route, %owner_id, "Idempotency-Key reused for a different request");
+            return error::ApiError::IdempotencyKeyReused.into_response();
+        }
+        Err(error) => {
+            record_outcome(&route, Outcome::Error);
+            return error.into_response();
+        }
+    };
+
+    if let Some(response) = replay {
+        return match decode_response(&tenant_state, response) {
+            Ok(response) => {
+                record_outcome(&route, Outcome::Replayed);
+                logger::info!(%route, %owner_id, "Replaying stored response");
+                response
+            }
+            Err(error) => {
+                record_outcome(&route, Outcome::Error);
+                error.into_response()
+            }
+        };
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    record_outcome(&route, Outcome::Executed);
+
+    let (response_parts, response_body) = response.into_parts();
+    let response_body = match buffer_response(response_body).await {
+        Ok(response_body) => response_body,
+        // The reservation lapses after `in_flight_secs`, after which a retry runs again.
+        Err(response_body) => {
+            logger::warn!(%route, "Could not read the response whole, passing it on unstored");
+            return Response::from_parts(response_parts, response_body);
+        }
+    };
+
+    if response_parts.status.is_server_error() {
+        release(&tenant_state, config, id, &request_hash).await;
+    } else {
+        let stored = encode_response(&tenant_state, response_parts.status, &response_body);
+        let stored = match stored {
+            Ok(stored) => store_response(&tenant_state, config, id, &request_hash, stored).await,
+            Err(error) => Err(error),
+        };
+        // The reservation lapses after `in_flight_secs`, after which a retry runs again.
+        if let Err(error) = stored {
+            logger::error!(?error, %route, "Failed to store the idempotent response");
+        }
+    }
+
+    Response::from_parts(response_parts, Body::from(response_body))
+}
+
+/// Read `body` whole if it fits in [`MAX_RESPONSE_BYTES`]. Otherwise, or when reading it fails,
+/// returns a body sending what was read followed by the rest, as the original would have.
+async fn buffer_response(body: Body) -> Result<Bytes, Body> {
+    let mut stream = body.into_data_stream();
+    let mut chunks = Vec::new();
+    let mut len = 0;
+
+    while let Some(chunk) = stream.next().await {
+        let failed = chunk.is_err();
+        len += chunk.as_ref().map_or(0, Bytes::len);
+        chunks.push(chunk);
+        if failed || len > MAX_RESPONSE_BYTES {
+            return Err(Body::from_stream(
+                futures::stream::iter(chunks).chain(stream),
+            ));
+        }
+    }
+
+    let mut buffered = Vec::with_capacity(len);
+    for chunk in chunks.into_iter().flatten() {
+        buffered.extend_from_slice(&chunk);
+    }
+    Ok(buffered.into())
+}
+
+/// Key encrypting the stored responses, derived from the tenant master key so that the master key
+/// itself only ever encrypts what it was meant for.
+fn cipher(tenant_state: &TenantAppState) -> Result<GcmAes256, ContainerError<error::ApiError>> {
+    let mut key = [0; 32];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, &[])
+        .extract(tenant_state.config.tenant_secrets.master_key.peek())
+        .expand(&[CIPHER_KEY_INFO], hkdf::HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut key))
+        .map_err(|_| error::ApiError::UnknownError)?;
+
+    Ok(GcmAes256::new(key.to_vec()))
+}
+
+/// The status, big-endian, followed by the body, encrypted.
+fn encode_response(
+    tenant_state: &TenantAppState,
+    status: StatusCode,
+    body: &[u8],
+) -> Result<Vec<u8>, ContainerError<error::ApiError>> {
+    let mut plaintext = Vec::with_capacity(body.len() + 2);
+    plaintext.extend_from_slice(&status.as_u16().to_be_bytes());
+    plaintext.extend_from_slice(body);
+
+    Ok(cipher(tenant_state)?.encrypt(plaintext)?)
+}
+
+fn decode_response(
+    tenant_state: &TenantAppState,
+    stored: Vec<u8>,
+) -> Result<Response, ContainerError<error::ApiError>> {
+    let plaintext = cipher(tenant_state)?.decrypt(stored)?;
+    let (status, body) = match plaintext.as_slice() {
+        [high, low, body @ ..] => (u16::from_be_bytes([*high, *low]), body.to_vec()),
+        _ => return Err(error::ApiError::DecodingError.into()),
+    };
+    let status = StatusCode::from_u16(status).map_err(|_| error::ApiError::DecodingError)?;
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    Ok(response)
+}
+
+async fn reserve(
+    tenant_state: &TenantAppState,
+    config: &IdempotencyConfig,
+    id: IdempotencyKeyId<'_>,
+    request_hash: &str,
+) -> Result<Claim, ContainerError<error::ApiError>> {
+    match config.store {
+        IdempotencyStore::Postgres => {
+            let now = utils::date_time::now();
+            let reserved = tenant_state
+                .db
+                .insert_idempotency_record(IdempotencyRecordNew {
+                    owner_id: id.owner_id.to_owned(),
+                    route: id.route.to_owned(),
+                    idempotency_key: id.idempotency_key.to_owned(),
+                    request_hash: request_hash.to_owned(),
+                    created_at: now,
+                    expires_at: now
+                        .saturating_add(time::Duration::seconds(i64::from(config.in_flight_secs))),
+                })
+                .await?;
+            if reserved {
+                return Ok(Claim::Acquired);
+            }
+
+            match tenant_state.db.find_idempotency_record(id).await {
+                Ok(record) => Ok(Claim::of_existing(
+                    &record.request_hash,
+                    record.response,
+                    request_hash,
+                )),
+                // Expired since the insert; the next retry takes the key over.
+                Err(error) if error.is_not_found() => Ok(Claim::InProgress),
+                Err(error) => Err(error.into()),
+            }
+        }
+        #[cfg(feature = "redis")]
+        IdempotencyStore::Redis => Ok(redis::reserve(
+            redis_store(tenant_state)?,
+            &tenant_state.config.tenant_id,
+            id,
+            request_hash,
+            config.in_flight_secs,
+        )
+        .await
+        .map_err(|err| err.change_context(error::ApiError::DatabaseError))?),
+    }
+}
+
+async fn store_response(
+    tenant_state: &TenantAppState,
+    config: &IdempotencyConfig,
+    id: IdempotencyKeyId<'_>,
+    request_hash: &str,
+    response: Vec<u8>,
+) -> Result<(), ContainerError<error::ApiError>> {
+    match config.store {
+        IdempotencyStore::Postgres => {
+            let expires_at = utils::date_time::now()
+                .saturating_add(time::Duration::seconds(i64::from(config.ttl_secs)));
+            Ok(tenant_state
+                .db
+                .update_idempotency_record_response(id, request_hash, response, expires_at)
+                .await?)
+        }
+        #[cfg(feature = "redis")]
+        IdempotencyStore::Redis => Ok(redis::store_response(
+            redis_store(tenant_state)?,
+            &tenant_state.config.tenant_id,
+            id,
+            request_hash,
+            &response,
+            config.ttl_secs,
+        )
+        .await
+        .map_err(|err| err.change_context(error::ApiError::DatabaseError))?),
+    }
+}
+
+/// Drop the reservation so a retry runs the request again. Best effort: otherwise it lapses after
+/// `in_flight_secs`.
+async fn release(
+    tenant_state: &TenantAppState,
+    config: &IdempotencyConfig,
+    id: IdempotencyKeyId<'_>,
+    request_hash: &str,
+) {
+    let released = match config.store {
+        IdempotencyStore::Postgres => tenant_state
+            .db
+            .delete_idempotency_reservation(id, request_hash)
+            .await
+            .map_err(ContainerError::<error::ApiError>::from),
+        #[cfg(feature = "redis")]
+        IdempotencyStore::Redis => match redis_store(tenant_state) {
+            Ok(store) => redis::release(store, &tenant_state.config.tenant_id, id, request_hash)
+                .await
+                .map_err(|err| err.change_context(error::ApiError::DatabaseError).into()),
+            Err(error) => Err(error),
+        },
+    };
+
+    if let Err(error) = released {
+        logger::warn!(?error, "Failed to release the idempotency key");
+    }
+}
+
+#[cfg(feature = "redis")]
+fn redis_store(
+    tenant_state: &TenantAppState,
+) -> Result<&crate::storage::redis::RedisStore, ContainerError<error::ApiError>> {
+    tenant_state
+        .redis
+        .as_ref()
+        .ok_or_else(|| error::ApiError::UnknownError.into())
+}
+
+/// Spawn the task deleting expired records from the `idempotency_record` table of every tenant.
+pub fn spawn_purger(global_app_state: &Arc<GlobalAppState>) -> Option<tokio::task::JoinHandle<()>> {
+    let config = &global_app_state.global_config.idempotency;
+    if !config.enabled || config.store != IdempotencyStore::Postgres {
+        return None;
+    }
+    let global_app_state = Arc::clone(global_app_state);
+
+    Some(tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(PURGE_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let tenants: Vec<_> = {
+                    let guard = global_app_state.tenants_app_state.read().await;
+                    guard
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect::<Vec<_>>()
+                };
+                for (tenant_id, tenant_state) in tenants.iter() {
+                    match tenant_state
+                        .db
+                        .delete_expired_idempotency_records(utils::date_time::now())
+                        .await
+                    {
+                        Ok(deleted) => {
+                            logger::debug!(%tenant_id, deleted, "Purged expired idempotency records");
+                        }
+                        Err(error) => {
+                            logger::warn!(
+                                ?error,
+                                %tenant_id,
+                                "Failed to purge expired idempotency records"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        .instrument(tracing::info_span!("idempotency_purger")),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+
+    use super::*;
+
+    #[test]
+    fn test_existing_record_is_replayed_only_for_the_same_request() {
+        assert!(matches!(
+            Claim::of_existing("hash", Some(vec![1]), "hash"),
+            Claim::Replay(response) if response == vec![1]
+        ));
+        assert!(matches!(
+            Claim::of_existing("hash", None, "hash"),
+            Claim::InProgress
+        ));
+        assert!(matches!(
+            Claim::of_existing("hash", Some(vec![1]), "other"),
+            Claim::Mismatch
+        ));
+        assert!(matches!(
+            Claim::of_existing("hash", None, "other"),
+            Claim::Mismatch
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_response_is_passed_on_unchanged() {
+        let small = buffer_response(Body::from("{}")).await.unwrap();
+        assert_eq!(small, Bytes::from("{}"));
+
+        let large = vec![b'a'; MAX_RESPONSE_BYTES + 1];
+        let passed_on = buffer_response(Body::from(large.clone()))
+            .await
+            .unwrap_err();
+        let passed_on = axum::body::to_bytes(passed_on, usize::MAX).await.unwrap();
+        assert_eq!(passed_on, large);
+    }
+
+    fn postgres_config() -> IdempotencyConfig {
+        IdempotencyConfig {
+            enabled: true,
+            store: IdempotencyStore::Postgres,
+            ..IdempotencyConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the development database"]
+    async fn test_postgres_reservation_is_exclusive_until_released() {
+        let state = TenantAppState::for_tests(|_| {}).await;
+        let config = postgres_config();
+        let key = uuid::Uuid::now_v7().to_string();
+        let id = IdempotencyKeyId {
+            owner_id: "idempotency_owner",
+            route: "/api/v2/vault/add",
+            idempotency_key: &key,
+        };
+
+        assert!(matches!(
+            reserve(&state, &config, id, "hash").await.unwrap(),
+            Claim::Acquired
+        ));
+        assert!(matches!(
+            reserve(&state, &config, id, "hash").await.unwrap(),
+            Claim::InProgress
+        ));
+        assert!(matches!(
+            reserve(&state, &config, id, "other").await.unwrap(),
+            Claim::Mismatch
+        ));
+
+        // only the request holding the reservation releases it
+        release(&state, &config, id, "other").await;
+        assert!(matches!(
+            reserve(&state, &config, id, "hash").await.unwrap(),
+            Claim::InProgress
+        ));
+        release(&state, &config, id, "hash").await;
+        assert!(matches!(
+            reserve(&state, &config, id, "other").await.unwrap(),
+            Claim::Acquired
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the development database"]
+    async fn test_postgres_stored_response_is_replayed() {
+        let state = TenantAppState::for_tests(|_| {}).await;
+        let config = postgres_config();
+        let key = uuid::Uuid::now_v7().to_string();
+        let id = IdempotencyKeyId {
+            owner_id: "idempotency_owner",
+            route: "/api/v2/vault/add",
+            idempotency_key: &key,
+        };
+
+        reserve(&state, &config, id, "hash").await.unwrap();
+        let stored = encode_response(&state, StatusCode::CREATED, br#"{"version":1}"#).unwrap();
+        store_response(&state, &config, id, "hash", stored)
+            .await
+            .unwrap();
+        // a stored response is kept
+        release(&state, &config, id, "hash").await;
+
+        let claim = reserve(&state, &config, id, "hash").await.unwrap();
+        let Claim::Replay(stored) = claim else {
+            panic!("expected the stored response, got {claim:?}");
+        };
+        let replayed = decode_response(&state, stored).unwrap();
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        assert_eq!(replayed.headers().get(IDEMPOTENT_REPLAYED).unwrap(), "true");
+        let body = axum::body::to_bytes(replayed.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, Bytes::from_static(br#"{"version":1}"#));
+
+        assert!(matches!(
+            reserve(&state, &config, id, "other").await.unwrap(),
+            Claim::Mismatch
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+//! Idempotency records kept in Redis, so every locker instance sees the same reservations.
+
+use hyperswitch_redis_interface::errors::RedisError;
+
+use super::Claim;
+use crate::storage::{
+    redis::{RedisStore, prefixed_key},
+    types::IdempotencyKeyId,
+};
+
+/// Reserve `KEYS[1]` for the request hashing to `ARGV[1]` for `ARGV[2]` seconds. Returns nothing
+/// when reserved, otherwise the request hash and hex encoded response stored under the key.
+const RESERVE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    redis.call('HSET', KEYS[1], 'request_hash', ARGV[1])
+    redis.call('EXPIRE', KEYS[1], ARGV[2])
+    return {}
+end
+return redis.call('HMGET', KEYS[1], 'request_hash', 'response')
+";
+
+/// Store the response `ARGV[2]` for `ARGV[3]` seconds if `KEYS[1]` is still reserved by the
+/// request hashing to `ARGV[1]`. Returns whether it was stored.
+const STORE_SCRIPT: &str = r"
+if redis.call('HGET', KEYS[1], 'request_hash') ~= ARGV[1]
+    or redis.call('HEXISTS', KEYS[1], 'response') == 1 then
+    return 0
+end
+redis.call('HSET', KEYS[1], 'response', ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+return 1
+";
+
+/// Drop `KEYS[1]` if still reserved by the request hashing to `ARGV[1]`.
+const RELEASE_SCRIPT: &str = r"
+if redis.call('HGET', KEYS[1], 'request_hash') == ARGV[1]
+    and redis.call('HEXISTS', KEYS[1], 'response') == 0 then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+fn script_error(err: impl std::fmt::Debug) -> error_stack::Report<RedisError> {
+    error_stack::Report::new(RedisError::UnknownResult).attach_printable(format!("{err:?}"))
+}
+
+/// Named after the tenant too, so tenants stay apart even when they share a `redis_key_prefix`.
+fn key(tenant_id: &str, id: IdempotencyKeyId<'_>) -> String {
+    format!(
+        "idempotency:{tenant_id}:{}:{}:{}",
+        id.owner_id, id.route, id.idempotency_key
+    )
+}
+
+pub(super) async fn reserve(
+    store: &RedisStore,
+    tenant_id: &str,
+    id: IdempotencyKeyId<'_>,
+    request_hash: &str,
+    in_flight_secs: u32,
+) -> error_stack::Result<Claim, RedisError> {
+    let redis_conn = store.get_redis_conn();
+    let result = redis_conn
+        .evaluate_redis_script::<_, Vec<Option<String>>>(
+            RESERVE_SCRIPT,
+            vec![prefixed_key(&redis_conn, &key(tenant_id, id))],
+            vec![request_hash.to_owned(), in_flight_secs.to_string()],
+        )
+        .await
+        .map_err(script_error)?;
+
+    match result.as_slice() {
+        [] => Ok(Claim::Acquired),
+        [Some(stored_hash), response] => {
+            let response = response
+                .as_deref()
+                .map(hex::decode)
+                .transpose()
+                .map_err(script_error)?;
+            Ok(Claim::of_existing(stored_hash, response, request_hash))
+        }
+        _ => Err(error_stack::Report::new(RedisError::UnknownResult)
+            .attach_printable(format!("Unexpected idempotency record {result:?}"))),
+    }
+}
+
+pub(super) async fn store_response(
+    store: &RedisStore,
+    tenant_id: &str,
+    id: IdempotencyKeyId<'_>,
+    request_hash: &str,
+    response: &[u8],
+    ttl_secs: u32,
+) -> error_stack::Result<(), RedisError> {
+    let redis_conn = store.get_redis_conn();
+    let stored = redis_conn
+        .evaluate_redis_script::<_, i64>(
+            STORE_SCRIPT,
+            vec![prefixed_key(&redis_conn, &key(tenant_id, id))],
+            vec![
+                request_hash.to_owned(),
+                hex::encode(response),
+                ttl_secs.to_string(),
+            ],
+        )
+        .await
+        .map_err(script_error)?;
+
+    match stored {
+        1 => Ok(()),
+        _ => Err(error_stack::Report::new(RedisError::NotFound)
+            .attach_printable("idempotency key is no longer reserved by this request")),
+    }
+}
+
+pub(super) async fn release(
+    store: &RedisStore,
+    tenant_id: &str,
+    id: IdempotencyKeyId<'_>,
+    request_hash: &str,
+) -> error_stack::Result<(), RedisError> {
+    let redis_conn = store.get_redis_conn();
+    redis_conn
+        .evaluate_redis_script::<_, i64>(
+            RELEASE_SCRIPT,
+            vec![prefixed_key(&redis_conn, &key(tenant_id, id))],
+            vec![request_hash.to_owned()],
+        )
+        .await
+        .map_err(script_error)?;
+
+    Ok(())
+}
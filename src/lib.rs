@@ -1,17 +1,24 @@
+pub mod anomaly;
 pub mod api_client;
 pub mod app;
+pub mod audit;
 pub mod config;
 pub mod crypto;
 pub mod custom_extractors;
 pub mod domain;
 pub mod error;
+pub mod idempotency;
 pub mod logger;
 #[cfg(feature = "middleware")]
 pub mod middleware;
 pub mod observability;
+#[cfg(feature = "limit")]
+pub mod rate_limit;
 pub mod routes;
 pub mod runtime_config;
+pub mod secrets_refresh;
 pub mod storage;
 pub mod tenant;
+pub mod usage;
 pub mod utils;
 pub mod validations;
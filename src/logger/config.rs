@@ -9,6 +9,9 @@ use serde::Deserialize;
 pub struct Log {
     /// Logging to a console.
     pub console: LogConsole,
+    /// Exporting spans to a trace collector.
+    #[serde(default)]
+    pub traces: crate::observability::TracesConfig,
 }
 
 /// Logging to a console.
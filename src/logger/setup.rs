@@ -10,6 +10,7 @@ use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{Layer, prelude::*};
 
 use super::config;
+use crate::observability::traces;
 
 fn get_envfilter_directive(
     default_log_level: tracing::Level,
@@ -93,6 +94,18 @@ fn get_logger_config(
 #[derive(Debug)]
 pub struct TelemetryGuard {
     _log_guards: Vec<WorkerGuard>,
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        // flush the spans still queued for export
+        if let Some(tracer_provider) = self.tracer_provider.take() {
+            if let Err(error) = tracer_provider.shutdown() {
+                eprintln!("Failed to shut down the tracer provider: {error:?}");
+            }
+        }
+    }
 }
 
 /// Setup logging sub-system specifying the logging configuration, service (binary) name, and a
@@ -114,6 +127,21 @@ pub fn setup(
         layers.push(console_layer);
     }
 
+    let tracer_provider = traces::init_tracer_provider(&config.traces, service_name);
+    if let Some(tracer_provider) = &tracer_provider {
+        // request spans are created at DEBUG, so this crate's spans are kept at that level
+        let traces_filter = tracing_subscriber::EnvFilter::new(get_envfilter_directive(
+            tracing::Level::WARN,
+            tracing::Level::DEBUG,
+            crates_to_filter.as_ref(),
+        ));
+        layers.push(
+            traces::layer(tracer_provider, service_name)
+                .with_filter(traces_filter)
+                .boxed(),
+        );
+    }
+
     let subscriber = tracing_subscriber::registry().with(layers);
 
     #[cfg(feature = "console")]
@@ -123,5 +151,6 @@ pub fn setup(
 
     Ok(TelemetryGuard {
         _log_guards: components.guards,
+        tracer_provider,
     })
 }
@@ -1,5 +1,6 @@
 mod macros;
 pub(crate) mod metrics;
+pub mod traces;
 
 use std::num::NonZeroU64;
 
@@ -7,6 +8,7 @@ pub use self::metrics::{
     HttpRequestMetricsLayer, init_metrics, spawn_bg_metrics_collector,
     start_prometheus_metrics_server,
 };
+pub use self::traces::TracesConfig;
 
 #[derive(Debug, Clone, serde::Deserialize, Default)]
 #[serde(tag = "mode", rename_all = "snake_case")]
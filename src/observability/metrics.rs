@@ -177,6 +177,13 @@ pub fn spawn_bg_metrics_collector(
 
                 #[cfg(feature = "caching")]
                 tenant_state.db.collect_cache_entry_count(tenant_id).await;
+                #[cfg(feature = "caching")]
+                if let Some(dek_cache) = &tenant_state.dek_cache {
+                    dek_cache.collect_entry_count(tenant_id).await;
+                }
+
+                #[cfg(feature = "kv")]
+                tenant_state.db.collect_drainer_backlog(tenant_id).await;
             }
         }
     });
@@ -205,6 +212,11 @@ histogram_metric_f64!(
     unit: "s",
     buckets: f64_histogram_buckets(),
 );
+counter_metric!(
+    pub(crate) SECRETS_REFRESH_COUNT, CARD_VAULT_METER,
+    name: "secrets.refresh.count",
+    description: "Number of rotatable secret refreshes, by trigger and outcome",
+);
 
 // HTTP server
 counter_metric!(
@@ -301,6 +313,75 @@ histogram_metric_f64!(
     unit: "s",
     buckets: f64_histogram_buckets(),
 );
+#[cfg(feature = "external_key_manager")]
+counter_metric!(
+    pub(crate) EXTERNAL_HTTP_RETRY_COUNT, CARD_VAULT_METER,
+    name: "external_http.retry.count",
+    description: "Number of external HTTP requests retried after a failed attempt",
+);
+
+// Circuit breaker
+#[cfg(feature = "external_key_manager")]
+gauge_metric!(
+    pub(crate) CIRCUIT_BREAKER_STATE, CARD_VAULT_METER,
+    name: "circuit_breaker.state",
+    description: "Circuit breaker state per service (0 = closed, 1 = open, 2 = half-open)",
+);
+#[cfg(feature = "external_key_manager")]
+counter_metric!(
+    pub(crate) CIRCUIT_BREAKER_TRANSITION_COUNT, CARD_VAULT_METER,
+    name: "circuit_breaker.transition.count",
+    description: "Number of circuit breaker state changes, by target state",
+);
+#[cfg(feature = "external_key_manager")]
+counter_metric!(
+    pub(crate) CIRCUIT_BREAKER_REJECTED_COUNT, CARD_VAULT_METER,
+    name: "circuit_breaker.rejected.count",
+    description: "Number of calls failed fast because the circuit breaker was open",
+);
+
+// Audit
+counter_metric!(
+    pub(crate) AUDIT_EVENT_COUNT, CARD_VAULT_METER,
+    name: "audit.event.count",
+    description: "Number of audit events written, by sink and outcome",
+);
+gauge_metric!(
+    pub(crate) AUDIT_QUEUE_CAPACITY, CARD_VAULT_METER,
+    name: "audit.queue.capacity",
+    description: "Free slots in the audit event queue; zero means handlers wait on the audit writer",
+);
+
+// Usage
+gauge_metric!(
+    pub(crate) STORED_ROW_COUNT, CARD_VAULT_METER,
+    name: "storage.stored_row.count",
+    description: "Number of rows stored per tenant",
+);
+gauge_metric!(
+    pub(crate) MERCHANT_STORED_ROW_COUNT, CARD_VAULT_METER,
+    name: "storage.merchant.stored_row.count",
+    description: "Number of rows stored by each of the merchants storing the most rows",
+);
+counter_metric!(
+    pub(crate) QUOTA_REJECTED_COUNT, CARD_VAULT_METER,
+    name: "usage.quota.rejected.count",
+    description: "Number of writes rejected because the merchant reached its stored row quota",
+);
+
+// Idempotency
+counter_metric!(
+    pub(crate) IDEMPOTENCY_REQUEST_COUNT, CARD_VAULT_METER,
+    name: "idempotency.request.count",
+    description: "Number of requests carrying an Idempotency-Key, by route and outcome",
+);
+
+// Anomaly detection
+counter_metric!(
+    pub(crate) RETRIEVAL_ANOMALY_COUNT, CARD_VAULT_METER,
+    name: "anomaly.retrieval.count",
+    description: "Number of callers found retrieving more distinct customers or cards than allowed",
+);
 
 // Cache
 #[cfg(feature = "caching")]
@@ -321,6 +402,12 @@ counter_metric!(
     name: "cache.removal.count",
     description: "Number of cache removal events",
 );
+#[cfg(all(feature = "caching", feature = "redis"))]
+counter_metric!(
+    pub(crate) CACHE_INVALIDATION_COUNT, CARD_VAULT_METER,
+    name: "cache.invalidation.count",
+    description: "Number of cache invalidations published or received over Redis",
+);
 #[cfg(feature = "caching")]
 gauge_metric!(
     pub(crate) CACHE_ENTRY_COUNT, CARD_VAULT_METER,
@@ -379,6 +466,12 @@ histogram_metric_f64!(
     buckets: f64_histogram_buckets(),
 );
 #[cfg(feature = "kv")]
+counter_metric!(
+    pub(crate) KV_STORAGE_SCHEME_COUNT, CARD_VAULT_METER,
+    name: "kv.storage_scheme.count",
+    description: "Storage scheme decisions, by resolved KV state",
+);
+#[cfg(feature = "kv")]
 counter_metric!(
     pub(crate) KV_DRAINER_PUSH_COUNT, CARD_VAULT_METER,
     name: "kv.drainer.push.count",
@@ -398,6 +491,53 @@ counter_metric!(
     name: "kv.cache_miss.count",
     description: "Redis cache misses that fell back to Postgres",
 );
+#[cfg(feature = "kv")]
+counter_metric!(
+    pub(crate) KV_DRAINER_ENTRY_COUNT, CARD_VAULT_METER,
+    name: "kv.drainer.entry.count",
+    description: "Number of drainer stream entries settled, by outcome",
+);
+#[cfg(feature = "kv")]
+counter_metric!(
+    pub(crate) KV_DRAINER_REPLAY_FAILURE_COUNT, CARD_VAULT_METER,
+    name: "kv.drainer.replay_failure.count",
+    description: "Number of failed drainer replay attempts, including ones later retried",
+);
+#[cfg(feature = "kv")]
+histogram_metric_f64!(
+    pub(crate) KV_DRAINER_REPLAY_DURATION, CARD_VAULT_METER,
+    name: "kv.drainer.replay.duration",
+    description: "Duration of drainer replay attempts against Postgres",
+    unit: "s",
+    buckets: f64_histogram_buckets(),
+);
+#[cfg(feature = "kv")]
+gauge_metric!(
+    pub(crate) KV_DRAINER_STREAM_LENGTH, CARD_VAULT_METER,
+    name: "kv.drainer.stream.length",
+    description: "Number of undrained entries in a drainer stream shard",
+);
+#[cfg(feature = "kv")]
+gauge_metric!(
+    pub(crate) KV_DRAINER_PENDING_COUNT, CARD_VAULT_METER,
+    name: "kv.drainer.pending.count",
+    description: "Number of drainer stream entries delivered to a drainer but not yet acknowledged",
+);
+#[cfg(feature = "kv")]
+gauge_metric!(
+    pub(crate) KV_DRAINER_OLDEST_ENTRY_AGE, CARD_VAULT_METER,
+    name: "kv.drainer.oldest_entry.age",
+    description: "Age of the oldest undrained entry in a drainer stream shard",
+    unit: "s",
+);
+#[cfg(feature = "kv")]
+histogram_metric_f64!(
+    pub(crate) KV_DRAINER_LAG, CARD_VAULT_METER,
+    name: "kv.drainer.lag",
+    description: "Time between an entry being pushed to a drainer stream and its replay starting",
+    unit: "s",
+    buckets: f64_histogram_buckets(),
+);
 
 #[derive(Debug, Clone, Copy, strum::IntoStaticStr)]
 #[strum(serialize_all = "snake_case")]
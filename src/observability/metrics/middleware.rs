@@ -1,6 +1,8 @@
 use std::{
+    collections::HashSet,
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::Instant,
 };
@@ -13,21 +15,40 @@ use axum::{
 use tower::{Layer, Service};
 
 use super::{HTTP_SERVER_ACTIVE_REQUESTS, HTTP_SERVER_REQUEST_COUNT, HTTP_SERVER_REQUEST_DURATION};
+use crate::storage::consts::X_TENANT_ID;
 
-#[derive(Debug, Clone, Copy)]
-pub struct HttpRequestMetricsLayer;
+/// Value of the `tenant_id` attribute for requests without a configured tenant. Only configured
+/// tenants become attribute values, so callers can't grow the number of series.
+const UNKNOWN_TENANT: &str = "unknown";
+
+#[derive(Debug, Clone)]
+pub struct HttpRequestMetricsLayer {
+    tenants: Arc<HashSet<String>>,
+}
+
+impl HttpRequestMetricsLayer {
+    pub fn new(tenants: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            tenants: Arc::new(tenants.into_iter().collect()),
+        }
+    }
+}
 
 impl<S> Layer<S> for HttpRequestMetricsLayer {
     type Service = HttpRequestMetricsService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        HttpRequestMetricsService { inner }
+        HttpRequestMetricsService {
+            inner,
+            tenants: Arc::clone(&self.tenants),
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct HttpRequestMetricsService<S> {
     inner: S,
+    tenants: Arc<HashSet<String>>,
 }
 
 // Using a drop guard ensures the counter is decremented on every exit path (normal response,
@@ -35,19 +56,25 @@ pub struct HttpRequestMetricsService<S> {
 struct ActiveRequestGuard {
     method: String,
     route: String,
+    tenant_id: String,
 }
 
 impl ActiveRequestGuard {
-    fn new(method: String, route: String) -> Self {
+    fn new(method: String, route: String, tenant_id: String) -> Self {
         HTTP_SERVER_ACTIVE_REQUESTS.add(
             1,
             crate::metric_attributes!(
                 ("http.request.method", method.clone()),
                 ("http.route", route.clone()),
+                ("tenant_id", tenant_id.clone()),
             ),
         );
 
-        Self { method, route }
+        Self {
+            method,
+            route,
+            tenant_id,
+        }
     }
 }
 
@@ -58,6 +85,7 @@ impl Drop for ActiveRequestGuard {
             crate::metric_attributes!(
                 ("http.request.method", self.method.clone()),
                 ("http.route", self.route.clone()),
+                ("tenant_id", self.tenant_id.clone()),
             ),
         );
     }
@@ -85,15 +113,24 @@ where
             .get::<MatchedPath>()
             .map(|p| p.as_str().to_owned())
             .unwrap_or_else(|| "UNKNOWN".to_string());
+        let tenant_id = req
+            .headers()
+            .get(X_TENANT_ID)
+            .and_then(|value| value.to_str().ok())
+            .filter(|tenant_id| self.tenants.contains(*tenant_id))
+            .unwrap_or(UNKNOWN_TENANT)
+            .to_owned();
 
         HTTP_SERVER_REQUEST_COUNT.add(
             1,
             crate::metric_attributes!(
                 ("http.request.method", method.clone()),
                 ("http.route", route.clone()),
+                ("tenant_id", tenant_id.clone()),
             ),
         );
-        let active_request_guard = ActiveRequestGuard::new(method.clone(), route.clone());
+        let active_request_guard =
+            ActiveRequestGuard::new(method.clone(), route.clone(), tenant_id.clone());
 
         let future = self.inner.call(req);
 
@@ -109,6 +146,7 @@ where
                     ("http.request.method", method.clone()),
                     ("http.route", route.clone()),
                     ("http.response.status_code", status.to_string()),
+                    ("tenant_id", tenant_id.clone()),
                 ),
             );
 
@@ -0,0 +1,248 @@
+//! Distributed tracing over OTLP.
+//!
+//! Spans are exported with a sampler that follows the caller's sampling decision and otherwise
+//! samples `sampling_ratio` of the traces started here. Incoming W3C `traceparent` headers make
+//! the request span a child of the caller's span, and outbound [`crate::api_client::ApiClient`]
+//! requests carry the current span along.
+//!
+//! Span fields can hold request data, so only the attributes in [`ALLOWED_ATTRIBUTES`] leave the
+//! process; every other attribute of a span or of its events is dropped before export.
+
+use std::time::Duration;
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::{
+    Context,
+    propagation::{Extractor, Injector},
+    trace::TracerProvider as _,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    Resource,
+    error::OTelSdkResult,
+    propagation::TraceContextPropagator,
+    trace::{BatchSpanProcessor, Sampler, SdkTracerProvider, SpanData, SpanProcessor},
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Attribute keys exported with spans and span events. Anything else is dropped.
+pub const ALLOWED_ATTRIBUTES: &[&str] = &[
+    // request span
+    "method",
+    "uri",
+    "version",
+    "tenant_id",
+    "request_id",
+    // database and outbound calls
+    "table",
+    "operation",
+    "pool",
+    "purpose",
+    "host",
+    "status_code",
+    "resource",
+    // added by the exporter layer
+    "level",
+    "target",
+    "busy_ns",
+    "idle_ns",
+    "code.filepath",
+    "code.namespace",
+    "code.lineno",
+    "thread.id",
+    "thread.name",
+    "otel.name",
+    "otel.kind",
+    "otel.status_code",
+];
+
+#[derive(Debug, Clone, serde::Deserialize, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum TracesConfig {
+    #[default]
+    Disabled,
+
+    Otlp {
+        endpoint: String,
+        #[serde(default = "default_endpoint_timeout")]
+        endpoint_timeout_secs: u64,
+        /// Share of traces started by the locker that are sampled, between 0 and 1. Requests
+        /// carrying a `traceparent` follow the caller's decision instead.
+        #[serde(default = "default_sampling_ratio")]
+        sampling_ratio: f64,
+    },
+}
+
+const fn default_endpoint_timeout() -> u64 {
+    10
+}
+
+const fn default_sampling_ratio() -> f64 {
+    1.0
+}
+
+impl TracesConfig {
+    pub fn validate(&self) -> Result<(), crate::error::ConfigurationError> {
+        match self {
+            Self::Disabled => Ok(()),
+            Self::Otlp {
+                endpoint,
+                sampling_ratio,
+                ..
+            } => {
+                if endpoint.trim().is_empty() {
+                    return Err(
+                        crate::error::ConfigurationError::InvalidConfigurationValueError(
+                            r#"log.traces.endpoint is required when mode is "otlp""#.into(),
+                        ),
+                    );
+                }
+                if !(0.0..=1.0).contains(sampling_ratio) {
+                    return Err(
+                        crate::error::ConfigurationError::InvalidConfigurationValueError(
+                            "log.traces.sampling_ratio must be between 0 and 1".into(),
+                        ),
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Build the tracer provider and install the W3C trace context propagator. `None` when traces
+/// are disabled or the exporter cannot be built.
+pub fn init_tracer_provider(
+    config: &TracesConfig,
+    service_name: &str,
+) -> Option<SdkTracerProvider> {
+    let TracesConfig::Otlp {
+        endpoint,
+        endpoint_timeout_secs,
+        sampling_ratio,
+    } = config
+    else {
+        return None;
+    };
+
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .with_timeout(Duration::from_secs(*endpoint_timeout_secs))
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(error) => {
+            // the subscriber is not installed yet, so this cannot go through `tracing`
+            eprintln!("Failed to build OTLP span exporter, traces disabled: {error:?}");
+            return None;
+        }
+    };
+
+    let provider = SdkTracerProvider::builder()
+        .with_span_processor(AttributeFilter {
+            inner: BatchSpanProcessor::builder(exporter).build(),
+        })
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            *sampling_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_owned())
+                .build(),
+        )
+        .build();
+
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    opentelemetry::global::set_tracer_provider(provider.clone());
+
+    Some(provider)
+}
+
+/// The `tracing` layer exporting spans to `provider`.
+pub fn layer<S>(
+    provider: &SdkTracerProvider,
+    service_name: &str,
+) -> tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(service_name.to_owned()))
+}
+
+/// Make `span` a child of the span described by the `traceparent` header, if any.
+pub fn set_parent_from_headers(span: &tracing::Span, headers: &HeaderMap) {
+    let context = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(context);
+}
+
+/// Add the `traceparent` of the current span to outbound `headers`.
+pub fn inject_current_context(headers: &mut HeaderMap) {
+    let context: Context = tracing::Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Drops every attribute not in [`ALLOWED_ATTRIBUTES`] before handing spans to the exporter.
+#[derive(Debug)]
+struct AttributeFilter<P> {
+    inner: P,
+}
+
+fn is_allowed(attribute: &opentelemetry::KeyValue) -> bool {
+    ALLOWED_ATTRIBUTES.contains(&attribute.key.as_str())
+}
+
+impl<P: SpanProcessor> SpanProcessor for AttributeFilter<P> {
+    fn on_start(&self, span: &mut opentelemetry_sdk::trace::Span, cx: &Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, mut span: SpanData) {
+        span.attributes.retain(is_allowed);
+        for event in span.events.events.iter_mut() {
+            event.attributes.retain(is_allowed);
+        }
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource);
+    }
+}
@@ -0,0 +1,400 @@
+//! Keyed rate limiting of the locker routes.
+//!
+//! Every route listed under `limit.routes` gets a token bucket per tenant and, optionally, one per
+//! merchant (`merchant_id` in v1 bodies, `entity_id` in v2 bodies). A request takes a token from
+//! each of its buckets, or from none of them when any is empty, in which case it is rejected with
+//! `429 Too Many Requests`. Buckets live in process memory by default; with `store = "redis"` they
+//! are shared by every locker instance.
+
+#[cfg(feature = "redis")]
+mod redis;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request, State},
+    http::request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{error, logger, observability::metrics, tenant::GlobalAppState, utils};
+
+/// Buckets kept in memory before the least used are dropped; a dropped bucket starts out full
+/// again.
+const MAX_MEMORY_BUCKETS: u64 = 100_000;
+
+/// Legacy routes the top level `request_count` / `duration` apply to.
+const LEGACY_ROUTES: [&str; 2] = ["/data/delete", "/cards/delete"];
+
+#[derive(Clone, serde::Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Deprecated, use `routes`: a per-tenant bucket for `/data/delete` and `/cards/delete`.
+    pub request_count: Option<u32>,
+    /// Deprecated, see `request_count` (in sec).
+    pub duration: Option<u32>,
+    pub store: RateLimitStore,
+    /// Buckets keyed by the route template, e.g. `/data/retrieve` or `/api/v2/vault/retrieve`.
+    pub routes: HashMap<String, RouteLimit>,
+}
+
+#[derive(Clone, Copy, serde::Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitStore {
+    #[default]
+    Memory,
+    #[cfg(feature = "redis")]
+    Redis,
+}
+
+#[derive(Clone, Copy, serde::Deserialize, Debug, Default)]
+pub struct RouteLimit {
+    pub tenant: Option<BucketConfig>,
+    pub merchant: Option<BucketConfig>,
+}
+
+/// Allows bursts of `request_count` requests, refilled at `request_count` per `duration`.
+#[derive(Clone, Copy, serde::Deserialize, Debug)]
+pub struct BucketConfig {
+    pub request_count: u32,
+    pub duration: u32, // in sec
+}
+
+impl BucketConfig {
+    fn capacity(&self) -> f64 {
+        f64::from(self.request_count)
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        f64::from(self.request_count) / f64::from(self.duration)
+    }
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), error::ConfigurationError> {
+        if self.request_count.is_some() != self.duration.is_some() {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "limit.request_count and limit.duration must be set together".into(),
+            ));
+        }
+
+        for (route, limit) in self.effective_routes() {
+            for bucket in [limit.tenant, limit.merchant].into_iter().flatten() {
+                if bucket.request_count == 0 || bucket.duration == 0 {
+                    return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                        format!(
+                            "limit.routes.\"{route}\" request_count and duration must be greater than 0"
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `routes`, with the legacy delete limit filled in for delete routes not listed there.
+    fn effective_routes(&self) -> HashMap<String, RouteLimit> {
+        let mut routes = self.routes.clone();
+        if let (Some(request_count), Some(duration)) = (self.request_count, self.duration) {
+            for route in LEGACY_ROUTES {
+                routes.entry(route.to_owned()).or_insert(RouteLimit {
+                    tenant: Some(BucketConfig {
+                        request_count,
+                        duration,
+                    }),
+                    merchant: None,
+                });
+            }
+        }
+        routes
+    }
+}
+
+#[derive(Debug, Clone, Copy, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+enum Scope {
+    Tenant,
+    Merchant,
+}
+
+crate::impl_metric_value_from!(Scope);
+
+struct Bucket {
+    key: String,
+    scope: Scope,
+    config: BucketConfig,
+}
+
+enum Decision {
+    Allowed,
+    Limited(Scope),
+}
+
+pub struct RateLimiter {
+    routes: HashMap<String, RouteLimit>,
+    store: Store,
+}
+
+enum Store {
+    Memory(moka::future::Cache<String, Arc<MemoryBucket>>),
+    #[cfg(feature = "redis")]
+    Redis(crate::storage::redis::RedisStore),
+}
+
+struct MemoryBucket {
+    config: BucketConfig,
+    tokens: Mutex<Tokens>,
+}
+
+struct Tokens {
+    available: f64,
+    updated_at: Instant,
+}
+
+impl MemoryBucket {
+    fn full(config: BucketConfig, now: Instant) -> Self {
+        Self {
+            config,
+            tokens: Mutex::new(Tokens {
+                available: config.capacity(),
+                updated_at: now,
+            }),
+        }
+    }
+}
+
+impl Tokens {
+    fn refill(&mut self, config: &BucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.available =
+            (self.available + elapsed * config.refill_per_sec()).min(config.capacity());
+        self.updated_at = now;
+    }
+}
+
+/// Drops a bucket left unused for its `duration`, by which time it would be full again.
+struct IdleExpiry;
+
+impl moka::Expiry<String, Arc<MemoryBucket>> for IdleExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &String,
+        bucket: &Arc<MemoryBucket>,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(Duration::from_secs(u64::from(bucket.config.duration)))
+    }
+
+    fn expire_after_read(
+        &self,
+        _key: &String,
+        bucket: &Arc<MemoryBucket>,
+        _read_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+        _last_modified_at: Instant,
+    ) -> Option<Duration> {
+        Some(Duration::from_secs(u64::from(bucket.config.duration)))
+    }
+}
+
+impl RateLimiter {
+    pub fn new(
+        config: &RateLimitConfig,
+        #[cfg(feature = "redis")] redis: Option<&crate::storage::redis::RedisStore>,
+    ) -> Result<Self, error::ConfigurationError> {
+        let store = match config.store {
+            RateLimitStore::Memory => Store::Memory(
+                moka::future::Cache::builder()
+                    .max_capacity(MAX_MEMORY_BUCKETS)
+                    .expire_after(IdleExpiry)
+                    .build(),
+            ),
+            #[cfg(feature = "redis")]
+            RateLimitStore::Redis => Store::Redis(redis.cloned().ok_or_else(|| {
+                error::ConfigurationError::InvalidConfigurationValueError(
+                    "limit.store = \"redis\" requires a reachable redis".into(),
+                )
+            })?),
+        };
+
+        Ok(Self {
+            routes: config.effective_routes(),
+            store,
+        })
+    }
+
+    fn buckets(
+        &self,
+        route: &str,
+        tenant_id: &str,
+        merchant_id: Option<&str>,
+    ) -> Option<Vec<Bucket>> {
+        let limit = self.routes.get(route)?;
+        // The `{tenant_id}` hash tag keeps the buckets of a request on one Redis cluster slot.
+        let mut buckets = Vec::with_capacity(2);
+        if let Some(config) = limit.tenant {
+            buckets.push(Bucket {
+                key: format!("rate_limit:{{{tenant_id}}}:{route}:tenant"),
+                scope: Scope::Tenant,
+                config,
+            });
+        }
+        if let (Some(config), Some(merchant_id)) = (limit.merchant, merchant_id) {
+            buckets.push(Bucket {
+                key: format!("rate_limit:{{{tenant_id}}}:{route}:merchant:{merchant_id}"),
+                scope: Scope::Merchant,
+                config,
+            });
+        }
+        Some(buckets)
+    }
+
+    async fn acquire(&self, buckets: &[Bucket]) -> Decision {
+        match &self.store {
+            Store::Memory(state) => acquire_in_memory(state, buckets, Instant::now()).await,
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => match redis::acquire(store, buckets).await {
+                Ok(decision) => decision,
+                Err(error) => {
+                    // Failing open keeps the locker serving requests while Redis is unavailable.
+                    logger::warn!(?error, "Rate limiter Redis call failed, allowing request");
+                    Decision::Allowed
+                }
+            },
+        }
+    }
+}
+
+async fn acquire_in_memory(
+    state: &moka::future::Cache<String, Arc<MemoryBucket>>,
+    buckets: &[Bucket],
+    now: Instant,
+) -> Decision {
+    let mut entries = Vec::with_capacity(buckets.len());
+    for bucket in buckets {
+        let entry = state
+            .get_with_by_ref(&bucket.key, async {
+                Arc::new(MemoryBucket::full(bucket.config, now))
+            })
+            .await;
+        entries.push(entry);
+    }
+
+    // Every request of a route locks its buckets in the same order, tenant before merchant.
+    let mut tokens = entries
+        .iter()
+        .map(|entry| entry.tokens.lock().unwrap_or_else(PoisonError::into_inner))
+        .collect::<Vec<_>>();
+    for ((bucket, entry), tokens) in buckets.iter().zip(&entries).zip(tokens.iter_mut()) {
+        tokens.refill(&entry.config, now);
+        if tokens.available < 1.0 {
+            return Decision::Limited(bucket.scope);
+        }
+    }
+
+    for tokens in &mut tokens {
+        tokens.available -= 1.0;
+    }
+    Decision::Allowed
+}
+
+/// Middleware applying the buckets configured for the matched route.
+pub async fn middleware(
+    State(state): State<Arc<GlobalAppState>>,
+    matched_path: Option<MatchedPath>,
+    parts: request::Parts,
+    body: Bytes,
+    next: Next,
+) -> Response {
+    let request_scope = utils::RequestScope::new(matched_path.as_ref(), &parts, &body);
+    let route = request_scope.route;
+    // Unknown tenants are rejected by the handler, and must not create buckets.
+    let tenant_id = request_scope
+        .tenant_id
+        .filter(|tenant_id| state.known_tenants.contains(*tenant_id));
+
+    if let Some(tenant_id) = tenant_id {
+        let owner_id = request_scope.owner_id();
+        let merchant_id = owner_id.as_deref();
+
+        if let Some(buckets) = state.rate_limiter.buckets(route, tenant_id, merchant_id) {
+            if let Decision::Limited(scope) = state.rate_limiter.acquire(&buckets).await {
+                metrics::HTTP_SERVER_RATE_LIMITED_REQUEST_COUNT.add(
+                    1,
+                    crate::metric_attributes!(
+                        ("http.request.method", parts.method.to_string()),
+                        ("http.route", route.to_owned()),
+                        ("tenant_id", tenant_id.to_owned()),
+                        ("scope", scope),
+                    ),
+                );
+                logger::warn!(
+                    route,
+                    tenant_id,
+                    merchant_id,
+                    scope = <&'static str>::from(scope),
+                    "Rate limit applied"
+                );
+
+                return error::ApiError::RateLimited.into_response();
+            }
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn bucket(key: &str, scope: Scope, request_count: u32) -> Bucket {
+        Bucket {
+            key: key.to_owned(),
+            scope,
+            config: BucketConfig {
+                request_count,
+                duration: 1,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_buckets_take_tokens_together_and_refill() {
+        let state = moka::future::Cache::new(16);
+        let buckets = [
+            bucket("tenant", Scope::Tenant, 2),
+            bucket("merchant", Scope::Merchant, 1),
+        ];
+        let now = Instant::now();
+
+        assert!(matches!(
+            acquire_in_memory(&state, &buckets, now).await,
+            Decision::Allowed
+        ));
+        assert!(matches!(
+            acquire_in_memory(&state, &buckets, now).await,
+            Decision::Limited(Scope::Merchant)
+        ));
+        // the rejected request did not take the tenant's second token
+        assert!(matches!(
+            acquire_in_memory(&state, &buckets[..1], now).await,
+            Decision::Allowed
+        ));
+
+        let later = now + Duration::from_secs(1);
+        assert!(matches!(
+            acquire_in_memory(&state, &buckets, later).await,
+            Decision::Allowed
+        ));
+    }
+}
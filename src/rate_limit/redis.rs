@@ -0,0 +1,82 @@
+//! Token buckets shared through Redis, so every locker instance draws from the same buckets.
+
+use hyperswitch_redis_interface::errors::RedisError;
+
+use super::{Bucket, Decision};
+use crate::storage::redis::{RedisStore, prefixed_key};
+
+/// Take a token from every bucket in `KEYS`, or from none of them. `ARGV` holds the capacity and
+/// refill rate (per second) of each key in turn. Returns `{1, 0}` when allowed and `{0, i}` when
+/// the `i`-th bucket is empty. Timestamps come from the Redis server clock.
+const ACQUIRE_SCRIPT: &str = r"
+local time = redis.call('TIME')
+local now_ms = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local tokens = {}
+for i, key in ipairs(KEYS) do
+    local capacity = tonumber(ARGV[2 * i - 1])
+    local rate = tonumber(ARGV[2 * i])
+    local bucket = redis.call('HMGET', key, 'tokens', 'updated_at')
+    local available = tonumber(bucket[1]) or capacity
+    local updated_at = tonumber(bucket[2]) or now_ms
+    available = math.min(capacity, available + math.max(0, now_ms - updated_at) * rate / 1000)
+    if available < 1 then
+        return {0, i}
+    end
+    tokens[i] = available
+end
+for i, key in ipairs(KEYS) do
+    local capacity = tonumber(ARGV[2 * i - 1])
+    local rate = tonumber(ARGV[2 * i])
+    redis.call('HSET', key, 'tokens', tostring(tokens[i] - 1), 'updated_at', now_ms)
+    redis.call('PEXPIRE', key, math.ceil(capacity * 1000 / rate))
+end
+return {1, 0}
+";
+
+pub(super) async fn acquire(
+    store: &RedisStore,
+    buckets: &[Bucket],
+) -> error_stack::Result<Decision, RedisError> {
+    if buckets.is_empty() {
+        return Ok(Decision::Allowed);
+    }
+
+    let redis_conn = store.get_redis_conn();
+    let keys = buckets
+        .iter()
+        .map(|bucket| prefixed_key(&redis_conn, &bucket.key))
+        .collect::<Vec<_>>();
+    let args = buckets
+        .iter()
+        .flat_map(|bucket| {
+            [
+                bucket.config.capacity().to_string(),
+                bucket.config.refill_per_sec().to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let result = redis_conn
+        .evaluate_redis_script::<_, Vec<i64>>(ACQUIRE_SCRIPT, keys, args)
+        .await
+        .map_err(|err| {
+            error_stack::Report::new(RedisError::UnknownResult).attach_printable(format!("{err:?}"))
+        })?;
+
+    match result.as_slice() {
+        [1, _] => Ok(Decision::Allowed),
+        [0, index] => {
+            let bucket = usize::try_from(*index)
+                .ok()
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| buckets.get(index))
+                .ok_or_else(|| {
+                    error_stack::Report::new(RedisError::UnknownResult)
+                        .attach_printable(format!("Unexpected bucket index {index}"))
+                })?;
+            Ok(Decision::Limited(bucket.scope))
+        }
+        _ => Err(error_stack::Report::new(RedisError::UnknownResult)
+            .attach_printable(format!("Unexpected rate limit script result {result:?}"))),
+    }
+}
@@ -1,3 +1,7 @@
+#[cfg(feature = "caching")]
+pub mod admin;
+pub mod anomaly;
+pub mod audit;
 pub mod data;
 pub mod entity;
 pub mod health;
@@ -12,6 +16,22 @@ fn record_expired_data_encountered(resource: crate::observability::metrics::Reso
         .add(1, crate::metric_attributes!(("resource", resource)));
 }
 
+/// Record an expired row that was read and delete it in the background with `delete`.
+fn delete_expired_data<F, T, E>(resource: crate::observability::metrics::Resource, delete: F)
+where
+    F: std::future::Future<Output = Result<T, E>> + Send + 'static,
+{
+    record_expired_data_encountered(resource);
+
+    tokio::spawn(async move {
+        let outcome = match delete.await {
+            Ok(_) => crate::observability::metrics::TtlDeletionOutcome::Deleted,
+            Err(_) => crate::observability::metrics::TtlDeletionOutcome::Failed,
+        };
+        record_ttl_deletion_result(resource, outcome);
+    });
+}
+
 fn record_ttl_deletion_result(
     resource: crate::observability::metrics::Resource,
     outcome: crate::observability::metrics::TtlDeletionOutcome,
@@ -0,0 +1,149 @@
+//! Operator endpoints for the in-memory caches of a tenant.
+//!
+//! Mounted behind the JWE middleware like the card APIs and only open to the roles in
+//! `caller_auth.admin_roles`. Every mutating call is recorded in the audit log.
+
+use std::sync::Arc;
+
+use axum::{Json, routing::post};
+
+use crate::{
+    app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
+    custom_extractors::{AdminCaller, TenantStateResolver},
+    error::{self, ContainerError},
+    routes::data::types::Validation,
+    storage::caching::{CacheName, CacheReport},
+    tenant::GlobalAppState,
+};
+
+pub fn serve() -> axum::Router<Arc<GlobalAppState>> {
+    axum::Router::new()
+        .route("/cache/stats", post(cache_stats))
+        .route("/cache/invalidate", post(invalidate_cache))
+        .route("/cache/flush", post(flush_cache))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CacheStatsResponse {
+    pub tenant_id: String,
+    pub caches: Vec<CacheReport>,
+}
+
+/// Request body for `POST /admin/cache/invalidate`.
+#[derive(Debug, serde::Deserialize)]
+pub struct InvalidateCacheRequest {
+    pub cache_name: CacheName,
+    /// The id for `merchant`, `entity` and `dek`, the hex-encoded hash for `hash_table` and
+    /// `fingerprint`.
+    pub key: String,
+}
+
+impl Validation for InvalidateCacheRequest {
+    type Error = error::ApiError;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        if self.key.trim().is_empty() {
+            return Err(error::ApiError::ValidationError("key must not be empty"));
+        }
+
+        match self.cache_name {
+            CacheName::HashTable | CacheName::Fingerprint if hex::decode(&self.key).is_err() => {
+                Err(error::ApiError::ValidationError(
+                    "key must be hex-encoded for hash_table and fingerprint caches",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Request body for `POST /admin/cache/flush`.
+#[derive(Debug, serde::Deserialize)]
+pub struct FlushCacheRequest {
+    pub cache_name: CacheName,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CacheActionResponse {
+    pub cache_name: CacheName,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+/// `/admin/cache/stats` handler
+#[tracing::instrument(skip_all)]
+pub async fn cache_stats(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    _admin: AdminCaller,
+) -> Json<CacheStatsResponse> {
+    let mut caches = tenant_app_state.db.cache_reports().await;
+    if let Some(dek_cache) = &tenant_app_state.dek_cache {
+        caches.push(dek_cache.report().await);
+    }
+
+    Json(CacheStatsResponse {
+        tenant_id: tenant_app_state.config.tenant_id.clone(),
+        caches,
+    })
+}
+
+/// `/admin/cache/invalidate` handler
+#[tracing::instrument(skip_all)]
+pub async fn invalidate_cache(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    _admin: AdminCaller,
+    audit: AuditContext,
+    Json(request): Json<InvalidateCacheRequest>,
+) -> Result<Json<CacheActionResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::CacheInvalidate)
+        .resource_id(Some(format!("{}:{}", request.cache_name, request.key)));
+
+    let result = invalidate_cache_inner(&tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn invalidate_cache_inner(
+    tenant_app_state: &TenantAppState,
+    request: InvalidateCacheRequest,
+) -> Result<Json<CacheActionResponse>, ContainerError<error::ApiError>> {
+    request.validate()?;
+
+    tenant_app_state
+        .invalidate_cache(request.cache_name, Some(request.key.clone()))
+        .await;
+
+    Ok(Json(CacheActionResponse {
+        cache_name: request.cache_name,
+        key: Some(request.key),
+        message: "Cache entry invalidated".into(),
+    }))
+}
+
+/// `/admin/cache/flush` handler
+#[tracing::instrument(skip_all)]
+pub async fn flush_cache(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    _admin: AdminCaller,
+    audit: AuditContext,
+    Json(request): Json<FlushCacheRequest>,
+) -> Json<CacheActionResponse> {
+    tenant_app_state
+        .invalidate_cache(request.cache_name, None)
+        .await;
+
+    audit
+        .record(
+            AuditEvent::new(AuditAction::CacheFlush)
+                .resource_id(Some(request.cache_name.to_string())),
+        )
+        .await;
+
+    Json(CacheActionResponse {
+        cache_name: request.cache_name,
+        key: None,
+        message: "Cache flushed".into(),
+    })
+}
@@ -0,0 +1,92 @@
+//! Operator endpoints for callers blocked by anomaly detection.
+//!
+//! Scoped to the tenant of the caller, mounted behind the JWE middleware and restricted to callers
+//! in `caller_auth.admin_roles`. Clearing a block is recorded as an audit event and, with Redis
+//! configured, applied on every instance.
+
+use axum::Json;
+
+use crate::{
+    anomaly,
+    app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
+    custom_extractors::{AdminCaller, TenantStateResolver},
+    error::{self, ContainerError},
+    logger,
+    routes::data::types::Validation,
+};
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct BlockedCallersResponse {
+    /// Key identity of each blocked caller, e.g. `payments:3f9a12c07be45d61`, sorted.
+    pub callers: Vec<String>,
+}
+
+/// Request body for `POST /admin/anomaly/unblock`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct UnblockCallerRequest {
+    /// Key identity of the blocked caller, as listed by `/admin/anomaly/blocked`.
+    pub caller: String,
+}
+
+impl Validation for UnblockCallerRequest {
+    type Error = error::ApiError;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        if self.caller.is_empty() {
+            return Err(error::ApiError::ValidationError("caller must not be empty"));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct UnblockCallerResponse {
+    /// Whether the caller was blocked.
+    pub unblocked: bool,
+}
+
+/// `/admin/anomaly/blocked` handler
+#[tracing::instrument(skip_all)]
+pub async fn list_blocked_callers(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    _admin: AdminCaller,
+) -> Result<Json<BlockedCallersResponse>, ContainerError<error::ApiError>> {
+    let callers = anomaly::blocked_callers(&tenant_app_state).await;
+
+    Ok(Json(BlockedCallersResponse { callers }))
+}
+
+/// `/admin/anomaly/unblock` handler
+#[tracing::instrument(skip_all)]
+pub async fn unblock_caller(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    _admin: AdminCaller,
+    audit: AuditContext,
+    Json(request): Json<UnblockCallerRequest>,
+) -> Result<Json<UnblockCallerResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::AnomalyUnblock).resource_id(Some(&request.caller));
+
+    let result = unblock_caller_inner(&tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn unblock_caller_inner(
+    tenant_app_state: &TenantAppState,
+    request: UnblockCallerRequest,
+) -> Result<Json<UnblockCallerResponse>, ContainerError<error::ApiError>> {
+    request.validate()?;
+
+    let unblocked = anomaly::unblock_caller(tenant_app_state, &request.caller).await?;
+
+    logger::info!(
+        caller = %request.caller,
+        unblocked,
+        "anomaly block cleared"
+    );
+
+    Ok(Json(UnblockCallerResponse { unblocked }))
+}
@@ -0,0 +1,159 @@
+//! Operator endpoint for reading the audit trail back.
+//!
+//! Reads the `audit_log` table of the postgres audit sink, scoped to the tenant of the caller.
+//! Mounted behind the JWE middleware and only open to `caller_auth.admin_roles`; every query is
+//! itself recorded as an audit event.
+
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+
+use crate::{
+    audit::{AuditAction, AuditContext, AuditEvent, record::SealedAuditRecord},
+    custom_extractors::{AdminCaller, TenantStateResolver},
+    error::{self, ContainerError, ResultContainerExt},
+    logger,
+    routes::data::types::Validation,
+    storage::{AuditInterface, types::AuditLogFilter},
+    tenant::GlobalAppState,
+};
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+/// Request body for `POST /admin/audit/query`. Unset filters match everything.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct AuditQueryRequest {
+    /// Merchant or entity id.
+    pub merchant_id: Option<String>,
+    /// `card_reference` or `vault_id`.
+    pub resource_id: Option<String>,
+    /// Role the caller that performed the action authenticated as with `x-caller-api-key`;
+    /// events of callers that sent no key carry no role and never match.
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    /// Inclusive lower bound on the event time.
+    #[serde(
+        default,
+        with = "crate::utils::primitive_datetime_serde::iso8601::option"
+    )]
+    pub from: Option<time::PrimitiveDateTime>,
+    /// Exclusive upper bound on the event time.
+    #[serde(
+        default,
+        with = "crate::utils::primitive_datetime_serde::iso8601::option"
+    )]
+    pub to: Option<time::PrimitiveDateTime>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<i64>,
+}
+
+fn default_limit() -> i64 {
+    DEFAULT_LIMIT
+}
+
+impl Validation for AuditQueryRequest {
+    type Error = error::ApiError;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        if !(1..=MAX_LIMIT).contains(&self.limit) {
+            return Err(error::ApiError::ValidationError(
+                "limit must be between 1 and 1000",
+            ));
+        }
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err(error::ApiError::ValidationError("from must be before to"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct AuditQueryResponse {
+    /// Oldest first.
+    pub events: Vec<SealedAuditRecord>,
+    /// Pass as `cursor` to fetch the next page; absent on the last page.
+    pub next_cursor: Option<i64>,
+}
+
+/// `/admin/audit/query` handler
+#[tracing::instrument(skip_all)]
+pub async fn query_audit_log(
+    State(global_app_state): State<Arc<GlobalAppState>>,
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    _admin: AdminCaller,
+    audit: AuditContext,
+    Json(request): Json<AuditQueryRequest>,
+) -> Result<Json<AuditQueryResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::AuditQuery).resource_id(request.resource_id.clone());
+    let event = match &request.merchant_id {
+        Some(merchant_id) => event.merchant_id(merchant_id),
+        None => event,
+    };
+
+    let result = query_audit_log_inner(
+        &global_app_state,
+        tenant_app_state.config.tenant_id.clone(),
+        request,
+    )
+    .await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn query_audit_log_inner(
+    global_app_state: &GlobalAppState,
+    tenant_id: String,
+    request: AuditQueryRequest,
+) -> Result<Json<AuditQueryResponse>, ContainerError<error::ApiError>> {
+    request.validate()?;
+
+    let storage = global_app_state
+        .auditor
+        .as_ref()
+        .and_then(|auditor| auditor.storage())
+        .ok_or(error::ApiError::ValidationError(
+            "audit queries require the postgres audit sink",
+        ))?;
+
+    let limit = request.limit;
+    let rows = storage
+        .find_audit_events(&AuditLogFilter {
+            tenant_id: Some(tenant_id),
+            merchant_id: request.merchant_id,
+            resource_id: request.resource_id,
+            caller_role: request.actor,
+            action: request
+                .action
+                .map(|action| <&str>::from(action).to_string()),
+            from: request.from,
+            to: request.to,
+            after: request.cursor,
+            limit,
+        })
+        .await?;
+
+    let next_cursor = i64::try_from(rows.len())
+        .is_ok_and(|len| len >= limit)
+        .then(|| rows.last().map(|row| row.id))
+        .flatten();
+
+    let events = rows
+        .into_iter()
+        .map(|row| serde_json::from_str::<SealedAuditRecord>(&row.event))
+        .collect::<Result<Vec<_>, _>>()
+        .change_error(error::ApiError::DecodingError)?;
+
+    logger::info!(events = events.len(), "audit log queried");
+
+    Ok(Json(AuditQueryResponse {
+        events,
+        next_cursor,
+    }))
+}
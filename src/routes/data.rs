@@ -5,91 +5,63 @@ pub mod types;
 use std::sync::Arc;
 
 use axum::{Json, routing::post};
-#[cfg(feature = "limit")]
-use axum::{error_handling::HandleErrorLayer, extract::MatchedPath, response::IntoResponse};
 use hyperswitch_masking::Secret;
 
 use self::types::Validation;
 use crate::{
+    anomaly,
+    app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
     crypto::{hash_manager::managers::sha::Sha512, keymanager},
-    custom_extractors::{OptionalFingerprintId, TenantStateResolver},
+    custom_extractors::{OptionalCallerKey, OptionalFingerprintId, TenantStateResolver},
     domain::{fingerprint, hash},
     error::{self, ContainerError, ResultContainerExt},
     logger,
     observability::metrics,
-    storage::{HashInterface, LockerInterface},
+    storage::{
+        HashInterface, LockerInterface,
+        types::{LockerPrecondition, LockerUpdate, StoredRowTable},
+    },
     tenant::GlobalAppState,
-    utils,
+    usage, utils,
 };
 
-#[cfg(feature = "limit")]
-const BUFFER_LIMIT: usize = 1024;
-
-#[cfg(feature = "limit")]
-async fn ratelimit_err_handler(
-    method: hyper::Method,
-    matched_path: Option<MatchedPath>,
-    _: axum::BoxError,
-) -> impl IntoResponse {
-    let route = matched_path
-        .map(|path| path.as_str().to_owned())
-        .unwrap_or_else(|| "UNKNOWN".to_string());
-
-    crate::observability::metrics::HTTP_SERVER_RATE_LIMITED_REQUEST_COUNT.add(
-        1,
-        crate::metric_attributes!(
-            ("http.request.method", method.to_string()),
-            ("http.route", route),
-        ),
-    );
-
-    (hyper::StatusCode::TOO_MANY_REQUESTS, "Rate Limit Applied")
-}
-
 ///
 /// Function for registering routes that is specifically handling the main locker apis
 ///
-#[allow(clippy::let_and_return)]
-pub fn serve(
-    #[cfg(feature = "limit")] global_app_state: Arc<GlobalAppState>,
-) -> axum::Router<Arc<GlobalAppState>> {
-    #[cfg(feature = "limit")]
-    let ratelimit_middleware = tower::ServiceBuilder::new()
-        .layer(HandleErrorLayer::new(ratelimit_err_handler))
-        .buffer(
-            global_app_state
-                .global_config
-                .limit
-                .buffer_size
-                .unwrap_or(BUFFER_LIMIT),
-        )
-        .load_shed()
-        .rate_limit(
-            global_app_state.global_config.limit.request_count,
-            std::time::Duration::from_secs(global_app_state.global_config.limit.duration),
-        )
-        .into_inner();
-
-    #[cfg(feature = "limit")]
-    let delete_route = post(delete_card).layer(ratelimit_middleware);
-
-    #[cfg(not(feature = "limit"))]
-    let delete_route = post(delete_card);
-
-    let router = axum::Router::new()
-        .route("/delete", delete_route)
+pub fn serve() -> axum::Router<Arc<GlobalAppState>> {
+    axum::Router::new()
+        .route("/delete", post(delete_card))
         .route("/add", post(add_card))
         .route("/retrieve", post(retrieve_card))
-        .route("/fingerprint", post(get_or_insert_fingerprint));
-
-    router
+        .route("/ttl", post(update_card_ttl))
+        .route("/fingerprint", post(get_or_insert_fingerprint))
 }
 
 /// `/data/add` handling the requirement of storing data
 #[tracing::instrument(skip_all)]
 pub async fn add_card(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
     Json(request): Json<types::StoreCardRequest>,
+) -> Result<Json<types::StoreCardResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::CardAdd).merchant_id(&request.merchant_id);
+    let result = add_card_inner(tenant_app_state, request).await;
+    let card_reference = result
+        .as_ref()
+        .ok()
+        .and_then(|response| response.payload.as_ref())
+        .map(|payload| payload.card_reference.clone());
+    audit
+        .record(event.resource_id(card_reference).result(&result))
+        .await;
+
+    result
+}
+
+async fn add_card_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::StoreCardRequest,
 ) -> Result<Json<types::StoreCardResponse>, ContainerError<error::ApiError>> {
     request.validate()?;
 
@@ -132,6 +104,13 @@ pub async fn add_card(
                     (Some(duplication_check), decrypted_locker_data)
                 }
                 None => {
+                    usage::ensure_capacity(
+                        &tenant_app_state,
+                        StoredRowTable::Locker,
+                        &request.merchant_id,
+                    )
+                    .await?;
+
                     let encrypted_locker_data = crypto_operation::encrypt_data_and_insert_into_db(
                         &tenant_app_state,
                         crypto_manager,
@@ -147,6 +126,13 @@ pub async fn add_card(
             (duplication_check, output)
         }
         None => {
+            usage::ensure_capacity(
+                &tenant_app_state,
+                StoredRowTable::Locker,
+                &request.merchant_id,
+            )
+            .await?;
+
             let hash_table = hash::insert_or_get(&tenant_app_state, hash_data).await?;
 
             let encrypted_locker_data = crypto_operation::encrypt_data_and_insert_into_db(
@@ -171,7 +157,21 @@ pub async fn add_card(
 #[tracing::instrument(skip_all)]
 pub async fn delete_card(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
     Json(request): Json<types::DeleteCardRequest>,
+) -> Result<Json<types::DeleteCardResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::CardDelete)
+        .merchant_id(&request.merchant_id)
+        .resource_id(Some(&request.card_reference));
+    let result = delete_card_inner(tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn delete_card_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::DeleteCardRequest,
 ) -> Result<Json<types::DeleteCardResponse>, ContainerError<error::ApiError>> {
     let _entity = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
         .find_by_entity_id(&tenant_app_state, request.merchant_id.clone())
@@ -198,7 +198,34 @@ pub async fn delete_card(
 #[tracing::instrument(skip_all)]
 pub async fn retrieve_card(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
+    OptionalCallerKey(caller_key): OptionalCallerKey,
+    audit: AuditContext,
     Json(request): Json<types::RetrieveCardRequest>,
+) -> Result<Json<types::RetrieveCardResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::CardRetrieve)
+        .merchant_id(&request.merchant_id)
+        .resource_id(Some(&request.card_reference));
+    let result = match anomaly::screen_retrieval(
+        &tenant_app_state,
+        &audit,
+        caller_key.as_deref(),
+        &request.merchant_id,
+        &request.merchant_customer_id,
+        &request.card_reference,
+    )
+    .await
+    {
+        Ok(()) => retrieve_card_inner(tenant_app_state, request).await,
+        Err(error) => Err(error),
+    };
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn retrieve_card_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::RetrieveCardRequest,
 ) -> Result<Json<types::RetrieveCardResponse>, ContainerError<error::ApiError>> {
     let crypto_manager = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
         .find_by_entity_id(&tenant_app_state, request.merchant_id.clone())
@@ -220,26 +247,15 @@ pub async fn retrieve_card(
         .ttl
         .map(|ttl| -> Result<(), error::ApiError> {
             if utils::date_time::now() > ttl {
-                super::record_expired_data_encountered(metrics::Resource::Locker);
-
-                tokio::spawn(async move {
-                    let result = tenant_app_state
+                super::delete_expired_data(metrics::Resource::Locker, async move {
+                    tenant_app_state
                         .db
                         .delete_locker(
                             request.card_reference.into(),
                             &request.merchant_id,
                             &request.merchant_customer_id,
                         )
-                        .await;
-
-                    super::record_ttl_deletion_result(
-                        metrics::Resource::Locker,
-                        if result.is_ok() {
-                            metrics::TtlDeletionOutcome::Deleted
-                        } else {
-                            metrics::TtlDeletionOutcome::Failed
-                        },
-                    );
+                        .await
                 });
 
                 Err(error::ApiError::NotFoundError)
@@ -255,12 +271,112 @@ pub async fn retrieve_card(
     Ok(response)
 }
 
+/// `/data/ttl` handling the requirement of setting, extending or clearing the expiry of data
+#[tracing::instrument(skip_all)]
+pub async fn update_card_ttl(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
+    Json(request): Json<types::UpdateCardTtlRequest>,
+) -> Result<Json<types::UpdateCardTtlResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::CardTtlUpdate)
+        .merchant_id(&request.merchant_id)
+        .resource_id(Some(&request.card_reference));
+    let result = update_card_ttl_inner(tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn update_card_ttl_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::UpdateCardTtlRequest,
+) -> Result<Json<types::UpdateCardTtlResponse>, ContainerError<error::ApiError>> {
+    request.validate()?;
+
+    let _entity = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
+        .find_by_entity_id(&tenant_app_state, request.merchant_id.clone())
+        .await?;
+
+    let locker = tenant_app_state
+        .db
+        .find_by_locker_id_merchant_id_customer_id(
+            request.card_reference.clone().into(),
+            &request.merchant_id,
+            &request.merchant_customer_id,
+        )
+        .await?;
+
+    // Data that has already expired cannot be revived by moving its ttl.
+    if locker.is_expired_at(utils::date_time::now()) {
+        super::delete_expired_data(metrics::Resource::Locker, async move {
+            tenant_app_state
+                .db
+                .delete_locker(
+                    request.card_reference.into(),
+                    &request.merchant_id,
+                    &request.merchant_customer_id,
+                )
+                .await
+        });
+
+        return Err(error::ApiError::NotFoundError.into());
+    }
+
+    let update = LockerUpdate::ttl(*request.ttl);
+    let _locker = match request.if_version {
+        Some(version) => {
+            tenant_app_state
+                .db
+                .update_locker_if(
+                    request.card_reference.into(),
+                    &request.merchant_id,
+                    &request.merchant_customer_id,
+                    update,
+                    LockerPrecondition { version },
+                )
+                .await?
+        }
+        None => {
+            tenant_app_state
+                .db
+                .update_locker(
+                    request.card_reference.into(),
+                    &request.merchant_id,
+                    &request.merchant_customer_id,
+                    update,
+                )
+                .await?
+        }
+    };
+
+    let response = Json(types::UpdateCardTtlResponse {
+        status: types::Status::Ok,
+    });
+    logger::info!(update_card_ttl_response=?response);
+
+    Ok(response)
+}
+
 /// `/cards/fingerprint` handling the creation and retrieval of card fingerprint
 #[tracing::instrument(skip_all)]
 pub async fn get_or_insert_fingerprint(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
     OptionalFingerprintId(fingerprint_id): OptionalFingerprintId,
+    audit: AuditContext,
     Json(request): Json<types::FingerprintRequest>,
+) -> Result<Json<types::FingerprintResponse>, ContainerError<error::ApiError>> {
+    let result = get_or_insert_fingerprint_inner(tenant_app_state, fingerprint_id, request).await;
+    audit
+        .record(AuditEvent::new(AuditAction::Fingerprint).result(&result))
+        .await;
+
+    result
+}
+
+async fn get_or_insert_fingerprint_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    fingerprint_id: Option<Secret<String>>,
+    request: types::FingerprintRequest,
 ) -> Result<Json<types::FingerprintResponse>, ContainerError<error::ApiError>> {
     let fingerprint =
         fingerprint::get_or_insert(&tenant_app_state, request.data, request.key, fingerprint_id)
@@ -271,3 +387,52 @@ pub async fn get_or_insert_fingerprint(
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn store_request(merchant_id: &str, enc_card_data: &str) -> types::StoreCardRequest {
+        serde_json::from_value(serde_json::json!({
+            "merchant_id": merchant_id,
+            "merchant_customer_id": "quota_customer",
+            "enc_card_data": enc_card_data,
+            "ttl": null,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the development database"]
+    async fn add_card_is_rejected_once_the_merchant_quota_is_used() {
+        let merchant_id = format!("quota_{}", uuid::Uuid::now_v7().simple());
+        let state = TenantAppState::for_tests(|config| {
+            config.usage.quota.merchants.insert(merchant_id.clone(), 1);
+        })
+        .await;
+
+        add_card_inner(
+            Arc::clone(&state),
+            store_request(&merchant_id, "first_card"),
+        )
+        .await
+        .unwrap();
+        // storing the same card again adds no row
+        add_card_inner(
+            Arc::clone(&state),
+            store_request(&merchant_id, "first_card"),
+        )
+        .await
+        .unwrap();
+        let rejected = add_card_inner(state, store_request(&merchant_id, "second_card"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            rejected.get_inner(),
+            error::ApiError::QuotaExceeded(_)
+        ));
+    }
+}
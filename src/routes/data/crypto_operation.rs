@@ -1,4 +1,5 @@
-use hyperswitch_masking::ExposeInterface;
+use error_stack::ResultExt;
+use hyperswitch_masking::{ExposeInterface, PeekInterface};
 
 use crate::{
     app::TenantAppState,
@@ -7,9 +8,13 @@ use crate::{
     error::{self, ContainerError, ResultContainerExt},
     routes::{data::types, routes_v2::data::types as types_v2},
     storage::{
-        storage_v2::types::VaultNew,
+        storage_v2::{
+            VaultInterface,
+            types::{Vault, VaultNew, VaultPrecondition, VaultUpdate},
+        },
         types::{Locker, LockerNew},
     },
+    utils,
 };
 
 pub async fn encrypt_data_and_insert_into_db<'a>(
@@ -66,10 +71,15 @@ pub async fn encrypt_data_and_upsert_into_db_v2(
         .encrypt_data(tenant_app_state, data_to_be_encrypted.into())
         .await?;
 
+    let precondition = request
+        .if_version
+        .map(|version| VaultPrecondition { version });
     let vault_new = VaultNew::new(request, encrypted_data.into());
 
     let vault = match mode {
-        Some(types_v2::WriteMode::Upsert) => vault::upsert(tenant_app_state, vault_new).await?,
+        Some(types_v2::WriteMode::Upsert) => {
+            vault::upsert(tenant_app_state, vault_new, precondition).await?
+        }
         None | Some(types_v2::WriteMode::Insert) => {
             vault::get_or_insert(tenant_app_state, vault_new).await?
         }
@@ -77,3 +87,46 @@ pub async fn encrypt_data_and_upsert_into_db_v2(
 
     Ok(vault)
 }
+
+/// Decrypt the stored document, apply `patch` as an RFC 7396 merge patch, re-encrypt it and write
+/// it back, provided the row is still at the version `vault` was read at.
+pub async fn decrypt_patch_and_update_db_v2(
+    tenant_app_state: &TenantAppState,
+    crypto_operator: &dyn CryptoOperationsManager,
+    vault: Vault,
+    patch: &serde_json::Value,
+) -> Result<Vault, ContainerError<error::ApiError>> {
+    let encrypted_data = vault
+        .data
+        .get_encrypted_inner_value()
+        .ok_or(error::ApiError::UnknownError)
+        .attach_printable("Stored data was not in encrypted form")?;
+
+    let decrypted_data = crypto_operator
+        .decrypt_data(tenant_app_state, encrypted_data)
+        .await?;
+    let mut document: serde_json::Value = serde_json::from_slice(decrypted_data.peek().as_ref())
+        .change_error(error::ApiError::DecodingError)?;
+
+    utils::json_merge_patch(&mut document, patch);
+
+    let data_to_be_encrypted =
+        serde_json::to_vec(&document).change_error(error::ApiError::EncodingError)?;
+    let patched_data = crypto_operator
+        .encrypt_data(tenant_app_state, data_to_be_encrypted.into())
+        .await?;
+
+    let vault = tenant_app_state
+        .db
+        .update_vault_data_if(
+            vault.vault_id,
+            vault.entity_id,
+            VaultUpdate::data(patched_data.into()),
+            VaultPrecondition {
+                version: vault.version,
+            },
+        )
+        .await?;
+
+    Ok(vault)
+}
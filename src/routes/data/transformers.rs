@@ -31,6 +31,7 @@ impl From<storage::storage_v2::types::Vault>
         Self {
             entity_id: value.entity_id,
             vault_id: value.vault_id,
+            version: value.version,
         }
     }
 }
@@ -55,6 +56,7 @@ impl TryFrom<storage::types::Locker> for super::types::RetrieveCardResponse {
             payload: Some(super::types::RetrieveCardRespPayload {
                 card,
                 enc_card_data,
+                version: value.version,
             }),
         })
     }
@@ -101,6 +101,18 @@ impl<'de> serde::Deserialize<'de> for Ttl {
     }
 }
 
+impl Ttl {
+    /// For `#[serde(deserialize_with)]` on a `ttl` that must be sent. Serde reports such a field
+    /// as missing instead of deserializing `None` from its absence, so only an explicit `null`
+    /// clears the expiry.
+    pub fn deserialize_required<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        <Self as serde::Deserialize<'de>>::deserialize(deserializer)
+    }
+}
+
 impl std::ops::Deref for Ttl {
     type Target = Option<time::PrimitiveDateTime>;
     fn deref(&self) -> &Self::Target {
@@ -133,6 +145,7 @@ pub struct RetrieveCardResponse {
 pub struct RetrieveCardRespPayload {
     pub card: Option<Card>,
     pub enc_card_data: Option<String>,
+    pub version: i32,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -147,6 +160,25 @@ pub struct DeleteCardResponse {
     pub status: Status,
 }
 
+// Update Card TTL Data Structures
+
+/// `ttl` is in seconds from now; `null` clears the expiry, leaving it out is rejected. With
+/// `if_version` set, the update only applies to that version of the card.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct UpdateCardTtlRequest {
+    pub merchant_id: String,
+    pub merchant_customer_id: String,
+    pub card_reference: String,
+    #[serde(deserialize_with = "Ttl::deserialize_required")]
+    pub ttl: Ttl,
+    pub if_version: Option<i32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct UpdateCardTtlResponse {
+    pub status: Status,
+}
+
 #[derive(serde::Deserialize)]
 pub struct FingerprintRequest {
     pub data: Secret<String>,
@@ -189,6 +221,14 @@ impl Validation for StoreCardRequest {
     }
 }
 
+impl Validation for UpdateCardTtlRequest {
+    type Error = error::ApiError;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        self.ttl.validate()
+    }
+}
+
 pub trait SecretDataManager {
     fn get_encrypted_inner_value(&self) -> Option<Secret<Vec<u8>>>;
     fn set_decrypted_data(self, decrypted_data: StrongSecret<Vec<u8>>) -> Self;
@@ -204,3 +244,30 @@ impl SecretDataManager for Locker {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn update_card_ttl_request_requires_ttl() {
+        let request = |ttl: &str| {
+            serde_json::from_str::<UpdateCardTtlRequest>(&format!(
+                r#"{{"merchant_id":"m","merchant_customer_id":"c","card_reference":"r"{ttl}}}"#
+            ))
+        };
+
+        assert!(request("").is_err());
+        assert!(request(r#","ttl":null"#).unwrap().ttl.is_none());
+        assert!(request(r#","ttl":60"#).unwrap().ttl.is_some());
+
+        // Storing a card without a ttl still means it never expires.
+        let store = serde_json::from_str::<StoreCardRequest>(
+            r#"{"merchant_id":"m","merchant_customer_id":"c","enc_card_data":"data"}"#,
+        )
+        .unwrap();
+        assert!(store.ttl.is_none());
+    }
+}
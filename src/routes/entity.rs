@@ -1,7 +1,11 @@
+use std::sync::Arc;
+
 use axum::Json;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
     crypto::keymanager,
     custom_extractors::TenantStateResolver,
     error::{self, ContainerError},
@@ -44,7 +48,19 @@ pub struct CreateEntityResponse {
 /// existing record unchanged if it already exists.
 pub async fn create_entity(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
     Json(request): Json<CreateEntityRequest>,
+) -> Result<Json<CreateEntityResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::EntityCreate).merchant_id(&request.entity_id);
+    let result = create_entity_inner(tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn create_entity_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: CreateEntityRequest,
 ) -> Result<Json<CreateEntityResponse>, ContainerError<error::ApiError>> {
     request.validate()?;
 
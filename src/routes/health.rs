@@ -54,8 +54,37 @@ pub struct Diagnostics {
     database: DatabaseHealth,
     #[cfg(feature = "external_key_manager")]
     keymanager_status: HealthState,
+    #[cfg(feature = "external_key_manager")]
+    keymanager_circuit_breaker: crate::api_client::circuit_breaker::BreakerSnapshot,
     #[cfg(feature = "redis")]
     redis_status: HealthState,
+    #[cfg(feature = "kv")]
+    kv_drainer: DrainerHealth,
+    #[cfg(feature = "kv")]
+    kv_state: KvStateHealth,
+}
+
+/// KV state resolved from runtime config for this tenant.
+#[cfg(feature = "kv")]
+#[derive(Debug, serde::Serialize, Default)]
+pub struct KvStateHealth {
+    tenant: crate::storage::kv::KvState,
+    /// Merchant or entity ids resolving to a different state than the tenant.
+    merchant_overrides: std::collections::BTreeMap<String, crate::storage::kv::KvState>,
+}
+
+#[cfg(feature = "kv")]
+#[derive(Debug, serde::Serialize, Default)]
+pub struct DrainerHealth {
+    status: HealthState,
+    /// Undrained entries across all shards.
+    backlog: u64,
+    /// Entries read by a drainer but not yet acknowledged.
+    pending: u64,
+    oldest_entry_age_secs: Option<u64>,
+    /// The oldest undrained entry is close to `ttl_for_kv`; its record may expire from Redis
+    /// before reaching Postgres.
+    at_risk: bool,
 }
 
 #[derive(Debug, serde::Serialize, Default)]
@@ -157,12 +186,56 @@ pub async fn diagnostics(TenantStateResolver(state): TenantStateResolver) -> Jso
         },
     };
 
+    #[cfg(feature = "kv")]
+    let kv_drainer = match &state.redis {
+        None => DrainerHealth {
+            status: HealthState::Disabled,
+            ..Default::default()
+        },
+        Some(_) => match record_health_check(state.db.drainer_backlog(), "kv_drainer").await {
+            Ok(backlog) => {
+                let at_risk = backlog.nears_ttl();
+                if at_risk {
+                    crate::logger::warn!(
+                        tenant_id = %state.config.tenant_id,
+                        oldest_entry_age = ?backlog.oldest_entry_age(),
+                        "Drainer backlog is close to the KV TTL"
+                    );
+                }
+
+                DrainerHealth {
+                    status: HealthState::Working,
+                    backlog: backlog.length(),
+                    pending: backlog.pending(),
+                    oldest_entry_age_secs: backlog.oldest_entry_age().map(|age| age.as_secs()),
+                    at_risk,
+                }
+            }
+            Err(err) => {
+                crate::logger::error!(kv_drainer_err=?err);
+                DrainerHealth::default()
+            }
+        },
+    };
+
+    #[cfg(feature = "kv")]
+    let kv_state = KvStateHealth {
+        tenant: state.db.kv_settings(None).await,
+        merchant_overrides: state.db.kv_merchant_overrides().await,
+    };
+
     axum::Json(Diagnostics {
         key_custodian_locked: false,
         database: db_health,
         #[cfg(feature = "external_key_manager")]
         keymanager_status,
+        #[cfg(feature = "external_key_manager")]
+        keymanager_circuit_breaker: state.api_client.key_manager.breaker.snapshot(),
         #[cfg(feature = "redis")]
         redis_status,
+        #[cfg(feature = "kv")]
+        kv_drainer,
+        #[cfg(feature = "kv")]
+        kv_state,
     })
 }
@@ -6,6 +6,7 @@ use hyperswitch_masking::{ExposeInterface, Secret};
 
 use crate::{
     app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
     config::TenantConfig,
     crypto::encryption_manager::{encryption_interface::Encryption, managers::aes::GcmAes256},
     custom_extractors::TenantId,
@@ -65,14 +66,19 @@ pub fn serve() -> axum::Router<Arc<GlobalAppState>> {
 pub async fn key1(
     State(global_app_state): State<Arc<GlobalAppState>>,
     TenantId(tenant_id): TenantId,
+    audit: AuditContext,
     Json(payload): Json<CustodianReqPayload>,
 ) -> Json<CustodianRespPayload> {
     let mut key_state = global_app_state.tenants_key_state.write().await;
     key_state
         .entry(tenant_id.to_string())
         .and_modify(|key_state_data| key_state_data.key1 = Some(payload.key));
+    drop(key_state);
 
     logger::info!("Received key1");
+    audit
+        .record(AuditEvent::new(AuditAction::CustodianKey1))
+        .await;
     Json(CustodianRespPayload {
         message: "Received Key1".into(),
     })
@@ -83,14 +89,19 @@ pub async fn key1(
 pub async fn key2(
     State(global_app_state): State<Arc<GlobalAppState>>,
     TenantId(tenant_id): TenantId,
+    audit: AuditContext,
     Json(payload): Json<CustodianReqPayload>,
 ) -> Json<CustodianRespPayload> {
     let mut key_state = global_app_state.tenants_key_state.write().await;
     key_state
         .entry(tenant_id.to_string())
         .and_modify(|key_state_data| key_state_data.key2 = Some(payload.key));
+    drop(key_state);
 
     logger::info!("Received key2");
+    audit
+        .record(AuditEvent::new(AuditAction::CustodianKey2))
+        .await;
     Json(CustodianRespPayload {
         message: "Received Key2".into(),
     })
@@ -101,6 +112,19 @@ pub async fn key2(
 pub async fn decrypt(
     State(global_app_state): State<Arc<GlobalAppState>>,
     TenantId(tenant_id): TenantId,
+    audit: AuditContext,
+) -> Result<Json<CustodianRespPayload>, error::ContainerError<error::ApiError>> {
+    let result = decrypt_inner(global_app_state, tenant_id).await;
+    audit
+        .record(AuditEvent::new(AuditAction::CustodianUnlock).result(&result))
+        .await;
+
+    result
+}
+
+async fn decrypt_inner(
+    global_app_state: Arc<GlobalAppState>,
+    tenant_id: String,
 ) -> Result<Json<CustodianRespPayload>, error::ContainerError<error::ApiError>> {
     let mut key_state_map = global_app_state.tenants_key_state.write().await;
     let key_state_for_tenant = key_state_map
@@ -119,7 +143,7 @@ pub async fn decrypt(
             aes_decrypt_custodian_key(&mut tenant_config, inner_key1, inner_key2).await?;
 
             let tenant_app_state = TenantAppState::new(
-                &global_app_state.global_config,
+                &global_app_state.current_config(),
                 tenant_config,
                 global_app_state.api_client.clone(),
                 #[cfg(feature = "redis")]
@@ -1,10 +1,14 @@
+use std::sync::Arc;
+
 use axum::Json;
 use base64::Engine;
+use futures::StreamExt;
 use hyperswitch_masking::ExposeInterface;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
     crypto::{
         consts::BASE64_ENGINE,
         encryption_manager::managers::aes::GcmAes256,
@@ -14,30 +18,113 @@ use crate::{
         },
     },
     custom_extractors::TenantStateResolver,
-    error::{self, ContainerError, ResultContainerExt},
+    error::{self, ContainerError, NotFoundError},
     logger,
     storage::{
         EntityInterface, MerchantInterface, consts,
-        types::{Entity, Merchant},
+        types::{Entity, MigrationCandidate},
         utils,
     },
 };
 
+/// Upper bound on concurrent transfers, whatever the request asks for.
+const MAX_CONCURRENCY: usize = 32;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerchantKeyTransferRequest {
+    /// Merchants examined in this batch.
     pub limit: i64,
+    /// `next_cursor` of the previous batch; absent to start from the beginning.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Report what would be transferred without calling the key manager.
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+}
+
+fn default_concurrency() -> usize {
+    8
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferKeyResponse {
     pub total_transferred: usize,
+    pub already_present: usize,
+    pub failed: usize,
+    pub dry_run: bool,
+    /// Pass as `cursor` to continue; absent once every merchant has been examined.
+    pub next_cursor: Option<String>,
+    pub results: Vec<MerchantTransferResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerchantTransferResult {
+    pub merchant_id: String,
+    #[serde(flatten)]
+    pub outcome: TransferOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TransferOutcome {
+    Transferred,
+    AlreadyPresent,
+    /// Dry run only: the key would have been transferred.
+    Pending,
+    Failed {
+        reason: String,
+    },
 }
 
-#[tracing::instrument(skip_all)]
+#[tracing::instrument(skip_all, fields(dry_run = request.dry_run))]
 pub async fn transfer_keys(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
     Json(request): Json<MerchantKeyTransferRequest>,
 ) -> Result<Json<TransferKeyResponse>, ContainerError<error::ApiError>> {
+    let result = transfer_keys_inner(tenant_app_state, request).await;
+
+    // One event per key that actually moved (or failed to); dry runs and merchants already
+    // migrated touch no key.
+    match &result {
+        Ok(response) => {
+            for transfer in &response.results {
+                let event =
+                    AuditEvent::new(AuditAction::KeyTransfer).merchant_id(&transfer.merchant_id);
+                match &transfer.outcome {
+                    TransferOutcome::Transferred => audit.record(event).await,
+                    TransferOutcome::Failed { reason } => {
+                        audit.record(event.failed(reason.clone())).await
+                    }
+                    TransferOutcome::AlreadyPresent | TransferOutcome::Pending => {}
+                }
+            }
+        }
+        Err(_) => {
+            audit
+                .record(AuditEvent::new(AuditAction::KeyTransfer).result(&result))
+                .await
+        }
+    }
+
+    result
+}
+
+async fn transfer_keys_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: MerchantKeyTransferRequest,
+) -> Result<Json<TransferKeyResponse>, ContainerError<error::ApiError>> {
+    if request.limit <= 0 {
+        return Err(error::ApiError::ValidationError("limit must be greater than 0").into());
+    }
+    if request.concurrency == 0 || request.concurrency > MAX_CONCURRENCY {
+        return Err(
+            error::ApiError::ValidationError("concurrency must be between 1 and 32").into(),
+        );
+    }
+
     let master_encryption = GcmAes256::new(
         tenant_app_state
             .config
@@ -48,40 +135,116 @@ pub async fn transfer_keys(
     );
     let merchant_keys = tenant_app_state
         .db
-        .find_all_keys_excluding_entity_keys(&master_encryption, request.limit)
+        .find_keys_for_migration(&master_encryption, request.cursor.as_deref(), request.limit)
         .await?;
 
-    logger::debug!("Number of keys to be migrated: {}", merchant_keys.len());
+    let next_cursor = next_cursor(&merchant_keys, request.limit);
 
-    merchant_keys
-        .iter()
-        .for_each(|inner| logger::debug!("Migrating merchant: {:?}", inner.merchant_id.clone()));
+    logger::debug!("Number of merchants examined: {}", merchant_keys.len());
 
-    let no_of_keys_migrated =
-        send_request_to_key_service_for_merchant(&tenant_app_state, merchant_keys).await?;
+    let results = futures::stream::iter(merchant_keys)
+        .map(|candidate| transfer_merchant_key(&tenant_app_state, candidate, request.dry_run))
+        .buffer_unordered(request.concurrency)
+        .collect::<Vec<_>>()
+        .await;
 
-    Ok(Json(TransferKeyResponse {
-        total_transferred: no_of_keys_migrated,
-    }))
+    let count = |outcome: fn(&TransferOutcome) -> bool| {
+        results
+            .iter()
+            .filter(|result| outcome(&result.outcome))
+            .count()
+    };
+
+    let response = TransferKeyResponse {
+        total_transferred: count(|outcome| *outcome == TransferOutcome::Transferred),
+        already_present: count(|outcome| *outcome == TransferOutcome::AlreadyPresent),
+        failed: count(|outcome| matches!(outcome, TransferOutcome::Failed { .. })),
+        dry_run: request.dry_run,
+        next_cursor,
+        results,
+    };
+
+    logger::info!(
+        tenant_id = %tenant_app_state.config.tenant_id,
+        transferred = response.total_transferred,
+        already_present = response.already_present,
+        failed = response.failed,
+        dry_run = response.dry_run,
+        next_cursor = ?response.next_cursor,
+        "key migration batch finished"
+    );
+
+    Ok(Json(response))
 }
 
-pub async fn send_request_to_key_service_for_merchant(
+/// Cursor after the last merchant of a full page, whether or not its key could be read. A short
+/// page means the end of the merchant table has been reached.
+fn next_cursor(candidates: &[MigrationCandidate], limit: i64) -> Option<String> {
+    usize::try_from(limit)
+        .is_ok_and(|limit| candidates.len() >= limit)
+        .then(|| {
+            candidates
+                .last()
+                .map(|candidate| candidate.merchant_id.clone())
+        })
+        .flatten()
+}
+
+async fn transfer_merchant_key(
     state: &TenantAppState,
-    keys: Vec<Merchant>,
-) -> Result<usize, ContainerError<error::ApiError>> {
-    futures::future::try_join_all(keys.into_iter().map(|key| async move {
-        let key_encoded = BASE64_ENGINE.encode(key.enc_key.expose());
-        let req = DataKeyTransferRequest {
-            identifier: Identifier::Entity(utils::generate_nano_id(consts::ID_LENGTH)),
-            key: key_encoded,
-        };
-        migrate_key_to_key_manager(state, &key.merchant_id, req).await
-    }))
-    .await
-    .change_error(error::ApiError::KeyManagerError(
-        "Failed while migrating keys",
-    ))
-    .map(|v| v.len())
+    MigrationCandidate {
+        merchant_id,
+        migrated,
+        merchant,
+    }: MigrationCandidate,
+    dry_run: bool,
+) -> MerchantTransferResult {
+    let outcome = match (migrated, merchant, dry_run) {
+        (true, _, _) => TransferOutcome::AlreadyPresent,
+        (false, Err(reason), _) => {
+            logger::error!(%merchant_id, %reason, "Failed to read merchant key for migration");
+            TransferOutcome::Failed { reason }
+        }
+        (false, Ok(_), true) => TransferOutcome::Pending,
+        (false, Ok(merchant), false) => {
+            let req = DataKeyTransferRequest {
+                identifier: Identifier::Entity(utils::generate_nano_id(consts::ID_LENGTH)),
+                key: BASE64_ENGINE.encode(merchant.enc_key.expose()),
+            };
+
+            match migrate_key_to_key_manager(state, &merchant.merchant_id, req).await {
+                Ok(_) => TransferOutcome::Transferred,
+                // Another run created the entity between the page read and the insert.
+                Err(err) if already_migrated(state, &merchant.merchant_id).await => {
+                    logger::warn!(
+                        ?err,
+                        merchant_id = %merchant.merchant_id,
+                        "merchant migrated concurrently; the key just transferred is unused"
+                    );
+                    TransferOutcome::AlreadyPresent
+                }
+                Err(err) => TransferOutcome::Failed {
+                    reason: err.get_inner().to_string(),
+                },
+            }
+        }
+    };
+
+    MerchantTransferResult {
+        merchant_id,
+        outcome,
+    }
+}
+
+async fn already_migrated(state: &TenantAppState, merchant_id: &str) -> bool {
+    match state.db.find_by_entity_id(merchant_id).await {
+        Ok(_) => true,
+        Err(err) if err.is_not_found() => false,
+        Err(err) => {
+            logger::error!(?err, merchant_id, "Failed to look up entity");
+            false
+        }
+    }
 }
 
 pub async fn migrate_key_to_key_manager(
@@ -99,3 +262,25 @@ pub async fn migrate_key_to_key_manager(
             logger::error!(?err, "Failed to insert into entity table: {}", entity_id);
         })?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(merchant_id: &str) -> MigrationCandidate {
+        MigrationCandidate {
+            merchant_id: merchant_id.to_string(),
+            migrated: false,
+            merchant: Err("failed to decrypt the merchant key".to_string()),
+        }
+    }
+
+    #[test]
+    fn next_cursor_moves_past_merchants_whose_key_failed_to_decrypt() {
+        let page = [candidate("m_1"), candidate("m_2")];
+
+        assert_eq!(next_cursor(&page, 2).as_deref(), Some("m_2"));
+        assert_eq!(next_cursor(&page, 3), None);
+        assert_eq!(next_cursor(&[], 2), None);
+    }
+}
@@ -1,24 +1,52 @@
 pub mod types;
 
+use std::sync::Arc;
+
 use axum::{Json, extract::Query};
 use error_stack::ResultExt;
-use hyperswitch_masking::PeekInterface;
+use hyperswitch_masking::{PeekInterface, Secret};
 
 use crate::{
+    anomaly,
+    app::TenantAppState,
+    audit::{AuditAction, AuditContext, AuditEvent},
     crypto::keymanager,
-    custom_extractors::TenantStateResolver,
-    error::{self, ContainerError, ResultContainerExt},
+    custom_extractors::{OptionalCallerKey, OptionalCallerRole, TenantStateResolver},
+    error::{self, ContainerError, NotFoundError as _, ResultContainerExt},
     logger,
     observability::metrics,
     routes::data::{crypto_operation, types::Validation},
-    storage::storage_v2::VaultInterface,
-    utils,
+    storage::{
+        storage_v2::{
+            VaultInterface,
+            types::{VaultListFilter, VaultPrecondition, VaultUpdate},
+        },
+        types::StoredRowTable,
+    },
+    usage, utils,
 };
 
+/// Read-patch-write rounds attempted before a concurrent writer is reported as a conflict.
+const MERGE_PATCH_ATTEMPTS: u8 = 3;
+
 #[tracing::instrument(skip_all)]
 pub async fn delete_data(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
     Json(request): Json<types::DeleteDataRequest>,
+) -> Result<Json<types::DeleteDataResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::DataDelete)
+        .merchant_id(&request.entity_id)
+        .resource_id(Some(&request.vault_id));
+    let result = delete_data_inner(tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn delete_data_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::DeleteDataRequest,
 ) -> Result<Json<types::DeleteDataResponse>, ContainerError<error::ApiError>> {
     let _entity = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
         .find_by_entity_id(&tenant_app_state, request.entity_id.clone())
@@ -45,8 +73,50 @@ pub async fn delete_data(
 #[tracing::instrument(skip_all)]
 pub async fn retrieve_data(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
+    OptionalCallerRole(caller_role): OptionalCallerRole,
+    OptionalCallerKey(caller_key): OptionalCallerKey,
+    audit: AuditContext,
     Json(request): Json<types::RetrieveDataRequest>,
 ) -> Result<Json<types::RetrieveDataResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::DataRetrieve)
+        .merchant_id(&request.entity_id)
+        .resource_id(Some(&request.vault_id));
+    let result = match anomaly::screen_retrieval(
+        &tenant_app_state,
+        &audit,
+        caller_key.as_deref(),
+        &request.entity_id,
+        &request.entity_id,
+        &request.vault_id,
+    )
+    .await
+    {
+        Ok(()) => retrieve_data_inner(tenant_app_state, caller_role, request).await,
+        Err(error) => Err(error),
+    };
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn retrieve_data_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    caller_role: Option<String>,
+    request: types::RetrieveDataRequest,
+) -> Result<Json<types::RetrieveDataResponse>, ContainerError<error::ApiError>> {
+    request.validate()?;
+
+    if !tenant_app_state
+        .config
+        .retrieve
+        .allows(caller_role.as_deref(), request.fields.as_deref())
+    {
+        return Err(error::ApiError::Forbidden(
+            "caller role may not retrieve the full document or restricted fields",
+        )
+        .into());
+    }
+
     let crypto_manager = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
         .find_by_entity_id(&tenant_app_state, request.entity_id.clone())
         .await?;
@@ -63,22 +133,11 @@ pub async fn retrieve_data(
         .expires_at
         .map(|ttl| -> Result<(), error::ApiError> {
             if utils::date_time::now() > ttl {
-                crate::routes::record_expired_data_encountered(metrics::Resource::Vault);
-
-                tokio::spawn(async move {
-                    let result = tenant_app_state
+                crate::routes::delete_expired_data(metrics::Resource::Vault, async move {
+                    tenant_app_state
                         .db
                         .delete_from_vault(request.vault_id.into(), &request.entity_id)
-                        .await;
-
-                    crate::routes::record_ttl_deletion_result(
-                        metrics::Resource::Vault,
-                        if result.is_ok() {
-                            metrics::TtlDeletionOutcome::Deleted
-                        } else {
-                            metrics::TtlDeletionOutcome::Failed
-                        },
-                    );
+                        .await
                 });
 
                 Err(error::ApiError::NotFoundError)
@@ -92,26 +151,80 @@ pub async fn retrieve_data(
         .get_decrypted_inner_value()
         .ok_or(error::ApiError::UnknownError)
         .attach_printable("Failed to decrypt the stored data")?;
-    let data_value = serde_json::from_slice(decrypted_inner_data.peek().as_ref())
-        .change_error(error::ApiError::DecodingError)?;
+    let data_value: Secret<serde_json::Value> =
+        serde_json::from_slice(decrypted_inner_data.peek().as_ref())
+            .change_error(error::ApiError::DecodingError)?;
+    let data_value = match &request.fields {
+        Some(fields) => Secret::new(serde_json::Value::Object(utils::json_project(
+            data_value.peek(),
+            fields,
+        ))),
+        None => data_value,
+    };
 
     logger::info!(retrieve_data_response = "retrieve data was successful");
 
-    Ok(Json(types::RetrieveDataResponse { data: data_value }))
+    Ok(Json(types::RetrieveDataResponse {
+        data: data_value,
+        version: decrypted_data.version,
+    }))
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn add_data(
     TenantStateResolver(tenant_app_state): TenantStateResolver,
     Query(params): Query<types::StoreDataRequestQueryParams>,
+    audit: AuditContext,
     Json(request): Json<types::StoreDataRequest>,
+) -> Result<Json<types::StoreDataResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::DataAdd)
+        .merchant_id(&request.entity_id)
+        .resource_id(Some(&request.vault_id));
+    let result = add_data_inner(tenant_app_state, params, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn add_data_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    params: types::StoreDataRequestQueryParams,
+    request: types::StoreDataRequest,
 ) -> Result<Json<types::StoreDataResponse>, ContainerError<error::ApiError>> {
     request.validate()?;
 
+    if request.if_version.is_some() && !matches!(params.mode, Some(types::WriteMode::Upsert)) {
+        return Err(error::ApiError::ValidationError("if_version requires mode=upsert").into());
+    }
+
     let crypto_manager = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
         .find_or_create_entity(&tenant_app_state, request.entity_id.clone())
         .await?;
 
+    // Both modes write to an existing `vault_id` in place, so only a new row counts against the
+    // quota.
+    if tenant_app_state
+        .config
+        .quota
+        .limit_for(&request.entity_id)
+        .is_some()
+    {
+        let exists = match tenant_app_state
+            .db
+            .find_by_vault_id_entity_id(Secret::new(request.vault_id.clone()), &request.entity_id)
+            .await
+        {
+            Ok(_) => true,
+            Err(err) if err.is_not_found() => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        if !exists {
+            usage::ensure_capacity(&tenant_app_state, StoredRowTable::Vault, &request.entity_id)
+                .await?;
+        }
+    }
+
     let insert_data = crypto_operation::encrypt_data_and_upsert_into_db_v2(
         &tenant_app_state,
         crypto_manager,
@@ -125,3 +238,292 @@ pub async fn add_data(
 
     Ok(response)
 }
+
+#[tracing::instrument(skip_all)]
+pub async fn update_data_ttl(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
+    Json(request): Json<types::UpdateDataTtlRequest>,
+) -> Result<Json<types::UpdateDataTtlResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::DataTtlUpdate)
+        .merchant_id(&request.entity_id)
+        .resource_id(Some(&request.vault_id));
+    let result = update_data_ttl_inner(tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn update_data_ttl_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::UpdateDataTtlRequest,
+) -> Result<Json<types::UpdateDataTtlResponse>, ContainerError<error::ApiError>> {
+    request.validate()?;
+
+    let _entity = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
+        .find_by_entity_id(&tenant_app_state, request.entity_id.clone())
+        .await?;
+
+    let vault_data = tenant_app_state
+        .db
+        .find_by_vault_id_entity_id(request.vault_id.clone().into(), &request.entity_id)
+        .await?;
+
+    // An expired row is treated as already gone, so its ttl can no longer be moved.
+    if vault_data.is_expired_at(utils::date_time::now()) {
+        crate::routes::delete_expired_data(metrics::Resource::Vault, async move {
+            tenant_app_state
+                .db
+                .delete_from_vault(request.vault_id.into(), &request.entity_id)
+                .await
+        });
+
+        return Err(error::ApiError::NotFoundError.into());
+    }
+
+    let update = VaultUpdate::ttl(*request.ttl);
+    let updated = match request.if_version {
+        Some(version) => {
+            tenant_app_state
+                .db
+                .update_vault_data_if(
+                    request.vault_id.into(),
+                    request.entity_id,
+                    update,
+                    VaultPrecondition { version },
+                )
+                .await?
+        }
+        None => {
+            tenant_app_state
+                .db
+                .update_vault_data(request.vault_id.into(), request.entity_id, update)
+                .await?
+        }
+    };
+
+    let response = Json(types::UpdateDataTtlResponse::from(updated));
+    logger::info!(update_data_ttl_response=?response, "update data ttl was successful");
+
+    Ok(response)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn list_data(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
+    Json(request): Json<types::ListDataRequest>,
+) -> Result<Json<types::ListDataResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::DataList).merchant_id(&request.entity_id);
+    let result = list_data_inner(tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn list_data_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::ListDataRequest,
+) -> Result<Json<types::ListDataResponse>, ContainerError<error::ApiError>> {
+    request.validate()?;
+
+    let _entity = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
+        .find_by_entity_id(&tenant_app_state, request.entity_id.clone())
+        .await?;
+
+    let limit = request.limit.unwrap_or(types::DEFAULT_LIST_LIMIT);
+
+    // One extra row tells us whether another page follows.
+    let mut rows = tenant_app_state
+        .db
+        .list_vault_by_entity_id(VaultListFilter {
+            entity_id: request.entity_id.clone(),
+            after: request.cursor.map(|cursor| cursor.0),
+            limit: limit.saturating_add(1),
+        })
+        .await?;
+
+    let next_cursor = (rows.len() > limit)
+        .then(|| {
+            rows.truncate(limit);
+            rows.last().map(types::ListDataCursor::from)
+        })
+        .flatten();
+
+    let response = Json(types::ListDataResponse {
+        entity_id: request.entity_id,
+        data: rows.into_iter().map(types::ListDataItem::from).collect(),
+        next_cursor,
+    });
+    logger::info!(list_data_response=?response, "list data was successful");
+
+    Ok(response)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn update_data(
+    TenantStateResolver(tenant_app_state): TenantStateResolver,
+    audit: AuditContext,
+    Json(request): Json<types::UpdateDataRequest>,
+) -> Result<Json<types::UpdateDataResponse>, ContainerError<error::ApiError>> {
+    let event = AuditEvent::new(AuditAction::DataUpdate)
+        .merchant_id(&request.entity_id)
+        .resource_id(Some(&request.vault_id));
+    let result = update_data_inner(tenant_app_state, request).await;
+    audit.record(event.result(&result)).await;
+
+    result
+}
+
+async fn update_data_inner(
+    tenant_app_state: Arc<TenantAppState>,
+    request: types::UpdateDataRequest,
+) -> Result<Json<types::UpdateDataResponse>, ContainerError<error::ApiError>> {
+    let crypto_manager = keymanager::get_dek_manager(&tenant_app_state.config.external_key_manager)
+        .find_by_entity_id(&tenant_app_state, request.entity_id.clone())
+        .await?;
+
+    let mut attempt = 1;
+    let updated = loop {
+        let vault_data = tenant_app_state
+            .db
+            .find_by_vault_id_entity_id(request.vault_id.clone().into(), &request.entity_id)
+            .await?;
+
+        if vault_data.is_expired_at(utils::date_time::now()) {
+            crate::routes::delete_expired_data(metrics::Resource::Vault, async move {
+                tenant_app_state
+                    .db
+                    .delete_from_vault(request.vault_id.into(), &request.entity_id)
+                    .await
+            });
+
+            return Err(error::ApiError::NotFoundError.into());
+        }
+
+        // A caller-supplied version is final: once it no longer matches, retrying cannot help.
+        if request
+            .if_version
+            .is_some_and(|version| version != vault_data.version)
+        {
+            return Err(error::ApiError::Conflict("vault").into());
+        }
+
+        match crypto_operation::decrypt_patch_and_update_db_v2(
+            &tenant_app_state,
+            crypto_manager.as_ref(),
+            vault_data,
+            request.patch.peek(),
+        )
+        .await
+        {
+            // Someone else wrote the row after we read it; start over from the fresh copy.
+            Err(err)
+                if attempt < MERGE_PATCH_ATTEMPTS
+                    && matches!(err.get_inner(), error::ApiError::Conflict(_)) =>
+            {
+                logger::info!(attempt, "vault data changed during update, retrying");
+                attempt += 1;
+            }
+            result => break result?,
+        }
+    };
+
+    let response = Json(types::UpdateDataResponse::from(updated));
+    logger::info!(update_data_response=?response, "update data was successful");
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn store_request(entity_id: &str, vault_id: &str) -> types::StoreDataRequest {
+        serde_json::from_value(serde_json::json!({
+            "entity_id": entity_id,
+            "vault_id": vault_id,
+            "data": { "card_number": "4242424242424242" },
+            "ttl": null,
+        }))
+        .unwrap()
+    }
+
+    fn upsert() -> types::StoreDataRequestQueryParams {
+        types::StoreDataRequestQueryParams {
+            mode: Some(types::WriteMode::Upsert),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the development database"]
+    async fn add_data_is_rejected_once_the_entity_quota_is_used() {
+        let entity_id = format!("quota_{}", uuid::Uuid::now_v7().simple());
+        let state = TenantAppState::for_tests(|config| {
+            config.usage.quota.merchants.insert(entity_id.clone(), 1);
+        })
+        .await;
+
+        add_data_inner(
+            Arc::clone(&state),
+            upsert(),
+            store_request(&entity_id, "vault_1"),
+        )
+        .await
+        .unwrap();
+        // writing an existing vault_id in place adds no row
+        add_data_inner(
+            Arc::clone(&state),
+            upsert(),
+            store_request(&entity_id, "vault_1"),
+        )
+        .await
+        .unwrap();
+        let rejected = add_data_inner(state, upsert(), store_request(&entity_id, "vault_2"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            rejected.get_inner(),
+            error::ApiError::QuotaExceeded(_)
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the development database"]
+    async fn conditional_update_tells_a_stale_version_from_a_missing_row() {
+        let entity_id = format!("if_version_{}", uuid::Uuid::now_v7().simple());
+        let state = TenantAppState::for_tests(|_| {}).await;
+        let stored = add_data_inner(
+            Arc::clone(&state),
+            upsert(),
+            store_request(&entity_id, "vault_1"),
+        )
+        .await
+        .unwrap();
+        let update_if = |version| {
+            state.db.update_vault_data_if(
+                "vault_1".to_string().into(),
+                entity_id.clone(),
+                VaultUpdate::ttl(None),
+                VaultPrecondition { version },
+            )
+        };
+
+        let stale = update_if(stored.version + 1).await.unwrap_err();
+        assert!(matches!(stale.get_inner(), error::VaultDBError::Conflict));
+
+        state
+            .db
+            .delete_from_vault("vault_1".to_string().into(), &entity_id)
+            .await
+            .unwrap();
+        let missing = update_if(stored.version).await.unwrap_err();
+        assert!(matches!(
+            missing.get_inner(),
+            error::VaultDBError::NotFoundError
+        ));
+    }
+}
@@ -1,11 +1,20 @@
-use hyperswitch_masking::{Secret, StrongSecret};
+use base64::Engine;
+use hyperswitch_masking::{PeekInterface, Secret, StrongSecret};
 
 use crate::{
     error,
     routes::data::types::{SecretDataManager, Ttl, Validation},
-    storage::{storage_v2::types::Vault, types::Encryptable},
+    storage::{
+        storage_v2::types::{Vault, VaultCursor},
+        types::Encryptable,
+    },
 };
 
+/// Page size used by `/vault/list` when the request does not set one.
+pub const DEFAULT_LIST_LIMIT: usize = 20;
+/// Upper bound on the page size accepted by `/vault/list`.
+pub const MAX_LIST_LIMIT: usize = 100;
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct DeleteDataRequest {
     pub entity_id: String,
@@ -18,23 +27,31 @@ pub struct DeleteDataResponse {
     pub vault_id: Secret<String>,
 }
 
+/// `fields` narrows the response to the values at those JSON pointers (RFC 6901).
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct RetrieveDataRequest {
     pub entity_id: String,
     pub vault_id: String,
+    pub fields: Option<Vec<String>>,
 }
 
+/// `data` is the whole document, or, when `fields` were requested, an object mapping each
+/// pointer that resolved to its value.
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct RetrieveDataResponse {
     pub data: Secret<serde_json::Value>,
+    pub version: i32,
 }
 
+/// `if_version` makes an upsert conditional: it only overwrites a row still at that version, and
+/// requires `mode=upsert`.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct StoreDataRequest {
     pub entity_id: String,
     pub vault_id: String,
     pub data: Secret<serde_json::Value>,
     pub ttl: Ttl,
+    pub if_version: Option<i32>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -52,6 +69,154 @@ pub struct StoreDataRequestQueryParams {
 pub struct StoreDataResponse {
     pub entity_id: String,
     pub vault_id: Secret<String>,
+    pub version: i32,
+}
+
+/// `ttl` is in seconds from now; `null` clears the expiry, leaving it out is rejected.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct UpdateDataTtlRequest {
+    pub entity_id: String,
+    pub vault_id: String,
+    #[serde(deserialize_with = "Ttl::deserialize_required")]
+    pub ttl: Ttl,
+    pub if_version: Option<i32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct UpdateDataTtlResponse {
+    pub entity_id: String,
+    pub vault_id: Secret<String>,
+    #[serde(with = "crate::utils::primitive_datetime_serde::iso8601::option")]
+    pub expires_at: Option<time::PrimitiveDateTime>,
+    pub version: i32,
+}
+
+impl From<Vault> for UpdateDataTtlResponse {
+    fn from(value: Vault) -> Self {
+        Self {
+            entity_id: value.entity_id,
+            vault_id: value.vault_id,
+            expires_at: value.expires_at,
+            version: value.version,
+        }
+    }
+}
+
+/// `patch` is an RFC 7396 merge patch applied to the stored document. With `if_version` set, the
+/// patch is only applied to that version of the row.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct UpdateDataRequest {
+    pub entity_id: String,
+    pub vault_id: String,
+    pub patch: Secret<serde_json::Value>,
+    pub if_version: Option<i32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct UpdateDataResponse {
+    pub entity_id: String,
+    pub vault_id: Secret<String>,
+    pub version: i32,
+}
+
+impl From<Vault> for UpdateDataResponse {
+    fn from(value: Vault) -> Self {
+        Self {
+            entity_id: value.entity_id,
+            vault_id: value.vault_id,
+            version: value.version,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ListDataRequest {
+    pub entity_id: String,
+    pub limit: Option<usize>,
+    pub cursor: Option<ListDataCursor>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ListDataResponse {
+    pub entity_id: String,
+    pub data: Vec<ListDataItem>,
+    pub next_cursor: Option<ListDataCursor>,
+}
+
+/// Listing entry; carries metadata only, the stored payload is never decrypted.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ListDataItem {
+    pub vault_id: Secret<String>,
+    #[serde(with = "crate::utils::primitive_datetime_serde::iso8601")]
+    pub created_at: time::PrimitiveDateTime,
+    #[serde(with = "crate::utils::primitive_datetime_serde::iso8601::option")]
+    pub expires_at: Option<time::PrimitiveDateTime>,
+}
+
+impl From<Vault> for ListDataItem {
+    fn from(value: Vault) -> Self {
+        Self {
+            vault_id: value.vault_id,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+        }
+    }
+}
+
+/// Opaque pagination cursor: base64url-encoded JSON of the last returned row's
+/// `(created_at, vault_id)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListDataCursor(pub VaultCursor);
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ListDataCursorPayload {
+    #[serde(with = "crate::utils::primitive_datetime_serde::iso8601")]
+    created_at: time::PrimitiveDateTime,
+    vault_id: String,
+}
+
+impl From<&Vault> for ListDataCursor {
+    fn from(value: &Vault) -> Self {
+        Self(VaultCursor {
+            created_at: value.created_at,
+            vault_id: value.vault_id.peek().clone(),
+        })
+    }
+}
+
+impl serde::Serialize for ListDataCursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let payload = ListDataCursorPayload {
+            created_at: self.0.created_at,
+            vault_id: self.0.vault_id.clone(),
+        };
+        let json = serde_json::to_vec(&payload).map_err(serde::ser::Error::custom)?;
+
+        let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+        serde::Serialize::serialize(&encoded, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ListDataCursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let encoded = <String as serde::Deserialize<'de>>::deserialize(deserializer)?;
+        let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(serde::de::Error::custom)?;
+        let payload: ListDataCursorPayload =
+            serde_json::from_slice(&json).map_err(serde::de::Error::custom)?;
+
+        Ok(Self(VaultCursor {
+            created_at: payload.created_at,
+            vault_id: payload.vault_id,
+        }))
+    }
 }
 
 impl SecretDataManager for Vault {
@@ -72,3 +237,101 @@ impl Validation for StoreDataRequest {
         self.ttl.validate()
     }
 }
+
+impl Validation for RetrieveDataRequest {
+    type Error = error::ApiError;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        match &self.fields {
+            Some(fields) if fields.is_empty() => Err(error::ApiError::ValidationError(
+                "fields must not be empty when present",
+            )),
+            Some(fields) if fields.iter().any(|field| !field.starts_with('/')) => Err(
+                error::ApiError::ValidationError("fields must be JSON pointers starting with '/'"),
+            ),
+            Some(_) | None => Ok(()),
+        }
+    }
+}
+
+impl Validation for UpdateDataTtlRequest {
+    type Error = error::ApiError;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        self.ttl.validate()
+    }
+}
+
+impl Validation for ListDataRequest {
+    type Error = error::ApiError;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        match self.limit {
+            Some(limit) if limit == 0 || limit > MAX_LIST_LIMIT => Err(
+                error::ApiError::ValidationError("limit must be between 1 and 100"),
+            ),
+            Some(_) | None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_data_cursor_round_trips_through_json() {
+        let created_at = time::Date::from_calendar_date(2024, time::Month::July, 2)
+            .ok()
+            .and_then(|date| {
+                time::Time::from_hms_micro(7, 14, 58, 123_456)
+                    .ok()
+                    .map(|time| time::PrimitiveDateTime::new(date, time))
+            });
+        assert!(created_at.is_some(), "failed to build timestamp");
+        let Some(created_at) = created_at else { return };
+
+        let cursor = ListDataCursor(VaultCursor {
+            created_at,
+            vault_id: "vault_1".to_string(),
+        });
+
+        let serialized = serde_json::to_string(&cursor);
+        assert!(serialized.is_ok(), "failed to serialize cursor");
+        let Ok(serialized) = serialized else { return };
+
+        let deserialized = serde_json::from_str::<ListDataCursor>(&serialized);
+        assert!(deserialized.is_ok(), "failed to deserialize cursor");
+        let Ok(deserialized) = deserialized else {
+            return;
+        };
+
+        assert_eq!(deserialized, cursor);
+    }
+
+    #[test]
+    fn list_data_cursor_rejects_garbage() {
+        assert!(serde_json::from_str::<ListDataCursor>("\"not a cursor\"").is_err());
+    }
+
+    #[test]
+    fn update_data_ttl_request_requires_ttl() {
+        let request = |ttl: &str| {
+            serde_json::from_str::<UpdateDataTtlRequest>(&format!(
+                r#"{{"entity_id":"e","vault_id":"v"{ttl}}}"#
+            ))
+        };
+
+        assert!(request("").is_err(), "a missing ttl must be rejected");
+
+        let cleared = request(r#","ttl":null"#);
+        assert!(cleared.is_ok(), "failed to deserialize a null ttl");
+        let Ok(cleared) = cleared else { return };
+        assert!(cleared.ttl.is_none());
+
+        let set = request(r#","ttl":60"#);
+        assert!(set.is_ok(), "failed to deserialize a ttl");
+        let Ok(set) = set else { return };
+        assert!(set.ttl.is_some());
+    }
+}
@@ -21,7 +21,8 @@ enum RuntimeConfigState {
     Enabled {
         endpoint_url: String,
         endpoint_path: String,
-        api_key: Secret<String>,
+        /// Replaced when the secrets refresher resolves a rotated key.
+        api_key: std::sync::RwLock<Secret<String>>,
         client: reqwest::Client,
         refresh_interval: Duration,
         /// Last-known-good config body; `None` until the first successful fetch.
@@ -83,7 +84,7 @@ impl RuntimeConfigManager {
                     state: RuntimeConfigState::Enabled {
                         endpoint_url: endpoint.base_url.clone(),
                         endpoint_path: endpoint.path.clone(),
-                        api_key: endpoint.api_key.clone(),
+                        api_key: std::sync::RwLock::new(endpoint.api_key.clone()),
                         client,
                         refresh_interval: Duration::from_secs(*refresh_interval_seconds),
                         cache: RwLock::new(None),
@@ -118,6 +119,18 @@ impl RuntimeConfigManager {
         }
     }
 
+    /// Use `api_key` for the following fetches. A no-op when disabled.
+    pub fn set_api_key(&self, api_key: Secret<String>) {
+        if let RuntimeConfigState::Enabled {
+            api_key: current, ..
+        } = &self.state
+        {
+            *current
+                .write()
+                .unwrap_or_else(std::sync::PoisonError::into_inner) = api_key;
+        }
+    }
+
     /// Spawn a background task that refreshes the config on an interval. Returns `None` when disabled.
     pub fn spawn_prefetch_task(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
         let refresh_interval = match &self.state {
@@ -162,7 +175,11 @@ impl RuntimeConfigManager {
             return;
         };
 
-        match Self::fetch_config(endpoint_url, endpoint_path, api_key, client).await {
+        let api_key = api_key
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        match Self::fetch_config(endpoint_url, endpoint_path, &api_key, client).await {
             Ok(body) => {
                 crate::logger::info!(config = %body, "Runtime config fetched");
                 *cache.write().await = Some(body);
@@ -0,0 +1,354 @@
+//! Pick up rotated secrets without a restart.
+//!
+//! [`GlobalConfig::fetch_raw_secrets`] resolves every secret once at startup. With
+//! `secrets_refresh.enabled`, a background task resolves the
+//! [`RotatableSecrets`](crate::config::RotatableSecrets) again every
+//! `interval_secs` (and on `SIGHUP`) and applies whatever changed: database pools are rebuilt
+//! with the new credentials, the HTTP client is rebuilt with the new mTLS identity and the
+//! runtime config API key is replaced. Requests already running keep the connections and
+//! clients they hold. Master keys and JWE keys are not refreshed.
+
+use std::{sync::Arc, time::Duration};
+
+use error_stack::ResultExt;
+use tracing::Instrument;
+
+use crate::{config::GlobalConfig, error, logger, observability::metrics, tenant::GlobalAppState};
+
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct SecretsRefreshConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+}
+
+impl Default for SecretsRefreshConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 3600,
+        }
+    }
+}
+
+impl SecretsRefreshConfig {
+    pub fn validate(&self) -> Result<(), error::ConfigurationError> {
+        if self.enabled && self.interval_secs == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "secrets_refresh.interval_secs must be greater than 0".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Spawn the refresher. `raw_config` is the configuration as read, before
+/// [`GlobalConfig::fetch_raw_secrets`]. Returns `None` when disabled.
+pub fn spawn(
+    global_app_state: &Arc<GlobalAppState>,
+    raw_config: GlobalConfig,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let config = &global_app_state.global_config.secrets_refresh;
+    if !config.enabled {
+        return None;
+    }
+
+    let interval = Duration::from_secs(config.interval_secs);
+    let state = Arc::clone(global_app_state);
+    logger::info!(
+        interval_secs = interval.as_secs(),
+        "Spawning secrets refresh task"
+    );
+
+    Some(tokio::spawn(
+        async move {
+            // the secrets were resolved moments ago, so skip the immediate first tick
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            #[cfg(unix)]
+            let mut hangup = match tokio::signal::unix::signal(
+                tokio::signal::unix::SignalKind::hangup(),
+            ) {
+                Ok(hangup) => Some(hangup),
+                Err(err) => {
+                    logger::warn!(
+                        ?err,
+                        "Failed to install SIGHUP handler; refreshing secrets on the interval only"
+                    );
+                    None
+                }
+            };
+
+            loop {
+                #[cfg(unix)]
+                let trigger = tokio::select! {
+                    _ = ticker.tick() => "interval",
+                    Some(()) = async {
+                        match hangup.as_mut() {
+                            Some(hangup) => hangup.recv().await,
+                            None => std::future::pending().await,
+                        }
+                    } => "signal",
+                };
+                #[cfg(not(unix))]
+                let trigger = {
+                    ticker.tick().await;
+                    "interval"
+                };
+
+                let outcome = match refresh(&state, &raw_config).await {
+                    Ok(true) => "rotated",
+                    Ok(false) => "unchanged",
+                    Err(err) => {
+                        logger::error!(
+                            ?err,
+                            trigger,
+                            "Failed to refresh secrets, keeping the current ones"
+                        );
+                        "failure"
+                    }
+                };
+                metrics::SECRETS_REFRESH_COUNT.add(
+                    1,
+                    crate::metric_attributes!(("trigger", trigger), ("outcome", outcome)),
+                );
+            }
+        }
+        .in_current_span(),
+    ))
+}
+
+/// Resolve the rotatable secrets and apply the ones that changed. Returns whether anything
+/// changed.
+async fn refresh(
+    state: &GlobalAppState,
+    raw_config: &GlobalConfig,
+) -> error_stack::Result<bool, error::ConfigurationError> {
+    let fresh = raw_config.fetch_rotatable_secrets().await?;
+    let current = state.rotatable_secrets();
+    if fresh == current {
+        return Ok(false);
+    }
+
+    let mut config = state.global_config.clone();
+    fresh.clone().apply(&mut config);
+
+    if fresh.database_password != current.database_password
+        || fresh.read_replica_password != current.read_replica_password
+    {
+        let tenants = state
+            .tenants_app_state
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for tenant in tenants {
+            tenant
+                .db
+                .reconnect(&config.database, config.read_replica.as_ref())
+                .await
+                .change_context(error::ConfigurationError::DatabaseError)
+                .attach_printable_lazy(|| {
+                    format!("Failed to reconnect tenant {}", tenant.config.tenant_id)
+                })?;
+        }
+
+        if let Some(auditor) = &state.auditor {
+            for storage in auditor.storages() {
+                storage
+                    .reconnect(&config.database, None)
+                    .await
+                    .change_context(error::ConfigurationError::DatabaseError)
+                    .attach_printable("Failed to reconnect the audit sink")?;
+            }
+        }
+        logger::info!("Database pools rebuilt with rotated credentials");
+    }
+
+    #[cfg(feature = "external_key_manager")]
+    if fresh.mtls != current.mtls {
+        state.api_client.reload(&config).map_err(|err| {
+            err.error
+                .change_context(error::ConfigurationError::InvalidConfigurationValueError(
+                    "rotated mTLS identity".into(),
+                ))
+        })?;
+        logger::info!("HTTP client rebuilt with the rotated mTLS identity");
+    }
+
+    if fresh.runtime_config_api_key != current.runtime_config_api_key {
+        if let Some(api_key) = fresh.runtime_config_api_key.clone() {
+            state.runtime_config_manager.set_api_key(api_key);
+            logger::info!("Runtime config API key replaced");
+        }
+    }
+
+    state.set_rotatable_secrets(fresh);
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use axum::{extract::ConnectInfo, http::HeaderMap, routing::get};
+    use hyperswitch_masking::Secret;
+
+    use crate::{
+        api_client::{ApiClient, Headers, Method},
+        config::{self, GlobalConfig, RotatableSecrets, RuntimeConfig, RuntimeConfigEndpoint},
+        runtime_config::RuntimeConfigManager,
+    };
+
+    fn development_config() -> GlobalConfig {
+        GlobalConfig::new_with_config_path(Some(
+            config::workspace_path().join("config/development.toml"),
+        ))
+        .unwrap()
+    }
+
+    async fn serve(router: axum::Router) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+        addr
+    }
+
+    #[test]
+    fn applied_secrets_are_the_ones_read_back() {
+        let mut config = development_config();
+        let mut fresh = RotatableSecrets::of(&config);
+        fresh.database_password = Secret::new("rotated_pass".to_owned());
+        assert_ne!(fresh, RotatableSecrets::of(&config));
+
+        fresh.clone().apply(&mut config);
+
+        assert_eq!(RotatableSecrets::of(&config), fresh);
+    }
+
+    #[tokio::test]
+    async fn runtime_config_fetches_after_rotation_send_the_new_key() {
+        let seen = Arc::new(Mutex::new(Vec::<String>::new()));
+        let router = axum::Router::new().route(
+            "/config",
+            get({
+                let seen = Arc::clone(&seen);
+                move |headers: HeaderMap| async move {
+                    let key = headers
+                        .get("x-internal-api-key")
+                        .and_then(|key| key.to_str().ok())
+                        .unwrap_or_default()
+                        .to_owned();
+                    seen.lock().unwrap().push(key.clone());
+                    match key.as_str() {
+                        "rotated_key" => Ok(axum::Json(serde_json::json!({
+                            "key": "locker",
+                            "value": r#"{"use_replica": "false"}"#,
+                        }))),
+                        _ => Err(axum::http::StatusCode::UNAUTHORIZED),
+                    }
+                }
+            }),
+        );
+        let addr = serve(router).await;
+
+        let manager = Arc::new(
+            RuntimeConfigManager::new(
+                &RuntimeConfig::Enabled {
+                    endpoint: RuntimeConfigEndpoint {
+                        base_url: format!("http://{addr}"),
+                        api_key: Secret::new("old_key".to_owned()),
+                        headers: Default::default(),
+                        path: "config".to_owned(),
+                    },
+                    refresh_interval_seconds: 1,
+                },
+                90,
+                1,
+            )
+            .unwrap(),
+        );
+        let prefetch = manager.spawn_prefetch_task().unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while seen.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the first fetch never arrived");
+        assert!(manager.get::<serde_json::Value>().await.is_none());
+
+        manager.set_api_key(Secret::new("rotated_key".to_owned()));
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while manager.get::<serde_json::Value>().await.is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("no fetch used the rotated key");
+        prefetch.abort();
+
+        assert_eq!(seen.lock().unwrap().first().unwrap(), "old_key");
+        assert_eq!(seen.lock().unwrap().last().unwrap(), "rotated_key");
+    }
+
+    #[tokio::test]
+    async fn reloaded_api_client_serves_the_following_requests() {
+        let peers = Arc::new(Mutex::new(Vec::<SocketAddr>::new()));
+        let router = axum::Router::new().route(
+            "/",
+            get({
+                let peers = Arc::clone(&peers);
+                move |ConnectInfo(peer): ConnectInfo<SocketAddr>| async move {
+                    peers.lock().unwrap().push(peer);
+                }
+            }),
+        );
+        let addr = serve(router).await;
+
+        let config = development_config();
+        let client = ApiClient::new(&config).unwrap();
+        // handed out before the rotation, like the client a tenant state holds
+        let held = client.clone();
+        let send = || {
+            held.send_request(
+                "test",
+                format!("http://{addr}/"),
+                Headers::new(),
+                Method::Get,
+                (),
+            )
+        };
+
+        send().await.unwrap();
+        send().await.unwrap();
+        client.reload(&config).unwrap();
+        send().await.unwrap();
+
+        let peers = peers.lock().unwrap();
+        assert_eq!(peers.len(), 3);
+        // pooled connection of the old client
+        assert_eq!(peers[0], peers[1]);
+        // the rebuilt client has no connections of its own yet
+        assert_ne!(peers[1], peers[2]);
+    }
+}
@@ -12,7 +12,7 @@ pub mod storage_v2;
 pub mod types;
 pub mod utils;
 
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
 
 use diesel_async::{
     AsyncPgConnection,
@@ -23,6 +23,7 @@ use diesel_async::{
 };
 use error_stack::ResultExt;
 use hyperswitch_masking::{PeekInterface, Secret};
+use tracing::Instrument;
 
 pub use self::scheme::StorageScheme;
 #[cfg(feature = "kv")]
@@ -40,17 +41,36 @@ pub struct RuntimeConfigValues {
     #[cfg(feature = "kv")]
     #[serde(default)]
     enable_kv: kv::KvState,
+    /// Per-tenant KV rollout, keyed by tenant id.
+    #[cfg(feature = "kv")]
+    #[serde(default)]
+    kv_overrides: rustc_hash::FxHashMap<String, kv::KvOverride>,
     #[serde(default)]
     use_replica: bool,
 }
 
+impl RuntimeConfigValues {
+    /// Merchant override, then tenant override, then the global `enable_kv`.
+    #[cfg(feature = "kv")]
+    fn kv_state_for(&self, tenant_id: &str, merchant_id: Option<&str>) -> kv::KvState {
+        self.kv_overrides
+            .get(tenant_id)
+            .map_or(self.enable_kv, |tenant_override| {
+                tenant_override.resolve(merchant_id, self.enable_kv)
+            })
+    }
+}
+
 /// Storage State that is to be passed though the application
 #[derive(Clone)]
 pub struct Storage {
-    primary_pg_pool: Arc<Pool<AsyncPgConnection>>,
-    replica_pg_pool: Option<Arc<Pool<AsyncPgConnection>>>,
+    primary_pg_pool: PgPool,
+    replica_pg_pool: Option<PgPool>,
+    schema: String,
     runtime_config_manager: Arc<crate::runtime_config::RuntimeConfigManager>,
     #[cfg(feature = "kv")]
+    tenant_id: String,
+    #[cfg(feature = "kv")]
     redis: Option<redis_store::RedisStore>,
     #[cfg(feature = "kv")]
     kv_config: crate::config::KvConfig,
@@ -58,6 +78,28 @@ pub struct Storage {
 
 type DeadPoolConnType = Object<AsyncPgConnection>;
 
+/// A connection pool that can be swapped for one with new credentials. Clones of a [`Storage`]
+/// share it; connections already checked out stay with the pool they came from until returned.
+#[derive(Clone)]
+struct PgPool(Arc<RwLock<Arc<Pool<AsyncPgConnection>>>>);
+
+impl PgPool {
+    fn new(pool: Pool<AsyncPgConnection>) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(pool))))
+    }
+
+    fn current(&self) -> Arc<Pool<AsyncPgConnection>> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn replace(&self, pool: Pool<AsyncPgConnection>) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(pool);
+    }
+}
+
 #[derive(Debug, Clone, Copy, strum::IntoStaticStr)]
 #[strum(serialize_all = "snake_case")]
 enum DbPool {
@@ -73,6 +115,7 @@ enum DbOperation {
     Delete,
     FindOne,
     Filter,
+    Count,
 }
 
 crate::impl_metric_value_from!(DbPool, DbOperation);
@@ -131,16 +174,17 @@ impl Storage {
         replica_config: Option<&Database>,
         schema: &str,
         runtime_config_manager: Arc<crate::runtime_config::RuntimeConfigManager>,
+        #[cfg(feature = "kv")] tenant_id: &str,
         #[cfg(feature = "kv")] redis: Option<redis_store::RedisStore>,
         #[cfg(feature = "kv")] kv_config: &crate::config::KvConfig,
     ) -> error_stack::Result<Self, error::StorageError> {
-        let pg_pool = Arc::new(Self::create_database_connection_pool(
+        let pg_pool = PgPool::new(Self::create_database_connection_pool(
             primary_config,
             schema,
         )?);
 
         let replica_pool = match replica_config {
-            Some(config) => Some(Arc::new(Self::create_database_connection_pool(
+            Some(config) => Some(PgPool::new(Self::create_database_connection_pool(
                 config, schema,
             )?)),
             None => None,
@@ -149,18 +193,58 @@ impl Storage {
         Ok(Self {
             primary_pg_pool: pg_pool,
             replica_pg_pool: replica_pool,
+            schema: schema.to_owned(),
             runtime_config_manager,
             #[cfg(feature = "kv")]
+            tenant_id: tenant_id.to_owned(),
+            #[cfg(feature = "kv")]
             redis,
             #[cfg(feature = "kv")]
             kv_config: kv_config.clone(),
         })
     }
 
+    /// Replace the connection pools with ones built from `primary_config` and `replica_config`,
+    /// e.g. after the database password was rotated. A connection is opened on each new pool
+    /// first, so credentials that do not work leave the current pools in place.
+    pub async fn reconnect(
+        &self,
+        primary_config: &Database,
+        replica_config: Option<&Database>,
+    ) -> error_stack::Result<(), error::StorageError> {
+        let primary = Self::create_database_connection_pool(primary_config, &self.schema)?;
+        primary
+            .get()
+            .await
+            .change_context(error::StorageError::PoolClientFailure)
+            .attach_printable("Failed to connect with the new primary credentials")?;
+
+        let replica = match (&self.replica_pg_pool, replica_config) {
+            (Some(_), Some(config)) => {
+                let replica = Self::create_database_connection_pool(config, &self.schema)?;
+                replica
+                    .get()
+                    .await
+                    .change_context(error::StorageError::PoolClientFailure)
+                    .attach_printable("Failed to connect with the new replica credentials")?;
+                Some(replica)
+            }
+            _ => None,
+        };
+
+        self.primary_pg_pool.replace(primary);
+        if let (Some(replica_pg_pool), Some(replica)) = (&self.replica_pg_pool, replica) {
+            replica_pg_pool.replace(replica);
+        }
+
+        Ok(())
+    }
+
     /// Get connection from database pool for accessing data
     pub async fn get_conn(&self) -> Result<DbConnection, ContainerError<error::StorageError>> {
         let pool = DbPool::Primary;
-        let conn = record_db_connection_acquire_duration(self.primary_pg_pool.get(), pool)
+        let pg_pool = self.primary_pg_pool.current();
+        let conn = record_db_connection_acquire_duration(pg_pool.get(), pool)
             .await
             .change_context(error::StorageError::PoolClientFailure)?;
 
@@ -175,6 +259,7 @@ impl Storage {
         match self.replica_pg_pool.as_ref() {
             Some(pg_pool) => {
                 let pool = DbPool::Replica;
+                let pg_pool = pg_pool.current();
                 let conn = record_db_connection_acquire_duration(pg_pool.get(), pool)
                     .await
                     .change_context(error::StorageError::PoolClientFailure)?;
@@ -217,16 +302,52 @@ impl Storage {
         }
     }
 
-    /// Resolve `KvState` from runtime config; fail-closed to `Disabled` when absent.
+    /// Resolve `KvState` from runtime config for this tenant and, when the record has one, its
+    /// merchant (v1) or entity (v2) id; fail-closed to `Disabled` when absent.
     #[cfg(feature = "kv")]
-    pub(crate) async fn kv_settings(&self) -> kv::KvState {
+    pub(crate) async fn kv_settings(&self, merchant_id: Option<&str>) -> kv::KvState {
         self.runtime_config_manager
             .get::<RuntimeConfigValues>()
             .await
-            .map(|runtime_config_values| runtime_config_values.enable_kv)
+            .map(|runtime_config_values| {
+                runtime_config_values.kv_state_for(&self.tenant_id, merchant_id)
+            })
             .unwrap_or(kv::KvState::Disabled)
     }
 
+    /// Merchants of this tenant whose KV state differs from the tenant-wide one.
+    #[cfg(feature = "kv")]
+    pub(crate) async fn kv_merchant_overrides(
+        &self,
+    ) -> std::collections::BTreeMap<String, kv::KvState> {
+        let Some(runtime_config_values) = self
+            .runtime_config_manager
+            .get::<RuntimeConfigValues>()
+            .await
+        else {
+            return std::collections::BTreeMap::new();
+        };
+
+        let tenant_state = runtime_config_values.kv_state_for(&self.tenant_id, None);
+        runtime_config_values
+            .kv_overrides
+            .get(&self.tenant_id)
+            .map(|tenant_override| {
+                tenant_override
+                    .merchants
+                    .iter()
+                    .filter(|(_, state)| **state != tenant_state)
+                    .map(|(merchant_id, state)| (merchant_id.clone(), *state))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "kv")]
+    pub(crate) fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+
     pub fn collect_db_pool_state(&self, tenant_id: &str) {
         use crate::observability::metrics::{
             DATABASE_POOL_AVAILABLE, DATABASE_POOL_SIZE, DATABASE_POOL_WAITING,
@@ -248,7 +369,7 @@ impl Storage {
             }
         }
 
-        let primary = self.primary_pg_pool.status();
+        let primary = self.primary_pg_pool.current().status();
         let pool = DbPool::Primary;
         let attrs = crate::metric_attributes!(("pool", pool), ("tenant_id", tenant_id.to_owned()));
 
@@ -263,7 +384,7 @@ impl Storage {
         }
 
         if let Some(replica) = &self.replica_pg_pool {
-            let replica = replica.status();
+            let replica = replica.current().status();
             let pool = DbPool::Replica;
             let attrs =
                 crate::metric_attributes!(("pool", pool), ("tenant_id", tenant_id.to_owned()));
@@ -374,14 +495,19 @@ pub(crate) trait MerchantInterface {
         key: &Self::Algorithm,
     ) -> Result<types::Merchant, ContainerError<Self::Error>>;
 
+    /// Page of up to `limit` merchants ordered by `merchant_id`, starting after `cursor`, each
+    /// with whether an entity (a key held by the external key manager) already exists for it.
+    /// A merchant whose key fails to decrypt is returned with the failure, so the page stays
+    /// whole and the cursor can move past it.
     // This function is under the `dead_code` lint to pass Clippy checks because it utilizes types
     // from both internal and external key_manager.
     #[allow(dead_code)]
-    async fn find_all_keys_excluding_entity_keys(
+    async fn find_keys_for_migration(
         &self,
         key: &Self::Algorithm,
+        cursor: Option<&str>,
         limit: i64,
-    ) -> Result<Vec<types::Merchant>, ContainerError<Self::Error>>;
+    ) -> Result<Vec<types::MigrationCandidate>, ContainerError<Self::Error>>;
 }
 
 ///
@@ -414,6 +540,27 @@ pub(crate) trait LockerInterface {
         customer_id: &str,
     ) -> Result<Option<types::Locker>, ContainerError<Self::Error>>;
 
+    /// Overwrite mutable fields for an existing locker row; a missing row surfaces as
+    /// `Error::is_not_found()`.
+    async fn update_locker(
+        &self,
+        locker_id: Secret<String>,
+        merchant_id: &str,
+        customer_id: &str,
+        update: types::LockerUpdate,
+    ) -> Result<types::Locker, ContainerError<Self::Error>>;
+
+    /// Like `update_locker`, but only applied while the row still satisfies `precondition`;
+    /// otherwise fails with `VaultDBError::Conflict`.
+    async fn update_locker_if(
+        &self,
+        locker_id: Secret<String>,
+        merchant_id: &str,
+        customer_id: &str,
+        update: types::LockerUpdate,
+        precondition: types::LockerPrecondition,
+    ) -> Result<types::Locker, ContainerError<Self::Error>>;
+
     /// Delete a locker row by primary key.
     async fn delete_locker(
         &self,
@@ -499,6 +646,109 @@ pub(crate) trait ReverseLookupInterface {
     ) -> Result<usize, ContainerError<Self::Error>>;
 }
 
+///
+/// AuditInterface:
+///
+/// Interface for the audit_log table. The table rejects updates and deletes, so it can only
+/// be appended to and read.
+pub(crate) trait AuditInterface {
+    type Error;
+
+    /// Append one sealed audit event.
+    async fn insert_audit_event(
+        &self,
+        new: types::AuditLogNew,
+    ) -> Result<(), ContainerError<Self::Error>>;
+
+    /// Fetch the events matching `filter`, oldest first.
+    async fn find_audit_events(
+        &self,
+        filter: &types::AuditLogFilter,
+    ) -> Result<Vec<types::AuditLogEvent>, ContainerError<Self::Error>>;
+
+    /// Fetch the event with the highest sequence in `chain_id`, if the chain has any.
+    async fn find_last_audit_event(
+        &self,
+        chain_id: &str,
+    ) -> Result<Option<types::AuditLogEvent>, ContainerError<Self::Error>>;
+}
+
+///
+/// IdempotencyInterface:
+///
+/// Interface for the idempotency_record table. Only records that have not expired are live;
+/// expired ones are replaced on the next reservation and purged in the background.
+pub(crate) trait IdempotencyInterface {
+    type Error;
+
+    /// Reserve the key of `new`, replacing it if expired. Returns `false` when the key is live.
+    async fn insert_idempotency_record(
+        &self,
+        new: types::IdempotencyRecordNew,
+    ) -> Result<bool, ContainerError<Self::Error>>;
+
+    /// Fetch the live record of `id`.
+    async fn find_idempotency_record(
+        &self,
+        id: types::IdempotencyKeyId<'_>,
+    ) -> Result<types::IdempotencyRecord, ContainerError<Self::Error>>;
+
+    /// Store the encrypted response of the request that reserved `id` with `request_hash`.
+    async fn update_idempotency_record_response(
+        &self,
+        id: types::IdempotencyKeyId<'_>,
+        request_hash: &str,
+        response: Vec<u8>,
+        expires_at: time::PrimitiveDateTime,
+    ) -> Result<(), ContainerError<Self::Error>>;
+
+    /// Drop the reservation of `id` made with `request_hash`, if no response was stored yet.
+    async fn delete_idempotency_reservation(
+        &self,
+        id: types::IdempotencyKeyId<'_>,
+        request_hash: &str,
+    ) -> Result<(), ContainerError<Self::Error>>;
+
+    /// Delete records expired before `now`, returning how many were deleted.
+    async fn delete_expired_idempotency_records(
+        &self,
+        now: time::PrimitiveDateTime,
+    ) -> Result<usize, ContainerError<Self::Error>>;
+}
+
+///
+/// UsageInterface:
+///
+/// Row counts over the tables holding stored data. Rows still pending in the KV store are not
+/// counted until drained.
+pub(crate) trait UsageInterface {
+    type Error;
+
+    /// Rows in `table`, only those owned by `owner_id` when given. Read from the replica when
+    /// one is configured.
+    async fn count_stored_rows(
+        &self,
+        table: types::StoredRowTable,
+        owner_id: Option<&str>,
+    ) -> Result<u64, ContainerError<Self::Error>>;
+
+    /// Rows owned by `owner_id` in `table`, read from the primary so rows written moments ago
+    /// are counted.
+    async fn count_owner_stored_rows(
+        &self,
+        table: types::StoredRowTable,
+        owner_id: &str,
+    ) -> Result<u64, ContainerError<Self::Error>>;
+
+    /// Up to `limit` owners with the most rows in `table`, largest first. Read from the replica
+    /// when one is configured.
+    async fn find_top_owners_by_stored_rows(
+        &self,
+        table: types::StoredRowTable,
+        limit: i64,
+    ) -> Result<Vec<(String, u64)>, ContainerError<Self::Error>>;
+}
+
 ///
 /// EntityInterface:
 ///
@@ -513,12 +763,27 @@ pub(crate) trait EntityInterface {
         entity_id: &str,
     ) -> Result<types::Entity, ContainerError<Self::Error>>;
 
+    /// Like `find_by_entity_id`, but always read from the primary, for callers that must see
+    /// writes made moments ago.
+    async fn find_by_entity_id_on_primary(
+        &self,
+        entity_id: &str,
+    ) -> Result<types::Entity, ContainerError<Self::Error>>;
+
     /// Insert a new merchant in the database by encrypting the dek with `master_key`
     async fn insert_entity(
         &self,
         entity_id: &str,
         identifier: &str,
     ) -> Result<types::Entity, ContainerError<Self::Error>>;
+
+    /// Record the wrapped DEK for `entity_id` unless one is already stored. The returned entity
+    /// carries whichever wrapped DEK won, so concurrent writers converge on the same key.
+    async fn set_wrapped_dek(
+        &self,
+        entity_id: &str,
+        wrapped_dek: Secret<Vec<u8>>,
+    ) -> Result<types::Entity, ContainerError<Self::Error>>;
 }
 
 async fn record_db_connection_acquire_duration<Fut, T, E>(future: Fut, pool: DbPool) -> Result<T, E>
@@ -538,6 +803,15 @@ where
     result
 }
 
+fn db_query_span(table_name: &str, operation: DbOperation, pool: DbPool) -> tracing::Span {
+    tracing::info_span!(
+        "db_query",
+        table = table_name,
+        operation = <&'static str>::from(operation),
+        pool = <&'static str>::from(pool),
+    )
+}
+
 #[track_caller]
 fn log_db_query<T, Q>(query: &Q, operation: DbOperation, pool: DbPool)
 where
@@ -582,7 +856,9 @@ where
     );
 
     let start = std::time::Instant::now();
-    let result = future.await;
+    let result = future
+        .instrument(db_query_span(table_name, operation, pool))
+        .await;
     let duration = start.elapsed();
     let outcome = if result.is_ok() { "success" } else { "error" };
 
@@ -599,7 +875,6 @@ where
     result
 }
 
-#[cfg_attr(feature = "kv", expect(dead_code))]
 async fn record_db_query_optional<T, Fut, R, E>(
     future: Fut,
     operation: DbOperation,
@@ -624,7 +899,9 @@ where
     );
 
     let start = std::time::Instant::now();
-    let result = future.await;
+    let result = future
+        .instrument(db_query_span(table_name, operation, pool))
+        .await;
     let duration = start.elapsed();
     let outcome = match &result {
         Ok(Some(_)) => "success",
@@ -669,7 +946,9 @@ where
     );
 
     let start = std::time::Instant::now();
-    let result = future.await;
+    let result = future
+        .instrument(db_query_span(table_name, operation, pool))
+        .await;
     let duration = start.elapsed();
     let outcome = match &result {
         Ok(rows) if *rows == 0 => "zero_rows",
@@ -689,3 +968,39 @@ where
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn database(password: &str, pool_size: usize) -> Database {
+        Database {
+            username: "db_user".to_owned(),
+            password: Secret::new(password.to_owned()),
+            host: "localhost".to_owned(),
+            port: 5432,
+            dbname: "locker".to_owned(),
+            pool_size: Some(pool_size),
+        }
+    }
+
+    #[test]
+    fn replaced_pool_is_handed_out_by_every_clone() {
+        let pool = PgPool::new(
+            Storage::create_database_connection_pool(&database("db_pass", 3), "public").unwrap(),
+        );
+        let shared = pool.clone();
+        let in_use = shared.current();
+
+        pool.replace(
+            Storage::create_database_connection_pool(&database("rotated_pass", 7), "public")
+                .unwrap(),
+        );
+
+        assert_eq!(shared.current().status().max_size, 7);
+        // whoever already holds the old pool keeps it until done
+        assert_eq!(in_use.status().max_size, 3);
+    }
+}
@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
 
 use super::types;
 
@@ -6,10 +9,10 @@ pub(super) type Cache<T, U> =
     moka::future::Cache<<T as super::Cacheable<U>>::Key, Arc<<T as super::Cacheable<U>>::Value>>;
 
 #[cfg(feature = "external_key_manager")]
-pub trait CacheableWithEntity<T>: super::Cacheable<types::Entity> {}
+pub trait CacheableWithEntity<T>: super::Cacheable<types::Entity, Key = String> {}
 
 #[cfg(feature = "external_key_manager")]
-impl<T: super::Cacheable<types::Entity>> CacheableWithEntity<T> for T {}
+impl<T: super::Cacheable<types::Entity, Key = String>> CacheableWithEntity<T> for T {}
 
 #[cfg(not(feature = "external_key_manager"))]
 pub trait CacheableWithEntity<T> {}
@@ -17,6 +20,82 @@ pub trait CacheableWithEntity<T> {}
 #[cfg(not(feature = "external_key_manager"))]
 impl<T> CacheableWithEntity<T> for T {}
 
+/// Caches that can be addressed by name, e.g. from the admin endpoints.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumString,
+    strum::IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CacheName {
+    Merchant,
+    HashTable,
+    Fingerprint,
+    Entity,
+    /// Ready key managers, see [`crate::crypto::keymanager::dek_cache`].
+    Dek,
+}
+
+crate::impl_metric_value_from!(CacheName);
+
+impl CacheName {
+    pub const ALL: [Self; 5] = [
+        Self::Merchant,
+        Self::HashTable,
+        Self::Fingerprint,
+        Self::Entity,
+        Self::Dek,
+    ];
+}
+
+/// Lookup outcomes of one cache since startup; moka does not track them itself.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheStats {
+    pub(crate) fn record_lookup(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn report(
+        &self,
+        name: &'static str,
+        entry_count: u64,
+        weighted_size: u64,
+    ) -> CacheReport {
+        CacheReport {
+            name,
+            entry_count,
+            weighted_size,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Point-in-time view of one cache.
+#[derive(Debug, serde::Serialize)]
+pub struct CacheReport {
+    pub name: &'static str,
+    pub entry_count: u64,
+    /// Sum of entry weights; equals `entry_count` as entries are unweighted.
+    pub weighted_size: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
 #[derive(Clone)]
 pub struct Caching<T>
 where
@@ -27,10 +106,18 @@ where
 {
     inner: T,
     merchant_cache: Cache<T, types::Merchant>,
+    merchant_stats: Arc<CacheStats>,
     hash_table_cache: Cache<T, types::HashTable>,
+    hash_table_stats: Arc<CacheStats>,
     fingerprint_cache: Cache<T, types::Fingerprint>,
+    fingerprint_stats: Arc<CacheStats>,
     #[cfg(feature = "external_key_manager")]
     entity_cache: Cache<T, types::Entity>,
+    #[cfg(feature = "external_key_manager")]
+    entity_stats: Arc<CacheStats>,
+    /// `None` without Redis: every pod then only sees its own writes until entries idle out.
+    #[cfg(feature = "redis")]
+    publisher: Option<invalidation::Publisher>,
 }
 
 impl<T> std::ops::Deref for Caching<T>
@@ -64,7 +151,7 @@ where
     cache.build()
 }
 
-fn cache_eviction_listener(
+pub(crate) fn cache_eviction_listener(
     cache_name: &'static str,
     removal_cause: moka::notification::RemovalCause,
 ) {
@@ -91,6 +178,7 @@ where
     T: super::Cacheable<U>,
 {
     fn get_cache(&self) -> &Cache<T, U>;
+    fn get_stats(&self) -> &CacheStats;
     fn cache_name(&self) -> &'static str;
 }
 
@@ -105,6 +193,10 @@ where
         &self.merchant_cache
     }
 
+    fn get_stats(&self) -> &CacheStats {
+        &self.merchant_stats
+    }
+
     fn cache_name(&self) -> &'static str {
         types::Merchant::CACHE_NAME
     }
@@ -121,6 +213,10 @@ where
         &self.hash_table_cache
     }
 
+    fn get_stats(&self) -> &CacheStats {
+        &self.hash_table_stats
+    }
+
     fn cache_name(&self) -> &'static str {
         types::HashTable::CACHE_NAME
     }
@@ -137,6 +233,10 @@ where
         &self.fingerprint_cache
     }
 
+    fn get_stats(&self) -> &CacheStats {
+        &self.fingerprint_stats
+    }
+
     fn cache_name(&self) -> &'static str {
         types::Fingerprint::CACHE_NAME
     }
@@ -154,6 +254,10 @@ where
         &self.entity_cache
     }
 
+    fn get_stats(&self) -> &CacheStats {
+        &self.entity_stats
+    }
+
     fn cache_name(&self) -> &'static str {
         types::Entity::CACHE_NAME
     }
@@ -187,6 +291,48 @@ where
         collect!(types::Entity);
     }
 
+    /// Entry counts and lookup outcomes of every cache.
+    pub async fn cache_reports(&self) -> Vec<CacheReport> {
+        let mut reports = Vec::new();
+
+        macro_rules! report {
+            ($type:ty) => {{
+                let cache = <Self as GetCache<T, $type>>::get_cache(self);
+                let stats = <Self as GetCache<T, $type>>::get_stats(self);
+
+                cache.run_pending_tasks().await;
+                reports.push(stats.report(
+                    <Self as GetCache<T, $type>>::cache_name(self),
+                    cache.entry_count(),
+                    cache.weighted_size(),
+                ));
+            }};
+        }
+
+        report!(types::Merchant);
+        report!(types::HashTable);
+        report!(types::Fingerprint);
+        #[cfg(feature = "external_key_manager")]
+        report!(types::Entity);
+
+        reports
+    }
+
+    /// Drop every entry of `cache_name` on this pod.
+    pub fn flush(&self, cache_name: CacheName) {
+        match cache_name {
+            CacheName::Merchant => self.merchant_cache.invalidate_all(),
+            CacheName::HashTable => self.hash_table_cache.invalidate_all(),
+            CacheName::Fingerprint => self.fingerprint_cache.invalidate_all(),
+            #[cfg(feature = "external_key_manager")]
+            CacheName::Entity => self.entity_cache.invalidate_all(),
+            #[cfg(not(feature = "external_key_manager"))]
+            CacheName::Entity => {}
+            // Held by the tenant state, not this layer.
+            CacheName::Dek => {}
+        }
+    }
+
     #[inline(always)]
     pub async fn lookup<U>(
         &self,
@@ -203,6 +349,8 @@ where
             },
         );
 
+        <Self as GetCache<T, U>>::get_stats(self).record_lookup(value.is_some());
+
         crate::observability::metrics::CACHE_LOOKUP_COUNT.add(
             1,
             crate::metric_attributes!(
@@ -231,7 +379,10 @@ where
         );
     }
 
-    pub fn implement_cache(config: &'_ crate::config::Cache) -> impl Fn(T) -> Self + '_ {
+    pub fn implement_cache(
+        config: &'_ crate::config::Cache,
+        #[cfg(feature = "redis")] publisher: Option<invalidation::Publisher>,
+    ) -> impl Fn(T) -> Self + '_ {
         move |inner: T| {
             let merchant_cache =
                 new_cache::<T, types::Merchant>(config, types::Merchant::CACHE_NAME);
@@ -245,10 +396,17 @@ where
             Self {
                 inner,
                 merchant_cache,
+                merchant_stats: Arc::default(),
                 hash_table_cache,
+                hash_table_stats: Arc::default(),
                 fingerprint_cache,
+                fingerprint_stats: Arc::default(),
                 #[cfg(feature = "external_key_manager")]
                 entity_cache,
+                #[cfg(feature = "external_key_manager")]
+                entity_stats: Arc::default(),
+                #[cfg(feature = "redis")]
+                publisher: publisher.clone(),
             }
         }
     }
@@ -258,4 +416,5 @@ where
 pub mod entity;
 pub mod fingerprint;
 pub mod hash_table;
+pub mod invalidation;
 pub mod merchant;
@@ -1,3 +1,5 @@
+use hyperswitch_masking::Secret;
+
 use crate::{
     error::{ContainerError, NotFoundError},
     storage::{self, types},
@@ -32,6 +34,16 @@ where
         }
     }
 
+    async fn find_by_entity_id_on_primary(
+        &self,
+        entity_id: &str,
+    ) -> Result<types::Entity, ContainerError<Self::Error>> {
+        let output = self.inner.find_by_entity_id_on_primary(entity_id).await?;
+        self.cache_data::<types::Entity>(output.entity_id.to_string(), output.clone())
+            .await;
+        Ok(output)
+    }
+
     async fn insert_entity(
         &self,
         entity_id: &str,
@@ -40,6 +52,24 @@ where
         let output = self.inner.insert_entity(entity_id, identifier).await?;
         self.cache_data::<types::Entity>(entity_id.to_string(), output.clone())
             .await;
+        self.publish_write(super::CacheName::Entity, entity_id.to_string())
+            .await;
+        Ok(output)
+    }
+
+    async fn set_wrapped_dek(
+        &self,
+        entity_id: &str,
+        wrapped_dek: Secret<Vec<u8>>,
+    ) -> Result<types::Entity, ContainerError<Self::Error>> {
+        let output = self.inner.set_wrapped_dek(entity_id, wrapped_dek).await?;
+        self.cache_data::<types::Entity>(entity_id.to_string(), output.clone())
+            .await;
+        // Other pods may hold the entity, or a key manager built from it, without the new DEK.
+        self.publish_write(super::CacheName::Entity, entity_id.to_string())
+            .await;
+        self.publish_write(super::CacheName::Dek, entity_id.to_string())
+            .await;
         Ok(output)
     }
 }
@@ -51,6 +51,11 @@ where
             .await?;
         self.cache_data::<types::Fingerprint>(output.fingerprint_hash.clone(), output.clone())
             .await;
+        self.publish_write(
+            super::CacheName::Fingerprint,
+            super::invalidation::CacheInvalidation::hash_key(&output.fingerprint_hash),
+        )
+        .await;
         Ok(output)
     }
 }
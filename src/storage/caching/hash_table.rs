@@ -45,8 +45,10 @@ where
         data_hash: Secret<Vec<u8>>,
     ) -> Result<types::HashTable, ContainerError<Self::Error>> {
         let output = self.inner.insert_hash(data_hash.clone()).await?;
+        let key = super::invalidation::CacheInvalidation::hash_key(&data_hash);
         self.cache_data::<types::HashTable>(data_hash, output.clone())
             .await;
+        self.publish_write(super::CacheName::HashTable, key).await;
         Ok(output)
     }
 }
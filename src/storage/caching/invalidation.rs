@@ -0,0 +1,207 @@
+//! Cross-instance eviction for the local caches.
+//!
+//! Every pod keeps its own moka caches, so a pod that writes cached data, or is told to drop it
+//! through the admin endpoints, publishes a [`CacheInvalidation`] on the tenant's channel; every
+//! other pod evicts the key (or flushes the cache) when the message arrives.
+
+use std::sync::LazyLock;
+
+use hyperswitch_masking::Secret;
+
+use super::{CacheName, CacheableWithEntity, Caching, GetCache};
+use crate::storage::{self, types};
+
+/// Identifies this process in the invalidations it publishes, so it skips them when they come back.
+static ORIGIN: LazyLock<String> = LazyLock::new(|| uuid::Uuid::now_v7().to_string());
+
+/// Pub/sub channel carrying the invalidations of `tenant_id`.
+#[cfg(feature = "redis")]
+pub fn channel(tenant_id: &str) -> String {
+    format!(
+        "{}:{tenant_id}",
+        storage::consts::CACHE_INVALIDATION_CHANNEL
+    )
+}
+
+/// Message published on the [`channel`] of its tenant.
+///
+/// `key` is the cache key as text: the id itself for `merchant`, `entity` and `dek`, the hex-encoded
+/// hash for `hash_table` and `fingerprint`. Without a key the whole cache is flushed.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CacheInvalidation {
+    pub tenant: String,
+    pub cache_name: CacheName,
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Process that published the message.
+    #[serde(default)]
+    pub origin: Option<String>,
+}
+
+impl CacheInvalidation {
+    pub fn new(tenant: String, cache_name: CacheName, key: Option<String>) -> Self {
+        Self {
+            tenant,
+            cache_name,
+            key,
+            origin: Some(ORIGIN.clone()),
+        }
+    }
+
+    /// Whether this process published the message, and so already applied it.
+    pub fn is_own(&self) -> bool {
+        self.origin.as_deref() == Some(ORIGIN.as_str())
+    }
+
+    /// Text form of a hash-keyed cache entry.
+    pub fn hash_key(hash: &Secret<Vec<u8>>) -> String {
+        use hyperswitch_masking::PeekInterface;
+
+        hex::encode(hash.peek())
+    }
+}
+
+/// Publishes the invalidations of one tenant.
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct Publisher {
+    tenant: String,
+    channel: String,
+    redis: storage::redis::RedisStore,
+}
+
+#[cfg(feature = "redis")]
+impl Publisher {
+    pub fn new(tenant: String, redis: storage::redis::RedisStore) -> Self {
+        Self {
+            channel: channel(&tenant),
+            tenant,
+            redis,
+        }
+    }
+
+    /// Best effort: if publishing fails, the other pods catch up once the entry idles out.
+    pub async fn publish(&self, invalidation: &CacheInvalidation) {
+        let cache_name = invalidation.cache_name;
+        let outcome = match serde_json::to_string(invalidation) {
+            Ok(message) => self
+                .redis
+                .publish(&self.channel, message)
+                .await
+                .inspect_err(|err| {
+                    crate::logger::warn!(?err, %cache_name, "Failed to publish cache invalidation");
+                })
+                .map_or("failure", |()| "success"),
+            Err(err) => {
+                crate::logger::warn!(?err, %cache_name, "Failed to serialize cache invalidation");
+                "failure"
+            }
+        };
+
+        crate::observability::metrics::CACHE_INVALIDATION_COUNT.add(
+            1,
+            crate::metric_attributes!(
+                ("cache", cache_name),
+                ("direction", "published"),
+                ("outcome", outcome),
+            ),
+        );
+    }
+}
+
+impl<T> Caching<T>
+where
+    T: storage::Cacheable<types::Merchant>
+        + storage::Cacheable<types::HashTable>
+        + storage::Cacheable<types::Fingerprint>
+        + CacheableWithEntity<T>,
+{
+    /// Send `invalidation` to the other pods of the tenant.
+    pub async fn publish(&self, invalidation: &CacheInvalidation) {
+        #[cfg(feature = "redis")]
+        if let Some(publisher) = &self.publisher {
+            publisher.publish(invalidation).await;
+        }
+
+        #[cfg(not(feature = "redis"))]
+        let _ = invalidation;
+    }
+
+    /// Have the other pods of the tenant drop `key` from `cache_name` after a write on this pod.
+    pub(super) async fn publish_write(&self, cache_name: CacheName, key: String) {
+        #[cfg(feature = "redis")]
+        if let Some(publisher) = &self.publisher {
+            publisher
+                .publish(&CacheInvalidation::new(
+                    publisher.tenant.clone(),
+                    cache_name,
+                    Some(key),
+                ))
+                .await;
+        }
+
+        #[cfg(not(feature = "redis"))]
+        let _ = (cache_name, key);
+    }
+}
+
+impl<T> Caching<T>
+where
+    T: storage::Cacheable<types::Merchant, Key = String>
+        + storage::Cacheable<types::HashTable, Key = Secret<Vec<u8>>>
+        + storage::Cacheable<types::Fingerprint, Key = Secret<Vec<u8>>>
+        + CacheableWithEntity<T>,
+{
+    /// Apply an invalidation to this pod's caches.
+    pub async fn apply(&self, invalidation: &CacheInvalidation) {
+        match &invalidation.key {
+            Some(key) => self.evict(invalidation.cache_name, key).await,
+            None => self.flush(invalidation.cache_name),
+        }
+    }
+
+    /// Drop `key` from this pod's `cache_name` cache. A key that cannot belong to the cache,
+    /// i.e. malformed hex for a hash-keyed one, is logged and ignored.
+    pub async fn evict(&self, cache_name: CacheName, key: &str) {
+        match cache_name {
+            CacheName::Merchant => {
+                GetCache::<T, types::Merchant>::get_cache(self)
+                    .invalidate(key)
+                    .await;
+            }
+            CacheName::HashTable => {
+                if let Some(hash) = decode_hash_key(cache_name, key) {
+                    GetCache::<T, types::HashTable>::get_cache(self)
+                        .invalidate(&hash)
+                        .await;
+                }
+            }
+            CacheName::Fingerprint => {
+                if let Some(hash) = decode_hash_key(cache_name, key) {
+                    GetCache::<T, types::Fingerprint>::get_cache(self)
+                        .invalidate(&hash)
+                        .await;
+                }
+            }
+            #[cfg(feature = "external_key_manager")]
+            CacheName::Entity => {
+                GetCache::<T, types::Entity>::get_cache(self)
+                    .invalidate(key)
+                    .await;
+            }
+            #[cfg(not(feature = "external_key_manager"))]
+            CacheName::Entity => {}
+            // Held by the tenant state, not this layer.
+            CacheName::Dek => {}
+        }
+    }
+}
+
+fn decode_hash_key(cache_name: CacheName, key: &str) -> Option<Secret<Vec<u8>>> {
+    hex::decode(key)
+        .inspect_err(|_| {
+            crate::logger::warn!(%cache_name, "Ignoring cache invalidation with a non-hex key");
+        })
+        .ok()
+        .map(Secret::new)
+}
@@ -43,18 +43,19 @@ where
     ) -> Result<types::Merchant, ContainerError<Self::Error>> {
         let merchant_id = new.merchant_id.to_string();
         let output = self.inner.insert_merchant(new, key).await?;
-        self.cache_data::<types::Merchant>(merchant_id, output.clone())
+        self.cache_data::<types::Merchant>(merchant_id.clone(), output.clone())
+            .await;
+        self.publish_write(super::CacheName::Merchant, merchant_id)
             .await;
         Ok(output)
     }
 
-    async fn find_all_keys_excluding_entity_keys(
+    async fn find_keys_for_migration(
         &self,
         key: &Self::Algorithm,
+        cursor: Option<&str>,
         limit: i64,
-    ) -> Result<Vec<types::Merchant>, ContainerError<Self::Error>> {
-        self.inner
-            .find_all_keys_excluding_entity_keys(key, limit)
-            .await
+    ) -> Result<Vec<types::MigrationCandidate>, ContainerError<Self::Error>> {
+        self.inner.find_keys_for_migration(key, cursor, limit).await
     }
 }
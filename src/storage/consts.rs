@@ -9,12 +9,21 @@ pub(crate) const ALPHABETS: [char; 62] = [
 /// Number of characters in a generated ID
 pub const ID_LENGTH: usize = 20;
 
+/// `version` given to a freshly inserted vault or locker row
+pub(crate) const INITIAL_ROW_VERSION: i32 = 1;
+
 /// Header key for tenant ID
 pub const X_TENANT_ID: &str = "x-tenant-id";
 /// Header key for request ID
 pub const X_REQUEST_ID: &str = "x-request-id";
 /// Header key for caller-supplied fingerprint ID (optional)
 pub const X_FINGERPRINT_ID: &str = "x-fingerprint-id";
+/// Header key for the API key a caller authenticates its role with (optional)
+pub const X_CALLER_API_KEY: &str = "x-caller-api-key";
+/// Header key for the caller-chosen key under which a write is run at most once (optional)
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Header key marking a response replayed for an already used `Idempotency-Key`
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
 /// Key written by the Redis health-check probe
 #[cfg(feature = "redis")]
 pub const REDIS_HEALTH_CHECK_KEY: &str = "health_check_redis";
@@ -25,6 +34,20 @@ pub const REDIS_HEALTH_CHECK_VALUE: &str = "1";
 #[cfg(feature = "redis")]
 pub const REDIS_HEALTH_CHECK_EXPIRY: i64 = 5;
 
+/// Prefix of the per-tenant pub/sub channels carrying cache invalidations between pods
+#[cfg(all(feature = "caching", feature = "redis"))]
+pub(crate) const CACHE_INVALIDATION_CHANNEL: &str = "card_vault_cache_invalidation";
+
+/// Pub/sub channel announcing that the blocked callers of a tenant changed, so other pods read
+/// them again
+#[cfg(feature = "redis")]
+pub(crate) const ANOMALY_BLOCK_CHANNEL: &str = "card_vault_anomaly_block";
+
+/// Age of the oldest undrained entry, as a percentage of `ttl_for_kv`, at which diagnostics
+/// flag the tenant: past the TTL the record is gone from Redis before reaching Postgres.
+#[cfg(feature = "kv")]
+pub(crate) const DRAINER_LAG_WARNING_PERCENT: u64 = 80;
+
 /// Header Constants
 pub mod headers {
     pub const CONTENT_TYPE: &str = "Content-Type";
@@ -1,6 +1,6 @@
 #[cfg(not(feature = "kv"))]
-use diesel::{BoolExpressionMethods, OptionalExtension};
-use diesel::{ExpressionMethods, QueryDsl, associations::HasTable};
+use diesel::OptionalExtension;
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, associations::HasTable};
 use diesel_async::{AsyncConnection, RunQueryDsl};
 #[cfg(not(feature = "kv"))]
 use hyperswitch_masking::ExposeInterface;
@@ -79,18 +79,25 @@ impl MerchantInterface for Storage {
         Ok(inner.decrypt(key)?)
     }
 
-    async fn find_all_keys_excluding_entity_keys(
+    async fn find_keys_for_migration(
         &self,
         key: &Self::Algorithm,
+        cursor: Option<&str>,
         limit: i64,
-    ) -> Result<Vec<types::Merchant>, ContainerError<Self::Error>> {
+    ) -> Result<Vec<types::MigrationCandidate>, ContainerError<Self::Error>> {
         let mut conn = self.route_conn().await?;
 
+        // Merchant ids are never empty, so no cursor starts from the first merchant.
         let query = schema::merchant::table
-            .filter(
-                schema::merchant::merchant_id
-                    .ne_all(schema::entity::table.select(schema::entity::entity_id)),
-            )
+            .select((
+                schema::merchant::all_columns,
+                diesel::dsl::exists(
+                    schema::entity::table
+                        .filter(schema::entity::entity_id.eq(schema::merchant::merchant_id)),
+                ),
+            ))
+            .filter(schema::merchant::merchant_id.gt(cursor.unwrap_or_default()))
+            .order(schema::merchant::merchant_id.asc())
             .limit(limit);
 
         let pool = conn.pool();
@@ -99,9 +106,9 @@ impl MerchantInterface for Storage {
             &query, operation, pool,
         );
 
-        let result: Result<Vec<types::MerchantInner>, ContainerError<Self::Error>> =
+        let result: Result<Vec<(types::MerchantInner, bool)>, ContainerError<Self::Error>> =
             super::record_db_query::<<types::MerchantInner as HasTable>::Table, _, _, _>(
-                query.load::<types::MerchantInner>(conn.get_mut()),
+                query.load::<(types::MerchantInner, bool)>(conn.get_mut()),
                 operation,
                 pool,
             )
@@ -109,14 +116,10 @@ impl MerchantInterface for Storage {
             .change_error(error::StorageError::FindError)
             .map_err(ContainerError::from);
 
-        result?
+        Ok(result?
             .into_iter()
-            .map(|inner| {
-                inner
-                    .decrypt(key)
-                    .change_error(error::MerchantDBError::DEKDecryptionError)
-            })
-            .collect::<Result<Vec<_>, _>>()
+            .map(|(inner, migrated)| inner.into_migration_candidate(key, migrated))
+            .collect())
     }
 }
 
@@ -274,6 +277,136 @@ impl super::LockerInterface for Storage {
         }
     }
 
+    async fn update_locker(
+        &self,
+        locker_id: Secret<String>,
+        merchant_id: &str,
+        customer_id: &str,
+        update: types::LockerUpdate,
+    ) -> Result<types::Locker, ContainerError<Self::Error>> {
+        #[cfg(feature = "kv")]
+        {
+            let pk = super::kv::impls::locker::LockerPrimaryKeyType {
+                locker_id,
+                merchant_id: merchant_id.to_string(),
+                customer_id: customer_id.to_string(),
+            };
+
+            return super::kv::update_resource_by_id::<types::Locker>(self, update, pk).await;
+        }
+
+        #[cfg(not(feature = "kv"))]
+        {
+            let mut conn = self.get_conn().await?;
+
+            // A missing row surfaces (via `?`) as `VaultDBError::NotFoundError`.
+            let query = diesel::update(types::LockerInner::table())
+                .filter(
+                    schema::locker::locker_id
+                        .eq(locker_id.expose())
+                        .and(schema::locker::merchant_id.eq(merchant_id))
+                        .and(schema::locker::customer_id.eq(customer_id)),
+                )
+                .set((
+                    update,
+                    schema::locker::version.eq(schema::locker::version + 1),
+                ));
+
+            let pool = conn.pool();
+            let operation = DbOperation::Update;
+            super::log_db_query::<<types::LockerInner as HasTable>::Table, _>(
+                &query, operation, pool,
+            );
+
+            let output: types::LockerInner =
+                super::record_db_query::<<types::LockerInner as HasTable>::Table, _, _, _>(
+                    query.get_result(conn.get_mut()),
+                    operation,
+                    pool,
+                )
+                .await?;
+
+            Ok(output.into())
+        }
+    }
+
+    async fn update_locker_if(
+        &self,
+        locker_id: Secret<String>,
+        merchant_id: &str,
+        customer_id: &str,
+        update: types::LockerUpdate,
+        precondition: types::LockerPrecondition,
+    ) -> Result<types::Locker, ContainerError<Self::Error>> {
+        #[cfg(feature = "kv")]
+        {
+            let pk = super::kv::impls::locker::LockerPrimaryKeyType {
+                locker_id,
+                merchant_id: merchant_id.to_string(),
+                customer_id: customer_id.to_string(),
+            };
+
+            return super::kv::conditional_update_resource_by_id::<types::Locker>(
+                self,
+                update,
+                pk,
+                precondition,
+            )
+            .await;
+        }
+
+        #[cfg(not(feature = "kv"))]
+        {
+            let mut conn = self.get_conn().await?;
+            let locker_id = locker_id.expose();
+
+            let query = diesel::update(types::LockerInner::table())
+                .filter(
+                    schema::locker::locker_id
+                        .eq(&locker_id)
+                        .and(schema::locker::merchant_id.eq(merchant_id))
+                        .and(schema::locker::customer_id.eq(customer_id))
+                        .and(schema::locker::version.eq(precondition.version)),
+                )
+                .set((
+                    update,
+                    schema::locker::version.eq(schema::locker::version + 1),
+                ));
+
+            let pool = conn.pool();
+            let operation = DbOperation::Update;
+            super::log_db_query::<<types::LockerInner as HasTable>::Table, _>(
+                &query, operation, pool,
+            );
+
+            let output = super::record_db_query_optional::<
+                <types::LockerInner as HasTable>::Table,
+                _,
+                _,
+                _,
+            >(
+                async {
+                    query
+                        .get_result::<types::LockerInner>(conn.get_mut())
+                        .await
+                        .optional()
+                },
+                operation,
+                pool,
+            )
+            .await?;
+
+            match output {
+                Some(locker) => Ok(locker.into()),
+                // No row matched: it either changed underneath us or is gone.
+                None if locker_exists(&mut conn, &locker_id, merchant_id, customer_id).await? => {
+                    Err(error::VaultDBError::Conflict.into())
+                }
+                None => Err(error::VaultDBError::NotFoundError.into()),
+            }
+        }
+    }
+
     async fn delete_locker(
         &self,
         locker_id: Secret<String>,
@@ -596,26 +729,16 @@ impl super::EntityInterface for Storage {
         entity_id: &str,
     ) -> Result<types::Entity, ContainerError<Self::Error>> {
         // Reads are routed to the read replica when enabled.
-        let mut conn = self.route_conn().await?;
-
-        // A missing row surfaces as `EntityDBError::NotFoundError` (see the `From<diesel>`
-        // classifier), which `find_or_create_entity` in the key manager checks via
-        // `is_not_found()`.
-        let query = types::Entity::table().filter(schema::entity::entity_id.eq(entity_id));
-
-        let pool = conn.pool();
-        let operation = DbOperation::FindOne;
-        super::log_db_query::<<types::Entity as HasTable>::Table, _>(&query, operation, pool);
-
-        let output: types::Entity = super::record_db_query::<
-            <types::Entity as HasTable>::Table,
-            _,
-            _,
-            _,
-        >(query.get_result(conn.get_mut()), operation, pool)
-        .await?;
+        let conn = self.route_conn().await?;
+        find_entity(conn, entity_id).await
+    }
 
-        Ok(output)
+    async fn find_by_entity_id_on_primary(
+        &self,
+        entity_id: &str,
+    ) -> Result<types::Entity, ContainerError<Self::Error>> {
+        let conn = self.get_conn().await?;
+        find_entity(conn, entity_id).await
     }
 
     async fn insert_entity(
@@ -644,6 +767,102 @@ impl super::EntityInterface for Storage {
 
         Ok(output)
     }
+
+    async fn set_wrapped_dek(
+        &self,
+        entity_id: &str,
+        wrapped_dek: Secret<Vec<u8>>,
+    ) -> Result<types::Entity, ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        let query = diesel::update(types::Entity::table())
+            .filter(
+                schema::entity::entity_id
+                    .eq(entity_id)
+                    .and(schema::entity::wrapped_dek.is_null()),
+            )
+            .set(schema::entity::wrapped_dek.eq(wrapped_dek.expose()));
+
+        let pool = conn.pool();
+        let operation = DbOperation::Update;
+        super::log_db_query::<<types::Entity as HasTable>::Table, _>(&query, operation, pool);
+
+        let output =
+            super::record_db_query_optional::<<types::Entity as HasTable>::Table, _, _, _>(
+                async {
+                    query
+                        .get_result::<types::Entity>(conn.get_mut())
+                        .await
+                        .optional()
+                },
+                operation,
+                pool,
+            )
+            .await?;
+
+        match output {
+            Some(entity) => Ok(entity),
+            // Another writer stored a wrapped DEK first; read back the one that won, on the
+            // primary since a lagging replica may not have it yet.
+            None => find_entity(conn, entity_id).await,
+        }
+    }
+}
+
+/// Whether the locker row exists, read on `conn` so that after a conditional update matched
+/// nothing a row that is gone can be told from one at another version.
+pub(crate) async fn locker_exists(
+    conn: &mut super::DbConnection,
+    locker_id: &str,
+    merchant_id: &str,
+    customer_id: &str,
+) -> Result<bool, ContainerError<error::VaultDBError>> {
+    let query = diesel::select(diesel::dsl::exists(
+        types::LockerInner::table().filter(
+            schema::locker::locker_id
+                .eq(locker_id)
+                .and(schema::locker::merchant_id.eq(merchant_id))
+                .and(schema::locker::customer_id.eq(customer_id)),
+        ),
+    ));
+
+    let pool = conn.pool();
+    let operation = DbOperation::FindOne;
+    super::log_db_query::<<types::LockerInner as HasTable>::Table, _>(&query, operation, pool);
+
+    let exists = super::record_db_query::<<types::LockerInner as HasTable>::Table, _, _, _>(
+        query.get_result::<bool>(conn.get_mut()),
+        operation,
+        pool,
+    )
+    .await?;
+
+    Ok(exists)
+}
+
+#[cfg(feature = "external_key_manager")]
+async fn find_entity(
+    mut conn: super::DbConnection,
+    entity_id: &str,
+) -> Result<types::Entity, ContainerError<error::EntityDBError>> {
+    // A missing row surfaces as `EntityDBError::NotFoundError` (see the `From<diesel>`
+    // classifier), which `find_or_create_entity` in the key manager checks via
+    // `is_not_found()`.
+    let query = types::Entity::table().filter(schema::entity::entity_id.eq(entity_id));
+
+    let pool = conn.pool();
+    let operation = DbOperation::FindOne;
+    super::log_db_query::<<types::Entity as HasTable>::Table, _>(&query, operation, pool);
+
+    let output: types::Entity =
+        super::record_db_query::<<types::Entity as HasTable>::Table, _, _, _>(
+            query.get_result(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+
+    Ok(output)
 }
 
 impl super::ReverseLookupInterface for Storage {
@@ -764,3 +983,394 @@ impl super::ReverseLookupInterface for Storage {
         }
     }
 }
+
+impl super::AuditInterface for Storage {
+    type Error = error::AuditDBError;
+
+    async fn insert_audit_event(
+        &self,
+        new: types::AuditLogNew,
+    ) -> Result<(), ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        let query = diesel::insert_into(schema::audit_log::table).values(new);
+
+        let pool = conn.pool();
+        let operation = DbOperation::Insert;
+        super::log_db_query::<schema::audit_log::table, _>(&query, operation, pool);
+
+        super::record_db_query_rows::<schema::audit_log::table, _, _>(
+            query.execute(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn find_audit_events(
+        &self,
+        filter: &types::AuditLogFilter,
+    ) -> Result<Vec<types::AuditLogEvent>, ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        let mut query = schema::audit_log::table
+            .select((schema::audit_log::id, schema::audit_log::event))
+            .order(schema::audit_log::id.asc())
+            .limit(filter.limit)
+            .into_boxed();
+
+        if let Some(tenant_id) = &filter.tenant_id {
+            query = query.filter(schema::audit_log::tenant_id.eq(tenant_id));
+        }
+        if let Some(merchant_id) = &filter.merchant_id {
+            query = query.filter(schema::audit_log::merchant_id.eq(merchant_id));
+        }
+        if let Some(resource_id) = &filter.resource_id {
+            query = query.filter(schema::audit_log::resource_id.eq(resource_id));
+        }
+        if let Some(caller_role) = &filter.caller_role {
+            query = query.filter(schema::audit_log::caller_role.eq(caller_role));
+        }
+        if let Some(action) = &filter.action {
+            query = query.filter(schema::audit_log::action.eq(action));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(schema::audit_log::created_at.ge(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(schema::audit_log::created_at.lt(to));
+        }
+        if let Some(after) = filter.after {
+            query = query.filter(schema::audit_log::id.gt(after));
+        }
+
+        let pool = conn.pool();
+        let operation = DbOperation::Filter;
+        super::log_db_query::<schema::audit_log::table, _>(&query, operation, pool);
+
+        let output = super::record_db_query::<schema::audit_log::table, _, _, _>(
+            query.load(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+        Ok(output)
+    }
+
+    async fn find_last_audit_event(
+        &self,
+        chain_id: &str,
+    ) -> Result<Option<types::AuditLogEvent>, ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        let query = schema::audit_log::table
+            .select((schema::audit_log::id, schema::audit_log::event))
+            .filter(schema::audit_log::chain_id.eq(chain_id))
+            .order(schema::audit_log::sequence.desc())
+            .limit(1);
+
+        let pool = conn.pool();
+        let operation = DbOperation::FindOne;
+        super::log_db_query::<schema::audit_log::table, _>(&query, operation, pool);
+
+        let output = super::record_db_query_optional::<schema::audit_log::table, _, _, _>(
+            async {
+                diesel::OptionalExtension::optional(
+                    query
+                        .get_result::<types::AuditLogEvent>(conn.get_mut())
+                        .await,
+                )
+            },
+            operation,
+            pool,
+        )
+        .await?;
+        Ok(output)
+    }
+}
+
+impl super::IdempotencyInterface for Storage {
+    type Error = error::IdempotencyDBError;
+
+    async fn insert_idempotency_record(
+        &self,
+        new: types::IdempotencyRecordNew,
+    ) -> Result<bool, ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+        let pool = conn.pool();
+
+        // An expired record under the same key is taken over by the new reservation.
+        let query = diesel::delete(schema::idempotency_record::table).filter(
+            schema::idempotency_record::owner_id
+                .eq(&new.owner_id)
+                .and(schema::idempotency_record::route.eq(&new.route))
+                .and(schema::idempotency_record::idempotency_key.eq(&new.idempotency_key))
+                .and(schema::idempotency_record::expires_at.le(new.created_at)),
+        );
+
+        let operation = DbOperation::Delete;
+        super::log_db_query::<schema::idempotency_record::table, _>(&query, operation, pool);
+
+        super::record_db_query_rows::<schema::idempotency_record::table, _, _>(
+            query.execute(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+
+        let query = diesel::insert_into(schema::idempotency_record::table)
+            .values(new)
+            .on_conflict_do_nothing();
+
+        let operation = DbOperation::Insert;
+        super::log_db_query::<schema::idempotency_record::table, _>(&query, operation, pool);
+
+        let inserted = super::record_db_query_rows::<schema::idempotency_record::table, _, _>(
+            query.execute(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+        Ok(inserted == 1)
+    }
+
+    async fn find_idempotency_record(
+        &self,
+        id: types::IdempotencyKeyId<'_>,
+    ) -> Result<types::IdempotencyRecord, ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        let query = schema::idempotency_record::table
+            .select((
+                schema::idempotency_record::request_hash,
+                schema::idempotency_record::response,
+            ))
+            .filter(
+                schema::idempotency_record::owner_id
+                    .eq(id.owner_id)
+                    .and(schema::idempotency_record::route.eq(id.route))
+                    .and(schema::idempotency_record::idempotency_key.eq(id.idempotency_key))
+                    .and(schema::idempotency_record::expires_at.gt(crate::utils::date_time::now())),
+            );
+
+        let pool = conn.pool();
+        let operation = DbOperation::FindOne;
+        super::log_db_query::<schema::idempotency_record::table, _>(&query, operation, pool);
+
+        let output = super::record_db_query::<schema::idempotency_record::table, _, _, _>(
+            query.get_result(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+        Ok(output)
+    }
+
+    async fn update_idempotency_record_response(
+        &self,
+        id: types::IdempotencyKeyId<'_>,
+        request_hash: &str,
+        response: Vec<u8>,
+        expires_at: time::PrimitiveDateTime,
+    ) -> Result<(), ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        // Only the reservation made by this request, in case it expired and was taken over.
+        let query = diesel::update(schema::idempotency_record::table)
+            .filter(
+                schema::idempotency_record::owner_id
+                    .eq(id.owner_id)
+                    .and(schema::idempotency_record::route.eq(id.route))
+                    .and(schema::idempotency_record::idempotency_key.eq(id.idempotency_key))
+                    .and(schema::idempotency_record::request_hash.eq(request_hash))
+                    .and(schema::idempotency_record::response.is_null()),
+            )
+            .set((
+                schema::idempotency_record::response.eq(Some(response)),
+                schema::idempotency_record::expires_at.eq(expires_at),
+            ));
+
+        let pool = conn.pool();
+        let operation = DbOperation::Update;
+        super::log_db_query::<schema::idempotency_record::table, _>(&query, operation, pool);
+
+        let updated = super::record_db_query_rows::<schema::idempotency_record::table, _, _>(
+            query.execute(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+
+        match updated {
+            0 => Err(error::IdempotencyDBError::NotFoundError.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_idempotency_reservation(
+        &self,
+        id: types::IdempotencyKeyId<'_>,
+        request_hash: &str,
+    ) -> Result<(), ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        let query = diesel::delete(schema::idempotency_record::table).filter(
+            schema::idempotency_record::owner_id
+                .eq(id.owner_id)
+                .and(schema::idempotency_record::route.eq(id.route))
+                .and(schema::idempotency_record::idempotency_key.eq(id.idempotency_key))
+                .and(schema::idempotency_record::request_hash.eq(request_hash))
+                .and(schema::idempotency_record::response.is_null()),
+        );
+
+        let pool = conn.pool();
+        let operation = DbOperation::Delete;
+        super::log_db_query::<schema::idempotency_record::table, _>(&query, operation, pool);
+
+        super::record_db_query_rows::<schema::idempotency_record::table, _, _>(
+            query.execute(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_idempotency_records(
+        &self,
+        now: time::PrimitiveDateTime,
+    ) -> Result<usize, ContainerError<Self::Error>> {
+        let mut conn = self.get_conn().await?;
+
+        let query = diesel::delete(schema::idempotency_record::table)
+            .filter(schema::idempotency_record::expires_at.le(now));
+
+        let pool = conn.pool();
+        let operation = DbOperation::Delete;
+        super::log_db_query::<schema::idempotency_record::table, _>(&query, operation, pool);
+
+        let output = super::record_db_query_rows::<schema::idempotency_record::table, _, _>(
+            query.execute(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+        Ok(output)
+    }
+}
+
+impl super::UsageInterface for Storage {
+    type Error = error::VaultDBError;
+
+    async fn count_stored_rows(
+        &self,
+        table: types::StoredRowTable,
+        owner_id: Option<&str>,
+    ) -> Result<u64, ContainerError<Self::Error>> {
+        let conn = self.route_conn().await?;
+        count_rows(conn, table, owner_id).await
+    }
+
+    async fn count_owner_stored_rows(
+        &self,
+        table: types::StoredRowTable,
+        owner_id: &str,
+    ) -> Result<u64, ContainerError<Self::Error>> {
+        let conn = self.get_conn().await?;
+        count_rows(conn, table, Some(owner_id)).await
+    }
+
+    async fn find_top_owners_by_stored_rows(
+        &self,
+        table: types::StoredRowTable,
+        limit: i64,
+    ) -> Result<Vec<(String, u64)>, ContainerError<Self::Error>> {
+        let mut conn = self.route_conn().await?;
+
+        let pool = conn.pool();
+        let operation = DbOperation::Count;
+
+        let output: Vec<(String, i64)> = match table {
+            types::StoredRowTable::Locker => {
+                let query = schema::locker::table
+                    .group_by(schema::locker::merchant_id)
+                    .select((schema::locker::merchant_id, diesel::dsl::count_star()))
+                    .order(diesel::dsl::count_star().desc())
+                    .limit(limit);
+                super::log_db_query::<schema::locker::table, _>(&query, operation, pool);
+
+                super::record_db_query::<schema::locker::table, _, _, _>(
+                    query.load(conn.get_mut()),
+                    operation,
+                    pool,
+                )
+                .await?
+            }
+            types::StoredRowTable::Vault => {
+                let query = schema::vault::table
+                    .group_by(schema::vault::entity_id)
+                    .select((schema::vault::entity_id, diesel::dsl::count_star()))
+                    .order(diesel::dsl::count_star().desc())
+                    .limit(limit);
+                super::log_db_query::<schema::vault::table, _>(&query, operation, pool);
+
+                super::record_db_query::<schema::vault::table, _, _, _>(
+                    query.load(conn.get_mut()),
+                    operation,
+                    pool,
+                )
+                .await?
+            }
+        };
+
+        Ok(output
+            .into_iter()
+            .map(|(owner_id, count)| (owner_id, u64::try_from(count).unwrap_or_default()))
+            .collect())
+    }
+}
+
+async fn count_rows(
+    mut conn: super::DbConnection,
+    table: types::StoredRowTable,
+    owner_id: Option<&str>,
+) -> Result<u64, ContainerError<error::VaultDBError>> {
+    let pool = conn.pool();
+    let operation = DbOperation::Count;
+
+    let count: i64 = match table {
+        types::StoredRowTable::Locker => {
+            let mut query = schema::locker::table.into_boxed();
+            if let Some(merchant_id) = owner_id {
+                query = query.filter(schema::locker::merchant_id.eq(merchant_id));
+            }
+            let query = query.count();
+            super::log_db_query::<schema::locker::table, _>(&query, operation, pool);
+
+            super::record_db_query::<schema::locker::table, _, _, _>(
+                query.get_result(conn.get_mut()),
+                operation,
+                pool,
+            )
+            .await?
+        }
+        types::StoredRowTable::Vault => {
+            let mut query = schema::vault::table.into_boxed();
+            if let Some(entity_id) = owner_id {
+                query = query.filter(schema::vault::entity_id.eq(entity_id));
+            }
+            let query = query.count();
+            super::log_db_query::<schema::vault::table, _>(&query, operation, pool);
+
+            super::record_db_query::<schema::vault::table, _, _, _>(
+                query.get_result(conn.get_mut()),
+                operation,
+                pool,
+            )
+            .await?
+        }
+    };
+
+    Ok(u64::try_from(count).unwrap_or_default())
+}
@@ -1,27 +1,38 @@
 //! KV (write-through Redis) framework.
 
+#[cfg(feature = "kv")]
+pub(crate) mod backlog;
+#[cfg(feature = "kv")]
+pub mod drainer;
 pub(crate) mod entity;
 #[cfg(feature = "kv")]
 pub(crate) mod impls;
 #[cfg(feature = "kv")]
+pub(crate) mod index;
+#[cfg(feature = "kv")]
 pub(crate) mod partition_key;
 #[cfg(feature = "kv")]
+pub mod reconcile;
+#[cfg(feature = "kv")]
 pub(crate) mod resource;
 pub(crate) mod scheme;
 pub(crate) mod serializable_query;
+#[cfg(feature = "kv")]
+pub(crate) mod standalone;
 pub(crate) mod wrapper;
 
 #[cfg(feature = "kv")]
 pub(crate) use self::{
     partition_key::PartitionKey,
     resource::{
-        delete_resource_by_id, delete_resource_by_id_with_reverse_lookup,
-        find_optional_resource_by_id, find_optional_resource_by_lookup_id, find_resource_by_id,
-        insert_resource, insert_resource_with_reverse_lookup, update_resource_by_id,
+        conditional_update_resource_by_id, delete_resource_by_id,
+        delete_resource_by_id_with_reverse_lookup, find_optional_resource_by_id,
+        find_optional_resource_by_lookup_id, find_resource_by_id, insert_resource,
+        insert_resource_with_reverse_lookup, list_resources, update_resource_by_id,
     },
 };
 pub(crate) use self::{
-    scheme::KvState,
+    scheme::{KvOverride, KvState},
     wrapper::{KvStoreContext, RedisConnInterface},
 };
 pub(crate) use super::scheme::StorageScheme;
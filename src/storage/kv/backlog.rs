@@ -0,0 +1,179 @@
+//! Drainer backlog sampling, shared by the background metrics collector and diagnostics.
+//!
+//! The drainer deletes entries once they are settled, so a stream's length is its undrained
+//! backlog and its first entry is the oldest write still missing from Postgres.
+
+use std::time::Duration;
+
+use hyperswitch_redis_interface::{RedisConnectionPool, errors::RedisError};
+
+use super::{RedisConnInterface, wrapper::BridgeRedis};
+use crate::{
+    observability::metrics,
+    storage::{Storage, consts, redis::prefixed_key},
+};
+
+/// Pending-entry count of one consumer group; a missing stream or group counts as zero.
+const PENDING_COUNT_SCRIPT: &str = r"
+local ok, summary = pcall(redis.call, 'XPENDING', KEYS[1], ARGV[1])
+if ok then
+    return summary[1]
+end
+return 0
+";
+
+#[derive(Debug)]
+pub(crate) struct ShardBacklog {
+    pub shard: u8,
+    /// Entries not yet drained, including pending ones.
+    pub length: u64,
+    /// Entries delivered to a drainer but not yet acknowledged.
+    pub pending: u64,
+    pub oldest_entry_age: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub(crate) struct DrainerBacklog {
+    pub shards: Vec<ShardBacklog>,
+    /// How long Redis keeps the records the backlog refers to.
+    pub ttl_for_kv: Duration,
+}
+
+impl DrainerBacklog {
+    pub(crate) fn length(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.length).sum()
+    }
+
+    pub(crate) fn pending(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.pending).sum()
+    }
+
+    pub(crate) fn oldest_entry_age(&self) -> Option<Duration> {
+        self.shards
+            .iter()
+            .filter_map(|shard| shard.oldest_entry_age)
+            .max()
+    }
+
+    /// Whether the oldest undrained entry is close enough to `ttl_for_kv` that its record may
+    /// expire from Redis before it reaches Postgres.
+    pub(crate) fn nears_ttl(&self) -> bool {
+        self.oldest_entry_age().is_some_and(|age| {
+            age.as_secs().saturating_mul(100)
+                >= self
+                    .ttl_for_kv
+                    .as_secs()
+                    .saturating_mul(consts::DRAINER_LAG_WARNING_PERCENT)
+        })
+    }
+}
+
+impl Storage {
+    /// Sample every drainer shard of this tenant.
+    pub(crate) async fn drainer_backlog(&self) -> error_stack::Result<DrainerBacklog, RedisError> {
+        let redis_conn = self.get_redis_conn()?;
+        let mut shards = Vec::with_capacity(usize::from(self.kv_config.drainer_num_partitions));
+
+        for shard in 0..self.kv_config.drainer_num_partitions {
+            let stream = self
+                .kv_config
+                .drainer_stream_name(&format!("shard_{shard}"));
+
+            let length = redis_conn
+                .stream_get_length(&stream.clone().into())
+                .await
+                .bridge()?;
+
+            shards.push(ShardBacklog {
+                shard,
+                length: u64::try_from(length).unwrap_or(u64::MAX),
+                pending: self.pending_count(&redis_conn, &stream).await?,
+                oldest_entry_age: oldest_entry_age(&redis_conn, &stream).await?,
+            });
+        }
+
+        Ok(DrainerBacklog {
+            shards,
+            ttl_for_kv: Duration::from_secs(u64::from(self.kv_config.ttl_for_kv)),
+        })
+    }
+
+    async fn pending_count(
+        &self,
+        redis_conn: &RedisConnectionPool,
+        stream: &str,
+    ) -> error_stack::Result<u64, RedisError> {
+        redis_conn
+            .evaluate_redis_script::<_, u64>(
+                PENDING_COUNT_SCRIPT,
+                vec![prefixed_key(redis_conn, stream)],
+                vec![self.kv_config.drainer.consumer_group.clone()],
+            )
+            .await
+            .bridge()
+    }
+
+    /// Export the drainer backlog gauges for this tenant. Skipped when Redis is not configured.
+    pub(crate) async fn collect_drainer_backlog(&self, tenant_id: &str) {
+        if self.redis.is_none() {
+            return;
+        }
+
+        let backlog = match self.drainer_backlog().await {
+            Ok(backlog) => backlog,
+            Err(error) => {
+                crate::logger::warn!(?error, tenant_id, "Failed to sample drainer backlog");
+                return;
+            }
+        };
+
+        for shard in backlog.shards {
+            let attrs = crate::metric_attributes!(
+                ("tenant_id", tenant_id.to_owned()),
+                ("shard", i64::from(shard.shard)),
+            );
+
+            metrics::KV_DRAINER_STREAM_LENGTH.record(shard.length, attrs);
+            metrics::KV_DRAINER_PENDING_COUNT.record(shard.pending, attrs);
+            metrics::KV_DRAINER_OLDEST_ENTRY_AGE
+                .record(shard.oldest_entry_age.unwrap_or_default().as_secs(), attrs);
+        }
+    }
+}
+
+/// Age of the first entry in `stream`, read off its Redis-assigned `<millis>-<seq>` id.
+async fn oldest_entry_age(
+    redis_conn: &RedisConnectionPool,
+    stream: &str,
+) -> error_stack::Result<Option<Duration>, RedisError> {
+    let entries = match redis_conn
+        .stream_read_entries(stream, "0", Some(1))
+        .await
+        .bridge()
+    {
+        Ok(entries) => entries,
+        Err(error)
+            if matches!(
+                error.current_context(),
+                RedisError::StreamEmptyOrNotAvailable
+            ) =>
+        {
+            return Ok(None);
+        }
+        Err(error) => return Err(error),
+    };
+
+    let now_millis =
+        u64::try_from(time::OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000)
+            .unwrap_or_default();
+
+    Ok(entries
+        .into_iter()
+        .flat_map(|(_, entries)| entries)
+        .next()
+        .and_then(|(id, _)| {
+            id.split_once('-')
+                .and_then(|(millis, _)| millis.parse::<u64>().ok())
+        })
+        .map(|pushed_at| Duration::from_millis(now_millis.saturating_sub(pushed_at))))
+}
@@ -0,0 +1,870 @@
+//! Replays KV drainer streams into Postgres.
+//!
+//! One task runs per tenant and shard on every replica, but a shard is only drained by the
+//! replica holding its Redis lease. The holder claims whatever the group left pending, e.g. for a
+//! replica that went away, re-drives it, then follows new entries. Entries of a shard are applied
+//! one at a time in stream order: an update or delete only makes sense once the insert before it
+//! has landed, so two replicas must never split a shard between them.
+
+use std::{collections::HashMap, ops::ControlFlow, sync::Arc, time::Duration};
+
+use hyperswitch_redis_interface::{RedisConnectionPool, errors::RedisError, types::RedisEntryId};
+use tokio::sync::watch;
+use tracing::Instrument;
+
+use super::{
+    serializable_query::{DatabaseOperation, SerializableQuery},
+    standalone::StandaloneContext,
+    wrapper::BridgeRedis,
+};
+use crate::{
+    config::{DrainerConfig, GlobalConfig},
+    error::{self, ContainerError},
+    logger,
+    observability::metrics,
+    storage::{Storage, redis::prefixed_key},
+};
+
+/// Entry field holding the serialized [`SerializableQuery`].
+const QUERY_FIELD: &str = "query";
+
+/// Entry field holding the unix timestamp (seconds) at which the writer pushed the entry.
+const PUSHED_AT_FIELD: &str = "pushed_at";
+
+/// Dead-letter field naming the entry's id in its source stream.
+const SOURCE_ENTRY_ID_FIELD: &str = "source_entry_id";
+
+/// Dead-letter field describing why the entry could not be replayed.
+const ERROR_FIELD: &str = "error";
+
+/// `XREADGROUP` id selecting entries delivered to this consumer but never acknowledged.
+const PENDING_ENTRIES: &str = "0";
+
+/// `XREADGROUP` id selecting entries never delivered to any consumer of the group.
+const NEW_ENTRIES: &str = ">";
+
+/// Pause after a failed stream read before trying again.
+const READ_FAILURE_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Suffix of the key holding a shard's lease, next to its stream.
+const LEASE_SUFFIX: &str = "LEASE";
+
+/// Take or renew lease `KEYS[1]` for owner `ARGV[1]` for `ARGV[2]` milliseconds. Returns whether
+/// `ARGV[1]` holds it.
+const ACQUIRE_LEASE_SCRIPT: &str = r"
+local owner = redis.call('GET', KEYS[1])
+if owner == false or owner == ARGV[1] then
+  redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+  return 1
+end
+return 0
+";
+
+/// Drop lease `KEYS[1]` if owner `ARGV[1]` still holds it.
+const RELEASE_LEASE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+  return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// Hand every entry pending in group `ARGV[1]` of stream `KEYS[1]` to consumer `ARGV[2]`.
+/// Returns how many entries were claimed.
+const CLAIM_PENDING_SCRIPT: &str = r"
+local cursor = '0-0'
+local claimed = 0
+repeat
+  local reply = redis.call(
+    'XAUTOCLAIM', KEYS[1], ARGV[1], ARGV[2], 0, cursor, 'COUNT', 100, 'JUSTID')
+  cursor = reply[1]
+  claimed = claimed + #reply[2]
+until cursor == '0-0'
+return claimed
+";
+
+#[derive(Debug, Clone, Copy, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+enum ReplayOutcome {
+    Applied,
+    /// The insert or update had already landed: the entry was re-driven after a crash between
+    /// the write and its acknowledgement, or after its acknowledgement failed.
+    AlreadyApplied,
+    DeadLettered,
+}
+
+crate::impl_metric_value_from!(ReplayOutcome);
+
+/// What a single replay attempt found.
+#[derive(Debug)]
+enum Executed {
+    Rows(usize),
+    /// The statement changed nothing because the row already holds what it writes.
+    AlreadyApplied,
+}
+
+#[derive(Debug)]
+enum ReplayError {
+    Connection(ContainerError<error::StorageError>),
+    Query(diesel::result::Error),
+    /// An update matched no row, usually because the insert before it was dead-lettered.
+    NoRowsUpdated,
+    /// Draining stopped while waiting to retry, on shutdown or because the shard lease was lost;
+    /// the entry is left pending.
+    Interrupted,
+}
+
+impl ReplayError {
+    /// Failures that say Postgres is unreachable rather than that the entry is bad.
+    fn is_connection(&self) -> bool {
+        match self {
+            Self::Connection(_) => true,
+            Self::Query(error) => matches!(
+                error,
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::ClosedConnection
+                        | diesel::result::DatabaseErrorKind::UnableToSendCommand,
+                    _,
+                )
+            ),
+            Self::NoRowsUpdated | Self::Interrupted => false,
+        }
+    }
+
+    /// Dead-letter description. Driver messages are kept, constraint details are not, so no
+    /// stored values end up in the dead-letter stream.
+    fn describe(&self) -> String {
+        match self {
+            Self::Connection(_) => "database connection unavailable".to_string(),
+            Self::Query(error) => error.to_string(),
+            Self::NoRowsUpdated => "update matched no rows".to_string(),
+            Self::Interrupted => "interrupted".to_string(),
+        }
+    }
+}
+
+/// Drain every tenant's shard streams until a shutdown signal arrives.
+pub async fn run(
+    global_config: GlobalConfig,
+) -> error_stack::Result<(), error::ConfigurationError> {
+    let context = StandaloneContext::connect(&global_config).await?;
+
+    let kv_config = &global_config.kv;
+    let consumer = kv_config
+        .drainer
+        .consumer_name
+        .clone()
+        .unwrap_or_else(|| gethostname::gethostname().to_string_lossy().into_owned());
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut handles = Vec::new();
+
+    for tenant_id in global_config.tenant_secrets.keys() {
+        let (storage, tenant_redis) = context.tenant_storage(&global_config, tenant_id).await?;
+
+        for shard in 0..kv_config.drainer_num_partitions {
+            let stream = kv_config.drainer_stream_name(&format!("shard_{shard}"));
+            let span = tracing::info_span!("drainer", tenant_id = %tenant_id, stream = %stream);
+
+            let shard_drainer = ShardDrainer {
+                tenant_id: tenant_id.clone(),
+                storage: storage.clone(),
+                redis: tenant_redis.get_redis_conn(),
+                dead_letter_stream: kv_config.drainer_dead_letter_stream_name(&stream),
+                lease: format!("{stream}_{LEASE_SUFFIX}"),
+                stream,
+                consumer: consumer.clone(),
+                config: kv_config.drainer.clone(),
+            };
+
+            handles.push(tokio::spawn(
+                shard_drainer.run(shutdown_rx.clone()).instrument(span),
+            ));
+        }
+    }
+
+    logger::info!(
+        consumer,
+        tenants = global_config.tenant_secrets.len(),
+        shards = kv_config.drainer_num_partitions,
+        "Drainer started"
+    );
+
+    crate::app::shutdown_signal().await;
+
+    // Receivers only go away when their task has already stopped.
+    let _ = shutdown_tx.send(true);
+    for result in futures::future::join_all(handles).await {
+        if let Err(error) = result {
+            logger::error!(?error, "Drainer task panicked");
+        }
+    }
+
+    logger::info!("Drainer stopped");
+    Ok(())
+}
+
+type StreamEntry = (String, HashMap<String, String>);
+
+struct ShardDrainer {
+    tenant_id: String,
+    storage: Storage,
+    redis: Arc<RedisConnectionPool>,
+    stream: String,
+    dead_letter_stream: String,
+    /// Key of the shard's lease; its value names the consumer draining the shard.
+    lease: String,
+    consumer: String,
+    config: DrainerConfig,
+}
+
+impl ShardDrainer {
+    async fn run(self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            match self.acquire_lease().await {
+                Ok(true) => {
+                    self.drain_while_leased(&shutdown).await;
+                    self.release_lease().await;
+                }
+                Ok(false) => {}
+                Err(error) => logger::error!(?error, "Failed to acquire drainer shard lease"),
+            }
+
+            tokio::select! {
+                () = tokio::time::sleep(self.lease_renewal_interval()) => {}
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+
+    fn lease_renewal_interval(&self) -> Duration {
+        Duration::from_millis(self.config.lease_ms / 3)
+    }
+
+    /// Drain the shard until shutdown, or until the lease is lost or cannot be renewed. The
+    /// lease is renewed alongside, so a replay retried through a Postgres outage keeps it.
+    async fn drain_while_leased(&self, shutdown: &watch::Receiver<bool>) {
+        self.create_consumer_group().await;
+
+        // Entries another consumer read but never settled come first, or newer entries would
+        // overtake them.
+        if let Err(error) = self.claim_pending().await {
+            logger::error!(?error, "Failed to claim pending drainer entries");
+            return;
+        }
+
+        let (stop_tx, mut stop) = watch::channel(false);
+        let keeper = async {
+            let mut shutdown = shutdown.clone();
+            loop {
+                tokio::select! {
+                    () = tokio::time::sleep(self.lease_renewal_interval()) => {}
+                    _ = shutdown.changed() => break,
+                }
+                match self.acquire_lease().await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        logger::warn!(consumer = %self.consumer, "Drainer shard lease taken over");
+                        break;
+                    }
+                    Err(error) => {
+                        // Stop before the lease can run out under us; it is taken again once
+                        // Redis answers.
+                        logger::error!(?error, "Failed to renew drainer shard lease");
+                        break;
+                    }
+                }
+            }
+            // The drain loop below holds the receiver until it has stopped.
+            let _ = stop_tx.send(true);
+        };
+
+        tokio::join!(keeper, self.drain(&mut stop));
+    }
+
+    async fn drain(&self, stop: &mut watch::Receiver<bool>) {
+        let mut read_from = PENDING_ENTRIES;
+        while !*stop.borrow() {
+            // Only the read is raced against stopping; an entry that has been picked up is
+            // always settled. Entries read but not yet processed stay pending for the next owner.
+            let entries = tokio::select! {
+                entries = self.read(read_from) => entries,
+                _ = stop.changed() => break,
+            };
+
+            match entries {
+                Ok(entries) if entries.is_empty() && read_from == PENDING_ENTRIES => {
+                    read_from = NEW_ENTRIES;
+                }
+                Ok(entries) => {
+                    let processed =
+                        process_in_order(entries, stop, async |entry_id, fields, stop| {
+                            self.process(entry_id, fields, stop).await
+                        })
+                        .await;
+                    if processed.is_break() && !*stop.borrow() {
+                        // Later entries of the shard must not overtake the unsettled one, so
+                        // start over from the pending entries, which it leads.
+                        read_from = PENDING_ENTRIES;
+                        tokio::select! {
+                            () = tokio::time::sleep(READ_FAILURE_BACKOFF) => {}
+                            _ = stop.changed() => break,
+                        }
+                    }
+                }
+                Err(error) => {
+                    logger::error!(?error, "Failed to read drainer stream");
+                    // The group may be gone, e.g. after the stream was deleted.
+                    self.create_consumer_group().await;
+                    tokio::time::sleep(READ_FAILURE_BACKOFF).await;
+                }
+            }
+        }
+    }
+
+    async fn acquire_lease(&self) -> error_stack::Result<bool, RedisError> {
+        let held = self
+            .redis
+            .evaluate_redis_script::<_, i64>(
+                ACQUIRE_LEASE_SCRIPT,
+                vec![prefixed_key(&self.redis, &self.lease)],
+                vec![self.consumer.clone(), self.config.lease_ms.to_string()],
+            )
+            .await
+            .bridge()?;
+
+        Ok(held == 1)
+    }
+
+    async fn release_lease(&self) {
+        if let Err(error) = self
+            .redis
+            .evaluate_redis_script::<_, i64>(
+                RELEASE_LEASE_SCRIPT,
+                vec![prefixed_key(&self.redis, &self.lease)],
+                vec![self.consumer.clone()],
+            )
+            .await
+            .bridge()
+        {
+            // It runs out on its own after `lease_ms`.
+            logger::warn!(?error, "Failed to release drainer shard lease");
+        }
+    }
+
+    async fn claim_pending(&self) -> error_stack::Result<(), RedisError> {
+        let claimed = self
+            .redis
+            .evaluate_redis_script::<_, u64>(
+                CLAIM_PENDING_SCRIPT,
+                vec![prefixed_key(&self.redis, &self.stream)],
+                vec![self.config.consumer_group.clone(), self.consumer.clone()],
+            )
+            .await
+            .bridge()?;
+
+        logger::info!(consumer = %self.consumer, claimed, "Took over drainer shard");
+        Ok(())
+    }
+
+    async fn create_consumer_group(&self) {
+        let from_start = RedisEntryId::UserSpecifiedID {
+            milliseconds: "0".to_string(),
+            sequence_number: "0".to_string(),
+        };
+
+        // Fails with BUSYGROUP whenever another replica (or an earlier run) got there first.
+        if let Err(error) = self
+            .redis
+            .consumer_group_create(
+                &self.stream.clone().into(),
+                &self.config.consumer_group,
+                &from_start,
+            )
+            .await
+            .bridge()
+        {
+            logger::debug!(?error, "Consumer group not created");
+        }
+    }
+
+    async fn read(&self, id: &str) -> error_stack::Result<Vec<StreamEntry>, RedisError> {
+        // Pending entries are already there; only block while waiting for new ones.
+        let block = (id == NEW_ENTRIES).then_some(self.config.block_ms);
+
+        match self
+            .redis
+            .stream_read_with_options(
+                self.stream.as_str(),
+                id,
+                Some(self.config.read_count),
+                block,
+                Some((self.config.consumer_group.as_str(), self.consumer.as_str())),
+            )
+            .await
+            .bridge()
+        {
+            Ok(streams) => Ok(streams
+                .into_iter()
+                .flat_map(|(_, entries)| entries)
+                .collect()),
+            Err(error)
+                if matches!(
+                    error.current_context(),
+                    RedisError::StreamEmptyOrNotAvailable
+                ) =>
+            {
+                Ok(Vec::new())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Replay one entry and settle it. Breaks when the entry was left pending.
+    async fn process(
+        &self,
+        entry_id: &str,
+        fields: HashMap<String, String>,
+        shutdown: &mut watch::Receiver<bool>,
+    ) -> ControlFlow<()> {
+        // Pending entries deleted from the stream come back without fields.
+        if fields.is_empty() {
+            self.settle(entry_id).await;
+            return ControlFlow::Continue(());
+        }
+
+        if let Some(pushed_at) = fields
+            .get(PUSHED_AT_FIELD)
+            .and_then(|pushed_at| pushed_at.parse::<i64>().ok())
+        {
+            let lag = time::OffsetDateTime::now_utc()
+                .unix_timestamp()
+                .saturating_sub(pushed_at);
+            let lag = Duration::from_secs(u64::try_from(lag).unwrap_or_default());
+            metrics::KV_DRAINER_LAG.record(
+                lag.as_secs_f64(),
+                crate::metric_attributes!(("tenant_id", self.tenant_id.clone())),
+            );
+        }
+
+        let query = match fields
+            .get(QUERY_FIELD)
+            .map(|query| serde_json::from_str::<SerializableQuery>(query))
+        {
+            Some(Ok(query)) => query,
+            Some(Err(error)) => {
+                logger::error!(entry_id, ?error, "Malformed drainer entry");
+                return self
+                    .dead_letter(entry_id, &fields, "malformed query", None)
+                    .await;
+            }
+            None => {
+                logger::error!(entry_id, "Drainer entry has no query");
+                return self
+                    .dead_letter(entry_id, &fields, "missing query", None)
+                    .await;
+            }
+        };
+
+        let attrs = crate::metric_attributes!(
+            ("tenant_id", self.tenant_id.clone()),
+            ("entity_type", query.entity_type()),
+            ("operation", query.operation().to_string()),
+        );
+
+        let storage = &self.storage;
+        let query = &query;
+        let result = replay(
+            &self.config,
+            query.operation(),
+            attrs,
+            shutdown,
+            || async move {
+                let mut conn = storage.get_conn().await.map_err(ReplayError::Connection)?;
+                let rows = query
+                    .execute(conn.get_mut())
+                    .await
+                    .map_err(ReplayError::Query)?;
+                if rows == 0
+                    && query
+                        .is_applied(conn.get_mut())
+                        .await
+                        .map_err(ReplayError::Query)?
+                {
+                    return Ok(Executed::AlreadyApplied);
+                }
+                Ok::<_, ReplayError>(Executed::Rows(rows))
+            },
+        )
+        .await;
+
+        match result {
+            Ok(outcome) => {
+                self.record_outcome(outcome, Some(query));
+                self.settle(entry_id).await;
+            }
+            Err(ReplayError::Interrupted) => {
+                logger::info!(entry_id, "Drainer stopped before settling entry");
+                return ControlFlow::Break(());
+            }
+            Err(error) => {
+                logger::error!(entry_id, ?error, "Giving up on drainer entry");
+                return self
+                    .dead_letter(entry_id, &fields, &error.describe(), Some(query))
+                    .await;
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Copy the entry to the dead-letter stream, then settle it. Breaks when the copy failed:
+    /// the entry then stays pending and is re-driven rather than being lost.
+    async fn dead_letter(
+        &self,
+        entry_id: &str,
+        fields: &HashMap<String, String>,
+        reason: &str,
+        query: Option<&SerializableQuery>,
+    ) -> ControlFlow<()> {
+        let mut entry = fields
+            .iter()
+            .map(|(field, value)| (field.as_str(), value.clone()))
+            .collect::<Vec<_>>();
+        entry.push((SOURCE_ENTRY_ID_FIELD, entry_id.to_string()));
+        entry.push((ERROR_FIELD, reason.to_string()));
+
+        match self
+            .redis
+            .stream_append_entry(
+                &self.dead_letter_stream.clone().into(),
+                &RedisEntryId::AutoGeneratedID,
+                entry,
+            )
+            .await
+            .bridge()
+        {
+            Ok(()) => {
+                self.record_outcome(ReplayOutcome::DeadLettered, query);
+                self.settle(entry_id).await;
+                ControlFlow::Continue(())
+            }
+            Err(error) => {
+                logger::error!(entry_id, ?error, "Failed to dead-letter drainer entry");
+                ControlFlow::Break(())
+            }
+        }
+    }
+
+    /// Acknowledge the entry and drop it from the stream, which therefore only ever holds work
+    /// that has not been drained yet.
+    async fn settle(&self, entry_id: &str) {
+        let stream = self.stream.clone().into();
+
+        if let Err(error) = self
+            .redis
+            .stream_acknowledge_entries(&stream, &self.config.consumer_group, entry_id)
+            .await
+            .bridge()
+        {
+            logger::error!(entry_id, ?error, "Failed to acknowledge drainer entry");
+            return;
+        }
+
+        if let Err(error) = self
+            .redis
+            .stream_delete_entries(&stream, entry_id)
+            .await
+            .bridge()
+        {
+            logger::warn!(entry_id, ?error, "Failed to delete drained entry");
+        }
+    }
+
+    fn record_outcome(&self, outcome: ReplayOutcome, query: Option<&SerializableQuery>) {
+        let (entity_type, operation) = query.map_or_else(
+            || ("unknown".to_string(), "unknown".to_string()),
+            |query| (query.entity_type(), query.operation().to_string()),
+        );
+
+        metrics::KV_DRAINER_ENTRY_COUNT.add(
+            1,
+            crate::metric_attributes!(
+                ("tenant_id", self.tenant_id.clone()),
+                ("entity_type", entity_type),
+                ("operation", operation),
+                ("outcome", outcome),
+            ),
+        );
+    }
+}
+
+/// Process `entries` in stream order, stopping at the first one `process` leaves pending or once
+/// `stop` is set. Breaks in either case.
+async fn process_in_order(
+    entries: Vec<StreamEntry>,
+    stop: &mut watch::Receiver<bool>,
+    mut process: impl AsyncFnMut(
+        &str,
+        HashMap<String, String>,
+        &mut watch::Receiver<bool>,
+    ) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    for (entry_id, fields) in entries {
+        if *stop.borrow() || process(&entry_id, fields, stop).await.is_break() {
+            return ControlFlow::Break(());
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Run `execute` until the entry is applied or has to be dead-lettered.
+///
+/// Connection failures say nothing about the entry, so they are retried for as long as Postgres
+/// stays away, with the delay capped at `max_retry_backoff_ms`: dead-lettering through an outage
+/// would drop every entry behind it and break the shard's ordering. Query failures are retried
+/// up to `max_attempts` times.
+async fn replay<F, Fut>(
+    config: &DrainerConfig,
+    operation: DatabaseOperation,
+    attrs: &[opentelemetry::KeyValue],
+    shutdown: &mut watch::Receiver<bool>,
+    mut execute: F,
+) -> Result<ReplayOutcome, ReplayError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<Executed, ReplayError>>,
+{
+    let max_backoff = Duration::from_millis(config.max_retry_backoff_ms);
+    let mut backoff = Duration::from_millis(config.retry_backoff_ms).min(max_backoff);
+    let mut attempt = 1;
+
+    loop {
+        let start = std::time::Instant::now();
+        let result = execute().await;
+        metrics::KV_DRAINER_REPLAY_DURATION.record(start.elapsed().as_secs_f64(), attrs);
+
+        match result {
+            Ok(Executed::AlreadyApplied) => return Ok(ReplayOutcome::AlreadyApplied),
+            // Replaying later writes on top of a missing row would silently lose this one.
+            Ok(Executed::Rows(0)) if operation == DatabaseOperation::Update => {
+                return Err(ReplayError::NoRowsUpdated);
+            }
+            Ok(Executed::Rows(_)) => return Ok(ReplayOutcome::Applied),
+            Err(ReplayError::Query(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            ))) if operation == DatabaseOperation::Insert => {
+                return Ok(ReplayOutcome::AlreadyApplied);
+            }
+            Err(error) => {
+                metrics::KV_DRAINER_REPLAY_FAILURE_COUNT.add(1, attrs);
+                let connection = error.is_connection();
+                if !connection && attempt >= config.max_attempts {
+                    return Err(error);
+                }
+                logger::warn!(
+                    attempt,
+                    connection,
+                    ?error,
+                    "Drainer replay failed, retrying"
+                );
+                tokio::select! {
+                    () = tokio::time::sleep(backoff) => {}
+                    _ = shutdown.changed() => return Err(ReplayError::Interrupted),
+                }
+                backoff = backoff.saturating_mul(2).min(max_backoff);
+                if !connection {
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn config(max_attempts: u8) -> DrainerConfig {
+        DrainerConfig {
+            max_attempts,
+            retry_backoff_ms: 1,
+            max_retry_backoff_ms: 2,
+            ..DrainerConfig::default()
+        }
+    }
+
+    fn database_error(kind: diesel::result::DatabaseErrorKind) -> ReplayError {
+        ReplayError::Query(diesel::result::Error::DatabaseError(
+            kind,
+            Box::new("replay test".to_string()),
+        ))
+    }
+
+    /// Replay with `results` returned in turn by successive attempts; also returns the number
+    /// of attempts made.
+    async fn replay_scripted(
+        config: &DrainerConfig,
+        operation: DatabaseOperation,
+        results: Vec<Result<Executed, ReplayError>>,
+    ) -> (Result<ReplayOutcome, ReplayError>, usize) {
+        let (_shutdown_tx, mut shutdown) = watch::channel(false);
+        let results = std::sync::Mutex::new(results.into_iter());
+        let attempts = AtomicUsize::new(0);
+
+        let outcome = replay(config, operation, &[], &mut shutdown, || {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            let result = results.lock().unwrap().next().unwrap();
+            async move { result }
+        })
+        .await;
+
+        (outcome, attempts.into_inner())
+    }
+
+    #[tokio::test]
+    async fn connection_failures_are_retried_past_max_attempts() {
+        let mut results = (0..5)
+            .map(|_| {
+                Err(ReplayError::Connection(ContainerError::from(
+                    error::StorageError::PoolClientFailure,
+                )))
+            })
+            .collect::<Vec<_>>();
+        results.push(Err(database_error(
+            diesel::result::DatabaseErrorKind::ClosedConnection,
+        )));
+        results.push(Ok(Executed::Rows(1)));
+
+        let (outcome, attempts) =
+            replay_scripted(&config(2), DatabaseOperation::Insert, results).await;
+
+        assert!(matches!(outcome, Ok(ReplayOutcome::Applied)));
+        assert_eq!(attempts, 7);
+    }
+
+    #[tokio::test]
+    async fn query_failures_give_up_after_max_attempts() {
+        let results = (0..5)
+            .map(|_| Err(ReplayError::Query(diesel::result::Error::NotFound)))
+            .collect();
+
+        let (outcome, attempts) =
+            replay_scripted(&config(3), DatabaseOperation::Delete, results).await;
+
+        assert!(matches!(outcome, Err(ReplayError::Query(_))));
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn duplicate_insert_is_already_applied() {
+        let results = vec![Err(database_error(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+        ))];
+
+        let (outcome, attempts) =
+            replay_scripted(&config(3), DatabaseOperation::Insert, results).await;
+
+        assert!(matches!(outcome, Ok(ReplayOutcome::AlreadyApplied)));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn zero_row_update_is_dead_lettered() {
+        let (outcome, attempts) = replay_scripted(
+            &config(3),
+            DatabaseOperation::Update,
+            vec![Ok(Executed::Rows(0))],
+        )
+        .await;
+        assert!(matches!(outcome, Err(ReplayError::NoRowsUpdated)));
+        assert_eq!(attempts, 1);
+
+        // A delete of a row already gone is settled.
+        let (outcome, _) = replay_scripted(
+            &config(3),
+            DatabaseOperation::Delete,
+            vec![Ok(Executed::Rows(0))],
+        )
+        .await;
+        assert!(matches!(outcome, Ok(ReplayOutcome::Applied)));
+    }
+
+    #[tokio::test]
+    async fn replayed_update_is_already_applied() {
+        let (outcome, attempts) = replay_scripted(
+            &config(3),
+            DatabaseOperation::Update,
+            vec![Ok(Executed::AlreadyApplied)],
+        )
+        .await;
+
+        assert!(matches!(outcome, Ok(ReplayOutcome::AlreadyApplied)));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn entries_behind_an_unsettled_one_are_not_processed() {
+        let (shutdown_tx, mut shutdown) = watch::channel(false);
+        let entries = || {
+            ["1-0", "2-0", "3-0"]
+                .map(|entry_id| (entry_id.to_string(), HashMap::new()))
+                .to_vec()
+        };
+
+        // e.g. the second entry could not be copied to the dead-letter stream
+        let mut processed = Vec::new();
+        let flow = process_in_order(entries(), &mut shutdown, async |entry_id, _, _| {
+            processed.push(entry_id.to_string());
+            if entry_id == "2-0" {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .await;
+        assert!(flow.is_break());
+        assert_eq!(processed, ["1-0", "2-0"]);
+
+        shutdown_tx.send(true).unwrap();
+        processed.clear();
+        let flow = process_in_order(entries(), &mut shutdown, async |entry_id, _, _| {
+            processed.push(entry_id.to_string());
+            ControlFlow::Continue(())
+        })
+        .await;
+        assert!(flow.is_break());
+        assert!(processed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn shutdown_interrupts_connection_retries() {
+        let config = DrainerConfig {
+            retry_backoff_ms: 60_000,
+            max_retry_backoff_ms: 60_000,
+            ..config(1)
+        };
+        let (shutdown_tx, mut shutdown) = watch::channel(false);
+        shutdown_tx.send(true).unwrap();
+
+        let outcome = replay(
+            &config,
+            DatabaseOperation::Insert,
+            &[],
+            &mut shutdown,
+            || async {
+                Err(ReplayError::Connection(ContainerError::from(
+                    error::StorageError::PoolClientFailure,
+                )))
+            },
+        )
+        .await;
+
+        assert!(matches!(outcome, Err(ReplayError::Interrupted)));
+    }
+}
@@ -13,7 +13,9 @@ impl From<&KvError> for ReverseLookupDBError {
         match e {
             KvError::DuplicateValue { .. } => Self::Duplicate,
             KvError::ValueNotFound(_) => Self::NotFoundError,
-            KvError::Backend | KvError::SerializationFailed => Self::UnknownError,
+            KvError::Conflict { .. } | KvError::Backend | KvError::SerializationFailed => {
+                Self::UnknownError
+            }
         }
     }
 }
@@ -23,7 +25,9 @@ impl From<&KvError> for HashDBError {
         match e {
             KvError::DuplicateValue { .. } => Self::Duplicate,
             KvError::ValueNotFound(_) => Self::DBFilterError,
-            KvError::Backend | KvError::SerializationFailed => Self::UnknownError,
+            KvError::Conflict { .. } | KvError::Backend | KvError::SerializationFailed => {
+                Self::UnknownError
+            }
         }
     }
 }
@@ -33,6 +37,7 @@ impl From<&KvError> for VaultDBError {
         match e {
             KvError::DuplicateValue { .. } => Self::Duplicate,
             KvError::ValueNotFound(_) => Self::NotFoundError,
+            KvError::Conflict { .. } => Self::Conflict,
             KvError::Backend | KvError::SerializationFailed => Self::UnknownError,
         }
     }
@@ -12,7 +12,7 @@ use crate::{
             PartitionKey, StorageScheme,
             entity::EntityType,
             partition_key::KvStorePartition,
-            resource::{DirectInsert, GetPartitionKey, KvResource},
+            resource::{DirectInsert, GetPartitionKey, KvReconcilableResource, KvResource},
             serializable_query::{SerializableQuery, generate_insert_query},
         },
         types::{Fingerprint, FingerprintTableNew},
@@ -112,6 +112,24 @@ impl KvResource for Fingerprint {
     }
 }
 
+impl KvReconcilableResource for Fingerprint {
+    const SCAN_PATTERN: &'static str = "fingerprint_*";
+
+    fn primary_key(entity: &Self) -> Self::PrimaryKeyType {
+        FingerprintPrimaryKey {
+            fingerprint_hash: entity.fingerprint_hash.clone(),
+        }
+    }
+
+    fn to_diesel_new(entity: Self) -> Self::DieselNew {
+        FingerprintTableNew {
+            fingerprint_hash: entity.fingerprint_hash,
+            fingerprint_id: entity.fingerprint_id,
+            updated_by: entity.updated_by,
+        }
+    }
+}
+
 impl From<&KvError> for FingerprintDBError {
     fn from(e: &KvError) -> Self {
         match e {
@@ -10,7 +10,7 @@ use crate::{
             StorageScheme,
             entity::EntityType,
             partition_key::{KvStorePartition, PartitionKey},
-            resource::{DirectInsert, GetPartitionKey, KvResource},
+            resource::{DirectInsert, GetPartitionKey, KvReconcilableResource, KvResource},
             serializable_query::{SerializableQuery, generate_insert_query},
         },
         types::{HashTable, HashTableNew},
@@ -105,3 +105,22 @@ impl KvResource for HashTable {
         Ok(output)
     }
 }
+
+impl KvReconcilableResource for HashTable {
+    const SCAN_PATTERN: &'static str = "hash_table_*";
+
+    fn primary_key(entity: &Self) -> Self::PrimaryKeyType {
+        HashTablePrimaryKey {
+            data_hash: entity.data_hash.clone(),
+        }
+    }
+
+    fn to_diesel_new(entity: Self) -> Self::DieselNew {
+        HashTableNew {
+            hash_id: entity.hash_id,
+            data_hash: entity.data_hash,
+            created_at: entity.created_at,
+            updated_by: entity.updated_by,
+        }
+    }
+}
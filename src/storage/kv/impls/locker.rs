@@ -1,4 +1,6 @@
-use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, associations::HasTable};
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, associations::HasTable,
+};
 use diesel_async::RunQueryDsl;
 use hyperswitch_masking::PeekInterface;
 
@@ -11,12 +13,17 @@ use crate::{
             entity::EntityType,
             partition_key::{KvStorePartition, PartitionKey},
             resource::{
-                GetLookupKey, GetPartitionKey, KvDeletableResource, KvDeletableWithLookup,
-                KvResource, KvSecondaryLookupResource, ReverseLookupInsert, ReverseLookupKey,
+                GetLookupKey, GetPartitionKey, KvConditionalUpdatableResource, KvDeletableResource,
+                KvDeletableWithLookup, KvReconcilableResource, KvResource,
+                KvSecondaryLookupResource, KvUpdatableResource, ReverseLookupInsert,
+                ReverseLookupKey,
+            },
+            serializable_query::{
+                SerializableQuery, generate_delete_query, generate_insert_query,
+                generate_update_query,
             },
-            serializable_query::{SerializableQuery, generate_delete_query, generate_insert_query},
         },
-        types::{Locker, LockerInner, LockerNew},
+        types::{Locker, LockerInner, LockerNew, LockerPrecondition, LockerUpdate},
     },
 };
 
@@ -70,6 +77,9 @@ impl GetLookupKey for LockerHashLookupKey {
             lookup_id: format!("locker_{merchant_id}_{customer_id}_{hash_id}"),
         }
     }
+    fn merchant_id(&self) -> Option<&str> {
+        Some(&self.merchant_id)
+    }
 }
 
 impl KvSecondaryLookupResource for Locker {
@@ -252,3 +262,169 @@ impl KvDeletableWithLookup for Locker {
         }
     }
 }
+
+impl KvUpdatableResource for Locker {
+    type DieselUpdate = LockerUpdate;
+
+    fn set_update_storage_scheme(update: &mut Self::DieselUpdate, scheme: StorageScheme) {
+        update.updated_by = scheme;
+    }
+
+    fn generate_update_drainer_query(
+        update: &Self::DieselUpdate,
+        pk: &Self::PrimaryKeyType,
+        updated: &Self::DieselEntity,
+    ) -> error_stack::Result<SerializableQuery, crate::error::kv::KvError> {
+        // A replay finds the row at (or past) `updated.version` and leaves it alone.
+        let query = diesel::update(crate::storage::schema::locker::table)
+            .filter(
+                crate::storage::schema::locker::locker_id
+                    .eq(pk.locker_id.peek().clone())
+                    .and(crate::storage::schema::locker::merchant_id.eq(pk.merchant_id.clone()))
+                    .and(crate::storage::schema::locker::customer_id.eq(pk.customer_id.clone()))
+                    .and(crate::storage::schema::locker::version.lt(updated.version)),
+            )
+            .set((
+                update.clone(),
+                crate::storage::schema::locker::version.eq(updated.version),
+            ));
+
+        let applied_check = crate::storage::schema::locker::table
+            .filter(
+                crate::storage::schema::locker::locker_id
+                    .eq(pk.locker_id.peek().clone())
+                    .and(crate::storage::schema::locker::merchant_id.eq(pk.merchant_id.clone()))
+                    .and(crate::storage::schema::locker::customer_id.eq(pk.customer_id.clone()))
+                    .and(crate::storage::schema::locker::version.ge(updated.version)),
+            )
+            .select(crate::storage::schema::locker::version);
+
+        generate_update_query::<_, _, Self::DieselEntity>(query, applied_check)
+    }
+
+    fn apply_update(update: Self::DieselUpdate, current: Self::DieselEntity) -> Self::DieselEntity {
+        LockerInner::from_update(update, current)
+    }
+
+    async fn storage_update(
+        store: &Storage,
+        update: Self::DieselUpdate,
+        pk: Self::PrimaryKeyType,
+    ) -> Result<Self, ContainerError<VaultDBError>> {
+        let mut conn = store.get_conn().await?;
+
+        let query = diesel::update(LockerInner::table())
+            .filter(
+                crate::storage::schema::locker::locker_id
+                    .eq(pk.locker_id.peek().as_str())
+                    .and(crate::storage::schema::locker::merchant_id.eq(pk.merchant_id.as_str()))
+                    .and(crate::storage::schema::locker::customer_id.eq(pk.customer_id.as_str())),
+            )
+            .set((
+                update,
+                crate::storage::schema::locker::version
+                    .eq(crate::storage::schema::locker::version + 1),
+            ));
+
+        let pool = conn.pool();
+        let operation = DbOperation::Update;
+        crate::storage::log_db_query::<<LockerInner as HasTable>::Table, _>(
+            &query, operation, pool,
+        );
+
+        let output: LockerInner = crate::storage::record_db_query::<
+            <LockerInner as HasTable>::Table,
+            _,
+            _,
+            _,
+        >(query.get_result(conn.get_mut()), operation, pool)
+        .await?;
+
+        Ok(output.into())
+    }
+}
+
+impl KvConditionalUpdatableResource for Locker {
+    type Precondition = LockerPrecondition;
+
+    fn precondition_holds(current: &Self::DieselEntity, precondition: &Self::Precondition) -> bool {
+        current.satisfies(precondition)
+    }
+
+    fn expected_version(precondition: &Self::Precondition) -> i32 {
+        precondition.version
+    }
+
+    async fn storage_update_if(
+        store: &Storage,
+        update: Self::DieselUpdate,
+        pk: Self::PrimaryKeyType,
+        precondition: &Self::Precondition,
+    ) -> Result<Option<Self>, ContainerError<VaultDBError>> {
+        let mut conn = store.get_conn().await?;
+
+        let query = diesel::update(LockerInner::table())
+            .filter(
+                crate::storage::schema::locker::locker_id
+                    .eq(pk.locker_id.peek().as_str())
+                    .and(crate::storage::schema::locker::merchant_id.eq(pk.merchant_id.as_str()))
+                    .and(crate::storage::schema::locker::customer_id.eq(pk.customer_id.as_str()))
+                    .and(crate::storage::schema::locker::version.eq(precondition.version)),
+            )
+            .set((
+                update,
+                crate::storage::schema::locker::version
+                    .eq(crate::storage::schema::locker::version + 1),
+            ));
+
+        let pool = conn.pool();
+        let operation = DbOperation::Update;
+        crate::storage::log_db_query::<<LockerInner as HasTable>::Table, _>(
+            &query, operation, pool,
+        );
+
+        let output =
+            crate::storage::record_db_query_optional::<<LockerInner as HasTable>::Table, _, _, _>(
+                async {
+                    query
+                        .get_result::<LockerInner>(conn.get_mut())
+                        .await
+                        .optional()
+                },
+                operation,
+                pool,
+            )
+            .await?;
+
+        match output {
+            Some(locker) => Ok(Some(locker.into())),
+            None if crate::storage::db::locker_exists(
+                &mut conn,
+                pk.locker_id.peek(),
+                &pk.merchant_id,
+                &pk.customer_id,
+            )
+            .await? =>
+            {
+                Ok(None)
+            }
+            None => Err(VaultDBError::NotFoundError.into()),
+        }
+    }
+}
+
+impl KvReconcilableResource for Locker {
+    const SCAN_PATTERN: &'static str = "locker_*";
+
+    fn primary_key(entity: &LockerInner) -> Self::PrimaryKeyType {
+        entity.primary_key()
+    }
+
+    fn is_stale(redis: &LockerInner, postgres: &LockerInner) -> bool {
+        redis.is_newer_than(postgres)
+    }
+
+    fn to_diesel_new(entity: LockerInner) -> Self::DieselNew {
+        entity.into()
+    }
+}
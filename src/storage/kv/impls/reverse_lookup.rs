@@ -10,7 +10,8 @@ use crate::{
             entity::EntityType,
             partition_key::{KvStorePartition, PartitionKey},
             resource::{
-                self as kv_resource, KvDeletableResource, KvDeleteWithoutLookup, KvResource,
+                self as kv_resource, KvDeletableResource, KvDeleteWithoutLookup,
+                KvReconcilableResource, KvResource,
             },
             serializable_query::{SerializableQuery, generate_delete_query, generate_insert_query},
         },
@@ -141,3 +142,23 @@ impl KvDeletableResource for ReverseLookup {
 }
 
 impl KvDeleteWithoutLookup for ReverseLookup {}
+
+impl KvReconcilableResource for ReverseLookup {
+    const SCAN_PATTERN: &'static str = "reverse_lookup_*";
+
+    fn primary_key(entity: &Self) -> Self::PrimaryKeyType {
+        ReverseLookupPrimaryKey {
+            lookup_id: entity.lookup_id.clone(),
+        }
+    }
+
+    fn to_diesel_new(entity: Self) -> Self::DieselNew {
+        ReverseLookupNew {
+            lookup_id: entity.lookup_id,
+            secondary_key: entity.secondary_key,
+            partition_key: entity.partition_key,
+            source: entity.source,
+            updated_by: entity.updated_by,
+        }
+    }
+}
@@ -1,5 +1,8 @@
-use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, associations::HasTable};
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, associations::HasTable,
+};
 use diesel_async::RunQueryDsl;
+use hyperswitch_masking::PeekInterface;
 
 use crate::{
     error::{ContainerError, VaultDBError},
@@ -8,10 +11,12 @@ use crate::{
         kv::{
             StorageScheme,
             entity::EntityType,
+            index::ListIndexEntry,
             partition_key::{KvStorePartition, PartitionKey},
             resource::{
-                DirectInsert, GetPartitionKey, KvDeletableResource, KvDeleteWithoutLookup,
-                KvResource, KvUpdatableResource,
+                DirectInsert, GetPartitionKey, KvConditionalUpdatableResource, KvDeletableResource,
+                KvDeleteWithoutLookup, KvListableResource, KvReconcilableResource, KvResource,
+                KvUpdatableResource,
             },
             serializable_query::{
                 SerializableQuery, generate_delete_query, generate_insert_query,
@@ -19,7 +24,12 @@ use crate::{
             },
         },
         schema,
-        storage_v2::types::{Vault, VaultInner, VaultNewInner, VaultUpdate},
+        storage_v2::{
+            db::vault_exists,
+            types::{
+                Vault, VaultInner, VaultListFilter, VaultNewInner, VaultPrecondition, VaultUpdate,
+            },
+        },
     },
 };
 
@@ -121,6 +131,66 @@ impl KvResource for Vault {
         .await?;
         Ok(output)
     }
+
+    fn list_index(entity: &Self::DieselEntity) -> Option<ListIndexEntry> {
+        Some(entity.list_index())
+    }
+}
+
+impl KvConditionalUpdatableResource for Vault {
+    type Precondition = VaultPrecondition;
+
+    fn precondition_holds(current: &Self::DieselEntity, precondition: &Self::Precondition) -> bool {
+        current.satisfies(precondition)
+    }
+
+    fn expected_version(precondition: &Self::Precondition) -> i32 {
+        precondition.version
+    }
+
+    async fn storage_update_if(
+        store: &Storage,
+        update: Self::DieselUpdate,
+        pk: Self::PrimaryKeyType,
+        precondition: &Self::Precondition,
+    ) -> Result<Option<Self>, ContainerError<VaultDBError>> {
+        let mut conn = store.get_conn().await?;
+
+        let query = diesel::update(VaultInner::table())
+            .filter(
+                schema::vault::vault_id
+                    .eq(pk.vault_id.as_str())
+                    .and(schema::vault::entity_id.eq(pk.entity_id.as_str()))
+                    .and(schema::vault::version.eq(precondition.version)),
+            )
+            .set((
+                update,
+                schema::vault::version.eq(schema::vault::version + 1),
+            ));
+
+        let pool = conn.pool();
+        let operation = DbOperation::Update;
+        crate::storage::log_db_query::<<VaultInner as HasTable>::Table, _>(&query, operation, pool);
+
+        let output =
+            crate::storage::record_db_query_optional::<<VaultInner as HasTable>::Table, _, _, _>(
+                async {
+                    query
+                        .get_result::<VaultInner>(conn.get_mut())
+                        .await
+                        .optional()
+                },
+                operation,
+                pool,
+            )
+            .await?;
+
+        match output {
+            Some(vault) => Ok(Some(vault.into())),
+            None if vault_exists(&mut conn, &pk.vault_id, &pk.entity_id).await? => Ok(None),
+            None => Err(VaultDBError::NotFoundError.into()),
+        }
+    }
 }
 
 impl KvDeletableResource for Vault {
@@ -173,16 +243,31 @@ impl KvUpdatableResource for Vault {
     fn generate_update_drainer_query(
         update: &Self::DieselUpdate,
         pk: &Self::PrimaryKeyType,
+        updated: &Self::DieselEntity,
     ) -> error_stack::Result<SerializableQuery, crate::error::kv::KvError> {
+        // A replay finds the row at (or past) `updated.version` and leaves it alone.
         let query = diesel::update(crate::storage::schema::vault::table)
             .filter(
                 crate::storage::schema::vault::vault_id
                     .eq(pk.vault_id.clone())
-                    .and(crate::storage::schema::vault::entity_id.eq(pk.entity_id.clone())),
+                    .and(crate::storage::schema::vault::entity_id.eq(pk.entity_id.clone()))
+                    .and(crate::storage::schema::vault::version.lt(updated.version)),
             )
-            .set(update.clone());
+            .set((
+                update.clone(),
+                crate::storage::schema::vault::version.eq(updated.version),
+            ));
 
-        generate_update_query::<_, Self::DieselEntity>(query)
+        let applied_check = crate::storage::schema::vault::table
+            .filter(
+                crate::storage::schema::vault::vault_id
+                    .eq(pk.vault_id.clone())
+                    .and(crate::storage::schema::vault::entity_id.eq(pk.entity_id.clone()))
+                    .and(crate::storage::schema::vault::version.ge(updated.version)),
+            )
+            .select(crate::storage::schema::vault::version);
+
+        generate_update_query::<_, _, Self::DieselEntity>(query, applied_check)
     }
 
     fn apply_update(update: Self::DieselUpdate, current: Self::DieselEntity) -> Self::DieselEntity {
@@ -202,7 +287,10 @@ impl KvUpdatableResource for Vault {
                     .eq(pk.vault_id.as_str())
                     .and(schema::vault::entity_id.eq(pk.entity_id.as_str())),
             )
-            .set(update);
+            .set((
+                update,
+                schema::vault::version.eq(schema::vault::version + 1),
+            ));
 
         let pool = conn.pool();
         let operation = DbOperation::Update;
@@ -218,3 +306,131 @@ impl KvUpdatableResource for Vault {
         Ok(output.into())
     }
 }
+
+/// Index of the Redis copies of one entity's vault rows.
+fn list_index_key(entity_id: &str) -> String {
+    format!("list_index_vault_{entity_id}")
+}
+
+/// Index entry of the Redis copy of a row of `entity_id` created at `created_at`.
+pub(crate) fn list_index_entry(
+    entity_id: &str,
+    created_at: time::PrimitiveDateTime,
+) -> ListIndexEntry {
+    ListIndexEntry {
+        key: list_index_key(entity_id),
+        score: list_index_score(created_at),
+    }
+}
+
+/// Index score of a row created at `created_at`: microseconds since the epoch, exact in the
+/// double Redis keeps scores as.
+fn list_index_score(created_at: time::PrimitiveDateTime) -> i64 {
+    i64::try_from(created_at.assume_utc().unix_timestamp_nanos() / 1_000).unwrap_or(i64::MAX)
+}
+
+impl KvListableResource for Vault {
+    type ListFilter = VaultListFilter;
+
+    fn list_index_key(filter: &Self::ListFilter) -> String {
+        list_index_key(&filter.entity_id)
+    }
+
+    fn list_index_min_score(filter: &Self::ListFilter) -> Option<i64> {
+        // Rows sharing the cursor's timestamp are read too; `matches_filter` drops the ones
+        // at or before its `vault_id`.
+        filter
+            .after
+            .as_ref()
+            .map(|after| list_index_score(after.created_at))
+    }
+
+    fn matches_filter(resource: &Self, filter: &Self::ListFilter) -> bool {
+        let not_expired = resource
+            .expires_at
+            .is_none_or(|expires_at| expires_at > crate::utils::date_time::now());
+        let after_cursor = filter.after.as_ref().is_none_or(|after| {
+            (resource.created_at, resource.vault_id.peek().as_str())
+                > (after.created_at, after.vault_id.as_str())
+        });
+
+        resource.entity_id == filter.entity_id && not_expired && after_cursor
+    }
+
+    fn list_order(a: &Self, b: &Self) -> std::cmp::Ordering {
+        (a.created_at, a.vault_id.peek()).cmp(&(b.created_at, b.vault_id.peek()))
+    }
+
+    fn same_record(a: &Self, b: &Self) -> bool {
+        a.entity_id == b.entity_id && a.vault_id.peek() == b.vault_id.peek()
+    }
+
+    fn page_size(filter: &Self::ListFilter) -> usize {
+        filter.limit
+    }
+
+    fn merchant_id(filter: &Self::ListFilter) -> Option<&str> {
+        Some(&filter.entity_id)
+    }
+
+    async fn storage_list(
+        store: &Storage,
+        filter: &Self::ListFilter,
+    ) -> Result<Vec<Self>, ContainerError<VaultDBError>> {
+        let mut conn = store.route_conn().await?;
+
+        let mut query = VaultInner::table()
+            .filter(schema::vault::entity_id.eq(filter.entity_id.as_str()))
+            .filter(
+                schema::vault::expires_at
+                    .is_null()
+                    .or(schema::vault::expires_at.gt(crate::utils::date_time::now())),
+            )
+            .order((
+                schema::vault::created_at.asc(),
+                schema::vault::vault_id.asc(),
+            ))
+            .limit(i64::try_from(filter.limit).unwrap_or(i64::MAX))
+            .into_boxed();
+
+        if let Some(after) = &filter.after {
+            query = query.filter(
+                schema::vault::created_at
+                    .gt(after.created_at)
+                    .or(schema::vault::created_at
+                        .eq(after.created_at)
+                        .and(schema::vault::vault_id.gt(after.vault_id.as_str()))),
+            );
+        }
+
+        let pool = conn.pool();
+        let operation = DbOperation::Filter;
+        crate::storage::log_db_query::<<VaultInner as HasTable>::Table, _>(&query, operation, pool);
+
+        let output: Vec<VaultInner> = crate::storage::record_db_query::<
+            <VaultInner as HasTable>::Table,
+            _,
+            _,
+            _,
+        >(query.load(conn.get_mut()), operation, pool)
+        .await?;
+
+        Ok(output.into_iter().map(Into::into).collect())
+    }
+}
+
+impl KvReconcilableResource for Vault {
+    const SCAN_PATTERN: &'static str = "vault_*";
+
+    fn primary_key(entity: &VaultInner) -> Self::PrimaryKeyType {
+        entity.primary_key()
+    }
+
+    fn is_stale(redis: &VaultInner, postgres: &VaultInner) -> bool {
+        redis.is_newer_than(postgres)
+    }
+
+    fn to_diesel_new(entity: VaultInner) -> Self::DieselNew {
+        entity.into()
+    }
+}
@@ -0,0 +1,105 @@
+//! Per-parent sorted-set indexes over the Redis copies of listable records.
+//!
+//! Every member is the partition key of one record, scored by its place in the list order, so a
+//! page is read with a bounded `ZRANGEBYSCORE` instead of a `SCAN` over the keyspace. Members whose
+//! record has expired or been deleted are dropped lazily by the list that finds them gone.
+
+use hyperswitch_redis_interface::errors::RedisError;
+
+use super::{KvStoreContext, wrapper::BridgeRedis};
+use crate::storage::redis::prefixed_key;
+
+/// Add member `ARGV[2]` to `KEYS[1]` with score `ARGV[1]` and push the index expiry out to
+/// `ARGV[3]` seconds, the lifetime of the record just written.
+const ADD_SCRIPT: &str = r"
+redis.call('ZADD', KEYS[1], ARGV[1], ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+return 1
+";
+
+/// Up to `ARGV[3]` members of `KEYS[1]` scored at least `ARGV[1]`, skipping the first `ARGV[2]`.
+/// Members sharing a score come back in partition key order.
+const RANGE_SCRIPT: &str = r"
+return redis.call('ZRANGEBYSCORE', KEYS[1], ARGV[1], '+inf', 'LIMIT', ARGV[2], ARGV[3])
+";
+
+/// Remove every member in `ARGV` from `KEYS[1]`.
+const REMOVE_SCRIPT: &str = r"
+return redis.call('ZREM', KEYS[1], unpack(ARGV))
+";
+
+/// Index entry for the Redis copy of one record.
+#[derive(Debug)]
+pub(crate) struct ListIndexEntry {
+    /// Index shared by every record under the same parent.
+    pub key: String,
+    /// Place of the record in the list order; ties are broken by partition key.
+    pub score: i64,
+}
+
+pub(crate) async fn add(
+    store: &impl KvStoreContext,
+    entry: &ListIndexEntry,
+    member: &str,
+) -> error_stack::Result<(), RedisError> {
+    let redis_conn = store.get_redis_conn()?;
+
+    redis_conn
+        .evaluate_redis_script::<_, i64>(
+            ADD_SCRIPT,
+            vec![prefixed_key(&redis_conn, &entry.key)],
+            vec![
+                entry.score.to_string(),
+                member.to_owned(),
+                store.ttl_for_kv().to_string(),
+            ],
+        )
+        .await
+        .bridge()?;
+
+    Ok(())
+}
+
+/// Read `count` members of `key` scored at least `min_score`, after skipping `offset` of them.
+pub(crate) async fn range(
+    store: &impl KvStoreContext,
+    key: &str,
+    min_score: Option<i64>,
+    offset: usize,
+    count: usize,
+) -> error_stack::Result<Vec<String>, RedisError> {
+    let redis_conn = store.get_redis_conn()?;
+    let min_score = min_score.map_or_else(|| "-inf".to_owned(), |score| score.to_string());
+
+    redis_conn
+        .evaluate_redis_script::<_, Vec<String>>(
+            RANGE_SCRIPT,
+            vec![prefixed_key(&redis_conn, key)],
+            vec![min_score, offset.to_string(), count.to_string()],
+        )
+        .await
+        .bridge()
+}
+
+pub(crate) async fn remove(
+    store: &impl KvStoreContext,
+    key: &str,
+    members: Vec<String>,
+) -> error_stack::Result<(), RedisError> {
+    if members.is_empty() {
+        return Ok(());
+    }
+
+    let redis_conn = store.get_redis_conn()?;
+
+    redis_conn
+        .evaluate_redis_script::<_, i64>(
+            REMOVE_SCRIPT,
+            vec![prefixed_key(&redis_conn, key)],
+            members,
+        )
+        .await
+        .bridge()?;
+
+    Ok(())
+}
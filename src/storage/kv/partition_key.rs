@@ -50,6 +50,20 @@ impl std::fmt::Display for PartitionKey<'_> {
     }
 }
 
+impl PartitionKey<'_> {
+    /// Merchant (v1) or entity (v2) id owning the record, used for per-merchant KV overrides.
+    pub(crate) fn merchant_id(&self) -> Option<&str> {
+        match self {
+            Self::Locker { merchant_id, .. } => Some(merchant_id),
+            Self::Vault { entity_id, .. } => Some(entity_id),
+            Self::CombinationKey { .. }
+            | Self::Fingerprint { .. }
+            | Self::HashTable { .. }
+            | Self::ReverseLookup { .. } => None,
+        }
+    }
+}
+
 /// Types that participate in KV sharding.
 pub(crate) trait KvStorePartition {
     fn partition_number(key: PartitionKey<'_>, num_partitions: u8) -> u32 {
@@ -9,7 +9,9 @@ use tracing::instrument;
 use super::{
     StorageScheme,
     entity::EntityType,
+    index::{self, ListIndexEntry},
     partition_key::{KvStorePartition, PartitionKey},
+    reconcile::ResourceDrift,
     scheme::KvState,
     serializable_query::SerializableQuery,
     wrapper::{KvOperation, KvResult, kv_wrapper},
@@ -34,6 +36,11 @@ pub(crate) trait GetPartitionKey {
 
 pub(crate) trait GetLookupKey {
     fn get_lookup_key(&self) -> ReverseLookupKey;
+
+    /// Merchant the looked-up record belongs to, when the lookup key carries it.
+    fn merchant_id(&self) -> Option<&str> {
+        None
+    }
 }
 
 pub(crate) struct DirectInsert;
@@ -118,6 +125,12 @@ pub(crate) trait KvResource:
         store: &Storage,
         pk: &Self::PrimaryKeyType,
     ) -> Result<Self::DieselEntity, ContainerError<Self::Error>>;
+
+    /// Index a Redis copy of this record is added to on every write, so `list_resources` can
+    /// find it before it is drained. Only listable resources keep one.
+    fn list_index(_entity: &Self::DieselEntity) -> Option<ListIndexEntry> {
+        None
+    }
 }
 
 /// Extension of `KvResource` for resources that support deletion by primary key.
@@ -155,16 +168,20 @@ pub(crate) trait KvDeletableWithLookup: KvDeletableResource {
 /// routed through Redis.
 pub(crate) trait KvUpdatableResource: KvResource {
     /// Diesel changeset/update type for this resource.
-    type DieselUpdate;
+    type DieselUpdate: Clone;
 
     /// Mark an update with the storage scheme selected for the operation.
     fn set_update_storage_scheme(diesel_update: &mut Self::DieselUpdate, scheme: StorageScheme);
 
     /// Build the UPDATE statement consumed by the drainer when Redis is the
     /// update path.
+    ///
+    /// `updated` is the merged entity written to Redis. The statement writes its absolute
+    /// version rather than incrementing the stored one, so replaying it is idempotent.
     fn generate_update_drainer_query(
         update: &Self::DieselUpdate,
         pk: &Self::PrimaryKeyType,
+        updated: &Self::DieselEntity,
     ) -> error_stack::Result<SerializableQuery, KvError>;
 
     /// Apply an update to the current Diesel entity stored in Redis.
@@ -181,6 +198,36 @@ pub(crate) trait KvUpdatableResource: KvResource {
     ) -> Result<Self, ContainerError<Self::Error>>;
 }
 
+/// Extension of `KvUpdatableResource` for compare-and-set updates.
+///
+/// The update is applied only while the stored record still satisfies the
+/// caller's `Precondition`, and fails with `KvError::Conflict` otherwise.
+/// Postgres evaluates the check inside the `UPDATE` statement itself. On the
+/// Redis path the row version of the stored copy is compared and the update
+/// written by a single script, so a write landing between the read and the
+/// `HSET` is never overwritten.
+pub(crate) trait KvConditionalUpdatableResource: KvUpdatableResource {
+    /// Snapshot of the stored state the caller computed its update from.
+    type Precondition;
+
+    /// Whether `current` still matches the snapshot.
+    fn precondition_holds(current: &Self::DieselEntity, precondition: &Self::Precondition) -> bool;
+
+    /// Row version the stored copy must still carry for the update to apply.
+    fn expected_version(precondition: &Self::Precondition) -> i32;
+
+    /// Conditionally update a record through the backing storage implementation.
+    ///
+    /// Returns `None` when the row exists but no longer satisfies the precondition, and a
+    /// not-found error when it is gone.
+    async fn storage_update_if(
+        store: &Storage,
+        update: Self::DieselUpdate,
+        pk: Self::PrimaryKeyType,
+        precondition: &Self::Precondition,
+    ) -> Result<Option<Self>, ContainerError<Self::Error>>;
+}
+
 /// Extension of `KvResource` for resources that support secondary-key lookups.
 ///
 /// `KvSecondaryLookupResource` is for resources whose Redis value is still
@@ -215,6 +262,71 @@ pub(crate) trait KvSecondaryLookupResource:
     ) -> Result<Self, ContainerError<Self::Error>>;
 }
 
+/// Extension of `KvResource` for resources that can be paged through by a parent key.
+///
+/// Postgres remains the source for each page. On the KV path, records that have
+/// not been drained yet are found through the per-parent index filled by
+/// `KvResource::list_index`, read back from Redis and merged into the Postgres
+/// page, with the Redis copy winning when both sides hold the same record.
+/// Deletes routed through Redis are only reflected once the drainer has replayed
+/// them.
+pub(crate) trait KvListableResource: KvResource {
+    /// Page request: parent key, keyset cursor and page size.
+    type ListFilter;
+
+    /// Index holding every Redis copy that can belong to the filter.
+    fn list_index_key(filter: &Self::ListFilter) -> String;
+
+    /// Lowest index score that can sit on the requested page; `None` reads from the start.
+    fn list_index_min_score(filter: &Self::ListFilter) -> Option<i64>;
+
+    /// Whether a record read from Redis belongs on the requested page, ignoring
+    /// the page size.
+    fn matches_filter(resource: &Self, filter: &Self::ListFilter) -> bool;
+
+    /// Page ordering; must agree with the `ORDER BY` used by `storage_list`.
+    fn list_order(a: &Self, b: &Self) -> std::cmp::Ordering;
+
+    /// Whether two records share a primary key.
+    fn same_record(a: &Self, b: &Self) -> bool;
+
+    /// Maximum number of records returned for the filter.
+    fn page_size(filter: &Self::ListFilter) -> usize;
+
+    /// Merchant every record on the page belongs to, when the filter pins one.
+    fn merchant_id(_filter: &Self::ListFilter) -> Option<&str> {
+        None
+    }
+
+    /// Read one page through the backing storage implementation.
+    async fn storage_list(
+        store: &Storage,
+        filter: &Self::ListFilter,
+    ) -> Result<Vec<Self>, ContainerError<Self::Error>>;
+}
+
+/// Extension of `KvResource` for resources whose Redis copies can be checked against Postgres.
+///
+/// Reconciliation walks every key matching `SCAN_PATTERN`, looks each record up in
+/// Postgres by the primary key rebuilt from its Redis copy and, when asked to, inserts
+/// the rows Postgres is missing from that copy.
+pub(crate) trait KvReconcilableResource: KvResource {
+    /// Redis glob covering every key of this resource.
+    const SCAN_PATTERN: &'static str;
+
+    /// Primary key of a record read back from Redis.
+    fn primary_key(entity: &Self::DieselEntity) -> Self::PrimaryKeyType;
+
+    /// Whether the Postgres row trails its Redis copy, i.e. an update is still undrained.
+    /// Tables without a row version cannot tell and never report stale rows.
+    fn is_stale(_redis: &Self::DieselEntity, _postgres: &Self::DieselEntity) -> bool {
+        false
+    }
+
+    /// Insertable record carrying everything in the Redis copy, used to replay a missing row.
+    fn to_diesel_new(entity: Self::DieselEntity) -> Self::DieselNew;
+}
+
 fn kv_backend_error<E>(report: Report<KvError>) -> ContainerError<E>
 where
     E: for<'a> From<&'a KvError> + error_stack::Context,
@@ -232,22 +344,59 @@ where
     }))
 }
 
-async fn decide_storage_scheme_for_find_operation(store: &Storage) -> StorageScheme {
-    let state = store.kv_settings().await;
-    match state {
+fn kv_conflict_error<E>(key: &str) -> ContainerError<E>
+where
+    E: for<'a> From<&'a KvError> + error_stack::Context,
+{
+    kv_backend_error::<E>(Report::new(KvError::Conflict {
+        key: key.to_string(),
+    }))
+}
+
+fn record_storage_scheme<M: KvResource>(
+    store: &Storage,
+    operation: &'static str,
+    state: KvState,
+    scheme: StorageScheme,
+) {
+    metrics::KV_STORAGE_SCHEME_COUNT.add(
+        1,
+        crate::metric_attributes!(
+            ("tenant_id", store.tenant_id().to_owned()),
+            ("resource", M::ENTITY_TYPE),
+            ("operation", operation),
+            ("kv_state", state),
+            ("scheme", scheme.to_string()),
+        ),
+    );
+}
+
+async fn decide_storage_scheme_for_find_operation<M: KvResource>(
+    store: &Storage,
+    merchant_id: Option<&str>,
+) -> StorageScheme {
+    let state = store.kv_settings(merchant_id).await;
+    let scheme = match state {
         KvState::Disabled => StorageScheme::PostgresOnly,
         // in softkill mode as well, always attempt RedisKv and fallback to postgres.
         KvState::Enabled | KvState::SoftKill => StorageScheme::RedisKv,
-    }
+    };
+    record_storage_scheme::<M>(store, "find", state, scheme);
+    scheme
 }
 
-async fn decide_storage_scheme_for_insert_operation(store: &Storage) -> StorageScheme {
-    let state = store.kv_settings().await;
-    match state {
+async fn decide_storage_scheme_for_insert_operation<M: KvResource>(
+    store: &Storage,
+    merchant_id: Option<&str>,
+) -> StorageScheme {
+    let state = store.kv_settings(merchant_id).await;
+    let scheme = match state {
         // in disabled and softkill mode, always push new inserts to PG
         KvState::Disabled | KvState::SoftKill => StorageScheme::PostgresOnly,
         KvState::Enabled => StorageScheme::RedisKv,
-    }
+    };
+    record_storage_scheme::<M>(store, "insert", state, scheme);
+    scheme
 }
 
 /// Call this to decide storage scheme for Update and Delete operations
@@ -258,11 +407,17 @@ async fn decide_storage_scheme_for_mutate_operation<M>(
 where
     M: KvResource,
 {
-    let state = store.kv_settings().await;
+    let state = store.kv_settings(partition_key.merchant_id()).await;
 
     match state {
-        KvState::Disabled => Ok((StorageScheme::PostgresOnly, None)),
-        KvState::Enabled => Ok((StorageScheme::RedisKv, None)),
+        KvState::Disabled => {
+            record_storage_scheme::<M>(store, "mutate", state, StorageScheme::PostgresOnly);
+            Ok((StorageScheme::PostgresOnly, None))
+        }
+        KvState::Enabled => {
+            record_storage_scheme::<M>(store, "mutate", state, StorageScheme::RedisKv);
+            Ok((StorageScheme::RedisKv, None))
+        }
         KvState::SoftKill => {
             // With this implementation, Hot keys may never recover out of KV.
             let partition_key_str = partition_key.to_string();
@@ -275,10 +430,14 @@ where
 
             match result {
                 // return the found redis item so that if the caller is doing update operation, updates can be applied.
-                Ok(KvResult::HGet(v)) => Ok((StorageScheme::RedisKv, Some(v))),
+                Ok(KvResult::HGet(v)) => {
+                    record_storage_scheme::<M>(store, "mutate", state, StorageScheme::RedisKv);
+                    Ok((StorageScheme::RedisKv, Some(v)))
+                }
                 Err(e) if matches!(e.current_context(), RedisError::NotFound) => {
                     crate::observability::metrics::KV_CACHE_MISS_COUNT
                         .add(1, crate::metric_attributes![("resource", M::ENTITY_TYPE)]);
+                    record_storage_scheme::<M>(store, "mutate", state, StorageScheme::PostgresOnly);
                     Ok((StorageScheme::PostgresOnly, None))
                 }
                 Err(e) => Err(kv_backend_error::<M::Error>(
@@ -288,6 +447,10 @@ where
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected HSetNx result for an HGet operation"),
                 )),
+                Ok(KvResult::HSetIfVersion(_)) => Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected HSetIfVersion result for an HGet operation"),
+                )),
                 Ok(KvResult::Hset(_)) => Err(kv_backend_error::<M::Error>(
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected Hset result for an HGet operation"),
@@ -296,11 +459,36 @@ where
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected HDel result for an HGet operation"),
                 )),
+                Ok(KvResult::Scan(_)) => Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected Scan result for an HGet operation"),
+                )),
             }
         }
     }
 }
 
+/// Add a record just written to Redis to its listing index. Every KV write indexes the record,
+/// so rows that moved into Redis through an update are listed from their KV copy as well.
+async fn index_for_listing<M: KvResource>(
+    store: &Storage,
+    entry: Option<ListIndexEntry>,
+    partition_key: &str,
+) {
+    let Some(entry) = entry else { return };
+
+    // The record is already in Redis and queued for the drainer; until it is drained it is
+    // only missing from listings.
+    if let Err(error) = index::add(store, &entry, partition_key).await {
+        crate::logger::error!(
+            ?error,
+            index = %entry.key,
+            resource = M::ENTITY_TYPE,
+            "Failed to index KV record for listing"
+        );
+    }
+}
+
 async fn insert_resource_inner<M, F>(
     store: &Storage,
     mut diesel_new: M::DieselNew,
@@ -311,7 +499,8 @@ where
     M: KvResource,
     F: FnOnce(&M::DieselNew, &PartitionKey<'_>) -> Option<ReverseLookupKey>,
 {
-    let scheme = decide_storage_scheme_for_insert_operation(store).await;
+    let scheme =
+        decide_storage_scheme_for_insert_operation::<M>(store, partition_key.merchant_id()).await;
     M::set_storage_scheme(&mut diesel_new, scheme);
 
     match scheme {
@@ -352,7 +541,15 @@ where
             })?;
 
             match reply.try_into_hsetnx() {
-                Ok(HsetnxReply::KeySet) => Ok(diesel_entity),
+                Ok(HsetnxReply::KeySet) => {
+                    index_for_listing::<M>(
+                        store,
+                        M::list_index(&diesel_entity),
+                        &partition_key_str,
+                    )
+                    .await;
+                    Ok(diesel_entity)
+                }
                 Ok(HsetnxReply::KeyNotSet) => {
                     Err(kv_duplicate_error::<M::Error>(&partition_key_str))
                 }
@@ -413,7 +610,7 @@ where
     M: KvResource,
 {
     let key = primary_key.get_partition_key();
-    let scheme = decide_storage_scheme_for_find_operation(store).await;
+    let scheme = decide_storage_scheme_for_find_operation::<M>(store, key.merchant_id()).await;
 
     match scheme {
         StorageScheme::PostgresOnly => M::storage_find(store, &primary_key).await,
@@ -442,6 +639,10 @@ where
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected HSetNx result for an HGet operation"),
                 )),
+                Ok(KvResult::HSetIfVersion(_)) => Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected HSetIfVersion result for an HGet operation"),
+                )),
                 Ok(KvResult::Hset(_)) => Err(kv_backend_error::<M::Error>(
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected Hset result for an HGet operation"),
@@ -450,6 +651,10 @@ where
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected HDel result for an HGet operation"),
                 )),
+                Ok(KvResult::Scan(_)) => Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected Scan result for an HGet operation"),
+                )),
             }
         }
     }
@@ -479,7 +684,8 @@ pub(crate) async fn find_resource_by_lookup_id<M>(
 where
     M: KvSecondaryLookupResource,
 {
-    let scheme = decide_storage_scheme_for_find_operation(store).await;
+    let scheme =
+        decide_storage_scheme_for_find_operation::<M>(store, lookup_key.merchant_id()).await;
     let lookup_id = lookup_key.get_lookup_key();
     match scheme {
         StorageScheme::PostgresOnly => M::storage_find_by_lookup(store, &lookup_key).await,
@@ -528,6 +734,10 @@ where
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected HSetNx result for an HGet operation"),
                 )),
+                Ok(KvResult::HSetIfVersion(_)) => Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected HSetIfVersion result for an HGet operation"),
+                )),
                 Ok(KvResult::Hset(_)) => Err(kv_backend_error::<M::Error>(
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected Hset result for an HGet operation"),
@@ -536,6 +746,10 @@ where
                     Report::new(KvError::Backend)
                         .attach_printable("unexpected HDel result for an HGet operation"),
                 )),
+                Ok(KvResult::Scan(_)) => Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected Scan result for an HGet operation"),
+                )),
             }
         }
     }
@@ -576,34 +790,122 @@ where
     match scheme {
         StorageScheme::PostgresOnly => M::storage_update(store, update, primary_key).await,
         StorageScheme::RedisKv => {
-            let key = primary_key.get_partition_key();
             let current = match cached {
                 Some(resource) => resource,
                 None => find_resource_by_id_inner::<M>(store, primary_key.clone()).await?,
             };
-            let update_query = M::generate_update_drainer_query(&update, &primary_key)
-                .map_err(kv_backend_error::<M::Error>)?;
-            let updated_model = M::apply_update(update, current);
+
+            write_update_to_redis::<M>(store, update, &primary_key, current).await
+        }
+    }
+}
+
+/// Compare-and-set variant of `update_resource_by_id`; a failed precondition surfaces
+/// as `KvError::Conflict` converted into the resource error, a missing record as not found.
+#[instrument(skip(store, update, primary_key, precondition), fields(resource = M::ENTITY_TYPE))]
+pub(crate) async fn conditional_update_resource_by_id<M>(
+    store: &Storage,
+    mut update: M::DieselUpdate,
+    primary_key: M::PrimaryKeyType,
+    precondition: M::Precondition,
+) -> Result<M, ContainerError<M::Error>>
+where
+    M: KvConditionalUpdatableResource,
+    M::PrimaryKeyType: Clone,
+    M::DieselEntity: Clone,
+{
+    let (scheme, cached) = {
+        let key = primary_key.get_partition_key();
+        decide_storage_scheme_for_mutate_operation::<M>(store, &key).await?
+    };
+    M::set_update_storage_scheme(&mut update, scheme);
+    let key_str = primary_key.get_partition_key().to_string();
+
+    match scheme {
+        StorageScheme::PostgresOnly => {
+            M::storage_update_if(store, update, primary_key, &precondition)
+                .await?
+                .ok_or_else(|| kv_conflict_error::<M::Error>(&key_str))
+        }
+        StorageScheme::RedisKv => {
+            let current = match cached {
+                Some(resource) => resource,
+                None => find_resource_by_id_inner::<M>(store, primary_key.clone()).await?,
+            };
+
+            if !M::precondition_holds(&current, &precondition) {
+                return Err(kv_conflict_error::<M::Error>(&key_str));
+            }
+
+            let key = primary_key.get_partition_key();
+            let updated_model = M::apply_update(update.clone(), current);
+            let update_query =
+                M::generate_update_drainer_query(&update, &primary_key, &updated_model)
+                    .map_err(kv_backend_error::<M::Error>)?;
             let updated_resource = updated_model.clone().into();
+            let list_entry = M::list_index(&updated_model);
 
-            let key_str = key.to_string();
-            kv_wrapper::<(), M::DieselEntity>(
+            let written = kv_wrapper::<(), M::DieselEntity>(
                 store,
-                KvOperation::<M::DieselEntity>::Hset((&key_str, updated_model), update_query),
+                KvOperation::<M::DieselEntity>::HSetIfVersion(
+                    (&key_str, updated_model),
+                    M::expected_version(&precondition),
+                    update_query,
+                ),
                 key.clone(),
             )
             .await
             .map_err(|e| kv_backend_error::<M::Error>(e.to_redis_failed_response(&key_str)))?
-            .try_into_hset()
+            .try_into_hset_if_version()
             .map_err(|e| {
                 kv_backend_error::<M::Error>(Report::new(e).change_context(KvError::Backend))
             })?;
 
+            if !written {
+                return Err(kv_conflict_error::<M::Error>(&key_str));
+            }
+
+            index_for_listing::<M>(store, list_entry, &key_str).await;
+
             Ok(updated_resource)
         }
     }
 }
 
+/// Merge `update` into `current`, write it back with `HSET` and queue the drainer query.
+async fn write_update_to_redis<M>(
+    store: &Storage,
+    update: M::DieselUpdate,
+    primary_key: &M::PrimaryKeyType,
+    current: M::DieselEntity,
+) -> Result<M, ContainerError<M::Error>>
+where
+    M: KvUpdatableResource,
+    M::DieselEntity: Clone,
+{
+    let key = primary_key.get_partition_key();
+    let updated_model = M::apply_update(update.clone(), current);
+    let update_query = M::generate_update_drainer_query(&update, primary_key, &updated_model)
+        .map_err(kv_backend_error::<M::Error>)?;
+    let updated_resource = updated_model.clone().into();
+    let list_entry = M::list_index(&updated_model);
+
+    let key_str = key.to_string();
+    kv_wrapper::<(), M::DieselEntity>(
+        store,
+        KvOperation::<M::DieselEntity>::Hset((&key_str, updated_model), update_query),
+        key.clone(),
+    )
+    .await
+    .map_err(|e| kv_backend_error::<M::Error>(e.to_redis_failed_response(&key_str)))?
+    .try_into_hset()
+    .map_err(|e| kv_backend_error::<M::Error>(Report::new(e).change_context(KvError::Backend)))?;
+
+    index_for_listing::<M>(store, list_entry, &key_str).await;
+
+    Ok(updated_resource)
+}
+
 #[instrument(skip(store, primary_key), fields(resource = M::ENTITY_TYPE))]
 async fn delete_resource_by_id_inner<M>(
     store: &Storage,
@@ -695,3 +997,212 @@ where
         Err(err) => Err(err),
     }
 }
+
+/// Page through a listable resource. `PostgresOnly` → `storage_list`; otherwise the
+/// Postgres page is merged with matching Redis records (see `KvListableResource`).
+#[instrument(skip(store, filter), fields(resource = M::ENTITY_TYPE))]
+pub(crate) async fn list_resources<M>(
+    store: &Storage,
+    filter: M::ListFilter,
+) -> Result<Vec<M>, ContainerError<M::Error>>
+where
+    M: KvListableResource,
+{
+    let scheme =
+        decide_storage_scheme_for_find_operation::<M>(store, M::merchant_id(&filter)).await;
+    let mut page = M::storage_list(store, &filter).await?;
+
+    if matches!(scheme, StorageScheme::PostgresOnly) {
+        return Ok(page);
+    }
+
+    let index_key = M::list_index_key(&filter);
+    let min_score = M::list_index_min_score(&filter);
+    let page_size = M::page_size(&filter);
+    let mut offset = 0;
+    let mut found = 0;
+    let mut gone = Vec::new();
+
+    // Walk the index in list order until a page of matching records is found or it runs out.
+    while found < page_size {
+        let members = index::range(store, &index_key, min_score, offset, page_size)
+            .await
+            .map_err(|e| kv_backend_error::<M::Error>(e.to_redis_failed_response(&index_key)))?;
+        offset += members.len();
+        let exhausted = members.len() < page_size;
+
+        for key in members {
+            let result = kv_wrapper::<M::DieselEntity, M::DieselEntity>(
+                store,
+                KvOperation::<M::DieselEntity>::HGet(&key),
+                PartitionKey::CombinationKey { combination: &key },
+            )
+            .await;
+
+            let resource: M = match result {
+                Ok(KvResult::HGet(v)) => v.into(),
+                // Expired or deleted since it was indexed.
+                Err(e) if matches!(e.current_context(), RedisError::NotFound) => {
+                    gone.push(key);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(kv_backend_error::<M::Error>(
+                        e.to_redis_failed_response(&key),
+                    ));
+                }
+                Ok(KvResult::HSetNx(_)) => {
+                    return Err(kv_backend_error::<M::Error>(
+                        Report::new(KvError::Backend)
+                            .attach_printable("unexpected HSetNx result for an HGet operation"),
+                    ));
+                }
+                Ok(KvResult::HSetIfVersion(_)) => {
+                    return Err(kv_backend_error::<M::Error>(
+                        Report::new(KvError::Backend).attach_printable(
+                            "unexpected HSetIfVersion result for an HGet operation",
+                        ),
+                    ));
+                }
+                Ok(KvResult::Hset(_)) => {
+                    return Err(kv_backend_error::<M::Error>(
+                        Report::new(KvError::Backend)
+                            .attach_printable("unexpected Hset result for an HGet operation"),
+                    ));
+                }
+                Ok(KvResult::HDel(_)) => {
+                    return Err(kv_backend_error::<M::Error>(
+                        Report::new(KvError::Backend)
+                            .attach_printable("unexpected HDel result for an HGet operation"),
+                    ));
+                }
+                Ok(KvResult::Scan(_)) => {
+                    return Err(kv_backend_error::<M::Error>(
+                        Report::new(KvError::Backend)
+                            .attach_printable("unexpected Scan result for an HGet operation"),
+                    ));
+                }
+            };
+
+            if !M::matches_filter(&resource, &filter) {
+                continue;
+            }
+
+            found += 1;
+            page.retain(|existing| !M::same_record(existing, &resource));
+            page.push(resource);
+        }
+
+        if exhausted {
+            break;
+        }
+    }
+
+    if let Err(error) = index::remove(store, &index_key, gone).await {
+        crate::logger::warn!(?error, index = %index_key, "Failed to prune KV list index");
+    }
+
+    page.sort_by(M::list_order);
+    page.truncate(M::page_size(&filter));
+
+    Ok(page)
+}
+
+/// Compare every Redis record of `M` with Postgres. With `replay`, rows missing from
+/// Postgres are inserted from their Redis copy; rows that only trail Redis are reported but
+/// left to the drainer.
+#[instrument(skip(store), fields(resource = M::ENTITY_TYPE))]
+pub(crate) async fn reconcile_resources<M>(
+    store: &Storage,
+    replay: bool,
+) -> Result<ResourceDrift, ContainerError<M::Error>>
+where
+    M: KvReconcilableResource,
+{
+    let pattern = M::SCAN_PATTERN;
+    let keys = kv_wrapper::<M::DieselEntity, M::DieselEntity>(
+        store,
+        KvOperation::<M::DieselEntity>::Scan(pattern),
+        PartitionKey::CombinationKey {
+            combination: pattern,
+        },
+    )
+    .await
+    .map_err(|e| kv_backend_error::<M::Error>(e.to_redis_failed_response(pattern)))?
+    .try_into_scan()
+    .map_err(|e| kv_backend_error::<M::Error>(Report::new(e).change_context(KvError::Backend)))?;
+
+    let mut drift = ResourceDrift::new(M::ENTITY_TYPE);
+
+    for key in keys {
+        let result = kv_wrapper::<M::DieselEntity, M::DieselEntity>(
+            store,
+            KvOperation::<M::DieselEntity>::HGet(&key),
+            PartitionKey::CombinationKey { combination: &key },
+        )
+        .await;
+
+        let entity = match result {
+            Ok(KvResult::HGet(v)) => v,
+            // Expired or deleted between the SCAN and the read.
+            Err(e) if matches!(e.current_context(), RedisError::NotFound) => continue,
+            Err(e) => {
+                return Err(kv_backend_error::<M::Error>(
+                    e.to_redis_failed_response(&key),
+                ));
+            }
+            Ok(KvResult::HSetNx(_)) => {
+                return Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected HSetNx result for an HGet operation"),
+                ));
+            }
+            Ok(KvResult::HSetIfVersion(_)) => {
+                return Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected HSetIfVersion result for an HGet operation"),
+                ));
+            }
+            Ok(KvResult::Hset(_)) => {
+                return Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected Hset result for an HGet operation"),
+                ));
+            }
+            Ok(KvResult::HDel(_)) => {
+                return Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected HDel result for an HGet operation"),
+                ));
+            }
+            Ok(KvResult::Scan(_)) => {
+                return Err(kv_backend_error::<M::Error>(
+                    Report::new(KvError::Backend)
+                        .attach_printable("unexpected Scan result for an HGet operation"),
+                ));
+            }
+        };
+
+        drift.scanned += 1;
+
+        match M::storage_find(store, &M::primary_key(&entity)).await {
+            Ok(row) if M::is_stale(&entity, &row) => drift.stale.push(key),
+            Ok(_) => drift.in_sync += 1,
+            Err(err) if err.get_inner().is_not_found() && replay => {
+                match M::storage_insert(M::to_diesel_new(entity), store).await {
+                    Ok(_) => drift.replayed.push(key),
+                    // The drainer got there between the read and the insert.
+                    Err(err) if err.get_inner().is_duplicate() => drift.in_sync += 1,
+                    Err(err) => {
+                        crate::logger::error!(?err, key, "Failed to replay missing row");
+                        drift.missing.push(key);
+                    }
+                }
+            }
+            Err(err) if err.get_inner().is_not_found() => drift.missing.push(key),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(drift)
+}
@@ -1,8 +1,21 @@
+use rustc_hash::FxHashMap;
+
 /// Tri-state KV master switch.
 ///
 /// `ttl_for_kv` must exceed max drainer replay lag — otherwise a KV-only
 /// fingerprint can expire in Redis before reaching Postgres.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, strum::Display)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Deserialize,
+    serde::Serialize,
+    strum::Display,
+    strum::IntoStaticStr,
+)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub(crate) enum KvState {
@@ -13,3 +26,25 @@ pub(crate) enum KvState {
     /// Insert to Postgres only; reads prefer Redis.
     SoftKill,
 }
+
+crate::impl_metric_value_from!(KvState);
+
+/// KV rollout override for one tenant. Unset levels fall back to the next broader one.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub(crate) struct KvOverride {
+    /// Replaces the global `enable_kv` for the whole tenant.
+    #[serde(default)]
+    pub enable_kv: Option<KvState>,
+    /// Replaces the tenant state for one merchant (v1) or entity (v2) id.
+    #[serde(default)]
+    pub merchants: FxHashMap<String, KvState>,
+}
+
+impl KvOverride {
+    pub(crate) fn resolve(&self, merchant_id: Option<&str>, global: KvState) -> KvState {
+        merchant_id
+            .and_then(|merchant_id| self.merchants.get(merchant_id).copied())
+            .or(self.enable_kv)
+            .unwrap_or(global)
+    }
+}
@@ -59,8 +59,10 @@ mod pg_type_metadata {
     }
 }
 
+use std::io::Write;
+
 use diesel::{
-    Insertable,
+    Insertable, QueryResult,
     associations::HasTable,
     debug_query,
     pg::Pg,
@@ -68,9 +70,12 @@ use diesel::{
         InsertStatement, QueryBuilder, QueryFragment, bind_collector::RawBytesBindCollector,
     },
     query_source::Table,
+    serialize::{IsNull, Output, ToSql},
+    sql_types::Nullable,
 };
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use error_stack::ResultExt;
-use hyperswitch_masking::Secret;
+use hyperswitch_masking::{PeekInterface, Secret};
 use tracing::debug;
 
 use super::entity::EntityType;
@@ -95,6 +100,24 @@ impl diesel::pg::PgMetadataLookup for KvPgMetadataLookup {
     }
 }
 
+/// SQL type for replayed binds. OID 0 leaves each parameter's type to the server, which infers
+/// it from the statement, so the stored binary values decode as their original column types
+/// (including custom types recorded with [`FAKE_OID`]).
+#[derive(diesel::sql_types::SqlType, diesel::query_builder::QueryId)]
+#[diesel(postgres_type(oid = 0, array_oid = 0))]
+struct Inferred;
+
+/// A bind value already encoded in Postgres binary format when the query was built.
+#[derive(Debug)]
+struct RawBind(SecretBinaryData);
+
+impl ToSql<Inferred, Pg> for RawBind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> diesel::serialize::Result {
+        out.write_all(self.0.peek())?;
+        Ok(IsNull::No)
+    }
+}
+
 /// SQL query and bind parameters in a serializable representation.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub(crate) struct SerializableQuery {
@@ -111,6 +134,11 @@ pub(crate) struct SerializableQuery {
     entity_type: String,
 
     operation: DatabaseOperation,
+
+    /// Statement selecting the row only once this one has been applied, checked when a replay
+    /// affects no rows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    applied_check: Option<Box<SerializableQuery>>,
 }
 
 #[derive(
@@ -133,6 +161,26 @@ impl SerializableQuery {
         self.operation
     }
 
+    /// Replay the statement on `conn`, returning the number of affected rows.
+    pub(crate) async fn execute(&self, conn: &mut AsyncPgConnection) -> QueryResult<usize> {
+        let query = self.binds.iter().cloned().fold(
+            diesel::sql_query(self.sql.as_str()).into_boxed::<Pg>(),
+            |query, bind| query.bind::<Nullable<Inferred>, _>(bind.map(RawBind)),
+        );
+
+        query.execute(conn).await
+    }
+
+    /// Whether the row already holds what the statement writes. Only statements built with an
+    /// applied check can tell; the others report `false`.
+    pub(crate) async fn is_applied(&self, conn: &mut AsyncPgConnection) -> QueryResult<bool> {
+        match &self.applied_check {
+            // For a `SELECT` the affected row count is the number of rows returned.
+            Some(check) => Ok(check.execute(conn).await? > 0),
+            None => Ok(false),
+        }
+    }
+
     /// Construct a `SerializableQuery` from any diesel query fragment.
     fn from_query<Q>(
         query: Q,
@@ -176,6 +224,7 @@ impl SerializableQuery {
             safe_to_cache_prepared,
             entity_type,
             operation,
+            applied_check: None,
         };
 
         Ok(serializable_query)
@@ -225,14 +274,28 @@ where
         .attach_printable("Failed to generate delete query")
 }
 
-pub(crate) fn generate_update_query<Q, N>(
+/// Serialize an update together with `applied_check`, a `SELECT` matching the row once the
+/// update has landed, so the drainer can tell a replayed update from one whose row is missing.
+pub(crate) fn generate_update_query<Q, C, N>(
     query: Q,
+    applied_check: C,
 ) -> error_stack::Result<SerializableQuery, KvError>
 where
     N: EntityType,
     Q: QueryFragment<Pg> + Send + 'static,
+    C: QueryFragment<Pg> + Send + 'static,
 {
     let entity_type = N::ENTITY_TYPE.to_owned();
-    SerializableQuery::from_query(query, entity_type, DatabaseOperation::Update)
-        .attach_printable("Failed to generate update query")
+    let applied_check = SerializableQuery::from_query(
+        applied_check,
+        entity_type.clone(),
+        DatabaseOperation::Update,
+    )
+    .attach_printable("Failed to generate applied check query")?;
+
+    let mut query = SerializableQuery::from_query(query, entity_type, DatabaseOperation::Update)
+        .attach_printable("Failed to generate update query")?;
+    query.applied_check = Some(Box::new(applied_check));
+
+    Ok(query)
 }
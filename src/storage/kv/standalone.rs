@@ -0,0 +1,86 @@
+//! Connections for the KV tools that run outside the locker (the drainer, reconciliation).
+//! They have no app state to borrow storage from, so they build their own per tenant.
+
+use std::sync::Arc;
+
+use error_stack::ResultExt;
+
+use crate::{
+    config::{GlobalConfig, TenantConfig},
+    error::ConfigurationError,
+    runtime_config::RuntimeConfigManager,
+    storage::{Storage, redis::RedisStore},
+};
+
+pub(crate) struct StandaloneContext {
+    redis: RedisStore,
+    runtime_config_manager: Arc<RuntimeConfigManager>,
+}
+
+impl StandaloneContext {
+    pub(crate) async fn connect(
+        global_config: &GlobalConfig,
+    ) -> error_stack::Result<Self, ConfigurationError> {
+        let redis_settings = global_config.redis.as_ref().ok_or_else(|| {
+            ConfigurationError::InvalidConfigurationValueError(
+                "KV tools require a [redis] section".into(),
+            )
+        })?;
+
+        let redis = RedisStore::new(redis_settings).await.change_context(
+            ConfigurationError::InvalidConfigurationValueError("failed to connect to redis".into()),
+        )?;
+        redis.spawn_error_watcher();
+
+        // Only needed to construct `Storage`; without a replica pool every query goes to the
+        // primary whatever the runtime config says.
+        let runtime_config_manager = Arc::new(
+            RuntimeConfigManager::new(
+                &global_config.runtime_config,
+                global_config.api_client.client_idle_timeout,
+                global_config.api_client.pool_max_idle_per_host,
+            )
+            .change_context(ConfigurationError::InvalidConfigurationValueError(
+                "failed to create runtime config manager".into(),
+            ))?,
+        );
+
+        Ok(Self {
+            redis,
+            runtime_config_manager,
+        })
+    }
+
+    /// Primary-only storage for `tenant_id`, together with its key-prefixed Redis handle.
+    pub(crate) async fn tenant_storage(
+        &self,
+        global_config: &GlobalConfig,
+        tenant_id: &str,
+    ) -> error_stack::Result<(Storage, RedisStore), ConfigurationError> {
+        if !global_config.tenant_secrets.contains_key(tenant_id) {
+            return Err(ConfigurationError::InvalidConfigurationValueError(format!(
+                "unknown tenant `{tenant_id}`"
+            ))
+            .into());
+        }
+
+        let tenant_config = TenantConfig::from_global_config(global_config, tenant_id.to_owned());
+        let tenant_redis = self
+            .redis
+            .clone_with_prefix(tenant_config.redis_key_prefix.trim());
+
+        let storage = Storage::new(
+            &global_config.database,
+            None,
+            &tenant_config.tenant_secrets.schema,
+            self.runtime_config_manager.clone(),
+            tenant_id,
+            Some(tenant_redis.clone()),
+            &global_config.kv,
+        )
+        .await
+        .change_context(ConfigurationError::DatabaseError)?;
+
+        Ok((storage, tenant_redis))
+    }
+}
@@ -13,11 +13,35 @@ use super::{
     partition_key::{KvStorePartition, PartitionKey},
     serializable_query::SerializableQuery,
 };
-use crate::{logger, observability::metrics};
+use crate::{
+    logger,
+    observability::metrics,
+    storage::{consts, redis::prefixed_key},
+};
 
 /// Drainer-entry `request_id`: log-only, empty (not threaded), kept for wire-format parity.
 const REQUEST_ID: &str = "VAULT_CONSTANT_REQUEST_ID";
 
+/// Write `ARGV[4]` to field `ARGV[1]` of `KEYS[1]` for `ARGV[5]` seconds unless the copy stored
+/// there carries a row version other than `ARGV[2]`; copies written before rows were versioned
+/// count as version `ARGV[3]`. Returns whether it was written. Nothing stored counts as a match:
+/// callers check the copy they read from Postgres themselves.
+const HSET_IF_VERSION_SCRIPT: &str = r"
+local current = redis.call('HGET', KEYS[1], ARGV[1])
+if current then
+    local version = cjson.decode(current).version or tonumber(ARGV[3])
+    if version ~= tonumber(ARGV[2]) then
+        return 0
+    end
+end
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[4])
+redis.call('EXPIRE', KEYS[1], ARGV[5])
+return 1
+";
+
+/// `COUNT` hint for each `SCAN` round trip.
+const SCAN_COUNT: u32 = 1000;
+
 /// Provides access to the Redis connection pool.
 pub(crate) trait RedisConnInterface {
     fn get_redis_conn(&self) -> error_stack::Result<Arc<RedisConnectionPool>, RedisError>;
@@ -76,7 +100,7 @@ fn redis_error_from_ref(err: &RedisError) -> RedisError {
 }
 
 /// Bridges `error_stack` 0.4 `Report<RedisError>` → 0.5.
-trait BridgeRedis<T> {
+pub(crate) trait BridgeRedis<T> {
     fn bridge(self) -> error_stack::Result<T, RedisError>;
 }
 
@@ -93,8 +117,13 @@ impl<T> BridgeRedis<T> for Result<T, error_stack_04::Report<RedisError>> {
 pub(crate) enum KvOperation<'a, S: serde::Serialize + Debug> {
     Hset((&'a str, S), SerializableQuery),
     HSetNx(&'a str, &'a S, SerializableQuery),
+    /// `Hset` applied only while the stored copy is still at the given row version, checked and
+    /// written in one script.
+    HSetIfVersion((&'a str, S), i32, SerializableQuery),
     HGet(&'a str),
     HDel(&'a str, SerializableQuery),
+    /// Match keys against a glob; read-only, nothing is pushed to the drainer.
+    Scan(&'a str),
 }
 
 /// The result of a KV operation.
@@ -103,7 +132,9 @@ pub(crate) enum KvResult<T: de::DeserializeOwned> {
     HGet(T),
     Hset(()),
     HSetNx(HsetnxReply),
+    HSetIfVersion(bool),
     HDel(usize),
+    Scan(Vec<String>),
 }
 
 impl<T: de::DeserializeOwned> KvResult<T> {
@@ -121,12 +152,26 @@ impl<T: de::DeserializeOwned> KvResult<T> {
         }
     }
 
+    pub(crate) fn try_into_hset_if_version(self) -> Result<bool, RedisError> {
+        match self {
+            Self::HSetIfVersion(v) => Ok(v),
+            _ => Err(RedisError::UnknownResult),
+        }
+    }
+
     pub(crate) fn try_into_hdel(self) -> Result<usize, RedisError> {
         match self {
             Self::HDel(v) => Ok(v),
             _ => Err(RedisError::UnknownResult),
         }
     }
+
+    pub(crate) fn try_into_scan(self) -> Result<Vec<String>, RedisError> {
+        match self {
+            Self::Scan(v) => Ok(v),
+            _ => Err(RedisError::UnknownResult),
+        }
+    }
 }
 
 impl<T> std::fmt::Display for KvOperation<'_, T>
@@ -137,8 +182,10 @@ where
         match self {
             Self::Hset(_, _) => f.write_str("Hset"),
             Self::HSetNx(_, _, _) => f.write_str("HSetNx"),
+            Self::HSetIfVersion(_, _, _) => f.write_str("HSetIfVersion"),
             Self::HGet(_) => f.write_str("HGet"),
             Self::HDel(_, _) => f.write_str("HDel"),
+            Self::Scan(_) => f.write_str("Scan"),
         }
     }
 }
@@ -195,6 +242,32 @@ where
                 }
             }
 
+            KvOperation::HSetIfVersion((field, value), version, query) => {
+                let serialized = serde_json::to_string(&value)
+                    .change_context(RedisError::JsonSerializationFailed)?;
+
+                let written = redis_conn
+                    .evaluate_redis_script::<_, i64>(
+                        HSET_IF_VERSION_SCRIPT,
+                        vec![prefixed_key(&redis_conn, &key)],
+                        vec![
+                            field.to_owned(),
+                            version.to_string(),
+                            consts::INITIAL_ROW_VERSION.to_string(),
+                            serialized,
+                            ttl.to_string(),
+                        ],
+                    )
+                    .await
+                    .bridge()?
+                    == 1;
+
+                if written {
+                    push_to_drainer_stream::<S>(store, query, partition_key).await?;
+                }
+                Ok(KvResult::HSetIfVersion(written))
+            }
+
             KvOperation::HGet(field) => {
                 let result = redis_conn
                     .get_hash_field_and_deserialize(&key.into(), field, type_name)
@@ -212,6 +285,29 @@ where
                 push_to_drainer_stream::<S>(store, query, partition_key).await?;
                 Ok(KvResult::HDel(result))
             }
+
+            KvOperation::Scan(pattern) => {
+                let keys = redis_conn
+                    .scan(&pattern.into(), Some(SCAN_COUNT), None)
+                    .await
+                    .bridge()?;
+
+                // SCAN hands back fully-qualified keys; drop the tenant prefix so callers can
+                // feed them back through the prefixing helpers above.
+                let keys = keys
+                    .into_iter()
+                    .map(|key| match redis_conn.key_prefix.as_str() {
+                        "" => key,
+                        prefix => key
+                            .strip_prefix(prefix)
+                            .and_then(|rest| rest.strip_prefix(':'))
+                            .map(str::to_owned)
+                            .unwrap_or(key),
+                    })
+                    .collect();
+
+                Ok(KvResult::Scan(keys))
+            }
         }
     };
 
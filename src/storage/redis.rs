@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
-use hyperswitch_redis_interface::{RedisConnectionPool, RedisSettings, errors::RedisError};
+use hyperswitch_redis_interface::{
+    PubsubInterface, RedisConnectionPool, RedisSettings, errors::RedisError,
+};
 use tracing::Instrument;
 
 use crate::storage::consts;
@@ -10,6 +12,15 @@ fn into_report(err: impl std::fmt::Debug) -> error_stack::Report<RedisError> {
     error_stack::Report::new(RedisError::RedisConnectionError).attach_printable(format!("{err:?}"))
 }
 
+/// `key` as stored through `redis_conn`. Scripts receive raw key names, so every key handed to
+/// `EVAL` needs the prefix the typed commands add on their own.
+pub(crate) fn prefixed_key(redis_conn: &RedisConnectionPool, key: &str) -> String {
+    match redis_conn.key_prefix.as_str() {
+        "" => key.to_owned(),
+        prefix => format!("{prefix}:{key}"),
+    }
+}
+
 /// A shared `redis_interface` connection pool handle.
 #[derive(Clone)]
 pub struct RedisStore {
@@ -27,6 +38,9 @@ impl std::fmt::Debug for RedisStore {
 impl RedisStore {
     pub async fn new(conf: &RedisSettings) -> error_stack::Result<Self, RedisError> {
         let pool = RedisConnectionPool::new(conf).await.map_err(into_report)?;
+        // Restores the subscriptions of every `subscribe` call after reconnects; one task serves
+        // all of them.
+        pool.subscriber.manage_subscriptions();
         Ok(Self {
             redis_conn: Arc::new(pool),
         })
@@ -58,6 +72,110 @@ impl RedisStore {
         self.redis_conn.clone()
     }
 
+    /// Publish `message` on `channel`. The key prefix of this handle does not apply to channels.
+    pub async fn publish(
+        &self,
+        channel: &str,
+        message: String,
+    ) -> error_stack::Result<(), RedisError> {
+        self.redis_conn
+            .publisher
+            .publish::<(), _, _>(channel, message)
+            .await
+            .map_err(into_report)
+    }
+
+    /// Subscribe to `channels` and run `on_message` on the payload of every message received on
+    /// any of them, in order, from a background task. The subscriptions are restored after
+    /// reconnects.
+    ///
+    /// `on_gap` runs whenever messages may have been missed: when the listener lagged behind
+    /// and messages were dropped, and after every reconnect, since messages published while
+    /// disconnected are never delivered.
+    pub async fn subscribe<F, Fut, G, GFut>(
+        &self,
+        channels: Vec<String>,
+        on_message: F,
+        on_gap: G,
+    ) -> error_stack::Result<(), RedisError>
+    where
+        F: Fn(String) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+        G: Fn() -> GFut + Send + 'static,
+        GFut: std::future::Future<Output = ()> + Send,
+    {
+        use tokio::sync::broadcast::error::RecvError;
+
+        let subscriber = self.redis_conn.subscriber.clone();
+        for channel in &channels {
+            subscriber
+                .subscribe::<(), _>(channel.as_str())
+                .await
+                .map_err(into_report)?;
+        }
+
+        let mut messages = subscriber.on_message();
+        let mut reconnects = subscriber.on_reconnect();
+        tokio::spawn(
+            async move {
+                let mut watch_reconnects = true;
+                loop {
+                    tokio::select! {
+                        message = messages.recv() => match message {
+                            Ok(message)
+                                if channels
+                                    .iter()
+                                    .any(|channel| channel.as_str() == &*message.channel) =>
+                            {
+                                match message.value.as_string() {
+                                    Some(payload) => on_message(payload).await,
+                                    None => crate::logger::warn!(
+                                        channel = &*message.channel,
+                                        "Ignoring non-text pub/sub message"
+                                    ),
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(RecvError::Lagged(skipped)) => {
+                                crate::logger::warn!(?channels, skipped, "Pub/sub listener lagged");
+                                on_gap().await;
+                            }
+                            Err(RecvError::Closed) => break,
+                        },
+                        reconnect = reconnects.recv(), if watch_reconnects => match reconnect {
+                            Ok(_) | Err(RecvError::Lagged(_)) => {
+                                crate::logger::info!(?channels, "Pub/sub listener reconnected");
+                                on_gap().await;
+                            }
+                            // Reconnects are no longer reported; keep delivering messages.
+                            Err(RecvError::Closed) => watch_reconnects = false,
+                        },
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+
+        Ok(())
+    }
+
+    /// Append `fields` as a new entry with a server-generated id to the stream at `stream`.
+    /// The key prefix of this handle applies to the stream name.
+    pub async fn stream_append(
+        &self,
+        stream: &str,
+        fields: Vec<(&str, String)>,
+    ) -> error_stack::Result<(), RedisError> {
+        self.redis_conn
+            .stream_append_entry(
+                &stream.to_owned().into(),
+                &hyperswitch_redis_interface::types::RedisEntryId::AutoGeneratedID,
+                fields,
+            )
+            .await
+            .map_err(into_report)
+    }
+
     pub async fn test(&self) -> error_stack::Result<(), RedisError> {
         let redis_conn = self.get_redis_conn();
         let key = consts::REDIS_HEALTH_CHECK_KEY.into();
@@ -1,5 +1,34 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_log (id) {
+        id -> Int8,
+        #[max_length = 64]
+        chain_id -> Varchar,
+        sequence -> Int8,
+        created_at -> Timestamp,
+        #[max_length = 255]
+        tenant_id -> Nullable<Varchar>,
+        #[max_length = 64]
+        action -> Varchar,
+        #[max_length = 16]
+        outcome -> Varchar,
+        #[max_length = 255]
+        merchant_id -> Nullable<Varchar>,
+        #[max_length = 255]
+        resource_id -> Nullable<Varchar>,
+        #[max_length = 64]
+        request_id -> Nullable<Varchar>,
+        #[max_length = 64]
+        prev_hash -> Varchar,
+        #[max_length = 64]
+        hash -> Varchar,
+        event -> Text,
+        #[max_length = 64]
+        caller_role -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     entity (entity_id) {
         id -> Int4,
@@ -8,6 +37,7 @@ diesel::table! {
         #[max_length = 255]
         enc_key_id -> Varchar,
         created_at -> Timestamp,
+        wrapped_dek -> Nullable<Bytea>,
     }
 }
 
@@ -34,6 +64,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    idempotency_record (owner_id, route, idempotency_key) {
+        #[max_length = 255]
+        owner_id -> Varchar,
+        #[max_length = 64]
+        route -> Varchar,
+        #[max_length = 255]
+        idempotency_key -> Varchar,
+        #[max_length = 64]
+        request_hash -> Varchar,
+        response -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     locker (merchant_id, customer_id, locker_id) {
         id -> Int4,
@@ -50,6 +96,7 @@ diesel::table! {
         ttl -> Nullable<Timestamp>,
         #[max_length = 32]
         updated_by -> Nullable<Varchar>,
+        version -> Int4,
     }
 }
 
@@ -86,13 +133,16 @@ diesel::table! {
         expires_at -> Nullable<Timestamp>,
         #[max_length = 32]
         updated_by -> Nullable<Varchar>,
+        version -> Int4,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
+    audit_log,
     entity,
     fingerprint,
     hash_table,
+    idempotency_record,
     locker,
     merchant,
     reverse_lookup,
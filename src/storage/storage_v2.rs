@@ -34,6 +34,22 @@ pub(crate) trait VaultInterface {
         update: types::VaultUpdate,
     ) -> Result<types::Vault, ContainerError<Self::Error>>;
 
+    /// Like `update_vault_data`, but only applied while the row still satisfies `precondition`;
+    /// otherwise fails with `VaultDBError::Conflict`.
+    async fn update_vault_data_if(
+        &self,
+        vault_id: Secret<String>,
+        entity_id: String,
+        update: types::VaultUpdate,
+        precondition: types::VaultPrecondition,
+    ) -> Result<types::Vault, ContainerError<Self::Error>>;
+
+    /// Keyset page of the rows stored for `filter.entity_id`, without decrypting them.
+    async fn list_vault_by_entity_id(
+        &self,
+        filter: types::VaultListFilter,
+    ) -> Result<Vec<types::Vault>, ContainerError<Self::Error>>;
+
     /// Delete a vault row by primary key.
     async fn delete_from_vault(
         &self,
@@ -1,6 +1,6 @@
 #[cfg(not(feature = "kv"))]
+use diesel::OptionalExtension;
 use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, associations::HasTable};
-#[cfg(not(feature = "kv"))]
 use diesel_async::RunQueryDsl;
 #[cfg(not(feature = "kv"))]
 use hyperswitch_masking::ExposeInterface;
@@ -9,14 +9,11 @@ use hyperswitch_masking::PeekInterface;
 use hyperswitch_masking::Secret;
 
 use super::{VaultInterface, types};
-use crate::{
-    error::{self, ContainerError},
-    storage::Storage,
-};
 #[cfg(not(feature = "kv"))]
+use crate::logger;
 use crate::{
-    logger,
-    storage::{DbOperation, schema},
+    error::{self, ContainerError},
+    storage::{DbOperation, Storage, schema},
 };
 
 impl VaultInterface for Storage {
@@ -144,7 +141,10 @@ impl VaultInterface for Storage {
                         .eq(vault_id.expose())
                         .and(schema::vault::entity_id.eq(&entity_id)),
                 )
-                .set(update);
+                .set((
+                    update,
+                    schema::vault::version.eq(schema::vault::version + 1),
+                ));
 
             let pool = conn.pool();
             let operation = DbOperation::Update;
@@ -163,6 +163,136 @@ impl VaultInterface for Storage {
         }
     }
 
+    async fn update_vault_data_if(
+        &self,
+        vault_id: Secret<String>,
+        entity_id: String,
+        update: types::VaultUpdate,
+        precondition: types::VaultPrecondition,
+    ) -> Result<types::Vault, ContainerError<Self::Error>> {
+        #[cfg(feature = "kv")]
+        {
+            let vault_id = vault_id.peek().clone();
+
+            return crate::storage::kv::conditional_update_resource_by_id::<types::Vault>(
+                self,
+                update,
+                crate::storage::kv::impls::vault::VaultPrimaryKey {
+                    entity_id,
+                    vault_id,
+                },
+                precondition,
+            )
+            .await;
+        }
+
+        #[cfg(not(feature = "kv"))]
+        {
+            let mut conn = self.get_conn().await?;
+            logger::info!("performing conditional update operation on vault data");
+            let vault_id = vault_id.expose();
+
+            let query = diesel::update(types::VaultInner::table())
+                .filter(
+                    schema::vault::vault_id
+                        .eq(&vault_id)
+                        .and(schema::vault::entity_id.eq(&entity_id))
+                        .and(schema::vault::version.eq(precondition.version)),
+                )
+                .set((
+                    update,
+                    schema::vault::version.eq(schema::vault::version + 1),
+                ));
+
+            let pool = conn.pool();
+            let operation = DbOperation::Update;
+            crate::storage::log_db_query::<<types::VaultInner as HasTable>::Table, _>(
+                &query, operation, pool,
+            );
+
+            let output = crate::storage::record_db_query_optional::<
+                <types::VaultInner as HasTable>::Table,
+                _,
+                _,
+                _,
+            >(
+                async {
+                    query
+                        .get_result::<types::VaultInner>(conn.get_mut())
+                        .await
+                        .optional()
+                },
+                operation,
+                pool,
+            )
+            .await?;
+
+            match output {
+                Some(vault) => Ok(vault.into()),
+                // No row matched: it either changed underneath us or is gone.
+                None if vault_exists(&mut conn, &vault_id, &entity_id).await? => {
+                    Err(error::VaultDBError::Conflict.into())
+                }
+                None => Err(error::VaultDBError::NotFoundError.into()),
+            }
+        }
+    }
+
+    async fn list_vault_by_entity_id(
+        &self,
+        filter: types::VaultListFilter,
+    ) -> Result<Vec<types::Vault>, ContainerError<Self::Error>> {
+        #[cfg(feature = "kv")]
+        {
+            return crate::storage::kv::list_resources::<types::Vault>(self, filter).await;
+        }
+
+        #[cfg(not(feature = "kv"))]
+        {
+            let mut conn = self.route_conn().await?;
+            logger::info!("performing list operation on vault data");
+
+            let mut query = types::VaultInner::table()
+                .filter(schema::vault::entity_id.eq(filter.entity_id.as_str()))
+                .filter(
+                    schema::vault::expires_at
+                        .is_null()
+                        .or(schema::vault::expires_at.gt(crate::utils::date_time::now())),
+                )
+                .order((
+                    schema::vault::created_at.asc(),
+                    schema::vault::vault_id.asc(),
+                ))
+                .limit(i64::try_from(filter.limit).unwrap_or(i64::MAX))
+                .into_boxed();
+
+            if let Some(after) = &filter.after {
+                query = query.filter(
+                    schema::vault::created_at
+                        .gt(after.created_at)
+                        .or(schema::vault::created_at
+                            .eq(after.created_at)
+                            .and(schema::vault::vault_id.gt(after.vault_id.as_str()))),
+                );
+            }
+
+            let pool = conn.pool();
+            let operation = DbOperation::Filter;
+            crate::storage::log_db_query::<<types::VaultInner as HasTable>::Table, _>(
+                &query, operation, pool,
+            );
+
+            let output: Vec<types::VaultInner> =
+                crate::storage::record_db_query::<<types::VaultInner as HasTable>::Table, _, _, _>(
+                    query.load(conn.get_mut()),
+                    operation,
+                    pool,
+                )
+                .await?;
+            Ok(output.into_iter().map(Into::into).collect())
+        }
+    }
+
     async fn delete_from_vault(
         &self,
         vault_id: Secret<String>,
@@ -206,3 +336,35 @@ impl VaultInterface for Storage {
         }
     }
 }
+
+/// Whether the vault row exists, read on `conn` so that after a conditional update matched
+/// nothing a row that is gone can be told from one at another version.
+pub(crate) async fn vault_exists(
+    conn: &mut crate::storage::DbConnection,
+    vault_id: &str,
+    entity_id: &str,
+) -> Result<bool, ContainerError<error::VaultDBError>> {
+    let query = diesel::select(diesel::dsl::exists(
+        types::VaultInner::table().filter(
+            schema::vault::vault_id
+                .eq(vault_id)
+                .and(schema::vault::entity_id.eq(entity_id)),
+        ),
+    ));
+
+    let pool = conn.pool();
+    let operation = DbOperation::FindOne;
+    crate::storage::log_db_query::<<types::VaultInner as HasTable>::Table, _>(
+        &query, operation, pool,
+    );
+
+    let exists =
+        crate::storage::record_db_query::<<types::VaultInner as HasTable>::Table, _, _, _>(
+            query.get_result::<bool>(conn.get_mut()),
+            operation,
+            pool,
+        )
+        .await?;
+
+    Ok(exists)
+}
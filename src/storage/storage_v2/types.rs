@@ -1,10 +1,12 @@
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+#[cfg(feature = "kv")]
+use hyperswitch_masking::PeekInterface;
 use hyperswitch_masking::Secret;
 
 use crate::{
     routes::routes_v2::data::types::StoreDataRequest,
     storage::{
-        schema,
+        consts, schema,
         scheme::StorageScheme,
         types::{Encryptable, Encrypted},
     },
@@ -18,6 +20,14 @@ pub struct Vault {
     pub created_at: time::PrimitiveDateTime,
     pub expires_at: Option<time::PrimitiveDateTime>,
     pub updated_by: Option<StorageScheme>,
+    pub version: i32,
+}
+
+impl Vault {
+    /// Whether the row's expiry lies before `now`.
+    pub fn is_expired_at(&self, now: time::PrimitiveDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| now > expires_at)
+    }
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -29,6 +39,7 @@ pub struct VaultNew {
     pub created_at: time::PrimitiveDateTime,
     pub expires_at: Option<time::PrimitiveDateTime>,
     pub updated_by: StorageScheme,
+    pub version: i32,
 }
 
 impl VaultNew {
@@ -40,23 +51,72 @@ impl VaultNew {
             created_at: crate::utils::date_time::now(),
             expires_at: *request.ttl,
             updated_by: StorageScheme::PostgresOnly,
+            version: consts::INITIAL_ROW_VERSION,
         }
     }
 }
 
+/// Keyset position in a vault listing; a page resumes strictly after this row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultCursor {
+    pub created_at: time::PrimitiveDateTime,
+    pub vault_id: String,
+}
+
+/// One page of an entity's vault rows, ordered by `(created_at, vault_id)`. Expired rows are
+/// skipped.
+#[derive(Debug, Clone)]
+pub struct VaultListFilter {
+    pub entity_id: String,
+    pub after: Option<VaultCursor>,
+    pub limit: usize,
+}
+
+/// Changeset for the vault table. A `None` field is left untouched; `expires_at` is doubly
+/// optional so that `Some(None)` clears the expiry. `version` is not part of the changeset:
+/// every update statement bumps it alongside.
 #[derive(Debug, Clone, AsChangeset)]
 #[diesel(table_name = schema::vault)]
 pub struct VaultUpdate {
-    pub encrypted_data: Encrypted,
-    pub expires_at: Option<time::PrimitiveDateTime>,
+    pub encrypted_data: Option<Encrypted>,
+    pub expires_at: Option<Option<time::PrimitiveDateTime>>,
     pub updated_by: StorageScheme,
 }
 
+impl VaultUpdate {
+    /// Set (or clear, when `None`) the expiry without touching the stored data.
+    pub fn ttl(expires_at: Option<time::PrimitiveDateTime>) -> Self {
+        Self {
+            encrypted_data: None,
+            expires_at: Some(expires_at),
+            // Placeholder — overwritten by `set_update_storage_scheme` under KV.
+            updated_by: StorageScheme::PostgresOnly,
+        }
+    }
+
+    /// Replace the stored data, keeping the expiry as is.
+    pub fn data(encrypted_data: Encrypted) -> Self {
+        Self {
+            encrypted_data: Some(encrypted_data),
+            expires_at: None,
+            updated_by: StorageScheme::PostgresOnly,
+        }
+    }
+}
+
+/// State a compare-and-set update expects to still find in the row.
+#[derive(Debug, Clone)]
+pub struct VaultPrecondition {
+    /// `version` the caller last read; any intervening write has bumped it.
+    pub version: i32,
+}
+
 impl From<VaultNew> for VaultUpdate {
     fn from(value: VaultNew) -> Self {
         Self {
-            encrypted_data: value.encrypted_data,
-            expires_at: value.expires_at,
+            encrypted_data: Some(value.encrypted_data),
+            // An upsert without a ttl keeps the existing expiry.
+            expires_at: value.expires_at.map(Some),
             updated_by: value.updated_by,
         }
     }
@@ -72,9 +132,35 @@ pub(crate) struct VaultInner {
     created_at: time::PrimitiveDateTime,
     expires_at: Option<time::PrimitiveDateTime>,
     updated_by: Option<StorageScheme>,
+    #[serde(default = "crate::storage::utils::initial_row_version")]
+    version: i32,
 }
 
 impl VaultInner {
+    #[cfg(feature = "kv")]
+    pub(crate) fn satisfies(&self, precondition: &VaultPrecondition) -> bool {
+        self.version == precondition.version
+    }
+
+    /// Whether this copy has seen more writes than `other`.
+    #[cfg(feature = "kv")]
+    pub(crate) fn is_newer_than(&self, other: &Self) -> bool {
+        self.version > other.version
+    }
+
+    #[cfg(feature = "kv")]
+    pub(crate) fn primary_key(&self) -> crate::storage::kv::impls::vault::VaultPrimaryKey {
+        crate::storage::kv::impls::vault::VaultPrimaryKey {
+            entity_id: self.entity_id.clone(),
+            vault_id: self.vault_id.peek().clone(),
+        }
+    }
+
+    #[cfg(feature = "kv")]
+    pub(crate) fn list_index(&self) -> crate::storage::kv::index::ListIndexEntry {
+        crate::storage::kv::impls::vault::list_index_entry(&self.entity_id, self.created_at)
+    }
+
     /// apply the updated fields from VaultUpdate on Vault
     #[cfg(feature = "kv")]
     pub(crate) fn from_update(new: VaultUpdate, current: Self) -> Self {
@@ -87,10 +173,11 @@ impl VaultInner {
             id: 0,
             entity_id: current.entity_id,
             vault_id: current.vault_id,
-            encrypted_data,
+            encrypted_data: encrypted_data.unwrap_or(current.encrypted_data),
             created_at: current.created_at,
-            expires_at,
+            expires_at: expires_at.unwrap_or(current.expires_at),
             updated_by: Some(updated_by),
+            version: current.version + 1,
         }
     }
 }
@@ -104,6 +191,23 @@ impl From<VaultNew> for VaultNewInner {
             created_at: value.created_at,
             expires_at: value.expires_at,
             updated_by: Some(value.updated_by),
+            version: value.version,
+        }
+    }
+}
+
+/// Rebuilds the insert for a row only present in Redis; `id` is assigned by Postgres.
+#[cfg(feature = "kv")]
+impl From<VaultInner> for VaultNewInner {
+    fn from(value: VaultInner) -> Self {
+        Self {
+            entity_id: value.entity_id,
+            vault_id: value.vault_id,
+            encrypted_data: value.encrypted_data,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            updated_by: value.updated_by,
+            version: value.version,
         }
     }
 }
@@ -118,6 +222,7 @@ impl From<VaultNewInner> for VaultInner {
             created_at: value.created_at,
             expires_at: value.expires_at,
             updated_by: value.updated_by,
+            version: value.version,
         }
     }
 }
@@ -131,6 +236,7 @@ impl From<VaultInner> for Vault {
             created_at: value.created_at,
             expires_at: value.expires_at,
             updated_by: value.updated_by,
+            version: value.version,
         }
     }
 }
@@ -144,6 +250,7 @@ pub struct VaultNewInner {
     created_at: time::PrimitiveDateTime,
     expires_at: Option<time::PrimitiveDateTime>,
     updated_by: Option<StorageScheme>,
+    version: i32,
 }
 
 impl VaultNewInner {
@@ -1,6 +1,6 @@
 use base64::Engine;
 use diesel::{
-    AsExpression, Identifiable, Insertable, Queryable,
+    AsChangeset, AsExpression, Identifiable, Insertable, Queryable,
     backend::Backend,
     deserialize::{self, FromSql},
     serialize::ToSql,
@@ -8,7 +8,7 @@ use diesel::{
 };
 use hyperswitch_masking::{ExposeInterface, PeekInterface, Secret, StrongSecret};
 
-use super::{schema, scheme::StorageScheme};
+use super::{consts, schema, scheme::StorageScheme};
 #[cfg(feature = "kv")]
 use crate::storage::kv;
 use crate::{
@@ -37,6 +37,35 @@ impl Merchant {
     pub const CACHE_NAME: &'static str = "merchant";
 }
 
+/// A merchant examined by key migration.
+#[derive(Debug)]
+pub struct MigrationCandidate {
+    pub merchant_id: String,
+    /// Whether an entity (a key held by the external key manager) already exists for it.
+    pub migrated: bool,
+    /// The merchant with its decrypted key, or why the stored key could not be decrypted.
+    pub merchant: Result<Merchant, String>,
+}
+
+impl MerchantInner {
+    /// Decrypt the key of this merchant for migration. A key that fails to decrypt is reported
+    /// on the candidate instead of failing the page it was read in.
+    pub(super) fn into_migration_candidate(
+        self,
+        key: &GcmAes256,
+        migrated: bool,
+    ) -> MigrationCandidate {
+        let merchant_id = self.merchant_id.clone();
+        let merchant = self.decrypt(key).map_err(|err| err.get_inner().to_string());
+
+        MigrationCandidate {
+            merchant_id,
+            migrated,
+            merchant,
+        }
+    }
+}
+
 #[derive(Debug, Insertable)]
 #[diesel(table_name = schema::merchant)]
 pub(crate) struct MerchantNewInner<'a> {
@@ -50,7 +79,7 @@ pub struct MerchantNew<'a> {
     pub enc_key: Secret<Vec<u8>>,
 }
 
-#[derive(Debug, Identifiable, Queryable, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Identifiable, Queryable, serde::Serialize, serde::Deserialize)]
 #[diesel(table_name = schema::locker)]
 pub(crate) struct LockerInner {
     id: i32,
@@ -64,6 +93,8 @@ pub(crate) struct LockerInner {
     #[serde(with = "crate::utils::primitive_datetime_serde::iso8601::option")]
     ttl: Option<time::PrimitiveDateTime>,
     pub updated_by: Option<StorageScheme>,
+    #[serde(default = "super::utils::initial_row_version")]
+    version: i32,
 }
 
 impl From<LockerNew> for LockerInner {
@@ -78,6 +109,59 @@ impl From<LockerNew> for LockerInner {
             hash_id: value.hash_id,
             ttl: value.ttl,
             updated_by: value.updated_by,
+            version: value.version,
+        }
+    }
+}
+
+impl LockerInner {
+    /// apply the updated fields from LockerUpdate on Locker
+    #[cfg(feature = "kv")]
+    pub(crate) fn from_update(new: LockerUpdate, current: Self) -> Self {
+        let LockerUpdate { ttl, updated_by } = new;
+        Self {
+            ttl: ttl.unwrap_or(current.ttl),
+            updated_by: Some(updated_by),
+            version: current.version + 1,
+            ..current
+        }
+    }
+
+    #[cfg(feature = "kv")]
+    pub(crate) fn satisfies(&self, precondition: &LockerPrecondition) -> bool {
+        self.version == precondition.version
+    }
+
+    /// Whether this copy has seen more writes than `other`.
+    #[cfg(feature = "kv")]
+    pub(crate) fn is_newer_than(&self, other: &Self) -> bool {
+        self.version > other.version
+    }
+
+    #[cfg(feature = "kv")]
+    pub(crate) fn primary_key(&self) -> kv::impls::locker::LockerPrimaryKeyType {
+        kv::impls::locker::LockerPrimaryKeyType {
+            locker_id: self.locker_id.clone(),
+            merchant_id: self.merchant_id.clone(),
+            customer_id: self.customer_id.clone(),
+        }
+    }
+}
+
+/// Rebuilds the insert for a row only present in Redis; `id` is assigned by Postgres.
+#[cfg(feature = "kv")]
+impl From<LockerInner> for LockerNew {
+    fn from(value: LockerInner) -> Self {
+        Self {
+            locker_id: value.locker_id,
+            merchant_id: value.merchant_id,
+            customer_id: value.customer_id,
+            enc_data: value.enc_data,
+            created_at: value.created_at,
+            hash_id: value.hash_id,
+            ttl: value.ttl,
+            updated_by: value.updated_by,
+            version: value.version,
         }
     }
 }
@@ -93,6 +177,7 @@ impl From<LockerInner> for Locker {
             hash_id: value.hash_id,
             ttl: value.ttl,
             updated_by: value.updated_by,
+            version: value.version,
         }
     }
 }
@@ -107,6 +192,14 @@ pub struct Locker {
     pub hash_id: String,
     pub ttl: Option<time::PrimitiveDateTime>,
     pub updated_by: Option<StorageScheme>,
+    pub version: i32,
+}
+
+impl Locker {
+    /// Whether the row's ttl lies before `now`.
+    pub fn is_expired_at(&self, now: time::PrimitiveDateTime) -> bool {
+        self.ttl.is_some_and(|ttl| now > ttl)
+    }
 }
 
 #[derive(Debug)]
@@ -152,6 +245,7 @@ pub struct LockerNew {
     pub hash_id: String,
     pub ttl: Option<time::PrimitiveDateTime>,
     pub updated_by: Option<StorageScheme>,
+    pub version: i32,
 }
 
 impl LockerNew {
@@ -169,10 +263,38 @@ impl LockerNew {
             ttl: *request.ttl,
             // Placeholder — overwritten by `set_storage_scheme` when locker joins KV.
             updated_by: Some(StorageScheme::PostgresOnly),
+            version: consts::INITIAL_ROW_VERSION,
         }
     }
 }
 
+/// Changeset for the locker table. `ttl` is doubly optional so that `Some(None)` clears the
+/// expiry while `None` leaves it untouched. Update statements bump `version` alongside.
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = schema::locker)]
+pub struct LockerUpdate {
+    pub ttl: Option<Option<time::PrimitiveDateTime>>,
+    pub updated_by: StorageScheme,
+}
+
+impl LockerUpdate {
+    /// Set (or clear, when `None`) the expiry of a locker row.
+    pub fn ttl(ttl: Option<time::PrimitiveDateTime>) -> Self {
+        Self {
+            ttl: Some(ttl),
+            // Placeholder — overwritten by `set_update_storage_scheme` under KV.
+            updated_by: StorageScheme::PostgresOnly,
+        }
+    }
+}
+
+/// State a compare-and-set locker update expects to still find in the row.
+#[derive(Debug, Clone)]
+pub struct LockerPrecondition {
+    /// `version` the caller last read.
+    pub version: i32,
+}
+
 impl From<LockerNew> for Locker {
     fn from(value: LockerNew) -> Self {
         Self {
@@ -184,6 +306,7 @@ impl From<LockerNew> for Locker {
             hash_id: value.hash_id,
             ttl: value.ttl,
             updated_by: value.updated_by,
+            version: value.version,
         }
     }
 }
@@ -229,6 +352,85 @@ impl From<ReverseLookupNew> for ReverseLookup {
     }
 }
 
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = schema::audit_log)]
+pub(crate) struct AuditLogNew {
+    pub chain_id: String,
+    pub sequence: i64,
+    pub created_at: time::PrimitiveDateTime,
+    pub tenant_id: Option<String>,
+    pub action: String,
+    pub outcome: String,
+    pub merchant_id: Option<String>,
+    pub resource_id: Option<String>,
+    pub request_id: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+    /// The sealed event exactly as hashed.
+    pub event: String,
+    pub caller_role: Option<String>,
+}
+
+/// A stored audit event; `event` is the sealed record as written.
+#[derive(Debug, Clone, Queryable)]
+pub(crate) struct AuditLogEvent {
+    pub id: i64,
+    pub event: String,
+}
+
+/// A request reserving an `Idempotency-Key`, before its response is known.
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = schema::idempotency_record)]
+pub(crate) struct IdempotencyRecordNew {
+    pub owner_id: String,
+    pub route: String,
+    pub idempotency_key: String,
+    pub request_hash: String,
+    pub created_at: time::PrimitiveDateTime,
+    pub expires_at: time::PrimitiveDateTime,
+}
+
+/// Identifies an `Idempotency-Key` within the merchant or entity and route it was sent for.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IdempotencyKeyId<'a> {
+    pub owner_id: &'a str,
+    pub route: &'a str,
+    pub idempotency_key: &'a str,
+}
+
+/// A live `Idempotency-Key`; `response` is `None` while the first request is still running.
+#[derive(Debug, Clone, Queryable)]
+pub(crate) struct IdempotencyRecord {
+    pub request_hash: String,
+    pub response: Option<Vec<u8>>,
+}
+
+/// One page of audit events in insertion order. `None` filters match everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogFilter {
+    pub tenant_id: Option<String>,
+    pub merchant_id: Option<String>,
+    pub resource_id: Option<String>,
+    pub caller_role: Option<String>,
+    pub action: Option<String>,
+    pub from: Option<time::PrimitiveDateTime>,
+    pub to: Option<time::PrimitiveDateTime>,
+    /// Only rows with a larger `id`.
+    pub after: Option<i64>,
+    pub limit: i64,
+}
+
+/// Tables holding stored payment data, counted for usage metrics and quotas. Rows are owned by
+/// `merchant_id` in `locker` and by `entity_id` in `vault`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum StoredRowTable {
+    Locker,
+    Vault,
+}
+
+crate::impl_metric_value_from!(StoredRowTable);
+
 #[derive(Debug, Clone, Identifiable, Queryable, serde::Serialize, serde::Deserialize)]
 #[diesel(table_name = schema::hash_table)]
 pub struct HashTable {
@@ -275,6 +477,8 @@ pub struct Entity {
     pub entity_id: String,
     pub enc_key_id: String,
     pub created_at: time::PrimitiveDateTime,
+    /// DEK wrapped by the external key manager, set on first use in `local_envelope` mode.
+    pub wrapped_dek: Option<Secret<Vec<u8>>>,
 }
 
 #[cfg(feature = "external_key_manager")]
@@ -548,6 +752,7 @@ mod tests {
             hash_id: "hash_id".to_string(),
             ttl: Some(ttl),
             updated_by: Some(StorageScheme::RedisKv),
+            version: 1,
         };
 
         let serialized = serde_json::to_value(&locker);
@@ -616,6 +821,7 @@ mod tests {
             hash_id: "hash_id".to_string(),
             ttl: Some(ttl),
             updated_by: Some(StorageScheme::RedisKv),
+            version: 1,
         };
 
         let serialized = serde_json::to_string(&locker);
@@ -631,4 +837,149 @@ mod tests {
         assert_eq!(deserialized.created_at, locker.created_at);
         assert_eq!(deserialized.ttl, locker.ttl);
     }
+
+    fn locker_inner(ttl: Option<time::PrimitiveDateTime>) -> Option<LockerInner> {
+        Some(LockerInner {
+            id: 1,
+            locker_id: Secret::new("locker_id".to_string()),
+            merchant_id: "merchant_id".to_string(),
+            customer_id: "customer_id".to_string(),
+            enc_data: Encrypted::new(Secret::new(vec![1, 2, 3])),
+            created_at: primitive_datetime(0, 0)?,
+            hash_id: "hash_id".to_string(),
+            ttl,
+            updated_by: Some(StorageScheme::PostgresOnly),
+            version: 1,
+        })
+    }
+
+    #[cfg(feature = "kv")]
+    #[test]
+    fn locker_update_sets_clears_and_keeps_the_ttl() {
+        let ttl = primitive_datetime(5, 0);
+        let current = locker_inner(primitive_datetime(2, 0));
+        assert!(current.is_some(), "failed to build locker");
+        let Some(current) = current else { return };
+
+        let moved = LockerInner::from_update(LockerUpdate::ttl(ttl), current.clone());
+        assert_eq!(moved.ttl, ttl);
+        assert_eq!(moved.version, 2);
+        assert_eq!(moved.updated_by, Some(StorageScheme::PostgresOnly));
+
+        let cleared = LockerInner::from_update(LockerUpdate::ttl(None), current.clone());
+        assert_eq!(cleared.ttl, None);
+
+        let untouched = LockerInner::from_update(
+            LockerUpdate {
+                ttl: None,
+                updated_by: StorageScheme::RedisKv,
+            },
+            current.clone(),
+        );
+        assert_eq!(untouched.ttl, current.ttl);
+        assert_eq!(untouched.updated_by, Some(StorageScheme::RedisKv));
+    }
+
+    #[cfg(feature = "kv")]
+    #[test]
+    fn vault_update_sets_clears_and_keeps_the_expiry() {
+        use crate::storage::storage_v2::types::{
+            Vault, VaultInner, VaultNew, VaultNewInner, VaultUpdate,
+        };
+
+        let created_at = primitive_datetime(0, 0);
+        assert!(created_at.is_some(), "failed to build created_at");
+        let Some(created_at) = created_at else { return };
+
+        let vault_new = |expires_at| VaultNew {
+            vault_id: Secret::new("vault_id".to_string()),
+            entity_id: "entity_id".to_string(),
+            encrypted_data: Encrypted::new(Secret::new(vec![1, 2, 3])),
+            created_at,
+            expires_at,
+            updated_by: StorageScheme::PostgresOnly,
+            version: 1,
+        };
+        let current = VaultInner::from(VaultNewInner::from(vault_new(primitive_datetime(2, 0))));
+        let expires_at = |update: VaultUpdate| {
+            Vault::from(VaultInner::from_update(update, current.clone())).expires_at
+        };
+
+        assert_eq!(
+            expires_at(VaultUpdate::ttl(primitive_datetime(5, 0))),
+            primitive_datetime(5, 0)
+        );
+        assert_eq!(expires_at(VaultUpdate::ttl(None)), None);
+        assert_eq!(
+            expires_at(VaultUpdate::data(Encrypted::new(Secret::new(vec![4])))),
+            primitive_datetime(2, 0)
+        );
+        // An upsert without a ttl keeps the existing expiry, in Redis as in Postgres.
+        assert_eq!(
+            expires_at(VaultUpdate::from(vault_new(None))),
+            primitive_datetime(2, 0)
+        );
+        assert_eq!(
+            expires_at(VaultUpdate::from(vault_new(primitive_datetime(5, 0)))),
+            primitive_datetime(5, 0)
+        );
+    }
+
+    #[test]
+    fn rows_past_their_ttl_are_expired() {
+        let now = primitive_datetime(3, 0);
+        assert!(now.is_some(), "failed to build now");
+        let Some(now) = now else { return };
+
+        let locker = |ttl| locker_inner(ttl).map(Locker::from);
+        assert!(locker(primitive_datetime(2, 0)).is_some_and(|locker| locker.is_expired_at(now)));
+        assert!(locker(primitive_datetime(4, 0)).is_some_and(|locker| !locker.is_expired_at(now)));
+        assert!(locker(None).is_some_and(|locker| !locker.is_expired_at(now)));
+
+        let vault = |expires_at| crate::storage::storage_v2::types::Vault {
+            vault_id: Secret::new("vault_id".to_string()),
+            entity_id: "entity_id".to_string(),
+            data: Encryptable::Encrypted(Secret::new(vec![1, 2, 3])),
+            created_at: now,
+            expires_at,
+            updated_by: None,
+            version: 1,
+        };
+        assert!(vault(primitive_datetime(2, 0)).is_expired_at(now));
+        assert!(!vault(primitive_datetime(4, 0)).is_expired_at(now));
+        assert!(!vault(None).is_expired_at(now));
+    }
+
+    #[test]
+    fn migration_candidate_reports_an_undecryptable_key() {
+        let created_at = primitive_datetime(0, 0);
+        assert!(created_at.is_some(), "failed to build created_at");
+        let Some(created_at) = created_at else { return };
+
+        let key = GcmAes256::new(vec![1; 32]);
+        let enc_key = key.encrypt(vec![7; 32]);
+        assert!(enc_key.is_ok(), "failed to encrypt merchant key");
+        let Ok(enc_key) = enc_key else { return };
+
+        let merchant = |merchant_id: &str| MerchantInner {
+            id: 1,
+            merchant_id: merchant_id.to_string(),
+            enc_key: Encrypted::new(Secret::new(enc_key.clone())),
+            created_at,
+        };
+
+        let readable = merchant("m_readable").into_migration_candidate(&key, false);
+        assert_eq!(readable.merchant_id, "m_readable");
+        assert!(
+            readable
+                .merchant
+                .is_ok_and(|merchant| merchant.enc_key.peek() == &vec![7; 32])
+        );
+
+        let unreadable =
+            merchant("m_unreadable").into_migration_candidate(&GcmAes256::new(vec![2; 32]), true);
+        assert_eq!(unreadable.merchant_id, "m_unreadable");
+        assert!(unreadable.migrated);
+        assert!(unreadable.merchant.is_err());
+    }
 }
@@ -8,3 +8,8 @@ pub fn generate_nano_id(id_length: usize) -> String {
 pub fn generate_uuid() -> String {
     uuid::Uuid::now_v7().to_string()
 }
+
+/// Serde default for `version` on rows cached in Redis before the column existed.
+pub(crate) fn initial_row_version() -> i32 {
+    consts::INITIAL_ROW_VERSION
+}
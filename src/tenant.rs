@@ -8,7 +8,11 @@ use crate::config::TenantConfig;
 #[cfg(feature = "key_custodian")]
 use crate::routes::key_custodian::CustodianKeyState;
 use crate::{
-    api_client::ApiClient, app::TenantAppState, config::GlobalConfig, error::ApiError,
+    api_client::ApiClient,
+    app::TenantAppState,
+    audit::Auditor,
+    config::{GlobalConfig, RotatableSecrets},
+    error::ApiError,
     runtime_config::RuntimeConfigManager,
 };
 
@@ -22,6 +26,12 @@ pub struct GlobalAppState {
     #[cfg(feature = "redis")]
     pub redis_store: Option<crate::storage::redis::RedisStore>,
     pub runtime_config_manager: Arc<RuntimeConfigManager>,
+    /// `None` unless `audit.enabled`.
+    pub auditor: Option<Auditor>,
+    #[cfg(feature = "limit")]
+    pub rate_limiter: crate::rate_limit::RateLimiter,
+    /// Secrets currently in use; differs from `global_config` once the refresher rotated them.
+    rotatable_secrets: std::sync::RwLock<RotatableSecrets>,
 }
 
 impl GlobalAppState {
@@ -78,6 +88,25 @@ impl GlobalAppState {
             .expect("Failed to create runtime config manager"),
         );
 
+        #[allow(clippy::expect_used)]
+        let auditor = Auditor::new(
+            &global_config,
+            runtime_config_manager.clone(),
+            #[cfg(feature = "redis")]
+            redis_store.as_ref(),
+        )
+        .await
+        .expect("Failed to initialize audit sinks");
+
+        #[cfg(feature = "limit")]
+        #[allow(clippy::expect_used)]
+        let rate_limiter = crate::rate_limit::RateLimiter::new(
+            &global_config.limit,
+            #[cfg(feature = "redis")]
+            redis_store.as_ref(),
+        )
+        .expect("Failed to initialize rate limiter");
+
         let tenants_app_state = {
             #[cfg(feature = "key_custodian")]
             {
@@ -106,6 +135,8 @@ impl GlobalAppState {
             }
         };
 
+        let rotatable_secrets = std::sync::RwLock::new(RotatableSecrets::of(&global_config));
+
         Arc::new(Self {
             tenants_app_state: RwLock::new(tenants_app_state),
             #[cfg(feature = "key_custodian")]
@@ -116,9 +147,115 @@ impl GlobalAppState {
             #[cfg(feature = "redis")]
             redis_store,
             runtime_config_manager,
+            auditor,
+            #[cfg(feature = "limit")]
+            rate_limiter,
+            rotatable_secrets,
         })
     }
 
+    pub fn rotatable_secrets(&self) -> RotatableSecrets {
+        self.rotatable_secrets
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    pub(crate) fn set_rotatable_secrets(&self, secrets: RotatableSecrets) {
+        *self
+            .rotatable_secrets
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = secrets;
+    }
+
+    /// `global_config` with the secrets currently in use, for building state after startup.
+    pub fn current_config(&self) -> GlobalConfig {
+        let mut config = self.global_config.clone();
+        self.rotatable_secrets().apply(&mut config);
+        config
+    }
+
+    /// Evict the keys other pods invalidate, listening on the channel of every known tenant.
+    /// Messages for tenants this pod has no state for yet (custodian still locked) are dropped:
+    /// their caches are empty.
+    #[cfg(all(feature = "caching", feature = "redis"))]
+    pub async fn spawn_cache_invalidation_listener(self: &Arc<Self>) {
+        use crate::storage::caching::invalidation::CacheInvalidation;
+
+        let Some(redis_store) = &self.redis_store else {
+            return;
+        };
+
+        let state = Arc::clone(self);
+        let gap_state = Arc::clone(self);
+        let subscribed = redis_store
+            .subscribe(
+                self.known_tenants
+                    .iter()
+                    .map(|tenant_id| crate::storage::caching::invalidation::channel(tenant_id))
+                    .collect(),
+                move |payload| {
+                    let state = state.clone();
+                    async move {
+                        let invalidation = match serde_json::from_str::<CacheInvalidation>(&payload)
+                        {
+                            Ok(invalidation) => invalidation,
+                            Err(err) => {
+                                crate::logger::warn!(?err, "Ignoring malformed cache invalidation");
+                                return;
+                            }
+                        };
+
+                        if invalidation.is_own() {
+                            return;
+                        }
+
+                        let outcome =
+                            match state.get_app_state_of_tenant(&invalidation.tenant).await {
+                                Ok(tenant_state) => {
+                                    tenant_state.apply_invalidation(&invalidation).await;
+                                    "evicted"
+                                }
+                                Err(_) => "skipped",
+                            };
+
+                        crate::observability::metrics::CACHE_INVALIDATION_COUNT.add(
+                            1,
+                            crate::metric_attributes!(
+                                ("cache", invalidation.cache_name),
+                                ("direction", "received"),
+                                ("outcome", outcome),
+                            ),
+                        );
+                    }
+                },
+                move || {
+                    let state = gap_state.clone();
+                    async move {
+                        // Any invalidation may have been missed, so nothing cached can be trusted.
+                        let tenants = state
+                            .tenants_app_state
+                            .read()
+                            .await
+                            .values()
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        for tenant_state in tenants {
+                            tenant_state.flush_local_caches().await;
+                        }
+                    }
+                },
+            )
+            .await;
+
+        if let Err(err) = subscribed {
+            crate::logger::error!(
+                ?err,
+                "Failed to subscribe to cache invalidations; caches only expire on idle"
+            );
+        }
+    }
+
     pub async fn get_app_state_of_tenant(
         &self,
         tenant_id: &str,
@@ -0,0 +1,287 @@
+//! Stored data usage per tenant and merchant.
+//!
+//! A background task counts the `locker` and `vault` rows of every tenant each
+//! `collection_interval_secs` and records them on `storage.stored_row.count`. With
+//! `merchant_metrics`, the `max_merchant_series` merchants storing the most rows are also recorded
+//! on `storage.merchant.stored_row.count`; merchants outside that set are left out to keep the
+//! number of series bounded, and a merchant that drops out of it keeps its last recorded value.
+//!
+//! Quotas cap the rows a merchant stores in each table. [`ensure_capacity`] checks a write that
+//! would add a row against a per-instance estimate of the merchant's rows ([`QuotaUsage`]), and
+//! only counts them exactly, on the primary, once the estimate comes within
+//! [`EXACT_COUNT_MARGIN_PERCENT`] of the quota or is older than `collection_interval_secs`. Writes
+//! made through other instances are only seen by the next exact count, so concurrent writes can
+//! overshoot a quota slightly.
+//!
+//! With the `kv` feature, rows still pending in the KV store are not counted until drained. Near
+//! the quota every write is counted exactly, so a burst written faster than the drainer replays it
+//! is not held back and can exceed the quota by the number of rows pending.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tracing::Instrument;
+
+use crate::{
+    app::TenantAppState,
+    error::{self, ContainerError},
+    logger,
+    observability::metrics,
+    storage::{UsageInterface, types::StoredRowTable},
+    tenant::GlobalAppState,
+};
+
+#[derive(Clone, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct UsageConfig {
+    pub collection_interval_secs: u64,
+    /// Also record row counts for the largest merchants.
+    pub merchant_metrics: bool,
+    pub max_merchant_series: usize,
+    pub quota: QuotaConfig,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            collection_interval_secs: 300,
+            merchant_metrics: false,
+            max_merchant_series: 100,
+            quota: QuotaConfig::default(),
+        }
+    }
+}
+
+impl UsageConfig {
+    pub fn validate(&self) -> Result<(), error::ConfigurationError> {
+        if self.collection_interval_secs == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "usage.collection_interval_secs must be greater than 0".into(),
+            ));
+        }
+
+        if self.merchant_metrics && self.max_merchant_series == 0 {
+            return Err(error::ConfigurationError::InvalidConfigurationValueError(
+                "usage.max_merchant_series must be greater than 0 when merchant_metrics is enabled"
+                    .into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Maximum number of rows a merchant may store, applied to `locker` and `vault` separately.
+#[derive(Clone, serde::Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct QuotaConfig {
+    /// Quota for merchants not listed in `merchants`; unlimited when unset.
+    pub default_max_stored_rows: Option<u64>,
+    pub merchants: HashMap<String, u64>,
+}
+
+impl QuotaConfig {
+    pub fn limit_for(&self, merchant_id: &str) -> Option<u64> {
+        self.merchants
+            .get(merchant_id)
+            .copied()
+            .or(self.default_max_stored_rows)
+    }
+}
+
+/// Share of a quota, in percent, within which [`ensure_capacity`] counts rows exactly instead of
+/// trusting the estimate.
+const EXACT_COUNT_MARGIN_PERCENT: u64 = 10;
+
+/// Estimates of the rows each owner stores in each table, kept by every instance for the owners
+/// with a quota.
+///
+/// An estimate starts from an exact count and grows by one with every write admitted on this
+/// instance. It is dropped `collection_interval_secs` after that count, so writes and deletes
+/// made through other instances are picked up by the next one.
+#[derive(Clone)]
+pub struct QuotaUsage {
+    estimates: moka::sync::Cache<(StoredRowTable, String), Arc<AtomicU64>>,
+}
+
+impl QuotaUsage {
+    pub fn new(config: &UsageConfig) -> Self {
+        Self {
+            estimates: moka::sync::Cache::builder()
+                .time_to_live(Duration::from_secs(config.collection_interval_secs))
+                .build(),
+        }
+    }
+
+    fn estimate(&self, table: StoredRowTable, owner_id: &str) -> Option<Arc<AtomicU64>> {
+        self.estimates.get(&(table, owner_id.to_owned()))
+    }
+
+    /// Start a new estimate from an exact count.
+    fn record_count(&self, table: StoredRowTable, owner_id: &str, stored: u64) -> Arc<AtomicU64> {
+        let estimate = Arc::new(AtomicU64::new(stored));
+        self.estimates
+            .insert((table, owner_id.to_owned()), Arc::clone(&estimate));
+        estimate
+    }
+}
+
+/// Whether `estimate` is close enough to `limit` to be counted exactly.
+fn near_limit(estimate: u64, limit: u64) -> bool {
+    let margin = (limit.saturating_mul(EXACT_COUNT_MARGIN_PERCENT) / 100).max(1);
+    estimate.saturating_add(margin) >= limit
+}
+
+/// Fail with [`error::ApiError::QuotaExceeded`] when `owner_id` already stores as many rows in
+/// `table` as its quota allows. Owners without a quota are not counted.
+pub async fn ensure_capacity(
+    tenant_app_state: &TenantAppState,
+    table: StoredRowTable,
+    owner_id: &str,
+) -> Result<(), ContainerError<error::ApiError>> {
+    let Some(limit) = tenant_app_state.config.quota.limit_for(owner_id) else {
+        return Ok(());
+    };
+    let usage = &tenant_app_state.quota_usage;
+
+    let estimate = match usage.estimate(table, owner_id) {
+        Some(estimate) if !near_limit(estimate.load(Ordering::Relaxed), limit) => estimate,
+        _ => {
+            let stored = tenant_app_state
+                .db
+                .count_owner_stored_rows(table, owner_id)
+                .await?;
+            usage.record_count(table, owner_id, stored)
+        }
+    };
+
+    let stored = estimate.load(Ordering::Relaxed);
+    if stored < limit {
+        // The write may still fail or turn out to add no row; the next exact count corrects that.
+        estimate.fetch_add(1, Ordering::Relaxed);
+        return Ok(());
+    }
+
+    metrics::QUOTA_REJECTED_COUNT.add(
+        1,
+        crate::metric_attributes!(
+            ("tenant_id", tenant_app_state.config.tenant_id.clone()),
+            ("table", table),
+        ),
+    );
+    logger::warn!(
+        tenant_id = %tenant_app_state.config.tenant_id,
+        merchant_id = owner_id,
+        table = <&'static str>::from(table),
+        stored,
+        limit,
+        "Stored row quota exceeded"
+    );
+
+    Err(error::ApiError::QuotaExceeded(table.into()).into())
+}
+
+/// Spawn the stored row collector, counting right away and then every
+/// `collection_interval_secs`.
+pub fn spawn_collector(global_app_state: &Arc<GlobalAppState>) -> tokio::task::JoinHandle<()> {
+    let config = global_app_state.global_config.usage.clone();
+    let interval = Duration::from_secs(config.collection_interval_secs);
+    let global_app_state = Arc::clone(global_app_state);
+
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                let tenants: Vec<_> = {
+                    let guard = global_app_state.tenants_app_state.read().await;
+                    guard
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect::<Vec<_>>()
+                };
+                for (tenant_id, tenant_state) in tenants.iter() {
+                    for table in [StoredRowTable::Locker, StoredRowTable::Vault] {
+                        collect_stored_rows(tenant_id, tenant_state, table, &config).await;
+                    }
+                }
+            }
+        }
+        .instrument(tracing::info_span!("usage_collector")),
+    )
+}
+
+async fn collect_stored_rows(
+    tenant_id: &str,
+    tenant_state: &TenantAppState,
+    table: StoredRowTable,
+    config: &UsageConfig,
+) {
+    match tenant_state.db.count_stored_rows(table, None).await {
+        Ok(count) => metrics::STORED_ROW_COUNT.record(
+            count,
+            crate::metric_attributes!(("tenant_id", tenant_id.to_owned()), ("table", table)),
+        ),
+        Err(error) => {
+            logger::warn!(?error, tenant_id, "Failed to count stored rows");
+            return;
+        }
+    }
+
+    if !config.merchant_metrics {
+        return;
+    }
+
+    let limit = i64::try_from(config.max_merchant_series).unwrap_or(i64::MAX);
+    match tenant_state
+        .db
+        .find_top_owners_by_stored_rows(table, limit)
+        .await
+    {
+        Ok(owners) => {
+            for (merchant_id, count) in owners {
+                metrics::MERCHANT_STORED_ROW_COUNT.record(
+                    count,
+                    crate::metric_attributes!(
+                        ("tenant_id", tenant_id.to_owned()),
+                        ("table", table),
+                        ("merchant_id", merchant_id),
+                    ),
+                );
+            }
+        }
+        Err(error) => {
+            logger::warn!(
+                ?error,
+                tenant_id,
+                "Failed to count stored rows per merchant"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_estimates_close_to_the_limit_are_counted_exactly() {
+        assert!(!near_limit(0, 1_000));
+        assert!(!near_limit(899, 1_000));
+        assert!(near_limit(900, 1_000));
+        assert!(near_limit(1_200, 1_000));
+        // small quotas keep a margin of one row
+        assert!(near_limit(0, 1));
+        assert!(!near_limit(3, 5));
+        assert!(near_limit(4, 5));
+    }
+}
@@ -1,4 +1,8 @@
-use axum::{body::Body, extract::Request};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::request,
+};
 
 use crate::storage::consts;
 
@@ -98,6 +102,48 @@ pub mod primitive_datetime_serde {
     }
 }
 
+/// Apply an RFC 7396 JSON merge patch to `target` in place: objects merge key by key, a `null`
+/// member removes that key, and any non-object patch replaces the target outright.
+pub fn json_merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+
+    if let serde_json::Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                json_merge_patch(
+                    target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                    value,
+                );
+            }
+        }
+    }
+}
+
+/// Pick the values at `pointers` (RFC 6901 JSON pointers) out of `document`, keyed by pointer.
+/// Pointers that resolve to nothing are left out.
+pub fn json_project(
+    document: &serde_json::Value,
+    pointers: &[String],
+) -> serde_json::Map<String, serde_json::Value> {
+    pointers
+        .iter()
+        .filter_map(|pointer| {
+            document
+                .pointer(pointer)
+                .map(|value| (pointer.clone(), value.clone()))
+        })
+        .collect()
+}
+
 /// Record the header's fields in request's trace
 pub fn record_fields_from_header(request: &Request<Body>) -> tracing::Span {
     let span = tracing::debug_span!(
@@ -120,5 +166,142 @@ pub fn record_fields_from_header(request: &Request<Body>) -> tracing::Span {
         .and_then(|value| value.to_str().ok())
         .map(|request_id| span.record("request_id", request_id));
 
+    crate::observability::traces::set_parent_from_headers(&span, request.headers());
+
     span
 }
+
+/// What the middlewares keying state on a request read from it before the handler runs.
+pub(crate) struct RequestScope<'a> {
+    /// Template of the matched route, empty when none matched.
+    pub route: &'a str,
+    /// The `x-tenant-id` header, not yet checked against the known tenants.
+    pub tenant_id: Option<&'a str>,
+    body: &'a [u8],
+}
+
+/// The owner a request body names, if any.
+#[derive(serde::Deserialize)]
+struct RequestOwner {
+    merchant_id: Option<String>,
+    entity_id: Option<String>,
+}
+
+impl<'a> RequestScope<'a> {
+    pub fn new(
+        matched_path: Option<&'a MatchedPath>,
+        parts: &'a request::Parts,
+        body: &'a [u8],
+    ) -> Self {
+        Self {
+            route: matched_path.map(MatchedPath::as_str).unwrap_or_default(),
+            tenant_id: parts
+                .headers
+                .get(consts::X_TENANT_ID)
+                .and_then(|value| value.to_str().ok()),
+            body,
+        }
+    }
+
+    /// `merchant_id` of a v1 body, or `entity_id` of a v2 body.
+    pub fn owner_id(&self) -> Option<String> {
+        serde_json::from_slice::<RequestOwner>(self.body)
+            .ok()
+            .and_then(|owner| owner.merchant_id.or(owner.entity_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn json_merge_patch_follows_rfc_7396_examples() {
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+
+        for (mut target, patch, expected) in cases {
+            json_merge_patch(&mut target, &patch);
+            assert_eq!(
+                target, expected,
+                "patch {patch} produced the wrong document"
+            );
+        }
+    }
+
+    #[test]
+    fn json_project_keeps_only_resolvable_pointers() {
+        let document = json!({
+            "card_number": "4242424242424242",
+            "card_exp_month": "03",
+            "billing": {"zip": "560001", "a/b": 1},
+            "tags": ["x", "y"]
+        });
+        let pointers = [
+            "/card_exp_month",
+            "/billing/zip",
+            "/billing/a~1b",
+            "/tags/1",
+            "/missing",
+            "/tags/5",
+        ]
+        .map(String::from);
+
+        let projected = serde_json::Value::Object(json_project(&document, &pointers));
+
+        assert_eq!(
+            projected,
+            json!({
+                "/card_exp_month": "03",
+                "/billing/zip": "560001",
+                "/billing/a~1b": 1,
+                "/tags/1": "y"
+            })
+        );
+    }
+}